@@ -0,0 +1,352 @@
+//! Derive macros for `openai-ox`, re-exported by it behind the `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Attribute, Data, DataEnum, DeriveInput,
+    Expr, ExprLit, Fields, Lit, LitStr, Meta,
+};
+
+/// Derives `openai_ox::extract::JsonSchema` and `openai_ox::extract::Extract`.
+///
+/// Works on structs with named fields and on enums with unit variants. Doc comments become
+/// schema descriptions, and `#[serde(rename, rename_all, skip)]` are honoured so the schema
+/// matches what serde deserializes. `#[serde(flatten)]` is rejected. Every field type must
+/// implement `JsonSchema` itself.
+#[proc_macro_derive(Extract)]
+pub fn derive_extract(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `openai_ox::chat::tool::ToolArgs` and `openai_ox::extract::JsonSchema` for the
+/// arguments struct of a function tool.
+///
+/// The function is named after the struct in snake_case unless `#[tool(name = "...")]` says
+/// otherwise, and the struct's doc comment becomes its description. Fields follow the same rules
+/// as for `Extract`, which must not be derived on the same type.
+#[proc_macro_derive(Tool, attributes(tool))]
+pub fn derive_tool(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_tool(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let json_schema = json_schema_impl(&input, "Extract")?;
+    let ident = &input.ident;
+    let name = SerdeAttrs::parse(&input.attrs)?
+        .rename
+        .unwrap_or_else(|| ident.to_string());
+    Ok(quote! {
+        #json_schema
+
+        impl ::openai_ox::extract::Extract for #ident {
+            const NAME: &'static str = #name;
+        }
+    })
+}
+
+fn expand_tool(input: DeriveInput) -> syn::Result<TokenStream2> {
+    if !matches!(input.data, Data::Struct(_)) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Tool can only be derived for structs with named fields",
+        ));
+    }
+    let json_schema = json_schema_impl(&input, "Tool")?;
+    let ident = &input.ident;
+    let name = tool_name(&input.attrs)?
+        .unwrap_or_else(|| RenameRule::Snake.apply_to_variant(&ident.unraw().to_string()));
+    let description = option(doc_string(&input.attrs));
+    Ok(quote! {
+        #json_schema
+
+        impl ::openai_ox::chat::tool::ToolArgs for #ident {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: ::std::option::Option<&'static str> = #description;
+        }
+    })
+}
+
+/// The `JsonSchema` impl shared by both derives, `derive` naming the one in error messages.
+fn json_schema_impl(input: &DeriveInput, derive: &str) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            format!("{} cannot be derived for generic types", derive),
+        ));
+    }
+    let ident = &input.ident;
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let description = option(doc_string(&input.attrs));
+    let schema = match &input.data {
+        Data::Struct(data) => struct_schema(&data.fields, container.rename_all)?,
+        Data::Enum(data) => enum_schema(data, container.rename_all)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("{} cannot be derived for unions", derive),
+            ))
+        }
+    };
+    Ok(quote! {
+        impl ::openai_ox::extract::JsonSchema for #ident {
+            fn json_schema() -> ::openai_ox::extract::serde_json::Value {
+                ::openai_ox::extract::with_description(#schema, #description)
+            }
+        }
+    })
+}
+
+/// Value of `#[tool(name = "...")]`.
+fn tool_name(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut name = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported tool attribute, expected `name`"))
+            }
+        })?;
+    }
+    Ok(name)
+}
+
+fn struct_schema(fields: &Fields, rename_all: Option<RenameRule>) -> syn::Result<TokenStream2> {
+    let Fields::Named(fields) = fields else {
+        return Err(syn::Error::new(
+            fields.span(),
+            "Extract can only be derived for structs with named fields",
+        ));
+    };
+    let mut keys = Vec::new();
+    let mut properties = Vec::new();
+    for field in &fields.named {
+        let attrs = SerdeAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        if let Some(flatten) = attrs.flatten {
+            return Err(syn::Error::new(
+                flatten,
+                "`#[serde(flatten)]` is not supported, the schema lists every field itself",
+            ));
+        }
+        let ident = ident.unraw().to_string();
+        let key = attrs.rename.unwrap_or_else(|| match rename_all {
+            Some(rule) => rule.apply_to_field(&ident),
+            None => ident,
+        });
+        let ty = &field.ty;
+        let description = option(doc_string(&field.attrs));
+        properties.push(quote! {
+            properties.insert(
+                #key.to_string(),
+                ::openai_ox::extract::with_description(
+                    <#ty as ::openai_ox::extract::JsonSchema>::json_schema(),
+                    #description,
+                ),
+            );
+        });
+        keys.push(key);
+    }
+    Ok(quote! {{
+        let mut properties = ::openai_ox::extract::serde_json::Map::new();
+        #(#properties)*
+        ::openai_ox::extract::serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": [#(#keys),*],
+            "additionalProperties": false,
+        })
+    }})
+}
+
+fn enum_schema(data: &DataEnum, rename_all: Option<RenameRule>) -> syn::Result<TokenStream2> {
+    let mut names = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Extract can only be derived for enums with unit variants",
+            ));
+        }
+        let attrs = SerdeAttrs::parse(&variant.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = variant.ident.unraw().to_string();
+        names.push(attrs.rename.unwrap_or_else(|| match rename_all {
+            Some(rule) => rule.apply_to_variant(&ident),
+            None => ident,
+        }));
+    }
+    Ok(quote! {
+        ::openai_ox::extract::serde_json::json!({
+            "type": "string",
+            "enum": [#(#names),*],
+        })
+    })
+}
+
+fn option(value: Option<String>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    }
+}
+
+/// Doc comment lines joined with spaces, `None` without docs.
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// The serde attributes that change the shape of the JSON.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    skip: bool,
+    /// Span of `flatten`, which the schema can't express.
+    flatten: Option<proc_macro2::Span>,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("rename_all") {
+                    parsed.rename_all = Some(RenameRule::parse(&meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    parsed.flatten = Some(meta.path.span());
+                } else if meta.input.peek(syn::Token![=]) {
+                    // Other serde attributes don't change the schema; skip their value.
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|nested| {
+                        if nested.input.peek(syn::Token![=]) {
+                            nested.value()?.parse::<Expr>()?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// A serde `rename_all` rule, applied the way serde applies it.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> syn::Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return Err(syn::Error::new_spanned(rule, "unknown rename rule")),
+        })
+    }
+
+    /// Renames a variant or type name, which is expected in PascalCase.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => variant[..1].to_ascii_lowercase() + &variant[1..],
+            RenameRule::Snake => {
+                let mut snake = String::new();
+                for (i, c) in variant.char_indices() {
+                    if i > 0 && c.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                }
+                snake
+            }
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Renames a field name, which is expected in snake_case.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(c);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
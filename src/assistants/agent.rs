@@ -0,0 +1,254 @@
+//! High level wrapper owning an assistant and a thread.
+//!
+//! [`AssistantAgent`] hides the message / run / poll / submit-tool-outputs loop behind a single
+//! [`ask`](AssistantAgent::ask) call. Tool calls requested by the run are executed with a local
+//! [`ToolRegistry`] and their outputs submitted automatically.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::{
+//!     assistants::agent::AssistantAgent,
+//!     chat::tool::{Function, ToolRegistry},
+//! };
+//!
+//! let registry = ToolRegistry::new().register(
+//!     Function::builder().name("get_time").build(),
+//!     |_| async { Ok::<_, String>("12:00".to_string()) },
+//! );
+//! let agent = AssistantAgent::create(&openai, "gpt-4o", "You are a helpful clock.", registry).await?;
+//! let reply = agent.ask("What time is it?").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::{future::join_all, stream::BoxStream, StreamExt};
+
+use crate::{backoff::Backoff, chat::tool::ToolRegistry, ApiRequestError, OpenAi};
+
+use super::{MessageRole, Run, RunStatus, RunStreamEvent, ToolOutput};
+
+#[derive(Debug, Clone)]
+pub struct AssistantAgent {
+    openai: OpenAi,
+    assistant_id: String,
+    thread_id: String,
+    registry: ToolRegistry,
+    backoff: Backoff,
+}
+
+fn run_failed(run: &Run) -> ApiRequestError {
+    let reason = run
+        .last_error
+        .as_ref()
+        .map(|e| format!(": {} ({})", e.message, e.code))
+        .unwrap_or_default();
+    ApiRequestError::UnexpectedResponse {
+        status: None,
+        response: format!(
+            "run {} ended with status {:?}{}",
+            run.id, run.status, reason
+        ),
+    }
+}
+
+impl AssistantAgent {
+    /// Wraps an existing assistant and thread.
+    pub fn new(
+        openai: &OpenAi,
+        assistant_id: impl Into<String>,
+        thread_id: impl Into<String>,
+        registry: ToolRegistry,
+    ) -> Self {
+        AssistantAgent {
+            openai: openai.clone(),
+            assistant_id: assistant_id.into(),
+            thread_id: thread_id.into(),
+            registry,
+            backoff: Backoff::builder()
+                .initial(std::time::Duration::from_millis(250))
+                .max(std::time::Duration::from_secs(2))
+                .build(),
+        }
+    }
+
+    /// Creates a new assistant exposing the registry's tools, and a fresh thread for it.
+    pub async fn create(
+        openai: &OpenAi,
+        model: impl Into<String>,
+        instructions: impl Into<String>,
+        registry: ToolRegistry,
+    ) -> Result<Self, ApiRequestError> {
+        let tools = (!registry.tools().is_empty()).then(|| registry.tools().clone());
+        let assistant = openai
+            .create_assistant()
+            .model(model)
+            .instructions(instructions)
+            .maybe_tools(tools)
+            .build()
+            .send()
+            .await?;
+        let thread = openai.create_thread().await?;
+        Ok(Self::new(openai, assistant.id, thread.id, registry))
+    }
+
+    /// Delay schedule used while polling runs in [`ask`](AssistantAgent::ask).
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn assistant_id(&self) -> &str {
+        &self.assistant_id
+    }
+
+    pub fn thread_id(&self) -> &str {
+        &self.thread_id
+    }
+
+    pub fn registry(&self) -> &ToolRegistry {
+        &self.registry
+    }
+
+    async fn run_tools(&self, run: &Run) -> Vec<ToolOutput> {
+        join_all(run.tool_calls().iter().map(|call| async move {
+            ToolOutput {
+                tool_call_id: call.id.clone(),
+                output: self
+                    .registry
+                    .call(&call.function.name, &call.function.arguments)
+                    .await,
+            }
+        }))
+        .await
+    }
+
+    /// Posts `text` to the thread, runs the assistant to completion and returns its reply.
+    pub async fn ask(&self, text: impl Into<String>) -> Result<String, ApiRequestError> {
+        let openai = &self.openai;
+        openai
+            .create_message(&self.thread_id, MessageRole::User, text)
+            .await?;
+        let mut run = openai
+            .create_run(&self.thread_id, &self.assistant_id)
+            .await?;
+        let mut delays = self.backoff.delays();
+        loop {
+            match run.status {
+                RunStatus::Completed => break,
+                RunStatus::RequiresAction => {
+                    let outputs = self.run_tools(&run).await;
+                    run = openai
+                        .submit_tool_outputs(&self.thread_id, &run.id, &outputs)
+                        .await?;
+                    delays = self.backoff.delays();
+                }
+                status if status.is_terminal() => return Err(run_failed(&run)),
+                _ => {
+                    if let Some(delay) = delays.next() {
+                        tokio::time::sleep(delay).await;
+                    }
+                    run = openai.retrieve_run(&self.thread_id, &run.id).await?;
+                }
+            }
+        }
+
+        let messages = openai.list_messages(&self.thread_id, Some(&run.id)).await?;
+        Ok(messages
+            .data
+            .iter()
+            .filter(|message| message.role == MessageRole::Assistant)
+            .map(|message| message.text())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Like [`ask`](AssistantAgent::ask), but yields the reply text as it is generated.
+    ///
+    /// Tool calls are handled in the background; the stream ends when the run finishes.
+    pub async fn ask_stream(
+        &self,
+        text: impl Into<String>,
+    ) -> Result<BoxStream<'static, Result<String, ApiRequestError>>, ApiRequestError> {
+        self.openai
+            .create_message(&self.thread_id, MessageRole::User, text)
+            .await?;
+        let events = self
+            .openai
+            .create_run_stream(&self.thread_id, &self.assistant_id)
+            .await;
+
+        Ok(
+            futures::stream::unfold(Some((self.clone(), events)), |state| async move {
+                let (agent, mut events) = state?;
+                loop {
+                    match events.next().await? {
+                        Ok(RunStreamEvent::MessageDelta(delta)) => {
+                            let text = delta.text();
+                            if !text.is_empty() {
+                                return Some((Ok(text), Some((agent, events))));
+                            }
+                        }
+                        Ok(RunStreamEvent::Run(run)) if run.status == RunStatus::RequiresAction => {
+                            let outputs = agent.run_tools(&run).await;
+                            events = agent
+                                .openai
+                                .submit_tool_outputs_stream(&agent.thread_id, &run.id, &outputs)
+                                .await;
+                        }
+                        Ok(RunStreamEvent::Run(run))
+                            if run.status.is_terminal() && run.status != RunStatus::Completed =>
+                        {
+                            return Some((Err(run_failed(&run)), None));
+                        }
+                        Ok(RunStreamEvent::Done) => return None,
+                        Ok(_) => {}
+                        Err(e) => return Some((Err(e), None)),
+                    }
+                }
+            })
+            .boxed(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::chat::tool::Function;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_required_action_dispatch() {
+        let registry = ToolRegistry::new().register(
+            Function::builder().name("echo").build(),
+            |args: Value| async move { Ok::<_, String>(args["text"].to_string()) },
+        );
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let agent = AssistantAgent::new(&openai, "asst_1", "thread_1", registry);
+        let run: Run = serde_json::from_value(json!({
+            "id": "run_1",
+            "object": "thread.run",
+            "created_at": 1,
+            "thread_id": "thread_1",
+            "assistant_id": "asst_1",
+            "status": "requires_action",
+            "required_action": {
+                "type": "submit_tool_outputs",
+                "submit_tool_outputs": {
+                    "tool_calls": [
+                        {"id": "call_1", "type": "function", "function": {"name": "echo", "arguments": "{\"text\":\"hi\"}"}},
+                        {"id": "call_2", "type": "function", "function": {"name": "missing", "arguments": "{}"}}
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let outputs = agent.run_tools(&run).await;
+        assert_eq!(outputs[0].tool_call_id, "call_1");
+        assert_eq!(outputs[0].output, "\"hi\"");
+        assert!(outputs[1].output.starts_with("Error: unknown tool"));
+    }
+}
@@ -0,0 +1,860 @@
+//! Types for the Assistants API (assistants, threads, messages, runs and run steps).
+//!
+//! Every request carries the `OpenAI-Beta: assistants=v2` header. Runs can be followed either
+//! by polling with [`Run::wait`] or as a stream of [`RunStreamEvent`]s. For the common "create
+//! an assistant, keep a thread, answer questions and run tools" flow see
+//! [`agent::AssistantAgent`].
+
+pub mod agent;
+pub mod steps;
+
+use std::time::{Duration, Instant};
+
+use bon::Builder;
+use futures::{stream::BoxStream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    auth::Authorize,
+    backoff::Backoff,
+    files::{FileRef, GeneratedFile},
+    retry::SendRetrying,
+    sse::{error_stream, idle_timeout, sse_events, SseEvent},
+    ApiError, ApiRequestError, ErrorResponse, OpenAi,
+};
+
+const ASSISTANTS_URL: &str = "v1/assistants";
+const THREADS_URL: &str = "v1/threads";
+const BETA_HEADER: (&str, &str) = ("OpenAI-Beta", "assistants=v2");
+
+#[derive(Debug, Clone, Serialize, Builder)]
+pub struct CreateAssistantRequest {
+    #[builder(into)]
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub tools: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+/// Changes to an assistant; fields left unset keep their current value.
+#[derive(Debug, Clone, Serialize, Builder)]
+pub struct ModifyAssistantRequest {
+    #[serde(skip)]
+    #[builder(into)]
+    pub assistant_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub tools: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assistant {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub model: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub tools: Vec<Value>,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Thread {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Text {
+    pub value: String,
+    #[serde(default)]
+    pub annotations: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageFile {
+    pub file_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageContent {
+    Text {
+        text: Text,
+    },
+    /// An image, e.g. a chart drawn by code interpreter.
+    ImageFile {
+        image_file: ImageFile,
+    },
+    /// Any content type not modelled above.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadMessage {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub thread_id: String,
+    pub role: MessageRole,
+    #[serde(default)]
+    pub content: Vec<MessageContent>,
+    #[serde(default)]
+    pub assistant_id: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+impl ThreadMessage {
+    /// Text parts joined with newlines.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::Text { text } => Some(text.value.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Files generated by code interpreter: image parts and `file_path` annotations, whose
+    /// `sandbox:/mnt/data/...` link gives the file name.
+    pub fn generated_files(&self) -> Vec<FileRef> {
+        let mut files = Vec::new();
+        for content in &self.content {
+            match content {
+                MessageContent::ImageFile { image_file } => files.push(FileRef {
+                    file_id: image_file.file_id.clone(),
+                    container_id: None,
+                    filename: None,
+                }),
+                MessageContent::Text { text } => {
+                    for annotation in &text.annotations {
+                        if annotation["type"] != "file_path" {
+                            continue;
+                        }
+                        let Some(file_id) = annotation["file_path"]["file_id"].as_str() else {
+                            continue;
+                        };
+                        files.push(FileRef {
+                            file_id: file_id.to_string(),
+                            container_id: None,
+                            filename: annotation["text"]
+                                .as_str()
+                                .and_then(|link| link.rsplit('/').next())
+                                .map(ToOwned::to_owned),
+                        });
+                    }
+                }
+                MessageContent::Unknown => {}
+            }
+        }
+        files
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct List<T> {
+    pub object: String,
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// Confirmation returned when an assistant, thread or message is deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deleted {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Queued,
+    InProgress,
+    RequiresAction,
+    Cancelling,
+    Cancelled,
+    Failed,
+    Completed,
+    Incomplete,
+    Expired,
+}
+
+impl RunStatus {
+    /// `true` once the run will not change anymore.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            RunStatus::Cancelled
+                | RunStatus::Failed
+                | RunStatus::Completed
+                | RunStatus::Incomplete
+                | RunStatus::Expired
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmitToolOutputs {
+    pub tool_calls: Vec<RunToolCall>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequiredAction {
+    pub submit_tool_outputs: SubmitToolOutputs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub thread_id: String,
+    pub assistant_id: String,
+    pub status: RunStatus,
+    #[serde(default)]
+    pub required_action: Option<RequiredAction>,
+    #[serde(default)]
+    pub last_error: Option<RunError>,
+}
+
+impl Run {
+    /// Tool calls the run is waiting for, empty unless the status is `requires_action`.
+    pub fn tool_calls(&self) -> &[RunToolCall] {
+        self.required_action
+            .as_ref()
+            .map(|action| action.submit_tool_outputs.tool_calls.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Polls until the run is finished or waits for tool outputs, for at most `timeout`. Check
+    /// [`Run::status`]: a failed run is returned as is, with the reason in [`Run::last_error`],
+    /// and a run still queued or in progress once `timeout` is up as last retrieved. The run
+    /// itself keeps going.
+    pub async fn wait(
+        self,
+        openai: &OpenAi,
+        backoff: Backoff,
+        timeout: Duration,
+    ) -> Result<Run, ApiRequestError> {
+        let deadline = Instant::now() + timeout;
+        let mut run = self;
+        let mut delays = backoff.delays();
+        while !run.status.is_terminal() && run.status != RunStatus::RequiresAction {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let delay = delays.next().unwrap_or_default().min(deadline - now);
+            tokio::time::sleep(delay).await;
+            run = openai.retrieve_run(&run.thread_id, &run.id).await?;
+        }
+        Ok(run)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutput {
+    pub tool_call_id: String,
+    pub output: String,
+}
+
+/// Text fragment of a `thread.message.delta` event.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MessageDelta {
+    pub id: String,
+    pub delta: MessageDeltaBody,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct MessageDeltaBody {
+    #[serde(default)]
+    pub content: Vec<MessageDeltaContent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageDeltaContent {
+    Text {
+        index: u32,
+        text: TextDelta,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TextDelta {
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+impl MessageDelta {
+    pub fn text(&self) -> String {
+        self.delta
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageDeltaContent::Text { text, .. } => text.value.as_deref(),
+                MessageDeltaContent::Unknown => None,
+            })
+            .collect()
+    }
+}
+
+/// Server-sent event of a streamed run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunStreamEvent {
+    MessageDelta(MessageDelta),
+    MessageCompleted(ThreadMessage),
+    /// Any `thread.run.*` event, carrying the run in its new state.
+    Run(Run),
+    Done,
+    /// Event not modelled above, passed through untouched.
+    Other(SseEvent),
+}
+
+impl RunStreamEvent {
+    fn parse(event: SseEvent) -> Result<Self, ApiRequestError> {
+        let name = event.event.as_deref().unwrap_or_default();
+        Ok(match name {
+            "thread.message.delta" => {
+                RunStreamEvent::MessageDelta(crate::json::from_str(&event.data)?)
+            }
+            "thread.message.completed" => {
+                RunStreamEvent::MessageCompleted(crate::json::from_str(&event.data)?)
+            }
+            "error" => {
+                let error: ErrorResponse = crate::json::from_str(&event.data)?;
+                return Err(ApiError::without_status(error.error));
+            }
+            "done" => RunStreamEvent::Done,
+            name if name.starts_with("thread.run.") && !name.starts_with("thread.run.step") => {
+                RunStreamEvent::Run(crate::json::from_str(&event.data)?)
+            }
+            _ => RunStreamEvent::Other(event),
+        })
+    }
+}
+
+async fn parse<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, ApiRequestError> {
+    if res.status().is_success() {
+        Ok(res.json().await?)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
+
+impl CreateAssistantRequest {
+    pub async fn send(&self) -> Result<Assistant, ApiRequestError> {
+        self.openai.assistants_post(ASSISTANTS_URL, self).await
+    }
+}
+
+impl ModifyAssistantRequest {
+    pub async fn send(&self) -> Result<Assistant, ApiRequestError> {
+        self.openai
+            .assistants_post(&format!("{}/{}", ASSISTANTS_URL, self.assistant_id), self)
+            .await
+    }
+}
+
+impl OpenAi {
+    async fn assistants_post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, ApiRequestError> {
+        let url = format!("{}/{}", self.inner.base_url, path);
+        let res = self
+            .inner
+            .client
+            .post(url)
+            .authorize(self)
+            .await?
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .json(body)
+            .send_observed(self)
+            .await?;
+        parse(res).await
+    }
+
+    async fn assistants_get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, ApiRequestError> {
+        let url = format!("{}/{}", self.inner.base_url, path);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .query(query)
+            .send_observed(self)
+            .await?;
+        parse(res).await
+    }
+
+    async fn assistants_delete(&self, path: &str) -> Result<Deleted, ApiRequestError> {
+        let url = format!("{}/{}", self.inner.base_url, path);
+        let res = self
+            .inner
+            .client
+            .delete(url)
+            .authorize(self)
+            .await?
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .send_observed(self)
+            .await?;
+        parse(res).await
+    }
+
+    async fn assistants_stream(
+        &self,
+        path: &str,
+        mut body: Value,
+    ) -> BoxStream<'static, Result<RunStreamEvent, ApiRequestError>> {
+        body["stream"] = Value::Bool(true);
+        let url = format!("{}/{}", self.inner.base_url, path);
+        let res = match self.inner.client.post(url).authorize(self).await {
+            Ok(request) => {
+                request
+                    .header(BETA_HEADER.0, BETA_HEADER.1)
+                    .json(&body)
+                    .send_streaming(self)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        match res {
+            Ok(res) if res.status().is_success() => sse_events(idle_timeout(
+                res.bytes_stream(),
+                self.inner.timeouts.stream_idle,
+            ))
+            .map(|event| event.and_then(RunStreamEvent::parse))
+            .boxed(),
+            res => error_stream(res).await,
+        }
+    }
+
+    pub fn create_assistant(
+        &self,
+    ) -> CreateAssistantRequestBuilder<create_assistant_request_builder::SetOpenai> {
+        CreateAssistantRequest::builder().openai(self.clone())
+    }
+
+    pub async fn retrieve_assistant(
+        &self,
+        assistant_id: &str,
+    ) -> Result<Assistant, ApiRequestError> {
+        self.assistants_get(&format!("{}/{}", ASSISTANTS_URL, assistant_id), &[])
+            .await
+    }
+
+    pub fn modify_assistant(
+        &self,
+        assistant_id: impl Into<String>,
+    ) -> ModifyAssistantRequestBuilder<
+        modify_assistant_request_builder::SetOpenai<
+            modify_assistant_request_builder::SetAssistantId,
+        >,
+    > {
+        ModifyAssistantRequest::builder()
+            .assistant_id(assistant_id)
+            .openai(self.clone())
+    }
+
+    /// Assistants of the organization, newest first. Pass the last id of a page as `after` to
+    /// get the next one.
+    pub async fn list_assistants(
+        &self,
+        after: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<List<Assistant>, ApiRequestError> {
+        let limit = limit.map(|limit| limit.to_string());
+        let mut query = Vec::new();
+        if let Some(after) = after {
+            query.push(("after", after));
+        }
+        if let Some(limit) = &limit {
+            query.push(("limit", limit.as_str()));
+        }
+        self.assistants_get(ASSISTANTS_URL, &query).await
+    }
+
+    pub async fn delete_assistant(&self, assistant_id: &str) -> Result<Deleted, ApiRequestError> {
+        self.assistants_delete(&format!("{}/{}", ASSISTANTS_URL, assistant_id))
+            .await
+    }
+
+    pub async fn create_thread(&self) -> Result<Thread, ApiRequestError> {
+        self.assistants_post(THREADS_URL, &json!({})).await
+    }
+
+    pub async fn retrieve_thread(&self, thread_id: &str) -> Result<Thread, ApiRequestError> {
+        self.assistants_get(&format!("{}/{}", THREADS_URL, thread_id), &[])
+            .await
+    }
+
+    pub async fn delete_thread(&self, thread_id: &str) -> Result<Deleted, ApiRequestError> {
+        self.assistants_delete(&format!("{}/{}", THREADS_URL, thread_id))
+            .await
+    }
+
+    pub async fn create_message(
+        &self,
+        thread_id: &str,
+        role: MessageRole,
+        content: impl Into<String>,
+    ) -> Result<ThreadMessage, ApiRequestError> {
+        self.assistants_post(
+            &format!("{}/{}/messages", THREADS_URL, thread_id),
+            &json!({"role": role, "content": content.into()}),
+        )
+        .await
+    }
+
+    /// Messages of a thread in chronological order, optionally limited to those created by
+    /// `run_id`.
+    pub async fn list_messages(
+        &self,
+        thread_id: &str,
+        run_id: Option<&str>,
+    ) -> Result<List<ThreadMessage>, ApiRequestError> {
+        let mut query = vec![("order", "asc")];
+        if let Some(run_id) = run_id {
+            query.push(("run_id", run_id));
+        }
+        self.assistants_get(&format!("{}/{}/messages", THREADS_URL, thread_id), &query)
+            .await
+    }
+
+    pub async fn delete_message(
+        &self,
+        thread_id: &str,
+        message_id: &str,
+    ) -> Result<Deleted, ApiRequestError> {
+        self.assistants_delete(&format!(
+            "{}/{}/messages/{}",
+            THREADS_URL, thread_id, message_id
+        ))
+        .await
+    }
+
+    /// Downloads the files code interpreter produced in the messages of `run_id`.
+    pub async fn download_run_files(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+    ) -> Result<Vec<GeneratedFile>, ApiRequestError> {
+        let messages = self.list_messages(thread_id, Some(run_id)).await?;
+        self.download_files(
+            messages
+                .data
+                .iter()
+                .flat_map(ThreadMessage::generated_files),
+        )
+        .await
+    }
+
+    pub async fn create_run(
+        &self,
+        thread_id: &str,
+        assistant_id: &str,
+    ) -> Result<Run, ApiRequestError> {
+        self.assistants_post(
+            &format!("{}/{}/runs", THREADS_URL, thread_id),
+            &json!({"assistant_id": assistant_id}),
+        )
+        .await
+    }
+
+    pub async fn create_run_stream(
+        &self,
+        thread_id: &str,
+        assistant_id: &str,
+    ) -> BoxStream<'static, Result<RunStreamEvent, ApiRequestError>> {
+        self.assistants_stream(
+            &format!("{}/{}/runs", THREADS_URL, thread_id),
+            json!({"assistant_id": assistant_id}),
+        )
+        .await
+    }
+
+    pub async fn retrieve_run(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+    ) -> Result<Run, ApiRequestError> {
+        self.assistants_get(
+            &format!("{}/{}/runs/{}", THREADS_URL, thread_id, run_id),
+            &[],
+        )
+        .await
+    }
+
+    pub async fn cancel_run(&self, thread_id: &str, run_id: &str) -> Result<Run, ApiRequestError> {
+        self.assistants_post(
+            &format!("{}/{}/runs/{}/cancel", THREADS_URL, thread_id, run_id),
+            &json!({}),
+        )
+        .await
+    }
+
+    pub async fn submit_tool_outputs(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        tool_outputs: &[ToolOutput],
+    ) -> Result<Run, ApiRequestError> {
+        self.assistants_post(
+            &format!(
+                "{}/{}/runs/{}/submit_tool_outputs",
+                THREADS_URL, thread_id, run_id
+            ),
+            &json!({"tool_outputs": tool_outputs}),
+        )
+        .await
+    }
+
+    pub async fn submit_tool_outputs_stream(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        tool_outputs: &[ToolOutput],
+    ) -> BoxStream<'static, Result<RunStreamEvent, ApiRequestError>> {
+        self.assistants_stream(
+            &format!(
+                "{}/{}/runs/{}/submit_tool_outputs",
+                THREADS_URL, thread_id, run_id
+            ),
+            json!({"tool_outputs": tool_outputs}),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_stream_event_parsing() {
+        let delta = RunStreamEvent::parse(SseEvent {
+            event: Some("thread.message.delta".into()),
+            data: json!({
+                "id": "msg_1",
+                "object": "thread.message.delta",
+                "delta": {"content": [{"index": 0, "type": "text", "text": {"value": "Hel"}}]}
+            })
+            .to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matches!(&delta, RunStreamEvent::MessageDelta(d) if d.text() == "Hel"));
+
+        let run = RunStreamEvent::parse(SseEvent {
+            event: Some("thread.run.requires_action".into()),
+            data: json!({
+                "id": "run_1",
+                "object": "thread.run",
+                "created_at": 1,
+                "thread_id": "thread_1",
+                "assistant_id": "asst_1",
+                "status": "requires_action",
+                "required_action": {
+                    "type": "submit_tool_outputs",
+                    "submit_tool_outputs": {
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {"name": "get_weather", "arguments": "{}"}
+                        }]
+                    }
+                }
+            })
+            .to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let RunStreamEvent::Run(run) = run else {
+            panic!("expected a run event");
+        };
+        assert_eq!(run.status, RunStatus::RequiresAction);
+        assert_eq!(run.tool_calls()[0].function.name, "get_weather");
+
+        let done = RunStreamEvent::parse(SseEvent {
+            event: Some("done".into()),
+            data: "[DONE]".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(done, RunStreamEvent::Done);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_run_wait() {
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let run = |status: &str| {
+            json!({
+                "id": "run_1",
+                "object": "thread.run",
+                "created_at": 1,
+                "thread_id": "thread_1",
+                "assistant_id": "asst_1",
+                "status": status
+            })
+        };
+        let path = "v1/threads/thread_1/runs/run_1";
+        let mock = MockOpenAi::start().await.unwrap();
+        let openai = mock.client();
+        let backoff = Backoff::builder().initial(Duration::from_millis(5)).build();
+        let queued: Run = serde_json::from_value(run("queued")).unwrap();
+
+        mock.push(path, MockResponse::json(&run("in_progress")))
+            .push(path, MockResponse::json(&run("completed")));
+        let done = queued
+            .clone()
+            .wait(&openai, backoff, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(done.status, RunStatus::Completed);
+        assert_eq!(mock.requests().len(), 2);
+
+        // A run that never finishes is given up on once the timeout is up.
+        for _ in 0..100 {
+            mock.push(path, MockResponse::json(&run("in_progress")));
+        }
+        let start = Instant::now();
+        let pending = queued
+            .wait(&openai, backoff, Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert_eq!(pending.status, RunStatus::InProgress);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_generated_files() {
+        let message: ThreadMessage = serde_json::from_value(json!({
+            "id": "msg_1",
+            "object": "thread.message",
+            "created_at": 1,
+            "thread_id": "thread_1",
+            "role": "assistant",
+            "content": [
+                {"type": "image_file", "image_file": {"file_id": "file-img"}},
+                {"type": "text", "text": {
+                    "value": "Download [data.csv](sandbox:/mnt/data/data.csv)",
+                    "annotations": [{
+                        "type": "file_path",
+                        "text": "sandbox:/mnt/data/data.csv",
+                        "file_path": {"file_id": "file-csv"},
+                        "start_index": 9,
+                        "end_index": 35
+                    }]
+                }}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            message.generated_files(),
+            [
+                FileRef {
+                    file_id: "file-img".to_string(),
+                    container_id: None,
+                    filename: None,
+                },
+                FileRef {
+                    file_id: "file-csv".to_string(),
+                    container_id: None,
+                    filename: Some("data.csv".to_string()),
+                },
+            ]
+        );
+    }
+}
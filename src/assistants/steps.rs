@@ -0,0 +1,243 @@
+//! Run steps: the individual message creations and tool calls a run went through.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{List, RunError, THREADS_URL};
+use crate::{ApiRequestError, OpenAi};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStepStatus {
+    InProgress,
+    Cancelled,
+    Failed,
+    Completed,
+    Expired,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageCreation {
+    pub message_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepDetails {
+    MessageCreation { message_creation: MessageCreation },
+    ToolCalls { tool_calls: Vec<StepToolCall> },
+}
+
+/// A tool call made during a run step, with its output once the step is completed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepToolCall {
+    Function {
+        id: String,
+        function: StepFunctionCall,
+    },
+    CodeInterpreter {
+        id: String,
+        code_interpreter: CodeInterpreterCall,
+    },
+    FileSearch {
+        id: String,
+        #[serde(default)]
+        file_search: FileSearchCall,
+    },
+}
+
+impl StepToolCall {
+    pub fn id(&self) -> &str {
+        match self {
+            StepToolCall::Function { id, .. }
+            | StepToolCall::CodeInterpreter { id, .. }
+            | StepToolCall::FileSearch { id, .. } => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepFunctionCall {
+    pub name: String,
+    pub arguments: String,
+    /// The tool output submitted for the call, `None` until then.
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeInterpreterCall {
+    pub input: String,
+    #[serde(default)]
+    pub outputs: Vec<CodeInterpreterOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CodeInterpreterOutput {
+    Logs { logs: String },
+    Image { image: CodeInterpreterImage },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeInterpreterImage {
+    pub file_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileSearchCall {
+    /// Only returned when the step was retrieved with the results included.
+    #[serde(default)]
+    pub results: Vec<FileSearchResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSearchResult {
+    pub file_id: String,
+    pub file_name: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunStep {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub run_id: String,
+    pub assistant_id: String,
+    pub thread_id: String,
+    pub status: RunStepStatus,
+    pub step_details: StepDetails,
+    #[serde(default)]
+    pub last_error: Option<RunError>,
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+    #[serde(default)]
+    pub usage: Option<Value>,
+}
+
+impl OpenAi {
+    /// Steps of a run in chronological order.
+    pub async fn list_run_steps(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+    ) -> Result<List<RunStep>, ApiRequestError> {
+        self.assistants_get(
+            &format!("{}/{}/runs/{}/steps", THREADS_URL, thread_id, run_id),
+            &[("order", "asc")],
+        )
+        .await
+    }
+
+    pub async fn retrieve_run_step(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        step_id: &str,
+    ) -> Result<RunStep, ApiRequestError> {
+        self.assistants_get(
+            &format!(
+                "{}/{}/runs/{}/steps/{}",
+                THREADS_URL, thread_id, run_id, step_id
+            ),
+            &[],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_run_step_deserialization() {
+        let step: RunStep = serde_json::from_value(json!({
+            "id": "step_1",
+            "object": "thread.run.step",
+            "created_at": 1,
+            "run_id": "run_1",
+            "assistant_id": "asst_1",
+            "thread_id": "thread_1",
+            "type": "message_creation",
+            "status": "completed",
+            "step_details": {
+                "type": "message_creation",
+                "message_creation": {"message_id": "msg_1"}
+            },
+            "last_error": null,
+            "completed_at": 2
+        }))
+        .unwrap();
+        assert_eq!(step.status, RunStepStatus::Completed);
+        assert!(matches!(
+            step.step_details,
+            StepDetails::MessageCreation { message_creation } if message_creation.message_id == "msg_1"
+        ));
+    }
+
+    #[test]
+    fn test_tool_call_step_details() {
+        let details: StepDetails = serde_json::from_value(json!({
+            "type": "tool_calls",
+            "tool_calls": [
+                {
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{}", "output": "sunny"}
+                },
+                {
+                    "id": "call_2",
+                    "type": "code_interpreter",
+                    "code_interpreter": {
+                        "input": "print(2 + 2)",
+                        "outputs": [
+                            {"type": "logs", "logs": "4"},
+                            {"type": "image", "image": {"file_id": "file-plot"}}
+                        ]
+                    }
+                },
+                {"id": "call_3", "type": "file_search", "file_search": {}}
+            ]
+        }))
+        .unwrap();
+        let StepDetails::ToolCalls { tool_calls } = details else {
+            panic!("expected tool calls");
+        };
+        assert_eq!(
+            tool_calls.iter().map(StepToolCall::id).collect::<Vec<_>>(),
+            ["call_1", "call_2", "call_3"]
+        );
+        assert!(matches!(
+            &tool_calls[0],
+            StepToolCall::Function { function, .. } if function.output.as_deref() == Some("sunny")
+        ));
+        let StepToolCall::CodeInterpreter {
+            code_interpreter, ..
+        } = &tool_calls[1]
+        else {
+            panic!("expected a code interpreter call");
+        };
+        assert_eq!(
+            code_interpreter.outputs,
+            [
+                CodeInterpreterOutput::Logs {
+                    logs: "4".to_string()
+                },
+                CodeInterpreterOutput::Image {
+                    image: CodeInterpreterImage {
+                        file_id: "file-plot".to_string()
+                    }
+                },
+            ]
+        );
+        assert!(matches!(
+            &tool_calls[2],
+            StepToolCall::FileSearch { file_search, .. } if file_search.results.is_empty()
+        ));
+    }
+}
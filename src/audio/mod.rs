@@ -1,2 +1,6 @@
 pub mod speech;
+pub mod subtitles;
 pub mod transcription;
+pub mod translate;
+pub mod translation;
+pub mod voice;
@@ -0,0 +1,313 @@
+//! SRT and WebVTT generation from timestamped transcription segments.
+//!
+//! Raw segments are rarely fit for broadcast: some are a few words long, others span several
+//! sentences. [`Subtitles::from_cues`] merges short neighbours and splits long cues on word
+//! boundaries so that every cue fits `max_lines` lines of `max_line_length` characters and lasts
+//! at most `max_duration`.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi, audio: Vec<u8>) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::audio::{
+//!     subtitles::{SubtitleOptions, Subtitles},
+//!     transcription::AudioFormat,
+//! };
+//!
+//! let transcription = openai
+//!     .transcription()
+//!     .model("whisper-1")
+//!     .audio(audio)
+//!     .format(AudioFormat::Mp3)
+//!     .build()
+//!     .send_verbose()
+//!     .await?;
+//! let srt = Subtitles::from_segments(&transcription.segments, &SubtitleOptions::default())
+//!     .shift(2.5)
+//!     .to_srt();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use bon::Builder;
+
+use super::transcription::TranscriptionSegment;
+
+/// A single subtitle, times in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub speaker: Option<String>,
+}
+
+impl Cue {
+    pub fn new(start: f64, end: f64, text: impl Into<String>) -> Self {
+        Cue {
+            start,
+            end,
+            text: text.into(),
+            speaker: None,
+        }
+    }
+
+    pub fn with_speaker(mut self, speaker: impl Into<String>) -> Self {
+        self.speaker = Some(speaker.into());
+        self
+    }
+}
+
+impl From<&TranscriptionSegment> for Cue {
+    fn from(segment: &TranscriptionSegment) -> Self {
+        Cue::new(segment.start, segment.end, segment.text.trim())
+    }
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct SubtitleOptions {
+    #[builder(default = 42)]
+    pub max_line_length: usize,
+    #[builder(default = 2)]
+    pub max_lines: usize,
+    #[builder(default = Duration::from_secs(7))]
+    pub max_duration: Duration,
+    /// Neighbouring cues further apart than this are never merged.
+    #[builder(default = Duration::from_millis(500))]
+    pub max_merge_gap: Duration,
+    /// Prefix cues with `Speaker: ` whenever the speaker changes.
+    #[builder(default)]
+    pub speaker_labels: bool,
+}
+
+impl Default for SubtitleOptions {
+    fn default() -> Self {
+        SubtitleOptions::builder().build()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subtitles {
+    pub cues: Vec<Cue>,
+}
+
+/// Words of `text` grouped into lines no longer than `max` characters.
+fn wrap(text: &str, max: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= max => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+fn timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+impl Subtitles {
+    pub fn from_segments(segments: &[TranscriptionSegment], options: &SubtitleOptions) -> Self {
+        Self::from_cues(segments.iter().map(Cue::from), options)
+    }
+
+    /// Merges and splits `cues` to fit the limits in `options`.
+    pub fn from_cues(cues: impl IntoIterator<Item = Cue>, options: &SubtitleOptions) -> Self {
+        let max_chars = options.max_line_length * options.max_lines.max(1);
+        let max_duration = options.max_duration.as_secs_f64();
+        let fits = |text: &str| wrap(text, options.max_line_length).len() <= options.max_lines;
+
+        let mut merged: Vec<Cue> = Vec::new();
+        for cue in cues.into_iter().filter(|cue| !cue.text.trim().is_empty()) {
+            if let Some(last) = merged.last_mut() {
+                let text = format!("{} {}", last.text, cue.text.trim());
+                if last.speaker == cue.speaker
+                    && cue.start - last.end <= options.max_merge_gap.as_secs_f64()
+                    && cue.end - last.start <= max_duration
+                    && fits(&text)
+                {
+                    last.text = text;
+                    last.end = cue.end;
+                    continue;
+                }
+            }
+            merged.push(cue);
+        }
+
+        let mut out = Vec::new();
+        for cue in merged {
+            let duration = (cue.end - cue.start).max(0.0);
+            if fits(&cue.text) && duration <= max_duration {
+                out.push(cue);
+                continue;
+            }
+            // Enough chunks to satisfy both the length and the duration limit.
+            let by_duration = (duration / max_duration).ceil() as usize;
+            let words: Vec<&str> = cue.text.split_whitespace().collect();
+            let total_chars = cue.text.chars().count().max(1);
+            let target = (total_chars / by_duration.max(1)).clamp(1, max_chars);
+            let mut chunks: Vec<String> = Vec::new();
+            for word in words {
+                match chunks.last_mut() {
+                    Some(chunk)
+                        if chunk.chars().count() + 1 + word.chars().count() <= target
+                            && fits(&format!("{} {}", chunk, word)) =>
+                    {
+                        chunk.push(' ');
+                        chunk.push_str(word);
+                    }
+                    _ => chunks.push(word.to_string()),
+                }
+            }
+            // Time is shared in proportion to the number of characters in each chunk.
+            let chunk_chars: usize = chunks.iter().map(|c| c.chars().count()).sum();
+            let mut start = cue.start;
+            for chunk in chunks {
+                let share = chunk.chars().count() as f64 / chunk_chars.max(1) as f64;
+                let end = (start + duration * share).min(cue.end);
+                out.push(Cue {
+                    start,
+                    end,
+                    text: chunk,
+                    speaker: cue.speaker.clone(),
+                });
+                start = end;
+            }
+            if let Some(last) = out.last_mut() {
+                last.end = cue.end;
+            }
+        }
+
+        if options.speaker_labels {
+            let mut previous: Option<String> = None;
+            for cue in &mut out {
+                if let Some(speaker) = cue
+                    .speaker
+                    .as_ref()
+                    .filter(|s| previous.as_ref() != Some(*s))
+                {
+                    cue.text = format!("{}: {}", speaker, cue.text);
+                }
+                previous = cue.speaker.clone();
+            }
+        }
+
+        for cue in &mut out {
+            cue.text = wrap(&cue.text, options.max_line_length).join("\n");
+        }
+        Subtitles { cues: out }
+    }
+
+    /// Moves every cue by `seconds`, dropping those that end up entirely before zero.
+    pub fn shift(mut self, seconds: f64) -> Self {
+        self.cues.retain_mut(|cue| {
+            cue.start = (cue.start + seconds).max(0.0);
+            cue.end += seconds;
+            cue.end > 0.0
+        });
+        self
+    }
+
+    pub fn to_srt(&self) -> String {
+        self.cues
+            .iter()
+            .enumerate()
+            .map(|(i, cue)| {
+                format!(
+                    "{}\n{} --> {}\n{}\n",
+                    i + 1,
+                    timestamp(cue.start, ','),
+                    timestamp(cue.end, ','),
+                    cue.text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_vtt(&self) -> String {
+        let mut out = String::from("WEBVTT\n");
+        for cue in &self.cues {
+            out.push_str(&format!(
+                "\n{} --> {}\n{}\n",
+                timestamp(cue.start, '.'),
+                timestamp(cue.end, '.'),
+                cue.text
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_split_and_format() {
+        let options = SubtitleOptions::builder()
+            .max_line_length(20)
+            .max_lines(1)
+            .max_duration(Duration::from_secs(4))
+            .build();
+        let subtitles = Subtitles::from_cues(
+            [
+                Cue::new(0.0, 0.8, "Hello"),
+                Cue::new(0.9, 1.5, "there."),
+                Cue::new(3.0, 9.0, "This sentence is far too long for one line"),
+            ],
+            &options,
+        );
+        let texts: Vec<_> = subtitles.cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Hello there.",
+                "This sentence is far",
+                "too long for one",
+                "line"
+            ]
+        );
+        assert_eq!(subtitles.cues[1].end, 6.0);
+        assert_eq!(subtitles.cues[3].end, 9.0);
+        assert!(subtitles
+            .cues
+            .iter()
+            .all(|c| c.end - c.start <= 4.0 && c.text.len() <= 20));
+
+        let srt = subtitles.clone().shift(-1.0).to_srt();
+        assert!(
+            srt.starts_with("1\n00:00:00,000 --> 00:00:00,500\nHello there.\n\n2\n00:00:02,000")
+        );
+        assert!(subtitles
+            .to_vtt()
+            .starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:01.500\nHello there.\n"));
+    }
+
+    #[test]
+    fn test_speaker_labels() {
+        let options = SubtitleOptions::builder().speaker_labels(true).build();
+        let subtitles = Subtitles::from_cues(
+            [
+                Cue::new(0.0, 1.0, "Hi.").with_speaker("Ann"),
+                Cue::new(1.2, 2.0, "Hello.").with_speaker("Bob"),
+                Cue::new(2.1, 3.0, "How are you?").with_speaker("Bob"),
+            ],
+            &options,
+        );
+        let texts: Vec<_> = subtitles.cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["Ann: Hi.", "Bob: Hello. How are you?"]);
+    }
+}
@@ -1,274 +1,307 @@
-// use bon::Builder;
-// use reqwest::multipart;
-// use serde::{de::DeserializeOwned, Deserialize};
-// use thiserror::Error;
+use std::path::Path;
 
-// use crate::{ApiRequestError, OpenAi, ErrorResponse, BASE_URL};
+use bon::Builder;
+use reqwest::multipart;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-// const API_URL: &str = "v1/audio/transcriptions";
+use super::translate::TranslatedTranscription;
+use crate::{
+    auth::Authorize, require_client, retry::SendRetrying, ApiRequest, ApiRequestError,
+    ApiRequestWithClient, OpenAi,
+};
 
-// #[derive(Debug)]
-// pub enum AudioFormat {
-//     Mp3,
-//     Mp4,
-//     Flac,
-//     Mpeg,
-//     Mpga,
-//     M4a,
-//     Ogg,
-//     Wav,
-//     Webm,
-// }
+const API_URL: &str = "v1/audio/transcriptions";
 
-// impl AudioFormat {
-//     pub fn to_mime(&self) -> &'static str {
-//         match self {
-//             AudioFormat::Mp3 => "audio/mpeg",
-//             AudioFormat::Mp4 => "audio/mp4",
-//             AudioFormat::Flac => "audio/flac",
-//             AudioFormat::Mpeg => "audio/mpeg",
-//             AudioFormat::Mpga => "audio/mpeg",
-//             AudioFormat::M4a => "audio/mp4",
-//             AudioFormat::Ogg => "audio/ogg",
-//             AudioFormat::Wav => "audio/wav",
-//             AudioFormat::Webm => "audio/webm",
-//         }
-//     }
-//     pub fn to_extension(&self) -> &'static str {
-//         match self {
-//             AudioFormat::Mp3 => "mp3",
-//             AudioFormat::Mp4 => "mp4",
-//             AudioFormat::Flac => "flac",
-//             AudioFormat::Mpeg => "mpeg",
-//             AudioFormat::Mpga => "mpga",
-//             AudioFormat::M4a => "m4a",
-//             AudioFormat::Ogg => "ogg",
-//             AudioFormat::Wav => "wav",
-//             AudioFormat::Webm => "webm",
-//         }
-//     }
-//     pub fn from_extension(extension: &str) -> Option<Self> {
-//         match extension {
-//             "mp3" => Some(AudioFormat::Mp3),
-//             "mp4" => Some(AudioFormat::Mp4),
-//             "flac" => Some(AudioFormat::Flac),
-//             "mpeg" => Some(AudioFormat::Mpeg),
-//             "mpga" => Some(AudioFormat::Mpga),
-//             "m4a" => Some(AudioFormat::M4a),
-//             "ogg" => Some(AudioFormat::Ogg),
-//             "wav" => Some(AudioFormat::Wav),
-//             "webm" => Some(AudioFormat::Webm),
-//             _ => None,
-//         }
-//     }
-// }
+/// Model used by [`OpenAi::quick_transcribe`].
+pub const QUICK_TRANSCRIBE_MODEL: &str = "whisper-1";
 
-// #[derive(Debug)]
-// pub enum ResponseFormat {
-//     Json,
-//     Text,
-//     Srt,
-//     VerboseJson,
-//     Vtt,
-// }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    Mp4,
+    Flac,
+    Mpeg,
+    Mpga,
+    M4a,
+    Ogg,
+    Wav,
+    Webm,
+}
 
-// #[derive(Debug, Default)]
-// pub struct TranscribeRequestBuilder {
-//     pub(crate) audio: Option<Vec<u8>>,
-//     pub(crate) model: Option<String>,
-//     pub(crate) language: Option<String>,
-//     pub(crate) prompt: Option<String>,
-//     pub(crate) format: Option<AudioFormat>,
-//     pub(crate) response_format: Option<ResponseFormat>,
-//     pub(crate) temperature: Option<f64>,
-//     pub(crate) openai: Option<OpenAi>,
-// }
+impl AudioFormat {
+    pub fn to_mime(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Mp4 => "audio/mp4",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Mpeg => "audio/mpeg",
+            AudioFormat::Mpga => "audio/mpeg",
+            AudioFormat::M4a => "audio/mp4",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Webm => "audio/webm",
+        }
+    }
+    pub fn to_extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Mp4 => "mp4",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Mpeg => "mpeg",
+            AudioFormat::Mpga => "mpga",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Webm => "webm",
+        }
+    }
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "mp3" => Some(AudioFormat::Mp3),
+            "mp4" => Some(AudioFormat::Mp4),
+            "flac" => Some(AudioFormat::Flac),
+            "mpeg" => Some(AudioFormat::Mpeg),
+            "mpga" => Some(AudioFormat::Mpga),
+            "m4a" => Some(AudioFormat::M4a),
+            "ogg" => Some(AudioFormat::Ogg),
+            "wav" => Some(AudioFormat::Wav),
+            "webm" => Some(AudioFormat::Webm),
+            _ => None,
+        }
+    }
+}
 
-// #[derive(Debug, Error)]
-// pub enum TranscibeRequestBuilderError {
-//     #[error("File not set")]
-//     FileNotSet,
-//     #[error("Model not set")]
-//     ModelNotSet,
-//     #[error("Client not set")]
-//     ClientNotSet,
-//     #[error("Format not set")]
-//     FormatNotSet,
-// }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    VerboseJson,
+    Vtt,
+}
 
-// #[derive(Debug)]
-// pub enum Audio {
-//     Bytes(Vec<u8>),
-//     File(String),
-// }
+impl ResponseFormat {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json => "json",
+            ResponseFormat::Text => "text",
+            ResponseFormat::Srt => "srt",
+            ResponseFormat::VerboseJson => "verbose_json",
+            ResponseFormat::Vtt => "vtt",
+        }
+    }
+}
 
-// impl From<Vec<u8>> for Audio {
-//     fn from(bytes: Vec<u8>) -> Self {
-//         Audio::Bytes(bytes)
-//     }
-// }
+#[derive(Debug, Clone, Builder)]
+pub struct TranscriptionRequest {
+    audio: Vec<u8>,
+    format: AudioFormat,
+    #[builder(into)]
+    model: String,
+    /// ISO-639-1 code of the spoken language.
+    #[builder(into)]
+    language: Option<String>,
+    #[builder(into)]
+    prompt: Option<String>,
+    temperature: Option<f64>,
+    openai: Option<OpenAi>,
+}
 
-// impl From<String> for Audio {
-//     fn from(file: String) -> Self {
-//         Audio::File(file)
-//     }
-// }
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcription {
+    pub text: String,
+}
 
-// #[derive(Debug, Builder)]
-// pub struct TranscribeRequest {
-//     audio: Vec<u8>,
-//     // #[builder(into)]
-//     // model: String,
-//     // language: Option<String>,
-//     // prompt: Option<String>,
-//     // format: AudioFormat,
-//     // response_format: Option<ResponseFormat>,
-//     // temperature: Option<f64>,
-//     // openai: OpenAi,
-// }
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionSegment {
+    pub id: u32,
+    /// Start time in seconds.
+    pub start: f64,
+    /// End time in seconds.
+    pub end: f64,
+    pub text: String,
+    #[serde(default)]
+    pub avg_logprob: Option<f64>,
+    #[serde(default)]
+    pub no_speech_prob: Option<f64>,
+}
 
-// // impl TranscribeRequestBuilder {
-// //     pub fn audio<T: Into<Audio>>(mut self, audio: T) -> Self {
-// //         match audio.into() {
-// //             Audio::Bytes(bytes) => self.audio = Some(bytes),
-// //             Audio::File(file) => {
-// //                 let bytes = std::fs::read(&file).unwrap();
-// //                 let format = AudioFormat::from_extension(file.split('.').last().unwrap()).unwrap();
-// //                 self.format = Some(format);
-// //                 self.audio = Some(bytes);
-// //             }
-// //         }
-// //         self
-// //     }
-// //     pub fn format(mut self, format: AudioFormat) -> Self {
-// //         self.format = Some(format);
-// //         self
-// //     }
-// //     pub fn model(mut self, model: &str) -> Self {
-// //         self.model = Some(model.to_string());
-// //         self
-// //     }
-// //     pub fn language(mut self, language: &str) -> Self {
-// //         self.language = Some(language.to_string());
-// //         self
-// //     }
-// //     pub fn prompt(mut self, prompt: &str) -> Self {
-// //         self.prompt = Some(prompt.to_string());
-// //         self
-// //     }
-// //     pub fn response_format(mut self, response_format: ResponseFormat) -> Self {
-// //         self.response_format = Some(response_format);
-// //         self
-// //     }
-// //     pub fn temperature(mut self, temperature: f64) -> Self {
-// //         self.temperature = Some(temperature);
-// //         self
-// //     }
-// //     pub fn openai(mut self, client: impl Into<OpenAi>) -> Self {
-// //         self.openai = Some(client.into());
-// //         self
-// //     }
-// //     pub fn build(self) -> Result<TranscribeRequest, TranscibeRequestBuilderError> {
-// //         let Some(audio) = self.audio else {
-// //             return Err(TranscibeRequestBuilderError::FileNotSet);
-// //         };
-// //         let Some(model) = self.model else {
-// //             return Err(TranscibeRequestBuilderError::ModelNotSet);
-// //         };
-// //         let Some(format) = self.format else {
-// //             return Err(TranscibeRequestBuilderError::FormatNotSet);
-// //         };
-// //         let Some(openai) = self.openai else {
-// //             return Err(TranscibeRequestBuilderError::ClientNotSet);
-// //         };
-// //         Ok(TranscribeRequest {
-// //             audio,
-// //             model,
-// //             language: self.language,
-// //             prompt: self.prompt,
-// //             format,
-// //             response_format: self.response_format,
-// //             temperature: self.temperature,
-// //             openai,
-// //         })
-// //     }
-// // }
+/// `verbose_json` output, available for `whisper-1`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerboseTranscription {
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub duration: Option<f64>,
+    pub text: String,
+    #[serde(default)]
+    pub segments: Vec<TranscriptionSegment>,
+}
 
-// // #[derive(Debug, Deserialize)]
-// // pub struct TranscribeJsonResponse {
-// //     pub text: String,
-// // }
+/// The audio as the `file` part of a multipart form.
+pub(super) fn audio_part(
+    audio: &[u8],
+    format: AudioFormat,
+) -> Result<multipart::Part, ApiRequestError> {
+    Ok(multipart::Part::bytes(audio.to_vec())
+        .file_name(format!("audio.{}", format.to_extension()))
+        .mime_str(format.to_mime())?)
+}
 
-// // impl TranscribeRequest {
-// //     pub async fn send<O: DeserializeOwned>(&self) -> Result<O, ApiRequestError> {
-// //         let url = format!("{}/{}", BASE_URL, API_URL);
-// //         let file = multipart::Part::bytes(self.audio.to_owned())
-// //             .file_name(format!("audio.{}", self.format.to_extension()))
-// //             .mime_str(self.format.to_mime())?;
-// //         let mut form = multipart::Form::new()
-// //             .part("file", file)
-// //             .text("model", self.model.clone());
-// //         if let Some(language) = &self.language {
-// //             form = form.text("language", language.to_owned());
-// //         }
-// //         if let Some(prompt) = &self.prompt {
-// //             form = form.text("prompt", prompt.to_owned());
-// //         }
-// //         if let Some(response_format) = &self.response_format {
-// //             form = form.text("response_format", format!("{:?}", response_format));
-// //         }
-// //         if let Some(temperature) = self.temperature {
-// //             form = form.text("temperature", temperature.to_string());
-// //         }
-// //         let req = self
-// //             .openai
-// //             .client
-// //             .post(&url)
-// //             .bearer_auth(&self.openai.api_key)
-// //             .multipart(form);
-// //         let res = req.send().await?;
-// //         if res.status().is_success() {
-// //             let data: O = res.json().await?;
-// //             Ok(data)
-// //         } else {
-// //             let error_response: ErrorResponse = res.json().await?;
-// //             Err(ApiRequestError::InvalidRequestError {
-// //                 message: error_response.error.message,
-// //                 param: error_response.error.param,
-// //                 code: error_response.error.code,
-// //             })
-// //         }
-// //     }
-// // }
+/// Posts a multipart `form` for `model` to `path`, returning the response only if it
+/// succeeded.
+pub(super) async fn post_form(
+    openai: &OpenAi,
+    path: &str,
+    model: &str,
+    form: multipart::Form,
+) -> Result<reqwest::Response, ApiRequestError> {
+    let url = openai.inner.model_url(path, model);
+    let res = openai
+        .inner
+        .client
+        .post(url)
+        .authorize(openai)
+        .await?
+        .multipart(form)
+        .send_retrying(openai)
+        .await?;
+    if res.status().is_success() {
+        Ok(res)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
 
-// // #[cfg(test)]
-// // mod tests {
-// //     use super::*;
-// //     use crate::OpenAiBuilder;
+impl TranscriptionRequest {
+    async fn post(
+        &self,
+        openai: &OpenAi,
+        response_format: ResponseFormat,
+    ) -> Result<reqwest::Response, ApiRequestError> {
+        let mut form = multipart::Form::new()
+            .part("file", audio_part(&self.audio, self.format)?)
+            .text("model", self.model.clone())
+            .text("response_format", response_format.as_str());
+        if let Some(language) = &self.language {
+            form = form.text("language", language.to_owned());
+        }
+        if let Some(prompt) = &self.prompt {
+            form = form.text("prompt", prompt.to_owned());
+        }
+        if let Some(temperature) = self.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        if response_format == ResponseFormat::VerboseJson {
+            form = form.text("timestamp_granularities[]", "segment");
+        }
+        post_form(openai, API_URL, &self.model, form).await
+    }
 
-// //     #[tokio::test]
-// //     async fn transcribe_test() {
-// //         let api_key = std::env::var("OPENAI_API_KEY").unwrap();
-// //         let client = reqwest::Client::new();
-// //         let openai = OpenAiBuilder::default()
-// //             .api_key(api_key)
-// //             .client(&client)
-// //             .build()
-// //             .unwrap();
-// //         let audio = std::fs::read(
-// //             "/home/ribelo/downloads/1 Comparison Of Vernacular And Refined Speech.mp3",
-// //         )
-// //         .unwrap();
-// //         let res: TranscribeJsonResponse = TranscribeRequestBuilder::default()
-// //             .audio(audio)
-// //             .format(AudioFormat::Mp3)
-// //             .model("whisper-1")
-// //             .openai(openai)
-// //             .build()
-// //             .unwrap()
-// //             .send()
-// //             .await
-// //             .unwrap();
-// //         dbg!(res);
-// //     }
-// // }
+    async fn send_json<O: DeserializeOwned>(
+        &self,
+        openai: &OpenAi,
+        response_format: ResponseFormat,
+    ) -> Result<O, ApiRequestError> {
+        Ok(self.post(openai, response_format).await?.json().await?)
+    }
+
+    pub async fn send(&self) -> Result<Transcription, ApiRequestError> {
+        self.send_with(require_client(&self.openai)?).await
+    }
+
+    /// Transcription with per-segment timestamps.
+    pub async fn send_verbose(&self) -> Result<VerboseTranscription, ApiRequestError> {
+        self.send_json(require_client(&self.openai)?, ResponseFormat::VerboseJson)
+            .await
+    }
+
+    /// Transcription rendered by the API as `text`, `srt` or `vtt`.
+    pub async fn send_formatted(
+        &self,
+        response_format: ResponseFormat,
+    ) -> Result<String, ApiRequestError> {
+        Ok(self
+            .post(require_client(&self.openai)?, response_format)
+            .await?
+            .text()
+            .await?)
+    }
+
+    /// Transcribes the audio and translates it into `target_language` with `chat_model`.
+    ///
+    /// Segment timestamps are kept for `whisper-1`; the `gpt-4o-*-transcribe` models only
+    /// return plain text, so their result has no segments.
+    pub async fn send_translated(
+        &self,
+        chat_model: impl Into<String>,
+        target_language: impl Into<String>,
+    ) -> Result<TranslatedTranscription, ApiRequestError> {
+        let source = if self.model.starts_with("whisper") {
+            self.send_verbose().await?
+        } else {
+            VerboseTranscription {
+                language: self.language.clone(),
+                duration: None,
+                text: self.send().await?.text,
+                segments: Vec::new(),
+            }
+        };
+        source
+            .translate(require_client(&self.openai)?, chat_model, target_language)
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiRequest for TranscriptionRequest {
+    type Response = Transcription;
+
+    async fn send_with(&self, openai: &OpenAi) -> Result<Transcription, ApiRequestError> {
+        self.send_json(openai, ResponseFormat::Json).await
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiRequestWithClient for TranscriptionRequest {
+    async fn send(&self) -> Result<Transcription, ApiRequestError> {
+        self.send_with(require_client(&self.openai)?).await
+    }
+}
+
+impl OpenAi {
+    pub fn transcription(
+        &self,
+    ) -> TranscriptionRequestBuilder<transcription_request_builder::SetOpenai> {
+        TranscriptionRequest::builder().openai(self.clone())
+    }
+
+    /// Transcribes the audio file at `path` with [`QUICK_TRANSCRIBE_MODEL`], guessing the format
+    /// from the file extension.
+    pub async fn quick_transcribe(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<String, ApiRequestError> {
+        let path = path.as_ref();
+        let format = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| AudioFormat::from_extension(&extension.to_lowercase()))
+            .ok_or_else(|| ApiRequestError::InvalidRequestError {
+                message: format!("Unsupported audio file: {}", path.display()),
+                param: Some("file".to_string()),
+                code: None,
+            })?;
+        let audio = tokio::fs::read(path).await?;
+        let res = self
+            .transcription()
+            .audio(audio)
+            .format(format)
+            .model(QUICK_TRANSCRIBE_MODEL)
+            .build()
+            .send()
+            .await?;
+        Ok(res.text)
+    }
+}
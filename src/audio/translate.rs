@@ -0,0 +1,215 @@
+//! Transcription followed by a chat-model translation into any language.
+//!
+//! The native translations endpoint only produces English. Here the transcript is translated
+//! segment by segment, so the timestamps of the source transcription carry over unchanged and the
+//! result can be fed straight into [`Subtitles`](super::subtitles::Subtitles).
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi, audio: Vec<u8>) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::audio::transcription::AudioFormat;
+//!
+//! let translated = openai
+//!     .transcription()
+//!     .model("whisper-1")
+//!     .audio(audio)
+//!     .format(AudioFormat::Wav)
+//!     .build()
+//!     .send_translated("gpt-4o-mini", "Polish")
+//!     .await?;
+//! for segment in &translated.segments {
+//!     println!("[{:.1}s] {}", segment.start, segment.text);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::transcription::{TranscriptionSegment, VerboseTranscription};
+use crate::{
+    chat::{
+        message::{Message, Messages},
+        Usage,
+    },
+    pipeline::Pipeline,
+    ApiRequestError, OpenAi,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslatedTranscription {
+    /// Language detected by the transcription model, if reported.
+    pub source_language: Option<String>,
+    pub target_language: String,
+    pub text: String,
+    /// Source segments with translated text; empty when the transcription model has no
+    /// timestamps.
+    pub segments: Vec<TranscriptionSegment>,
+    /// Tokens spent on the translation.
+    pub usage: Usage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SegmentText {
+    id: u32,
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SegmentTexts {
+    segments: Vec<SegmentText>,
+}
+
+fn source_texts(source: &VerboseTranscription) -> SegmentTexts {
+    let segments = if source.segments.is_empty() {
+        vec![SegmentText {
+            id: 0,
+            text: source.text.clone(),
+        }]
+    } else {
+        source
+            .segments
+            .iter()
+            .map(|segment| SegmentText {
+                id: segment.id,
+                text: segment.text.trim().to_string(),
+            })
+            .collect()
+    };
+    SegmentTexts { segments }
+}
+
+fn translation_messages(
+    source: &VerboseTranscription,
+    target_language: &str,
+) -> Result<Messages, ApiRequestError> {
+    Ok(Messages(vec![
+        Message::system(format!(
+            "Translate each segment of the transcript into {}. Reply only with JSON of the form \
+             {{\"segments\": [{{\"id\": number, \"text\": string}}]}}, keeping every id and \
+             translating each segment on its own.",
+            target_language
+        )),
+        Message::user(serde_json::to_string(&source_texts(source))?),
+    ]))
+}
+
+/// Puts the translated texts back onto the source segments, matching them by id.
+fn apply_translation(
+    source: &VerboseTranscription,
+    reply: SegmentTexts,
+) -> Result<(String, Vec<TranscriptionSegment>), ApiRequestError> {
+    let mut texts: HashMap<u32, String> = reply
+        .segments
+        .into_iter()
+        .map(|segment| (segment.id, segment.text.trim().to_string()))
+        .collect();
+    let mut take = |id: u32| {
+        texts
+            .remove(&id)
+            .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+                status: None,
+                response: format!("translation is missing segment {}", id),
+            })
+    };
+
+    if source.segments.is_empty() {
+        return Ok((take(0)?, Vec::new()));
+    }
+    let segments = source
+        .segments
+        .iter()
+        .map(|segment| {
+            Ok(TranscriptionSegment {
+                text: take(segment.id)?,
+                ..segment.clone()
+            })
+        })
+        .collect::<Result<Vec<_>, ApiRequestError>>()?;
+    let text = segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok((text, segments))
+}
+
+impl VerboseTranscription {
+    /// Translates the transcript with `chat_model`, keeping segment timestamps.
+    pub async fn translate(
+        &self,
+        openai: &OpenAi,
+        chat_model: impl Into<String>,
+        target_language: impl Into<String>,
+    ) -> Result<TranslatedTranscription, ApiRequestError> {
+        let target_language = target_language.into();
+        let messages = translation_messages(self, &target_language)?;
+        let output = Pipeline::chat(openai, chat_model, messages)
+            .then_parse::<SegmentTexts>()
+            .run()
+            .await?;
+        let usage = output.total_usage();
+        let (text, segments) = apply_translation(self, output.value)?;
+        Ok(TranslatedTranscription {
+            source_language: self.language.clone(),
+            target_language,
+            text,
+            segments,
+            usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(id: u32, start: f64, end: f64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            id,
+            start,
+            end,
+            text: text.to_string(),
+            avg_logprob: None,
+            no_speech_prob: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_translation_keeps_timestamps() {
+        let source = VerboseTranscription {
+            language: Some("english".to_string()),
+            duration: Some(3.0),
+            text: "Hello. Good bye.".to_string(),
+            segments: vec![
+                segment(0, 0.0, 1.2, " Hello."),
+                segment(1, 1.5, 3.0, " Good bye."),
+            ],
+        };
+        let messages = translation_messages(&source, "German").unwrap();
+        assert_eq!(
+            messages[1].content(),
+            Some(r#"{"segments":[{"id":0,"text":"Hello."},{"id":1,"text":"Good bye."}]}"#)
+        );
+
+        let reply: SegmentTexts = serde_json::from_str(
+            r#"{"segments": [{"id": 1, "text": "Auf Wiedersehen."}, {"id": 0, "text": "Hallo."}]}"#,
+        )
+        .unwrap();
+        let (text, segments) = apply_translation(&source, reply).unwrap();
+        assert_eq!(text, "Hallo. Auf Wiedersehen.");
+        assert_eq!(segments[1], segment(1, 1.5, 3.0, "Auf Wiedersehen."));
+
+        let reply = SegmentTexts {
+            segments: vec![SegmentText {
+                id: 0,
+                text: "Hallo.".to_string(),
+            }],
+        };
+        assert!(matches!(
+            apply_translation(&source, reply),
+            Err(ApiRequestError::UnexpectedResponse { .. })
+        ));
+    }
+}
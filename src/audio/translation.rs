@@ -0,0 +1,73 @@
+//! Speech translation into English (`/v1/audio/translations`).
+//!
+//! The endpoint only supports `whisper-1` and always produces English. For other target
+//! languages see [`translate`](super::translate).
+
+use bon::Builder;
+use reqwest::multipart;
+use serde::{Deserialize, Serialize};
+
+use super::transcription::{audio_part, post_form, AudioFormat, ResponseFormat};
+use crate::{ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/audio/translations";
+
+#[derive(Debug, Clone, Builder)]
+pub struct TranslationRequest {
+    audio: Vec<u8>,
+    format: AudioFormat,
+    #[builder(into, default = "whisper-1".to_string())]
+    model: String,
+    /// English text guiding the style of the translation.
+    #[builder(into)]
+    prompt: Option<String>,
+    temperature: Option<f64>,
+    openai: OpenAi,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Translation {
+    pub text: String,
+}
+
+impl TranslationRequest {
+    fn form(&self, response_format: ResponseFormat) -> Result<multipart::Form, ApiRequestError> {
+        let mut form = multipart::Form::new()
+            .part("file", audio_part(&self.audio, self.format)?)
+            .text("model", self.model.clone())
+            .text("response_format", response_format.as_str());
+        if let Some(prompt) = &self.prompt {
+            form = form.text("prompt", prompt.to_owned());
+        }
+        if let Some(temperature) = self.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        Ok(form)
+    }
+
+    pub async fn send(&self) -> Result<Translation, ApiRequestError> {
+        let form = self.form(ResponseFormat::Json)?;
+        Ok(post_form(&self.openai, API_URL, &self.model, form)
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// Translation rendered by the API as `text`, `srt` or `vtt`.
+    pub async fn send_formatted(
+        &self,
+        response_format: ResponseFormat,
+    ) -> Result<String, ApiRequestError> {
+        let form = self.form(response_format)?;
+        Ok(post_form(&self.openai, API_URL, &self.model, form)
+            .await?
+            .text()
+            .await?)
+    }
+}
+
+impl OpenAi {
+    pub fn translation(&self) -> TranslationRequestBuilder<translation_request_builder::SetOpenai> {
+        TranslationRequest::builder().openai(self.clone())
+    }
+}
@@ -0,0 +1,112 @@
+//! Push-to-talk voice assistant built from transcription, chat and speech.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi, recording: Vec<u8>) -> Result<(), openai_ox::audio::voice::VoiceError> {
+//! use openai_ox::audio::voice::VoicePipeline;
+//!
+//! let mut voice = VoicePipeline::builder()
+//!     .conversation(openai.conversation("gpt-4o-mini").with_system("Answer in one sentence."))
+//!     .voice("nova")
+//!     .build();
+//! let (text, mp3) = voice.respond(recording).await?;
+//! println!("{} ({} bytes of audio)", text, mp3.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use bon::Builder;
+use futures::stream::BoxStream;
+use thiserror::Error;
+
+use super::{
+    speech::{self, SpeechRequest, SpeechRequestError},
+    transcription::AudioFormat,
+};
+use crate::{
+    chat::{conversation::Conversation, message::Message},
+    ApiRequestError,
+};
+
+#[derive(Debug, Error)]
+pub enum VoiceError {
+    #[error(transparent)]
+    Api(#[from] ApiRequestError),
+    #[error(transparent)]
+    Speech(#[from] SpeechRequestError),
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct VoicePipeline {
+    conversation: Conversation,
+    #[builder(into, default = "whisper-1".to_string())]
+    transcription_model: String,
+    /// Language of the user's speech, improves transcription accuracy when known.
+    #[builder(into)]
+    language: Option<String>,
+    /// Format of the recorded audio passed to [`respond`](VoicePipeline::respond).
+    #[builder(default = AudioFormat::Wav)]
+    input_format: AudioFormat,
+    #[builder(into, default = "tts-1".to_string())]
+    speech_model: String,
+    #[builder(into, default = "alloy".to_string())]
+    voice: String,
+    #[builder(into)]
+    speech_instructions: Option<String>,
+    #[builder(default)]
+    output_format: speech::ResponseFormat,
+}
+
+impl VoicePipeline {
+    pub fn conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+
+    pub fn conversation_mut(&mut self) -> &mut Conversation {
+        &mut self.conversation
+    }
+
+    /// Transcribes the recording and adds the reply of the chat model to the conversation.
+    async fn reply(&mut self, audio: Vec<u8>) -> Result<String, ApiRequestError> {
+        let transcript = self
+            .conversation
+            .openai()
+            .transcription()
+            .model(self.transcription_model.clone())
+            .maybe_language(self.language.clone())
+            .audio(audio)
+            .format(self.input_format)
+            .build()
+            .send()
+            .await?;
+        self.conversation.send(Message::user(transcript.text)).await
+    }
+
+    fn speech(&self, text: &str) -> Result<SpeechRequest, SpeechRequestError> {
+        self.conversation
+            .openai()
+            .speech()
+            .model(self.speech_model.clone())
+            .voice(self.voice.clone())
+            .input(text)
+            .response_format(self.output_format)
+            .maybe_instructions(self.speech_instructions.clone())
+            .build()
+    }
+
+    /// Answers a recorded utterance with the reply text and its synthesized audio.
+    pub async fn respond(&mut self, audio: Vec<u8>) -> Result<(String, Vec<u8>), VoiceError> {
+        let text = self.reply(audio).await?;
+        let audio = self.speech(&text)?.send().await?;
+        Ok((text, audio))
+    }
+
+    /// Like [`respond`](VoicePipeline::respond), but streams the audio as it is synthesized.
+    pub async fn respond_stream(
+        &mut self,
+        audio: Vec<u8>,
+    ) -> Result<(String, BoxStream<'static, Result<Vec<u8>, ApiRequestError>>), VoiceError> {
+        let text = self.reply(audio).await?;
+        let stream = self.speech(&text)?.stream().await;
+        Ok((text, stream))
+    }
+}
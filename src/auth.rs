@@ -0,0 +1,448 @@
+//! Where the client gets its API key from and how it sends it.
+//!
+//! A plain string still works everywhere an [`ApiKeySource`] is expected. The other sources
+//! resolve the key lazily and cache it, so long-running services can rotate keys either on a
+//! fixed interval ([`ApiKeySource::refresh_every`]) or on demand ([`ApiKeySource::refresh`])
+//! without rebuilding the client. A request whose key can't be fetched fails with
+//! [`ApiRequestError::ApiKey`](crate::ApiRequestError::ApiKey) before it is sent. [`AuthScheme`]
+//! decides which header carries the key, for gateways and Azure deployments that don't accept
+//! `Authorization: Bearer`.
+
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use reqwest::header::HeaderValue;
+use thiserror::Error;
+
+use crate::OpenAi;
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("No API key is configured, but the auth scheme sends one")]
+    Missing,
+    #[error("Environment variable {0} is not set or is not valid unicode")]
+    MissingEnv(String),
+    #[error("API key callback failed: {0}")]
+    Callback(String),
+    #[cfg(feature = "keyring")]
+    #[error(transparent)]
+    Keyring(#[from] keyring::Error),
+}
+
+type KeyCallback = dyn Fn() -> Result<String, ApiKeyError> + Send + Sync;
+
+#[derive(Clone)]
+enum Source {
+    Static(Arc<str>),
+    Env(String),
+    Callback(Arc<KeyCallback>),
+    #[cfg(feature = "keyring")]
+    Keyring {
+        service: String,
+        user: String,
+    },
+}
+
+impl Source {
+    fn fetch(&self) -> Result<Arc<str>, ApiKeyError> {
+        match self {
+            Source::Static(key) => Ok(key.clone()),
+            Source::Env(var) => std::env::var(var)
+                .map(Into::into)
+                .map_err(|_| ApiKeyError::MissingEnv(var.clone())),
+            Source::Callback(callback) => callback().map(Into::into),
+            #[cfg(feature = "keyring")]
+            Source::Keyring { service, user } => {
+                Ok(keyring::Entry::new(service, user)?.get_password()?.into())
+            }
+        }
+    }
+
+    /// Callbacks may call out to a secret manager and the keyring to the OS, both blocking.
+    fn is_blocking(&self) -> bool {
+        match self {
+            Source::Static(_) | Source::Env(_) => false,
+            Source::Callback(_) => true,
+            #[cfg(feature = "keyring")]
+            Source::Keyring { .. } => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Cached {
+    key: Arc<str>,
+    fetched_at: Instant,
+}
+
+/// Source of the API key, shared by every clone of the client that was built from it.
+#[derive(Clone)]
+pub struct ApiKeySource {
+    source: Source,
+    refresh_every: Option<Duration>,
+    cached: Arc<RwLock<Option<Cached>>>,
+}
+
+impl ApiKeySource {
+    fn new(source: Source) -> Self {
+        ApiKeySource {
+            source,
+            refresh_every: None,
+            cached: Arc::default(),
+        }
+    }
+
+    /// A fixed key, which is what passing a string to `OpenAi::builder().api_key` does.
+    pub fn fixed(key: impl Into<Arc<str>>) -> Self {
+        Self::new(Source::Static(key.into()))
+    }
+
+    /// Reads the key from the environment variable `var`.
+    pub fn env(var: impl Into<String>) -> Self {
+        Self::new(Source::Env(var.into()))
+    }
+
+    /// Asks `callback` for the key, e.g. to read it from a secret manager.
+    pub fn callback<F>(callback: F) -> Self
+    where
+        F: Fn() -> Result<String, ApiKeyError> + Send + Sync + 'static,
+    {
+        Self::new(Source::Callback(Arc::new(callback)))
+    }
+
+    /// Reads the key from the OS keyring entry of `service` and `user`.
+    #[cfg(feature = "keyring")]
+    pub fn keyring(service: impl Into<String>, user: impl Into<String>) -> Self {
+        Self::new(Source::Keyring {
+            service: service.into(),
+            user: user.into(),
+        })
+    }
+
+    /// Fetches the key again once the cached one is older than `interval`.
+    pub fn refresh_every(mut self, interval: Duration) -> Self {
+        self.refresh_every = Some(interval);
+        self
+    }
+
+    /// Fetches the key now, replacing the cached one for every client sharing this source.
+    pub fn refresh(&self) -> Result<(), ApiKeyError> {
+        self.store(self.source.fetch()?);
+        Ok(())
+    }
+
+    /// Current key, fetching it first if nothing is cached or the cached key is stale.
+    pub fn key(&self) -> Result<Arc<str>, ApiKeyError> {
+        match self.fresh() {
+            Some(key) => Ok(key),
+            None => Ok(self.store(self.source.fetch()?)),
+        }
+    }
+
+    /// Like [`ApiKeySource::key`], but fetches from callbacks and the keyring on tokio's
+    /// blocking threads. Requests get their key this way.
+    pub async fn resolve(&self) -> Result<Arc<str>, ApiKeyError> {
+        if let Some(key) = self.fresh() {
+            return Ok(key);
+        }
+        let key = if self.source.is_blocking() {
+            let source = self.source.clone();
+            tokio::task::spawn_blocking(move || source.fetch())
+                .await
+                .map_err(|e| ApiKeyError::Callback(e.to_string()))??
+        } else {
+            self.source.fetch()?
+        };
+        Ok(self.store(key))
+    }
+
+    /// The cached key, unless there is none yet or it is due for a refresh.
+    fn fresh(&self) -> Option<Arc<str>> {
+        if let Source::Static(key) = &self.source {
+            return Some(key.clone());
+        }
+        let cached = self.cached.read().unwrap_or_else(|e| e.into_inner());
+        let cached = cached.as_ref()?;
+        let stale = self
+            .refresh_every
+            .is_some_and(|interval| cached.fetched_at.elapsed() >= interval);
+        (!stale).then(|| cached.key.clone())
+    }
+
+    fn store(&self, key: Arc<str>) -> Arc<str> {
+        *self.cached.write().unwrap_or_else(|e| e.into_inner()) = Some(Cached {
+            key: key.clone(),
+            fetched_at: Instant::now(),
+        });
+        key
+    }
+}
+
+impl fmt::Debug for ApiKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match &self.source {
+            Source::Static(_) => "Static",
+            Source::Env(_) => "Env",
+            Source::Callback(_) => "Callback",
+            #[cfg(feature = "keyring")]
+            Source::Keyring { .. } => "Keyring",
+        };
+        f.debug_struct("ApiKeySource")
+            .field("source", &kind)
+            .field("refresh_every", &self.refresh_every)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<String> for ApiKeySource {
+    fn from(key: String) -> Self {
+        ApiKeySource::fixed(key)
+    }
+}
+
+impl From<&str> for ApiKeySource {
+    fn from(key: &str) -> Self {
+        ApiKeySource::fixed(key)
+    }
+}
+
+type TokenCallback = dyn Fn() -> String + Send + Sync;
+
+/// How requests are authenticated.
+#[derive(Clone, Default)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`, what the OpenAI API expects.
+    #[default]
+    Bearer,
+    /// The bare key in the named header, e.g. `api-key` for Azure OpenAI.
+    Header(String),
+    /// Bearer token returned by the callback on every request, ignoring the API key. Useful for
+    /// short-lived tokens your own code caches, e.g. Microsoft Entra ID tokens.
+    Token(Arc<TokenCallback>),
+    /// No credentials, for local servers and gateways that authenticate by other means.
+    None,
+}
+
+impl AuthScheme {
+    pub fn header(name: impl Into<String>) -> Self {
+        AuthScheme::Header(name.into())
+    }
+
+    pub fn token<F>(callback: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        AuthScheme::Token(Arc::new(callback))
+    }
+
+    async fn apply(
+        &self,
+        request: reqwest::RequestBuilder,
+        key: Option<&ApiKeySource>,
+    ) -> Result<reqwest::RequestBuilder, ApiKeyError> {
+        Ok(match self {
+            AuthScheme::Bearer => request.bearer_auth(resolve(key).await?),
+            // Marked sensitive, like bearer credentials, so `redact_headers` hides it.
+            AuthScheme::Header(name) => {
+                let key = resolve(key).await?;
+                match HeaderValue::from_str(&key) {
+                    Ok(mut value) => {
+                        value.set_sensitive(true);
+                        request.header(name.as_str(), value)
+                    }
+                    Err(_) => request.header(name.as_str(), &*key),
+                }
+            }
+            AuthScheme::Token(callback) => request.bearer_auth(callback()),
+            AuthScheme::None => request,
+        })
+    }
+
+    /// Name and value of the credentials header, for transports that don't go through reqwest.
+    #[cfg_attr(not(feature = "realtime"), allow(dead_code))]
+    async fn header_pair(
+        &self,
+        key: Option<&ApiKeySource>,
+    ) -> Result<Option<(String, String)>, ApiKeyError> {
+        Ok(match self {
+            AuthScheme::Bearer => Some((
+                "Authorization".into(),
+                format!("Bearer {}", resolve(key).await?),
+            )),
+            AuthScheme::Header(name) => Some((name.clone(), resolve(key).await?.to_string())),
+            AuthScheme::Token(callback) => {
+                Some(("Authorization".into(), format!("Bearer {}", callback())))
+            }
+            AuthScheme::None => None,
+        })
+    }
+}
+
+async fn resolve(key: Option<&ApiKeySource>) -> Result<Arc<str>, ApiKeyError> {
+    key.ok_or(ApiKeyError::Missing)?.resolve().await
+}
+
+impl fmt::Debug for AuthScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthScheme::Bearer => f.write_str("Bearer"),
+            AuthScheme::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            AuthScheme::Token(_) => f.write_str("Token"),
+            AuthScheme::None => f.write_str("None"),
+        }
+    }
+}
+
+/// Adds the client's credentials to a request, failing when the key can't be fetched.
+pub(crate) trait Authorize: Sized {
+    async fn authorize(self, openai: &OpenAi) -> Result<Self, ApiKeyError>;
+}
+
+impl Authorize for reqwest::RequestBuilder {
+    async fn authorize(self, openai: &OpenAi) -> Result<Self, ApiKeyError> {
+        openai
+            .inner
+            .auth
+            .apply(self, openai.inner.api_key.as_ref())
+            .await
+    }
+}
+
+/// WebSocket handshake of a realtime session. Credentials that aren't valid header values are
+/// left out and the server rejects the handshake.
+#[cfg(feature = "realtime")]
+impl Authorize for tokio_tungstenite::tungstenite::handshake::client::Request {
+    async fn authorize(mut self, openai: &OpenAi) -> Result<Self, ApiKeyError> {
+        use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+
+        if let Some((name, value)) = openai
+            .inner
+            .auth
+            .header_pair(openai.inner.api_key.as_ref())
+            .await?
+        {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                self.headers_mut().insert(name, value);
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_callback_source_refreshes() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let source = ApiKeySource::callback(move || {
+            Ok(format!("sk-{}", counter.fetch_add(1, Ordering::SeqCst)))
+        });
+        assert_eq!(&*source.key().unwrap(), "sk-0");
+        assert_eq!(&*source.clone().key().unwrap(), "sk-0");
+
+        source.refresh().unwrap();
+        assert_eq!(&*source.key().unwrap(), "sk-1");
+
+        let stale = source.clone().refresh_every(Duration::ZERO);
+        assert_eq!(&*stale.key().unwrap(), "sk-2");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let failing = ApiKeySource::env("OPENAI_OX_TEST_UNSET_KEY");
+        assert!(matches!(failing.key(), Err(ApiKeyError::MissingEnv(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_fails_the_request() {
+        let source = ApiKeySource::callback(|| Ok("sk-blocking".to_string()));
+        assert_eq!(&*source.resolve().await.unwrap(), "sk-blocking");
+
+        let client = reqwest::Client::new();
+        let openai = OpenAi::builder()
+            .api_key(ApiKeySource::callback(|| {
+                Err(ApiKeyError::Callback("vault is sealed".to_string()))
+            }))
+            .client(client.clone())
+            .build();
+        let res = client.get("http://localhost").authorize(&openai).await;
+        assert!(matches!(res, Err(ApiKeyError::Callback(_))));
+
+        // Schemes that don't send the key don't fetch it either.
+        let openai = OpenAi::builder()
+            .api_key(ApiKeySource::env("OPENAI_OX_TEST_UNSET_KEY"))
+            .auth(AuthScheme::None)
+            .client(client.clone())
+            .build();
+        assert!(client
+            .get("http://localhost")
+            .authorize(&openai)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_auth_schemes() {
+        let client = reqwest::Client::new();
+        let header = |auth: AuthScheme, name: &'static str| {
+            let client = client.clone();
+            async move {
+                let openai = OpenAi::builder()
+                    .api_key("sk-test")
+                    .auth(auth)
+                    .client(client.clone())
+                    .build();
+                let request = client
+                    .get("http://localhost")
+                    .authorize(&openai)
+                    .await
+                    .unwrap()
+                    .build()
+                    .unwrap();
+                request
+                    .headers()
+                    .get(name)
+                    .map(|value| value.to_str().unwrap().to_string())
+            }
+        };
+        assert_eq!(
+            header(AuthScheme::Bearer, "authorization").await.as_deref(),
+            Some("Bearer sk-test")
+        );
+        assert_eq!(
+            header(AuthScheme::header("api-key"), "api-key")
+                .await
+                .as_deref(),
+            Some("sk-test")
+        );
+        assert_eq!(
+            header(AuthScheme::token(|| "tok".to_string()), "authorization")
+                .await
+                .as_deref(),
+            Some("Bearer tok")
+        );
+        assert_eq!(header(AuthScheme::None, "authorization").await, None);
+
+        let openai = OpenAi::builder().client(client.clone()).build();
+        let res = client.get("http://localhost").authorize(&openai).await;
+        assert!(matches!(res, Err(ApiKeyError::Missing)));
+        let openai = OpenAi::builder()
+            .auth(AuthScheme::None)
+            .client(client.clone())
+            .build();
+        assert!(client
+            .get("http://localhost")
+            .authorize(&openai)
+            .await
+            .is_ok());
+    }
+}
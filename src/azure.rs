@@ -0,0 +1,92 @@
+//! Azure OpenAI deployments.
+//!
+//! Azure serves each model from a named deployment under
+//! `{endpoint}/openai/deployments/{deployment}/...?api-version=...` and takes the key in an
+//! `api-key` header. With an [`AzureConfig`] on the client, chat, embeddings and audio requests
+//! are routed that way; the deployment is the one mapped to the request's model, or the model
+//! name itself.
+//!
+//! ```
+//! use openai_ox::{azure::AzureConfig, OpenAi};
+//!
+//! let openai = OpenAi::builder()
+//!     .base_url("https://my-resource.openai.azure.com")
+//!     .api_key("...")
+//!     .azure(AzureConfig::new("2024-10-21").deployment("gpt-4o", "prod-gpt4o"))
+//!     .build();
+//! ```
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AzureConfig {
+    api_version: String,
+    deployments: HashMap<String, String>,
+}
+
+impl AzureConfig {
+    pub fn new(api_version: impl Into<String>) -> Self {
+        AzureConfig {
+            api_version: api_version.into(),
+            deployments: HashMap::new(),
+        }
+    }
+
+    /// Sends requests for `model` to the deployment `name`.
+    pub fn deployment(mut self, model: impl Into<String>, name: impl Into<String>) -> Self {
+        self.deployments.insert(model.into(), name.into());
+        self
+    }
+
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+
+    /// Deployment serving `model`, the model name itself when none is mapped.
+    pub fn deployment_for<'a>(&'a self, model: &'a str) -> &'a str {
+        self.deployments.get(model).map_or(model, String::as_str)
+    }
+
+    /// URL of the endpoint `path`, e.g. `v1/chat/completions`, on the deployment of `model`.
+    pub(crate) fn url(&self, base_url: &str, path: &str, model: &str) -> String {
+        let path = path.strip_prefix("v1/").unwrap_or(path);
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            base_url,
+            self.deployment_for(model),
+            path,
+            self.api_version
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenAi;
+
+    #[test]
+    fn test_deployment_urls() {
+        let openai = OpenAi::builder()
+            .base_url("https://acme.openai.azure.com/")
+            .azure(AzureConfig::new("2024-10-21").deployment("gpt-4o", "prod-gpt4o"))
+            .build();
+        assert_eq!(
+            openai.inner.model_url("v1/chat/completions", "gpt-4o"),
+            "https://acme.openai.azure.com/openai/deployments/prod-gpt4o/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            openai.inner.model_url("v1/embeddings", "text-embedding-3-small"),
+            "https://acme.openai.azure.com/openai/deployments/text-embedding-3-small/embeddings?api-version=2024-10-21"
+        );
+        assert!(
+            matches!(&openai.inner.auth, crate::auth::AuthScheme::Header(name) if name == "api-key")
+        );
+
+        let openai = OpenAi::builder().build();
+        assert_eq!(
+            openai.inner.model_url("v1/chat/completions", "gpt-4o"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+    }
+}
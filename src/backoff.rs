@@ -0,0 +1,86 @@
+//! Exponential backoff used by helpers that poll long-running jobs.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use bon::Builder;
+
+/// Delay schedule growing by `multiplier` after every attempt, capped at `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Builder)]
+pub struct Backoff {
+    #[builder(default = Duration::from_secs(1))]
+    pub initial: Duration,
+    #[builder(default = Duration::from_secs(30))]
+    pub max: Duration,
+    #[builder(default = 2.0)]
+    pub multiplier: f64,
+    /// Fraction of every delay that is randomized, between 0 and 1. With `0.5` delays fall
+    /// anywhere between half and the full value, so many pollers don't wake up in lockstep.
+    #[builder(default = 0.0)]
+    pub jitter: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::builder().build()
+    }
+}
+
+impl Backoff {
+    /// Delay to wait before the `attempt`-th retry, starting from zero.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1.0)
+            .powi(attempt.min(i32::MAX as u32) as i32);
+        let secs = (self.initial.as_secs_f64() * factor).min(self.max.as_secs_f64());
+        Duration::from_secs_f64(secs)
+    }
+
+    /// Infinite iterator over successive delays, with jitter applied.
+    pub fn delays(self) -> impl Iterator<Item = Duration> {
+        (0..).map(move |attempt| self.jittered(self.delay(attempt)))
+    }
+
+    fn jittered(&self, delay: Duration) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        // `RandomState` is randomly seeded, which is all the randomness jitter needs.
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 - jitter * random)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let backoff = Backoff::builder()
+            .initial(Duration::from_millis(100))
+            .max(Duration::from_millis(500))
+            .build();
+        let delays: Vec<_> = backoff.delays().take(5).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+
+        let jittered = Backoff::builder()
+            .initial(Duration::from_millis(100))
+            .jitter(0.5)
+            .build();
+        for (attempt, delay) in jittered.delays().take(20).enumerate() {
+            let full = jittered.delay(attempt as u32);
+            assert!(delay <= full && delay >= full / 2);
+        }
+    }
+}
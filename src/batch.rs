@@ -0,0 +1,766 @@
+//! Batch API (`/v1/batches`) and a helper for running many chat completions as one batch.
+//!
+//! Batches are processed within 24 hours at half the price of synchronous requests. The
+//! [`ChatBatch`] helper takes care of writing the JSONL input file, submitting it, waiting for
+//! the batch to finish and mapping the output lines back to [`ChatCompletionResponse`]s.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::{backoff::Backoff, batch::ChatBatch, chat::message::Message};
+//!
+//! let mut batch = ChatBatch::new();
+//! for (i, text) in ["first", "second"].iter().enumerate() {
+//!     let request = openai
+//!         .chat_completion()
+//!         .model("gpt-4o-mini")
+//!         .messages(Message::user(format!("Summarize: {}", text)))
+//!         .build()?;
+//!     batch.push(format!("doc-{}", i), request);
+//! }
+//! let results = batch.run(&openai, Backoff::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use bon::Builder;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    auth::Authorize,
+    backoff::Backoff,
+    chat::{ChatCompletionRequest, ChatCompletionResponse},
+    files::FilePurpose,
+    retry::SendRetrying,
+    ApiError, ApiErrorDetail, ApiRequestError, ErrorResponse, OpenAi,
+};
+
+const API_URL: &str = "v1/batches";
+const CHAT_COMPLETIONS_URL: &str = "/v1/chat/completions";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Validating,
+    Failed,
+    InProgress,
+    Finalizing,
+    Completed,
+    Expired,
+    Cancelling,
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// `true` once the batch will not change anymore.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            BatchStatus::Failed
+                | BatchStatus::Completed
+                | BatchStatus::Expired
+                | BatchStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestCounts {
+    pub total: u32,
+    pub completed: u32,
+    pub failed: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    pub object: String,
+    pub endpoint: String,
+    pub input_file_id: String,
+    pub completion_window: String,
+    pub status: BatchStatus,
+    pub created_at: u64,
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    #[serde(default)]
+    pub error_file_id: Option<String>,
+    #[serde(default)]
+    pub errors: Option<Value>,
+    #[serde(default)]
+    pub request_counts: Option<RequestCounts>,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+/// A page of batches, newest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchList {
+    pub object: String,
+    pub data: Vec<Batch>,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Builder)]
+pub struct CreateBatchRequest {
+    #[builder(into)]
+    pub input_file_id: String,
+    /// Endpoint every line of the input file targets, e.g. `/v1/chat/completions`.
+    #[builder(into)]
+    pub endpoint: String,
+    #[builder(into, default = "24h".to_string())]
+    pub completion_window: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+/// One line of a batch input file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequestLine<T> {
+    pub custom_id: String,
+    pub method: String,
+    pub url: String,
+    pub body: T,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchLineResponse {
+    pub status_code: u16,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    pub body: Value,
+}
+
+/// One line of a batch output or error file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponseLine {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub custom_id: String,
+    #[serde(default)]
+    pub response: Option<BatchLineResponse>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+impl BatchResponseLine {
+    /// Deserializes a successful body, or turns the line's error into an [`ApiRequestError`].
+    pub fn into_result<T: serde::de::DeserializeOwned>(self) -> Result<T, ApiRequestError> {
+        match self.response {
+            Some(res) if (200..300).contains(&res.status_code) => {
+                Ok(serde_json::from_value(res.body)?)
+            }
+            Some(res) => {
+                let status = StatusCode::from_u16(res.status_code).ok();
+                match (
+                    status,
+                    serde_json::from_value::<ErrorResponse>(res.body.clone()),
+                ) {
+                    (Some(status), Ok(e)) => {
+                        Err(ApiError::new(status, e.error).into_request_error(None))
+                    }
+                    _ => Err(ApiRequestError::UnexpectedResponse {
+                        status,
+                        response: res.body.to_string(),
+                    }),
+                }
+            }
+            None => Err(match self.error {
+                Some(e) => match serde_json::from_value::<ApiErrorDetail>(e.clone()) {
+                    Ok(detail) => ApiError::without_status(detail),
+                    Err(_) => ApiRequestError::UnexpectedResponse {
+                        status: None,
+                        response: e.to_string(),
+                    },
+                },
+                None => ApiRequestError::UnexpectedResponse {
+                    status: None,
+                    response: "line contains neither a response nor an error".to_string(),
+                },
+            }),
+        }
+    }
+}
+
+/// Parses a JSONL batch output or error file.
+pub fn parse_jsonl(bytes: &[u8]) -> Result<Vec<BatchResponseLine>, ApiRequestError> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(ApiRequestError::from))
+        .collect()
+}
+
+async fn parse_batch(res: reqwest::Response) -> Result<Batch, ApiRequestError> {
+    if res.status().is_success() {
+        Ok(res.json().await?)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
+
+impl CreateBatchRequest {
+    pub async fn send(&self) -> Result<Batch, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .json(self)
+            .send_observed(&self.openai)
+            .await?;
+        parse_batch(res).await
+    }
+
+    /// Submits the batch and stores the request in `outbox` if it fails with a retryable error.
+    ///
+    /// Returns `Ok(None)` when the request was queued; the created [`Batch`] will be delivered by
+    /// the [`OutboxDriver`](crate::outbox::OutboxDriver) once a retry succeeds.
+    #[cfg(feature = "outbox")]
+    pub async fn send_or_enqueue(
+        &self,
+        outbox: &crate::outbox::Outbox,
+        key: Option<String>,
+    ) -> Result<Option<Batch>, crate::outbox::OutboxError> {
+        match self.send().await {
+            Ok(batch) => Ok(Some(batch)),
+            Err(e) if e.is_retryable() => {
+                outbox
+                    .enqueue(API_URL, serde_json::to_value(self)?, key)
+                    .await?;
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl OpenAi {
+    pub fn create_batch(
+        &self,
+    ) -> CreateBatchRequestBuilder<create_batch_request_builder::SetOpenai> {
+        CreateBatchRequest::builder().openai(self.clone())
+    }
+
+    pub async fn retrieve_batch(&self, batch_id: &str) -> Result<Batch, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, batch_id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_batch(res).await
+    }
+
+    pub async fn cancel_batch(&self, batch_id: &str) -> Result<Batch, ApiRequestError> {
+        let url = format!("{}/{}/{}/cancel", self.inner.base_url, API_URL, batch_id);
+        let res = self
+            .inner
+            .client
+            .post(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_batch(res).await
+    }
+
+    /// Batches of the organization, newest first. Pass the last id of a page as `after` to get
+    /// the next one.
+    pub async fn list_batches(
+        &self,
+        after: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<BatchList, ApiRequestError> {
+        let url = format!("{}/{}", self.inner.base_url, API_URL);
+        let mut query = Vec::new();
+        if let Some(after) = after {
+            query.push(("after", after.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .query(&query)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BatchWaitError {
+    #[error(transparent)]
+    Api(#[from] ApiRequestError),
+    #[error("Batch {} did not finish before the deadline", .batch.id)]
+    DeadlineExceeded { batch: Box<Batch> },
+    /// The batch failed, expired or was cancelled. `errors` holds the parsed error file, if
+    /// the batch produced one.
+    #[error("Batch {} ended with status {:?}", .batch.id, .batch.status)]
+    Failed {
+        batch: Box<Batch>,
+        errors: Vec<BatchResponseLine>,
+    },
+}
+
+type ProgressCallback = Arc<dyn Fn(&RequestCounts) + Send + Sync>;
+
+/// How [`Batch::wait`] polls.
+#[derive(Clone, Default)]
+pub struct PollOptions {
+    backoff: Backoff,
+    deadline: Option<Instant>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl std::fmt::Debug for PollOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PollOptions")
+            .field("backoff", &self.backoff)
+            .field("deadline", &self.deadline)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl PollOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Gives up at `deadline`; the batch itself keeps running.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_timeout(self, timeout: std::time::Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Called with the request counts after every poll.
+    pub fn on_progress(mut self, f: impl Fn(&RequestCounts) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+}
+
+impl Batch {
+    /// Polls until the batch reaches a terminal status.
+    ///
+    /// A batch that failed, expired or was cancelled is returned as
+    /// [`BatchWaitError::Failed`] together with the lines of its error file.
+    pub async fn wait(
+        self,
+        openai: &OpenAi,
+        options: PollOptions,
+    ) -> Result<Batch, BatchWaitError> {
+        let mut batch = self;
+        let mut delays = options.backoff.delays();
+        while !batch.status.is_terminal() {
+            let now = Instant::now();
+            let mut delay = delays.next().unwrap_or_default();
+            if let Some(deadline) = options.deadline {
+                if now >= deadline {
+                    return Err(BatchWaitError::DeadlineExceeded {
+                        batch: Box::new(batch),
+                    });
+                }
+                delay = delay.min(deadline - now);
+            }
+            tokio::time::sleep(delay).await;
+            batch = openai.retrieve_batch(&batch.id).await?;
+            if let Some(on_progress) = &options.on_progress {
+                on_progress(&batch.request_counts.unwrap_or_default());
+            }
+        }
+
+        if batch.status == BatchStatus::Completed {
+            return Ok(batch);
+        }
+        let errors = match &batch.error_file_id {
+            Some(file_id) => parse_jsonl(&openai.file_content(file_id).await?)?,
+            None => Vec::new(),
+        };
+        Err(BatchWaitError::Failed {
+            batch: Box::new(batch),
+            errors,
+        })
+    }
+}
+
+/// Chat completion requests collected for a single batch, keyed by `custom_id`.
+#[derive(Debug, Clone, Default)]
+pub struct ChatBatch {
+    requests: Vec<(String, ChatCompletionRequest)>,
+}
+
+impl ChatBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, custom_id: impl Into<String>, request: ChatCompletionRequest) {
+        self.requests.push((custom_id.into(), request));
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// The batch input file, one request per line.
+    pub fn to_jsonl(&self) -> Result<String, ApiRequestError> {
+        let mut out = String::new();
+        for (custom_id, request) in &self.requests {
+            let mut body = serde_json::to_value(request)?;
+            if let Value::Object(map) = &mut body {
+                map.remove("stream");
+            }
+            let line = BatchRequestLine {
+                custom_id: custom_id.clone(),
+                method: "POST".to_string(),
+                url: CHAT_COMPLETIONS_URL.to_string(),
+                body,
+            };
+            out.push_str(&serde_json::to_string(&line)?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Uploads the input file and creates the batch.
+    pub async fn submit(&self, openai: &OpenAi) -> Result<Batch, ApiRequestError> {
+        let file = openai
+            .upload_file(
+                "chat_batch.jsonl",
+                self.to_jsonl()?.into_bytes(),
+                FilePurpose::Batch,
+            )
+            .await?;
+        openai
+            .create_batch()
+            .input_file_id(file.id)
+            .endpoint(CHAT_COMPLETIONS_URL)
+            .build()
+            .send()
+            .await
+    }
+
+    /// Downloads the output and error files of a finished batch.
+    pub async fn results(
+        openai: &OpenAi,
+        batch: &Batch,
+    ) -> Result<HashMap<String, Result<ChatCompletionResponse, ApiRequestError>>, ApiRequestError>
+    {
+        let mut results = HashMap::new();
+        for file_id in [&batch.output_file_id, &batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            for line in parse_jsonl(&openai.file_content(file_id).await?)? {
+                results.insert(line.custom_id.clone(), line.into_result());
+            }
+        }
+        Ok(results)
+    }
+
+    /// Submits the batch, waits for it to finish and returns the responses by `custom_id`.
+    ///
+    /// Requests missing from the returned map did not run, e.g. because the batch expired.
+    pub async fn run(
+        &self,
+        openai: &OpenAi,
+        backoff: Backoff,
+    ) -> Result<HashMap<String, Result<ChatCompletionResponse, ApiRequestError>>, ApiRequestError>
+    {
+        let mut batch = self.submit(openai).await?;
+        let mut delays = backoff.delays();
+        while !batch.status.is_terminal() {
+            if let Some(delay) = delays.next() {
+                tokio::time::sleep(delay).await;
+            }
+            batch = openai.retrieve_batch(&batch.id).await?;
+        }
+        Self::results(openai, &batch).await
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, ChatCompletionRequest)> for ChatBatch {
+    fn from_iter<I: IntoIterator<Item = (S, ChatCompletionRequest)>>(iter: I) -> Self {
+        Self {
+            requests: iter
+                .into_iter()
+                .map(|(custom_id, request)| (custom_id.into(), request))
+                .collect(),
+        }
+    }
+}
+
+/// Uses the position of each request as its `custom_id`, so the results map back by index.
+impl From<Vec<ChatCompletionRequest>> for ChatBatch {
+    fn from(requests: Vec<ChatCompletionRequest>) -> Self {
+        requests
+            .into_iter()
+            .enumerate()
+            .map(|(i, request)| (i.to_string(), request))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::chat::message::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_chat_batch_jsonl_and_results() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let mut batch = ChatBatch::new();
+        batch.push(
+            "a",
+            openai
+                .chat_completion()
+                .model("gpt-4o-mini")
+                .messages(Message::user("Hi"))
+                .stream(true)
+                .build()
+                .unwrap(),
+        );
+        let jsonl = batch.to_jsonl().unwrap();
+        let line: Value = serde_json::from_str(jsonl.trim_end()).unwrap();
+        assert_eq!(
+            line,
+            json!({
+                "custom_id": "a",
+                "method": "POST",
+                "url": "/v1/chat/completions",
+                "body": {"model": "gpt-4o-mini", "messages": [{"role": "user", "content": "Hi"}]}
+            })
+        );
+
+        let output = [
+            json!({
+                "id": "batch_req_1",
+                "custom_id": "a",
+                "response": {
+                    "status_code": 200,
+                    "request_id": "req_1",
+                    "body": {
+                        "id": "chatcmpl-1",
+                        "object": "chat.completion",
+                        "created": 1,
+                        "model": "gpt-4o-mini",
+                        "system_fingerprint": "fp",
+                        "choices": [{
+                            "index": 0,
+                            "message": {"role": "assistant", "content": "Hello"},
+                            "finish_reason": "stop",
+                            "logprobs": null
+                        }],
+                        "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+                    }
+                },
+                "error": null
+            }),
+            json!({
+                "id": "batch_req_2",
+                "custom_id": "b",
+                "response": {
+                    "status_code": 400,
+                    "body": {"error": {"message": "bad model", "code": "model_not_found"}}
+                }
+            }),
+        ]
+        .map(|line| line.to_string())
+        .join("\n");
+        let mut lines = parse_jsonl(output.as_bytes()).unwrap().into_iter();
+        let ok: ChatCompletionResponse = lines.next().unwrap().into_result().unwrap();
+        assert_eq!(ok.choices[0].message.content(), Some("Hello"));
+        assert!(matches!(
+            lines.next().unwrap().into_result::<ChatCompletionResponse>(),
+            Err(ApiRequestError::Rejected(error))
+                if error.status == StatusCode::BAD_REQUEST && error.code.as_deref() == Some("model_not_found")
+        ));
+    }
+
+    #[test]
+    fn test_chat_batch_from_requests() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = |text: &str| {
+            openai
+                .chat_completion()
+                .model("gpt-4o-mini")
+                .messages(Message::user(text))
+                .build()
+                .unwrap()
+        };
+        let custom_ids = |batch: ChatBatch| -> Vec<Value> {
+            batch
+                .to_jsonl()
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap()["custom_id"].take())
+                .collect()
+        };
+
+        // Defaults to index based ids.
+        let indexed = ChatBatch::from(vec![request("Hi"), request("Bye")]);
+        assert_eq!(custom_ids(indexed), [json!("0"), json!("1")]);
+
+        let keyed: ChatBatch = [("greeting", request("Hi")), ("farewell", request("Bye"))]
+            .into_iter()
+            .collect();
+        assert_eq!(custom_ids(keyed), [json!("greeting"), json!("farewell")]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_list_batches() {
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let batch = |id: &str| {
+            json!({
+                "id": id,
+                "object": "batch",
+                "endpoint": "/v1/chat/completions",
+                "input_file_id": "file-in",
+                "completion_window": "24h",
+                "status": "completed",
+                "created_at": 1,
+                "request_counts": {"total": 2, "completed": 2, "failed": 0}
+            })
+        };
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/batches",
+            MockResponse::json(&json!({
+                "object": "list",
+                "data": [batch("batch_2"), batch("batch_1")],
+                "first_id": "batch_2",
+                "last_id": "batch_1",
+                "has_more": true
+            })),
+        );
+        let page = mock
+            .client()
+            .list_batches(Some("batch_3"), Some(2))
+            .await
+            .unwrap();
+        assert_eq!(
+            page.data.iter().map(|b| b.id.as_str()).collect::<Vec<_>>(),
+            ["batch_2", "batch_1"]
+        );
+        assert_eq!(page.last_id.as_deref(), Some("batch_1"));
+        assert!(page.has_more);
+        assert_eq!(page.data[0].request_counts.unwrap().completed, 2);
+
+        let request = &mock.requests()[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/v1/batches?after=batch_3&limit=2");
+    }
+
+    #[cfg(all(feature = "mock", feature = "outbox"))]
+    #[tokio::test]
+    async fn test_create_batch_send_or_enqueue() {
+        use crate::{
+            mock::{MockOpenAi, MockResponse},
+            outbox::Outbox,
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "openai-ox-batch-outbox-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let outbox = Outbox::open(&path).unwrap();
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/batches",
+            MockResponse::error(503, "overloaded", "Try again"),
+        );
+        let openai = mock.client();
+        let request = openai
+            .create_batch()
+            .input_file_id("file-in")
+            .endpoint(CHAT_COMPLETIONS_URL)
+            .build();
+        let res = request
+            .send_or_enqueue(&outbox, Some("nightly".into()))
+            .await
+            .unwrap();
+        assert!(res.is_none());
+        let entry = &outbox.pending()[0];
+        assert_eq!(entry.endpoint, API_URL);
+        assert_eq!(entry.body["input_file_id"], "file-in");
+
+        mock.push(
+            "v1/batches",
+            MockResponse::json(&json!({
+                "id": "batch_1",
+                "object": "batch",
+                "endpoint": CHAT_COMPLETIONS_URL,
+                "input_file_id": "file-in",
+                "completion_window": "24h",
+                "status": "validating",
+                "created_at": 1
+            })),
+        );
+        let mut created = Vec::new();
+        outbox
+            .drain(&openai, 5, |entry, data| {
+                let batch: Batch = serde_json::from_value(data).unwrap();
+                created.push((entry.key.clone(), batch.id));
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            created,
+            [(Some("nightly".to_string()), "batch_1".to_string())]
+        );
+        assert!(outbox.is_empty());
+        assert_eq!(
+            mock.requests()[1].json::<Value>().unwrap()["completion_window"],
+            "24h"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+//! Cancellation of in-flight requests and streams with a [`CancellationToken`].
+//!
+//! Cancelling drops the request future or the response stream, which closes its connection
+//! right away instead of reading a generation nobody waits for anymore. The request or stream
+//! then ends with [`ApiRequestError::Cancelled`].
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use futures::StreamExt;
+//! use openai_ox::{chat::message::Message, CancellationToken};
+//!
+//! let token = CancellationToken::new();
+//! // Hand a clone to the UI's stop button.
+//! let stop = token.clone();
+//! let request = openai
+//!     .chat_completion()
+//!     .model("gpt-4o")
+//!     .messages(Message::user("Write a long story"))
+//!     .build()?;
+//! let mut stream = request.stream_cancellable(token).await;
+//! while let Some(chunk) = stream.next().await {
+//!     match chunk {
+//!         Ok(chunk) => print!("{}", chunk.choices[0].delta.content.as_deref().unwrap_or_default()),
+//!         Err(openai_ox::ApiRequestError::Cancelled) => break,
+//!         Err(e) => return Err(e),
+//!     }
+//! }
+//! # drop(stop);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+pub use tokio_util::sync::CancellationToken;
+
+use crate::{ApiRequestError, ApiRequestWithClient};
+
+/// Runs `future` until it completes or `token` is cancelled, whichever happens first.
+pub async fn cancellable<T>(
+    token: &CancellationToken,
+    future: impl Future<Output = Result<T, ApiRequestError>>,
+) -> Result<T, ApiRequestError> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(ApiRequestError::Cancelled),
+        res = future => res,
+    }
+}
+
+/// `send` with a [`CancellationToken`], for every request that carries its client.
+#[async_trait::async_trait]
+pub trait SendCancellable: ApiRequestWithClient {
+    /// Sends the request, giving up on it as soon as `token` is cancelled.
+    async fn send_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<Self::Response, ApiRequestError>;
+}
+
+#[async_trait::async_trait]
+impl<R: ApiRequestWithClient + Sync> SendCancellable for R {
+    async fn send_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<Self::Response, ApiRequestError> {
+        cancellable(token, self.send()).await
+    }
+}
+
+/// Cancellation for any stream of results, e.g. one returned by a `stream` method.
+pub trait CancellableStream<T>: Stream<Item = Result<T, ApiRequestError>> {
+    /// Ends the stream with [`ApiRequestError::Cancelled`] once `token` is cancelled, dropping
+    /// the underlying response.
+    fn cancellable(
+        self,
+        token: CancellationToken,
+    ) -> BoxStream<'static, Result<T, ApiRequestError>>;
+}
+
+impl<S, T> CancellableStream<T> for S
+where
+    S: Stream<Item = Result<T, ApiRequestError>> + Send + 'static,
+    T: Send + 'static,
+{
+    fn cancellable(
+        self,
+        token: CancellationToken,
+    ) -> BoxStream<'static, Result<T, ApiRequestError>> {
+        stream::unfold(Some((self.boxed(), token)), |state| async move {
+            let (mut stream, token) = state?;
+            tokio::select! {
+                biased;
+                _ = token.cancelled() => Some((Err(ApiRequestError::Cancelled), None)),
+                item = stream.next() => Some((item?, Some((stream, token)))),
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Starts a stream with `start` and makes it [`cancellable`](CancellableStream::cancellable),
+/// cancelling the wait for the response too.
+pub(crate) async fn cancellable_stream<T: Send + 'static>(
+    token: CancellationToken,
+    start: impl Future<Output = BoxStream<'static, Result<T, ApiRequestError>>>,
+) -> BoxStream<'static, Result<T, ApiRequestError>> {
+    match cancellable(&token, async { Ok(start.await) }).await {
+        Ok(stream) => stream.cancellable(token),
+        Err(e) => stream::once(async { Err(e) }).boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancellable() {
+        let token = CancellationToken::new();
+        let chunks = stream::iter([Ok::<_, ApiRequestError>(1)]).chain(stream::pending());
+        let mut stream = chunks.cancellable(token.clone());
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        token.cancel();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ApiRequestError::Cancelled))
+        ));
+        assert!(stream.next().await.is_none());
+
+        let res = cancellable(&token, futures::future::pending::<Result<(), _>>()).await;
+        assert!(matches!(res, Err(ApiRequestError::Cancelled)));
+    }
+}
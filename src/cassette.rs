@@ -0,0 +1,308 @@
+//! Record-and-replay of API traffic for integration tests, in the style of VCR.
+//!
+//! A [`Cassette`] in record mode forwards every request to the real API and writes the
+//! exchanges to a JSON file on [`Cassette::save`]. In replay mode it answers from that file, in
+//! the recorded order per endpoint, without network access or a key. Credential headers are
+//! redacted before anything is written, and so are the headers named with
+//! [`Cassette::redact_header`], e.g. the one of a client using
+//! [`AuthScheme::Header`](crate::auth::AuthScheme::Header).
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::cassette::Cassette;
+//!
+//! // Records when the file is missing or `OPENAI_OX_RECORD` is set, replays otherwise.
+//! let cassette = Cassette::open("tests/cassettes/greeting.json").await?;
+//! let openai = cassette.client(std::env::var("OPENAI_API_KEY").unwrap_or_default());
+//! let reply = openai.generate("gpt-4o-mini", "Say hello").await?;
+//! cassette.save().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    middleware::SECRET_HEADERS,
+    mock::{MockOpenAi, MockRequest, MockResponse},
+    ApiRequestError, OpenAi, BASE_URL,
+};
+
+/// Environment variable forcing [`Cassette::open`] to record even when the file exists.
+pub const RECORD_ENV: &str = "OPENAI_OX_RECORD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// One request and the response it got, as stored in the cassette file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// Headers as sent, with credentials replaced by `[REDACTED]`.
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Body,
+}
+
+/// A body kept readable in the file when it is text, e.g. JSON or an event stream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Body {
+    Text(String),
+    Binary { base64: String },
+}
+
+impl Body {
+    fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Body::Text(text.to_string()),
+            Err(_) => Body::Binary {
+                base64: STANDARD.encode(bytes),
+            },
+        }
+    }
+
+    /// # Errors
+    ///
+    /// If a binary body is not valid base64.
+    pub fn bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        match self {
+            Body::Text(text) => Ok(text.clone().into_bytes()),
+            Body::Binary { base64 } => STANDARD.decode(base64),
+        }
+    }
+}
+
+impl From<(MockRequest, MockResponse)> for Interaction {
+    fn from((request, response): (MockRequest, MockResponse)) -> Self {
+        let headers = request
+            .headers
+            .into_iter()
+            .map(|(name, value)| {
+                if SECRET_HEADERS.contains(&name.as_str()) {
+                    (name, "[REDACTED]".to_string())
+                } else {
+                    (name, value)
+                }
+            })
+            .collect();
+        Interaction {
+            request: RecordedRequest {
+                method: request.method,
+                path: request.path,
+                headers,
+                body: Body::new(&request.body),
+            },
+            response: RecordedResponse {
+                status: response.status,
+                content_type: response.content_type,
+                body: Body::new(&response.body),
+            },
+        }
+    }
+}
+
+/// A recording or replaying mock server backed by a cassette file.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    server: MockOpenAi,
+    secret_headers: Vec<String>,
+}
+
+impl Cassette {
+    /// Replays `path` if it exists and [`RECORD_ENV`] is unset, records to it otherwise.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, ApiRequestError> {
+        let path = path.as_ref();
+        if std::env::var_os(RECORD_ENV).is_none() && tokio::fs::try_exists(path).await? {
+            Self::replay(path).await
+        } else {
+            Self::record(path, BASE_URL).await
+        }
+    }
+
+    /// Forwards requests to `upstream`, e.g. `https://api.openai.com`, for [`Cassette::save`]
+    /// to write to `path`.
+    pub async fn record(
+        path: impl Into<PathBuf>,
+        upstream: impl Into<String>,
+    ) -> Result<Self, ApiRequestError> {
+        let server = MockOpenAi::start().await?;
+        server.forward_to(upstream);
+        Ok(Cassette {
+            path: path.into(),
+            mode: CassetteMode::Record,
+            server,
+            secret_headers: Vec::new(),
+        })
+    }
+
+    /// Answers requests with the responses recorded in `path`.
+    pub async fn replay(path: impl Into<PathBuf>) -> Result<Self, ApiRequestError> {
+        let path = path.into();
+        let interactions: Vec<Interaction> =
+            serde_json::from_slice(&tokio::fs::read(&path).await?)?;
+        let server = MockOpenAi::start().await?;
+        for Interaction { request, response } in interactions {
+            let body = response
+                .body
+                .bytes()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+            let route = request.path.split('?').next().unwrap_or_default();
+            server.push(
+                route,
+                MockResponse {
+                    status: response.status,
+                    content_type: response.content_type,
+                    headers: Vec::new(),
+                    body,
+                },
+            );
+        }
+        Ok(Cassette {
+            path,
+            mode: CassetteMode::Replay,
+            server,
+            secret_headers: Vec::new(),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Also redacts the header `name`, for clients that send their key in a header of their
+    /// own.
+    pub fn redact_header(mut self, name: impl Into<String>) -> Self {
+        self.secret_headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Root URL of the cassette's server, for clients built with other settings than
+    /// [`client`](Self::client) uses.
+    pub fn base_url(&self) -> String {
+        self.server.base_url()
+    }
+
+    /// A client pointed at the cassette. `api_key` is only passed on when recording.
+    pub fn client(&self, api_key: impl Into<String>) -> OpenAi {
+        let api_key = match self.mode {
+            CassetteMode::Record => api_key.into(),
+            CassetteMode::Replay => "sk-mock".to_string(),
+        };
+        OpenAi::builder()
+            .api_key(api_key)
+            .base_url(self.server.base_url())
+            .build()
+    }
+
+    /// Exchanges recorded so far, empty when replaying.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.server
+            .forwarded()
+            .into_iter()
+            .map(|exchange| {
+                let mut interaction = Interaction::from(exchange);
+                for (name, value) in &mut interaction.request.headers {
+                    if self.secret_headers.contains(&name.to_ascii_lowercase()) {
+                        *value = "[REDACTED]".to_string();
+                    }
+                }
+                interaction
+            })
+            .collect()
+    }
+
+    /// Writes the recorded exchanges to the cassette file, creating its directory. Does
+    /// nothing when replaying.
+    pub async fn save(&self) -> Result<(), ApiRequestError> {
+        if self.mode == CassetteMode::Replay {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let json = serde_json::to_vec_pretty(&self.interactions())?;
+        tokio::fs::write(&self.path, json).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthScheme;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let api = MockOpenAi::start().await.unwrap();
+        api.push("v1/chat/completions", MockResponse::chat_text("Hello!"));
+        let path = std::env::temp_dir().join(format!("openai-ox-{}.json", std::process::id()));
+
+        let recorder = Cassette::record(&path, api.base_url()).await.unwrap();
+        let reply = recorder
+            .client("sk-secret")
+            .generate("gpt-4o-mini", "Hi")
+            .await;
+        assert_eq!(reply.unwrap(), "Hello!");
+        recorder.save().await.unwrap();
+        let saved = tokio::fs::read_to_string(&path).await.unwrap();
+        assert!(!saved.contains("sk-secret"));
+        assert_eq!(
+            api.requests()[0].header("authorization"),
+            Some("Bearer sk-secret")
+        );
+
+        let player = Cassette::replay(&path).await.unwrap();
+        let reply = player.client("").generate("gpt-4o-mini", "Hi").await;
+        assert_eq!(reply.unwrap(), "Hello!");
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_redact_custom_auth_header() {
+        let api = MockOpenAi::start().await.unwrap();
+        api.push("v1/chat/completions", MockResponse::chat_text("Hello!"));
+        let recorder = Cassette::record("unused.json", api.base_url())
+            .await
+            .unwrap()
+            .redact_header("X-Gateway-Key");
+        let openai = OpenAi::builder()
+            .api_key("sk-secret")
+            .auth(AuthScheme::header("x-gateway-key"))
+            .base_url(recorder.base_url())
+            .build();
+        openai.generate("gpt-4o-mini", "Hi").await.unwrap();
+        let headers = &recorder.interactions()[0].request.headers;
+        assert!(headers.contains(&("x-gateway-key".to_string(), "[REDACTED]".to_string())));
+        assert_eq!(api.requests()[0].header("x-gateway-key"), Some("sk-secret"));
+    }
+
+    #[test]
+    fn test_body() {
+        assert_eq!(Body::new(b"{}"), Body::Text("{}".to_string()));
+        let binary = Body::new(&[0xff, 0x00]);
+        assert!(matches!(binary, Body::Binary { .. }));
+        assert_eq!(binary.bytes().unwrap(), [0xff, 0x00]);
+    }
+}
@@ -0,0 +1,365 @@
+//! Reassembling streamed chat deltas into a complete assistant message.
+//!
+//! Streamed tool calls arrive in fragments: the first one for a call carries its `id` and function
+//! name, the following ones append pieces of the JSON arguments. [`StreamAccumulator`] collects
+//! them, together with the text and refusal, until the stream ends.
+//!
+//! ```no_run
+//! # async fn example(request: openai_ox::chat::ChatCompletionRequest) -> Result<(), openai_ox::ApiRequestError> {
+//! use futures::StreamExt;
+//! use openai_ox::chat::accumulator::StreamAccumulator;
+//!
+//! let mut stream = request.stream().await;
+//! let mut acc = StreamAccumulator::new();
+//! while let Some(chunk) = stream.next().await {
+//!     let chunk = chunk?;
+//!     if let Some(text) = acc.push(&chunk) {
+//!         print!("{}", text);
+//!     }
+//! }
+//! for call in acc.tool_calls() {
+//!     println!("{}({})", call.name(), call.function.arguments);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! When only the end result matters, [`ChatStreamExt`] folds the stream in one call:
+//!
+//! ```no_run
+//! # async fn example(request: openai_ox::chat::ChatCompletionRequest) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::chat::accumulator::ChatStreamExt;
+//!
+//! let response = request.stream().await.collect_response().await?;
+//! println!("{:?} {:?}", response.text(), response.usage);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::BTreeMap, pin::pin};
+
+use futures::{stream::BoxStream, Stream, StreamExt};
+
+use super::{
+    message::{AssistantMessage, Message},
+    tool::ToolCall,
+    ChatCompletionChunkResponse, ChatCompletionResponse, Choice, Delta, FinishReason,
+    LogprobContent, TokenLogprob, ToolCallDelta, Usage,
+};
+use crate::ApiRequestError;
+
+/// Accumulates the deltas of the first choice of a chat completion stream.
+#[derive(Debug, Clone, Default)]
+pub struct StreamAccumulator {
+    content: Option<String>,
+    refusal: Option<String>,
+    tool_calls: BTreeMap<u32, ToolCall>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the delta of the first choice in `chunk` and returns its text, if any. The final
+    /// usage chunk has no choices and only updates [`StreamAccumulator::usage`].
+    pub fn push<'a>(&mut self, chunk: &'a ChatCompletionChunkResponse) -> Option<&'a str> {
+        if let Some(usage) = chunk.usage {
+            self.usage = Some(usage);
+        }
+        let choice = chunk.choices.iter().find(|choice| choice.index == 0)?;
+        if let Some(finish_reason) = choice.finish_reason {
+            self.finish_reason = Some(finish_reason);
+        }
+        self.push_delta(&choice.delta);
+        choice.delta.content.as_deref()
+    }
+
+    pub fn push_delta(&mut self, delta: &Delta) {
+        if let Some(content) = &delta.content {
+            self.content
+                .get_or_insert_with(String::new)
+                .push_str(content);
+        }
+        if let Some(refusal) = &delta.refusal {
+            self.refusal
+                .get_or_insert_with(String::new)
+                .push_str(refusal);
+        }
+        for call in delta.tool_calls.iter().flatten() {
+            self.push_tool_call(call);
+        }
+    }
+
+    fn push_tool_call(&mut self, delta: &ToolCallDelta) {
+        let call = self
+            .tool_calls
+            .entry(delta.index)
+            .or_insert_with(|| ToolCall::new("", "", ""));
+        if let Some(id) = &delta.id {
+            call.id.push_str(id);
+        }
+        if let Some(tool_type) = &delta.tool_type {
+            call.tool_type.clone_from(tool_type);
+        }
+        if let Some(function) = &delta.function {
+            if let Some(name) = &function.name {
+                call.function.name.push_str(name);
+            }
+            if let Some(arguments) = &function.arguments {
+                call.function.arguments.push_str(arguments);
+            }
+        }
+    }
+
+    /// Text received so far.
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    pub fn refusal(&self) -> Option<&str> {
+        self.refusal.as_deref()
+    }
+
+    /// Tool calls in the order the model made them. Arguments are only complete once the
+    /// stream finished.
+    pub fn tool_calls(&self) -> Vec<ToolCall> {
+        self.tool_calls.values().cloned().collect()
+    }
+
+    /// Token usage of the request, sent in the final chunk when
+    /// [`StreamOptions::include_usage`](super::StreamOptions::include_usage) is set.
+    pub fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    /// Set by the last chunk of the choice.
+    pub fn finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason
+    }
+
+    /// The assistant message the stream amounts to, ready to be pushed to the conversation
+    /// before the tool results.
+    pub fn into_message(self) -> AssistantMessage {
+        AssistantMessage {
+            content: self.content,
+            name: None,
+            tool_calls: (!self.tool_calls.is_empty())
+                .then(|| self.tool_calls.into_values().collect()),
+            refusal: self.refusal,
+            audio: None,
+        }
+    }
+}
+
+/// Adapters folding a chat completion chunk stream, such as the one of
+/// [`ChatCompletionRequest::stream`](super::ChatCompletionRequest::stream).
+#[async_trait::async_trait]
+pub trait ChatStreamExt:
+    Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>> + Send + Sized
+{
+    /// Text of the first choice once the stream ended.
+    async fn collect_text(self) -> Result<String, ApiRequestError> {
+        let mut stream = pin!(self);
+        let mut acc = StreamAccumulator::new();
+        while let Some(chunk) = stream.next().await {
+            acc.push(&chunk?);
+        }
+        Ok(acc.content().unwrap_or_default().to_string())
+    }
+
+    /// The response a non-streaming request would have returned, with every choice, its tool
+    /// calls and log probabilities. Usage is only known when the stream included it. A choice
+    /// the stream didn't finish gets [`FinishReason::Unknown`].
+    async fn collect_response(self) -> Result<ChatCompletionResponse, ApiRequestError> {
+        let mut stream = pin!(self);
+        let mut head = None;
+        let mut usage = None;
+        let mut choices: BTreeMap<u32, (StreamAccumulator, Option<LogprobContent>)> =
+            BTreeMap::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            usage = chunk.usage.or(usage);
+            head.get_or_insert_with(|| {
+                (
+                    chunk.id.clone(),
+                    chunk.created,
+                    chunk.model.clone(),
+                    chunk.system_fingerprint.clone(),
+                )
+            });
+            for choice in &chunk.choices {
+                let (acc, logprobs) = choices.entry(choice.index).or_default();
+                acc.push_delta(&choice.delta);
+                acc.finish_reason = choice.finish_reason.or(acc.finish_reason);
+                if let Some(more) = &choice.logprobs {
+                    let logprobs = logprobs.get_or_insert_with(LogprobContent::default);
+                    extend_logprobs(&mut logprobs.content, &more.content);
+                    extend_logprobs(&mut logprobs.refusal, &more.refusal);
+                }
+            }
+        }
+        let Some((id, created, model, system_fingerprint)) = head else {
+            return Err(ApiRequestError::UnexpectedResponse {
+                status: None,
+                response: "stream ended without any chunk".to_string(),
+            });
+        };
+        let choices = choices
+            .into_iter()
+            .map(|(index, (acc, logprobs))| Choice {
+                index,
+                finish_reason: acc.finish_reason.unwrap_or(FinishReason::Unknown),
+                message: Message::Assistant(acc.into_message()),
+                logprobs,
+            })
+            .collect();
+        Ok(ChatCompletionResponse {
+            id,
+            choices,
+            created,
+            model,
+            system_fingerprint: system_fingerprint.unwrap_or_default(),
+            object: "chat.completion".to_string(),
+            usage: usage.unwrap_or_default(),
+        })
+    }
+
+    /// Only the non-empty text deltas of the first choice.
+    fn text_chunks<'a>(self) -> BoxStream<'a, Result<String, ApiRequestError>>
+    where
+        Self: 'a,
+    {
+        self.filter_map(|chunk| async move {
+            match chunk {
+                Ok(chunk) => chunk
+                    .choices
+                    .into_iter()
+                    .find(|choice| choice.index == 0)
+                    .and_then(|choice| choice.delta.content)
+                    .filter(|text| !text.is_empty())
+                    .map(Ok),
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .boxed()
+    }
+}
+
+impl<S> ChatStreamExt for S where
+    S: Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>> + Send
+{
+}
+
+fn extend_logprobs(into: &mut Option<Vec<TokenLogprob>>, more: &Option<Vec<TokenLogprob>>) {
+    if let Some(more) = more {
+        into.get_or_insert_with(Vec::new)
+            .extend(more.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tool_call_fragments() {
+        let chunks = [
+            json!([{"index": 0, "id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": ""}}]),
+            json!([{"index": 0, "function": {"arguments": "{\"city\":"}}]),
+            json!([{"index": 1, "id": "call_2", "type": "function", "function": {"name": "get_time", "arguments": "{}"}}]),
+            json!([{"index": 0, "function": {"arguments": "\"Paris\"}"}}]),
+        ];
+        let mut acc = StreamAccumulator::new();
+        for tool_calls in chunks {
+            let chunk: ChatCompletionChunkResponse = serde_json::from_value(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "system_fingerprint": null,
+                "choices": [{"index": 0, "delta": {"tool_calls": tool_calls}}]
+            }))
+            .unwrap();
+            assert_eq!(acc.push(&chunk), None);
+        }
+        let last: ChatCompletionChunkResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o-mini",
+            "system_fingerprint": null,
+            "choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]
+        }))
+        .unwrap();
+        acc.push(&last);
+        assert_eq!(acc.finish_reason(), Some(FinishReason::ToolCalls));
+
+        let message = acc.into_message();
+        assert_eq!(message.content, None);
+        assert_eq!(
+            message.tool_calls.unwrap(),
+            [
+                ToolCall::new("call_1", "get_weather", r#"{"city":"Paris"}"#),
+                ToolCall::new("call_2", "get_time", "{}"),
+            ]
+        );
+    }
+
+    fn text_stream() -> impl Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>> {
+        let chunk = |choices: serde_json::Value, usage: serde_json::Value| {
+            serde_json::from_value::<ChatCompletionChunkResponse>(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion.chunk",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "system_fingerprint": "fp",
+                "choices": choices,
+                "usage": usage
+            }))
+        };
+        let chunks = [
+            chunk(
+                json!([{"index": 0, "delta": {"role": "assistant", "content": ""}}]),
+                json!(null),
+            ),
+            chunk(
+                json!([{"index": 0, "delta": {"content": "Hel"}}, {"index": 1, "delta": {"content": "Hi"}}]),
+                json!(null),
+            ),
+            chunk(
+                json!([{"index": 0, "delta": {"content": "lo"}, "finish_reason": "stop"}]),
+                json!(null),
+            ),
+            chunk(
+                json!([]),
+                json!({"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}),
+            ),
+        ];
+        futures::stream::iter(chunks.map(|chunk| chunk.map_err(ApiRequestError::from)))
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_ext() {
+        assert_eq!(text_stream().collect_text().await.unwrap(), "Hello");
+        let chunks: Vec<String> = text_stream()
+            .text_chunks()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(chunks, ["Hel", "lo"]);
+
+        let response = text_stream().collect_response().await.unwrap();
+        assert_eq!(response.id, "chatcmpl-1");
+        assert_eq!(response.text(), Some("Hello"));
+        assert_eq!(response.usage.total_tokens, 5);
+        assert_eq!(response.choices.len(), 2);
+        assert_eq!(response.choices[0].finish_reason, FinishReason::Stop);
+        assert_eq!(response.choices[1].message.content(), Some("Hi"));
+        assert_eq!(response.choices[1].finish_reason, FinishReason::Unknown);
+    }
+}
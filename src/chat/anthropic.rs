@@ -0,0 +1,230 @@
+//! Conversion between [`Messages`] and the Anthropic Messages API shape.
+//!
+//! Anthropic keeps the system prompt outside of the message list, only knows `user` and
+//! `assistant` roles, and represents tool calls and tool results as content blocks. The types in
+//! this module mirror that wire format so a single canonical history can be sent to either
+//! provider.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    message::{AssistantMessage, Message, Messages, ToolMessage},
+    tool::ToolCall,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnthropicRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnthropicMessage {
+    pub role: AnthropicRole,
+    pub content: Vec<ContentBlock>,
+}
+
+/// A conversation in Anthropic shape: the extracted system prompt plus alternating messages.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnthropicConversation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub messages: Vec<AnthropicMessage>,
+}
+
+impl AnthropicConversation {
+    fn push(&mut self, role: AnthropicRole, blocks: Vec<ContentBlock>) {
+        if blocks.is_empty() {
+            return;
+        }
+        // Anthropic requires strictly alternating roles, so consecutive turns are merged.
+        match self.messages.last_mut() {
+            Some(last) if last.role == role => last.content.extend(blocks),
+            _ => self.messages.push(AnthropicMessage {
+                role,
+                content: blocks,
+            }),
+        }
+    }
+}
+
+fn tool_use_block(tool_call: &ToolCall) -> ContentBlock {
+    let arguments = &tool_call.function.arguments;
+    let input = match arguments.trim() {
+        "" => json!({}),
+        _ => serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.clone())),
+    };
+    ContentBlock::ToolUse {
+        id: tool_call.id.clone(),
+        name: tool_call.function.name.clone(),
+        input,
+    }
+}
+
+impl From<&Messages> for AnthropicConversation {
+    fn from(messages: &Messages) -> Self {
+        let mut conversation = AnthropicConversation::default();
+        let mut system = Vec::new();
+        for message in messages.iter() {
+            match message {
+                Message::System(msg) => system.push(msg.content.clone()),
+                Message::Developer(msg) => system.push(msg.content.clone()),
+                Message::User(msg) => conversation.push(
+                    AnthropicRole::User,
+                    vec![ContentBlock::Text {
+                        text: msg.content.clone(),
+                    }],
+                ),
+                Message::Assistant(msg) => {
+                    let mut blocks = Vec::new();
+                    if let Some(text) = msg.content.as_ref().filter(|s| !s.is_empty()) {
+                        blocks.push(ContentBlock::Text { text: text.clone() });
+                    }
+                    blocks.extend(msg.tool_calls().iter().map(tool_use_block));
+                    conversation.push(AnthropicRole::Assistant, blocks);
+                }
+                Message::Tool(msg) => conversation.push(
+                    AnthropicRole::User,
+                    vec![ContentBlock::ToolResult {
+                        tool_use_id: msg.tool_call_id.clone(),
+                        content: msg.content.clone(),
+                        is_error: None,
+                    }],
+                ),
+            }
+        }
+        if !system.is_empty() {
+            conversation.system = Some(system.join("\n\n"));
+        }
+        conversation
+    }
+}
+
+impl From<AnthropicConversation> for Messages {
+    fn from(conversation: AnthropicConversation) -> Self {
+        let mut messages = Messages::default();
+        if let Some(system) = conversation.system {
+            messages.push(Message::system(system));
+        }
+        for message in conversation.messages {
+            let mut texts = Vec::new();
+            let mut tool_calls = Vec::new();
+            for block in message.content {
+                match block {
+                    ContentBlock::Text { text } => texts.push(text),
+                    ContentBlock::ToolUse { id, name, input } => {
+                        tool_calls.push(ToolCall::new(id, name, input.to_string()))
+                    }
+                    ContentBlock::ToolResult {
+                        tool_use_id,
+                        content,
+                        ..
+                    } => messages.push(Message::Tool(
+                        ToolMessage::builder()
+                            .content(content)
+                            .tool_call_id(tool_use_id)
+                            .build(),
+                    )),
+                }
+            }
+            match message.role {
+                AnthropicRole::User if !texts.is_empty() => {
+                    messages.push(Message::user(texts.join("\n")))
+                }
+                AnthropicRole::User => {}
+                AnthropicRole::Assistant => {
+                    messages.push(Message::Assistant(
+                        AssistantMessage::builder()
+                            .maybe_content((!texts.is_empty()).then(|| texts.join("\n")))
+                            .maybe_tool_calls((!tool_calls.is_empty()).then_some(tool_calls))
+                            .build(),
+                    ));
+                }
+            }
+        }
+        messages
+    }
+}
+
+impl Messages {
+    /// Converts the history into Anthropic shape, extracting system messages into a single
+    /// system prompt and merging consecutive turns of the same role.
+    pub fn to_anthropic(&self) -> AnthropicConversation {
+        AnthropicConversation::from(self)
+    }
+
+    pub fn from_anthropic(conversation: AnthropicConversation) -> Self {
+        Messages::from(conversation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn history() -> Messages {
+        serde_json::from_value(json!([
+            {"role": "system", "content": "You are terse."},
+            {"role": "user", "content": "Weather in Paris?"},
+            {"role": "assistant", "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": {"name": "weather", "arguments": "{\"city\":\"Paris\"}"}
+            }]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "18C"},
+            {"role": "assistant", "content": "18C and sunny."}
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_to_anthropic() {
+        let conversation = history().to_anthropic();
+        assert_eq!(conversation.system.as_deref(), Some("You are terse."));
+        assert_eq!(
+            serde_json::to_value(&conversation.messages).unwrap(),
+            json!([
+                {"role": "user", "content": [{"type": "text", "text": "Weather in Paris?"}]},
+                {"role": "assistant", "content": [{
+                    "type": "tool_use", "id": "call_1", "name": "weather", "input": {"city": "Paris"}
+                }]},
+                {"role": "user", "content": [{
+                    "type": "tool_result", "tool_use_id": "call_1", "content": "18C"
+                }]},
+                {"role": "assistant", "content": [{"type": "text", "text": "18C and sunny."}]}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let messages = Messages::from_anthropic(history().to_anthropic());
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            serde_json::to_value(history()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Prompt caching helpers.
+//!
+//! OpenAI caches prompts by exact prefix, so requests only hit the cache when the static part
+//! (system prompt, tool definitions) comes first and is byte-for-byte identical every time.
+//! [`ChatCompletionRequest::optimize_for_prompt_cache`] enforces that ordering, and
+//! [`Usage::cache_hit_rate`] shows how much of the prompt was served from the cache.
+
+use super::{message::Messages, ChatCompletionRequest, Usage};
+
+impl Messages {
+    /// Moves system and developer messages to the front, keeping the relative order of all
+    /// messages.
+    ///
+    /// Only use it when system messages aren't tied to their position in the history.
+    pub fn system_first(&mut self) {
+        self.sort_by_key(|message| !message.role().is_instruction());
+    }
+}
+
+impl ChatCompletionRequest {
+    /// Puts the static content in a stable prefix: system messages first and tool definitions
+    /// sorted by name, so identical setups serialize identically regardless of the order they
+    /// were assembled in.
+    pub fn optimize_for_prompt_cache(&mut self) {
+        self.messages.system_first();
+        if let Some(tools) = &mut self.tools {
+            tools.sort_by(|a, b| a.name().cmp(b.name()));
+        }
+    }
+}
+
+impl Usage {
+    /// Prompt tokens served from the prompt cache.
+    pub fn cached_tokens(&self) -> u32 {
+        self.prompt_tokens_details
+            .map(|details| details.cached_tokens)
+            .unwrap_or_default()
+    }
+
+    /// Share of prompt tokens served from the cache, between 0 and 1. On usage summed over many
+    /// requests this is the overall hit rate.
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.prompt_tokens == 0 {
+            return 0.0;
+        }
+        f64::from(self.cached_tokens()) / f64::from(self.prompt_tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chat::{
+            message::{Message, Role},
+            tool::{Function, Tool},
+            PromptTokensDetails,
+        },
+        OpenAi,
+    };
+
+    #[test]
+    fn test_cache_prefix_and_hit_rate() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let mut request = openai
+            .chat_completion()
+            .model("gpt-4o")
+            .messages(Messages(vec![
+                Message::user("Hi"),
+                Message::system("Be brief."),
+                Message::assistant("Hello"),
+            ]))
+            .tools(vec![
+                Tool::function(Function::builder().name("search").build()),
+                Tool::function(Function::builder().name("calculator").build()),
+            ])
+            .build()
+            .unwrap();
+        request.optimize_for_prompt_cache();
+        let roles: Vec<_> = request.messages.iter().map(Message::role).collect();
+        assert_eq!(roles, [Role::System, Role::User, Role::Assistant]);
+        assert_eq!(request.tools.unwrap()[0].name(), "calculator");
+
+        let usage = Usage {
+            prompt_tokens: 2000,
+            prompt_tokens_details: Some(PromptTokensDetails {
+                cached_tokens: 1500,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(usage.cached_tokens(), 1500);
+        assert_eq!((usage + usage).cache_hit_rate(), 0.75);
+        assert_eq!(Usage::default().cache_hit_rate(), 0.0);
+    }
+}
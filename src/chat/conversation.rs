@@ -0,0 +1,300 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    chat::{
+        message::{Message, Messages, Role},
+        Usage,
+    },
+    ApiRequestError, OpenAi,
+};
+
+/// Version written by [`Conversation::save`].
+pub const CONVERSATION_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ConversationFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    #[error("Conversation format version {0} is not supported by this version of the crate")]
+    UnsupportedVersion(u64),
+}
+
+/// Sampling parameters applied to every request of a conversation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+/// A chat session that keeps its message history and accumulated token usage.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    model: String,
+    messages: Messages,
+    params: ConversationParams,
+    usage: Usage,
+    openai: OpenAi,
+}
+
+/// On-disk layout of version 1. Older layouts get their own struct and a conversion into the
+/// newest one when the format changes.
+#[derive(Serialize, Deserialize)]
+struct SavedConversationV1 {
+    version: u32,
+    model: String,
+    messages: Messages,
+    #[serde(default)]
+    params: ConversationParams,
+    #[serde(default)]
+    usage: Usage,
+}
+
+impl Conversation {
+    pub fn new(openai: &OpenAi, model: impl Into<String>) -> Self {
+        Conversation {
+            model: model.into(),
+            messages: Messages::default(),
+            params: ConversationParams::default(),
+            usage: Usage::default(),
+            openai: openai.clone(),
+        }
+    }
+
+    pub fn with_system(mut self, content: impl Into<String>) -> Self {
+        self.messages.push(Message::system(content));
+        self
+    }
+
+    pub fn with_params(mut self, params: ConversationParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn messages(&self) -> &Messages {
+        &self.messages
+    }
+
+    pub fn params(&self) -> &ConversationParams {
+        &self.params
+    }
+
+    /// Usage summed over every reply in this conversation.
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    pub(crate) fn openai(&self) -> &OpenAi {
+        &self.openai
+    }
+
+    pub fn push(&mut self, message: impl Into<Message>) {
+        self.messages.push(message.into());
+    }
+
+    /// Text of the latest assistant message.
+    pub fn last_reply(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|message| message.role() == Role::Assistant)
+            .and_then(Message::content)
+    }
+
+    /// Drops the history, keeping the system and developer messages.
+    pub fn clear(&mut self) {
+        self.messages
+            .retain(|message| message.role().is_instruction());
+    }
+
+    /// Appends `message`, sends the history and appends the reply.
+    ///
+    /// On error the history is left as it was before the call.
+    pub async fn send(&mut self, message: impl Into<Message>) -> Result<String, ApiRequestError> {
+        self.messages.push(message.into());
+        let request = self
+            .openai
+            .chat_completion()
+            .model(self.model.clone())
+            .messages(self.messages.clone())
+            .maybe_temperature(self.params.temperature)
+            .maybe_top_p(self.params.top_p)
+            .maybe_max_tokens(self.params.max_tokens)
+            .maybe_seed(self.params.seed)
+            .maybe_stop(self.params.stop.clone())
+            .build();
+        let res = match request {
+            Ok(request) => request.send().await,
+            Err(e) => Err(e.into()),
+        };
+        let reply = res.and_then(|res| {
+            if let Some(refusal) = res.refusal() {
+                return Err(ApiRequestError::Refusal {
+                    refusal: refusal.to_owned(),
+                });
+            }
+            let usage = res.usage;
+            res.choices
+                .into_iter()
+                .next()
+                .filter(|choice| choice.message.content().is_some())
+                .map(|choice| (choice.message, usage))
+                .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+                    status: None,
+                    response: "chat completion returned no content".to_string(),
+                })
+        });
+        match reply {
+            Ok((reply, usage)) => {
+                let text = reply.content().unwrap_or_default().to_string();
+                self.messages.push(reply);
+                self.usage += usage;
+                Ok(text)
+            }
+            Err(e) => {
+                self.messages.pop();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Conversation {
+    /// Writes the model, history, parameters and usage totals as versioned JSON.
+    pub fn save(&self, writer: impl Write) -> Result<(), ConversationFileError> {
+        let saved = SavedConversationV1 {
+            version: CONVERSATION_FORMAT_VERSION,
+            model: self.model.clone(),
+            messages: self.messages.clone(),
+            params: self.params.clone(),
+            usage: self.usage,
+        };
+        serde_json::to_writer(writer, &saved)?;
+        Ok(())
+    }
+
+    /// Restores a conversation written by [`Conversation::save`] by this or an older version
+    /// of the crate, sending future requests with `openai`.
+    pub fn load(openai: &OpenAi, reader: impl Read) -> Result<Self, ConversationFileError> {
+        let value: Value = serde_json::from_reader(reader)?;
+        let saved: SavedConversationV1 = match value["version"].as_u64() {
+            Some(1) => serde_json::from_value(value)?,
+            Some(version) => return Err(ConversationFileError::UnsupportedVersion(version)),
+            None => {
+                return Err(
+                    <serde_json::Error as serde::de::Error>::missing_field("version").into(),
+                )
+            }
+        };
+        Ok(Conversation {
+            model: saved.model,
+            messages: saved.messages,
+            params: saved.params,
+            usage: saved.usage,
+            openai: openai.clone(),
+        })
+    }
+}
+
+impl OpenAi {
+    pub fn conversation(&self, model: impl Into<String>) -> Conversation {
+        Conversation::new(self, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let mut conversation = openai.conversation("gpt-4o").with_system("Be brief.");
+        conversation.push(Message::user("Hi"));
+        conversation.push(Message::assistant("Hello"));
+        assert_eq!(conversation.last_reply(), Some("Hello"));
+
+        conversation.clear();
+        assert_eq!(conversation.messages().len(), 1);
+        assert_eq!(conversation.messages()[0].role(), Role::System);
+        assert_eq!(conversation.last_reply(), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let mut conversation = openai
+            .conversation("gpt-4o")
+            .with_system("Be brief.")
+            .with_params(ConversationParams {
+                temperature: Some(0.2),
+                ..Default::default()
+            });
+        conversation.push(Message::user("Hi"));
+        conversation.push(Message::assistant("Hello"));
+        conversation.usage.total_tokens = 12;
+
+        let mut file = Vec::new();
+        conversation.save(&mut file).unwrap();
+        let loaded = Conversation::load(&openai, file.as_slice()).unwrap();
+        assert_eq!(loaded.model(), "gpt-4o");
+        assert_eq!(loaded.messages().len(), 3);
+        assert_eq!(loaded.last_reply(), Some("Hello"));
+        assert_eq!(loaded.params().temperature, Some(0.2));
+        assert_eq!(loaded.usage().total_tokens, 12);
+
+        let future = br#"{"version": 99, "model": "gpt-4o", "messages": []}"#;
+        assert!(matches!(
+            Conversation::load(&openai, &future[..]),
+            Err(ConversationFileError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_save_and_load_audio_reply() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let mut conversation = openai.conversation("gpt-4o-audio-preview");
+        conversation.push(Message::user("Say hello"));
+        conversation.push(
+            serde_json::from_value::<Message>(serde_json::json!({
+                "role": "assistant",
+                "audio": {
+                    "id": "audio_1",
+                    "data": "UklGRg==",
+                    "expires_at": 1729018505,
+                    "transcript": "Hello!"
+                }
+            }))
+            .unwrap(),
+        );
+
+        let mut file = Vec::new();
+        conversation.save(&mut file).unwrap();
+        let loaded = Conversation::load(&openai, file.as_slice()).unwrap();
+        let Message::Assistant(reply) = &loaded.messages()[1] else {
+            panic!("expected an assistant message");
+        };
+        let audio = reply.audio.as_ref().unwrap();
+        assert_eq!(audio.id, "audio_1");
+        assert_eq!(audio.bytes().unwrap(), b"RIFF");
+        assert_eq!(audio.expires_at, Some(1729018505));
+        assert_eq!(audio.transcript, "Hello!");
+    }
+}
@@ -0,0 +1,187 @@
+//! Few-shot examples rendered as chat turns.
+//!
+//! Each example becomes a user message with the input followed by an assistant message with the
+//! ideal output, placed right after the system prompt. When the examples don't all fit in a
+//! token budget, the first ones win, so push them in order of importance.
+//!
+//! ```
+//! use openai_ox::chat::{
+//!     examples::ExampleSet,
+//!     message::{Message, Messages},
+//! };
+//!
+//! let examples = ExampleSet::new()
+//!     .example("I love it!", "positive")
+//!     .example("Broke after a day.", "negative");
+//! let mut messages = Messages(vec![
+//!     Message::system("Classify the sentiment of the review."),
+//!     Message::user("Does what it says."),
+//! ]);
+//! examples.apply_to(&mut messages, Some(500));
+//! assert_eq!(messages.len(), 6);
+//! assert_eq!(messages[1].content(), Some("I love it!"));
+//! ```
+
+use super::message::{Message, Messages, TOKENS_PER_MESSAGE};
+use crate::rate_limit::approximate_tokens;
+
+/// An input and the output the model should produce for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    pub input: String,
+    pub output: String,
+}
+
+impl Example {
+    pub fn new(input: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+        }
+    }
+
+    fn tokens(&self, count: &impl Fn(&str) -> usize) -> usize {
+        count(&self.input) + count(&self.output) + 2 * TOKENS_PER_MESSAGE
+    }
+
+    fn to_messages(&self) -> [Message; 2] {
+        [
+            Message::user(self.input.clone()),
+            Message::assistant(self.output.clone()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExampleSet {
+    examples: Vec<Example>,
+}
+
+impl ExampleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn example(mut self, input: impl Into<String>, output: impl Into<String>) -> Self {
+        self.push(Example::new(input, output));
+        self
+    }
+
+    pub fn push(&mut self, example: Example) {
+        self.examples.push(example);
+    }
+
+    pub fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Example> {
+        self.examples.iter()
+    }
+
+    /// The leading examples that fit in `budget` tokens, counted with [`approximate_tokens`]. Use
+    /// [`ExampleSet::select_with`] with a real tokenizer when the budget is tight.
+    pub fn select(&self, budget: usize) -> &[Example] {
+        self.select_with(budget, approximate_tokens)
+    }
+
+    /// The leading examples that fit in `budget` tokens, counted with `count`.
+    pub fn select_with(&self, budget: usize, count: impl Fn(&str) -> usize) -> &[Example] {
+        let mut used = 0;
+        let fitting = self
+            .examples
+            .iter()
+            .take_while(|example| {
+                used += example.tokens(&count);
+                used <= budget
+            })
+            .count();
+        &self.examples[..fitting]
+    }
+
+    /// Every example as alternating user and assistant messages.
+    pub fn to_messages(&self) -> Messages {
+        render(&self.examples)
+    }
+
+    /// Inserts the examples after the leading system and developer messages of `messages`, all
+    /// of them or as many as fit in `budget` tokens.
+    pub fn apply_to(&self, messages: &mut Messages, budget: Option<usize>) {
+        let examples = match budget {
+            Some(budget) => self.select(budget),
+            None => &self.examples,
+        };
+        let at = messages
+            .iter()
+            .take_while(|message| message.role().is_instruction())
+            .count();
+        messages.splice(at..at, render(examples));
+    }
+}
+
+fn render(examples: &[Example]) -> Messages {
+    examples.iter().flat_map(Example::to_messages).collect()
+}
+
+impl FromIterator<Example> for ExampleSet {
+    fn from_iter<I: IntoIterator<Item = Example>>(iter: I) -> Self {
+        Self {
+            examples: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<I: Into<String>, O: Into<String>> FromIterator<(I, O)> for ExampleSet {
+    fn from_iter<T: IntoIterator<Item = (I, O)>>(iter: T) -> Self {
+        iter.into_iter()
+            .map(|(input, output)| Example::new(input, output))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::message::Role;
+
+    #[test]
+    fn test_select_and_apply() {
+        let examples: ExampleSet = [
+            ("2 + 2", "4"),
+            ("10 / 4", "2.5"),
+            ("a very long arithmetic question", "42"),
+        ]
+        .into_iter()
+        .collect();
+        // "2 + 2" and "4" cost 2 + 1 tokens plus 6 of overhead, the second example 9 too.
+        assert_eq!(examples.select(17).len(), 1);
+        assert_eq!(examples.select(18).len(), 2);
+        assert_eq!(examples.select_with(100, |_| 40).len(), 1);
+
+        let mut messages = Messages(vec![Message::system("Calculate."), Message::user("3 * 3")]);
+        examples.apply_to(&mut messages, Some(18));
+        let roles: Vec<_> = messages.iter().map(Message::role).collect();
+        assert_eq!(
+            roles,
+            [
+                Role::System,
+                Role::User,
+                Role::Assistant,
+                Role::User,
+                Role::Assistant,
+                Role::User
+            ]
+        );
+        assert_eq!(messages[4].content(), Some("2.5"));
+        assert_eq!(messages[5].content(), Some("3 * 3"));
+
+        let mut no_system = Messages::from("3 * 3");
+        examples.apply_to(&mut no_system, None);
+        assert_eq!(no_system.len(), 7);
+        assert_eq!(no_system[0].content(), Some("2 + 2"));
+    }
+}
@@ -1,16 +1,25 @@
 use std::{
     ops::{Deref, DerefMut},
-    sync::Arc,
+    path::Path,
 };
 
-use bon::{builder, Builder};
-use serde::{Deserialize, Deserializer, Serialize};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bon::Builder;
+use serde::{Deserialize, Serialize, Serializer};
 use serde_json::Value;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use super::tool::ToolCall;
+use crate::{images::ImageFile, ApiRequestError};
+
+/// Tokens every chat message costs on top of its content: role and delimiters.
+pub const TOKENS_PER_MESSAGE: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
     System,
+    /// Instructions for o-series reasoning models, which take them in place of system messages.
+    Developer,
     User,
     Assistant,
     Tool,
@@ -31,20 +40,245 @@ impl From<String> for SystemMessage {
     }
 }
 
+impl Role {
+    /// Whether messages of the role instruct the model rather than take part in the
+    /// conversation.
+    pub fn is_instruction(self) -> bool {
+        matches!(self, Role::System | Role::Developer)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Builder)]
-pub struct UserMessage {
+pub struct DeveloperMessage {
     #[builder(into)]
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
 }
 
+impl From<String> for DeveloperMessage {
+    fn from(content: String) -> Self {
+        DeveloperMessage::builder().content(content).build()
+    }
+}
+
+impl From<SystemMessage> for DeveloperMessage {
+    fn from(message: SystemMessage) -> Self {
+        DeveloperMessage {
+            content: message.content,
+            name: message.name,
+        }
+    }
+}
+
+/// A user message. Images are sent after the text, which keeps plain text messages a single
+/// string on the wire.
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[serde(from = "WireUserMessage", into = "WireUserMessage")]
+pub struct UserMessage {
+    #[builder(into)]
+    pub content: String,
+    pub name: Option<String>,
+    /// Parts following the text, e.g. images for vision models.
+    #[builder(default)]
+    pub parts: Vec<MultimodalContent>,
+}
+
 impl From<String> for UserMessage {
     fn from(content: String) -> Self {
         UserMessage::builder().content(content).build()
     }
 }
 
+impl UserMessage {
+    pub fn with_image(mut self, image: impl Into<ImageUrl>) -> Self {
+        self.parts.push(MultimodalContent::ImageUrl {
+            image_url: image.into(),
+        });
+        self
+    }
+
+    /// Attaches recorded audio for audio-capable models such as `gpt-4o-audio-preview`.
+    pub fn with_audio(mut self, audio: impl AsRef<[u8]>, format: InputAudioFormat) -> Self {
+        self.parts.push(MultimodalContent::InputAudio {
+            input_audio: InputAudio::from_bytes(audio, format),
+        });
+        self
+    }
+
+    /// Attaches a local image, sent inline as a base64 data URL.
+    pub async fn with_image_path(self, path: impl AsRef<Path>) -> Result<Self, ApiRequestError> {
+        let path = path.as_ref();
+        let bytes = tokio::fs::read(path).await?;
+        let image = ImageFile::new(path.to_string_lossy(), bytes);
+        Ok(self.with_image(&image))
+    }
+}
+
+/// A part of a user message's content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MultimodalContent {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+    InputAudio { input_audio: InputAudio },
+}
+
+/// How closely a vision model looks at an image; `low` costs a fixed, small number of tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    Auto,
+    Low,
+    High,
+}
+
+/// An image given by URL or inline as a `data:` URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+impl ImageUrl {
+    pub fn new(url: impl Into<String>) -> Self {
+        ImageUrl {
+            url: url.into(),
+            detail: None,
+        }
+    }
+
+    /// `data:` URL of `bytes` of the given MIME type, e.g. `image/png`.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>, mime: &str) -> Self {
+        ImageUrl::new(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+    }
+
+    pub fn detail(mut self, detail: ImageDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+impl From<String> for ImageUrl {
+    fn from(url: String) -> Self {
+        ImageUrl::new(url)
+    }
+}
+
+impl From<&str> for ImageUrl {
+    fn from(url: &str) -> Self {
+        ImageUrl::new(url)
+    }
+}
+
+impl From<&ImageFile> for ImageUrl {
+    fn from(image: &ImageFile) -> Self {
+        ImageUrl::from_bytes(&image.bytes, image.mime())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputAudioFormat {
+    Wav,
+    Mp3,
+}
+
+/// Audio sent to the model, base64 encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputAudio {
+    pub data: String,
+    pub format: InputAudioFormat,
+}
+
+impl InputAudio {
+    pub fn from_bytes(audio: impl AsRef<[u8]>, format: InputAudioFormat) -> Self {
+        InputAudio {
+            data: STANDARD.encode(audio),
+            format,
+        }
+    }
+}
+
+/// Spoken reply of the model when the request asked for the `audio` modality.
+///
+/// Later turns only need the `id` to refer back to it, until it expires, so requests send only
+/// the `id` when the reply is passed back in the conversation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssistantAudio {
+    pub id: String,
+    /// Base64 encoded audio in the requested format.
+    #[serde(default)]
+    pub data: String,
+    /// Unix timestamp after which the audio can no longer be referenced.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub transcript: String,
+}
+
+impl AssistantAudio {
+    pub fn bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        STANDARD.decode(&self.data)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum WireContent {
+    Text(String),
+    Parts(Vec<MultimodalContent>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireUserMessage {
+    content: WireContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+impl From<UserMessage> for WireUserMessage {
+    fn from(message: UserMessage) -> Self {
+        let content = if message.parts.is_empty() {
+            WireContent::Text(message.content)
+        } else {
+            let text = (!message.content.is_empty()).then_some(MultimodalContent::Text {
+                text: message.content,
+            });
+            WireContent::Parts(text.into_iter().chain(message.parts).collect())
+        };
+        WireUserMessage {
+            content,
+            name: message.name,
+        }
+    }
+}
+
+impl From<WireUserMessage> for UserMessage {
+    fn from(message: WireUserMessage) -> Self {
+        let (content, parts) = match message.content {
+            WireContent::Text(text) => (text, Vec::new()),
+            WireContent::Parts(parts) => {
+                let mut texts = Vec::new();
+                let mut rest = Vec::new();
+                for part in parts {
+                    match part {
+                        MultimodalContent::Text { text } => texts.push(text),
+                        part => rest.push(part),
+                    }
+                }
+                (texts.join("\n"), rest)
+            }
+        };
+        UserMessage {
+            content,
+            name: message.name,
+            parts,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Builder)]
 pub struct AssistantMessage {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -53,9 +287,38 @@ pub struct AssistantMessage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<Value>>,
+    pub tool_calls: Option<Vec<ToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refusal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AssistantAudio>,
+}
+
+/// A typed part of an assistant reply: either regular text or a safety refusal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    Refusal { refusal: String },
+}
+
+impl AssistantMessage {
+    /// Text and refusal of the reply as content parts, refusal last.
+    pub fn parts(&self) -> Vec<ContentPart> {
+        let text = self
+            .content
+            .iter()
+            .map(|text| ContentPart::Text { text: text.clone() });
+        let refusal = self.refusal.iter().map(|refusal| ContentPart::Refusal {
+            refusal: refusal.clone(),
+        });
+        text.chain(refusal).collect()
+    }
+
+    /// The requested tool calls, empty when the model didn't call any tool.
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.tool_calls.as_deref().unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Builder)]
@@ -65,11 +328,30 @@ pub struct ToolMessage {
     pub tool_call_id: String,
 }
 
+impl ToolMessage {
+    /// Tool message answering `tool_call_id` with `result`.
+    ///
+    /// Strings are sent as they are, anything else as JSON. A result that fails to serialize is
+    /// reported to the model as an error, the same way failed tool handlers are.
+    pub fn from_result(tool_call_id: impl Into<String>, result: impl Serialize) -> Self {
+        let content = match serde_json::to_value(result) {
+            Ok(Value::String(text)) => text,
+            Ok(value) => value.to_string(),
+            Err(e) => format!("Error: {}", e),
+        };
+        ToolMessage::builder()
+            .content(content)
+            .tool_call_id(tool_call_id.into())
+            .build()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "role")]
 #[serde(rename_all = "lowercase")]
 pub enum Message {
     System(SystemMessage),
+    Developer(DeveloperMessage),
     User(UserMessage),
     Assistant(AssistantMessage),
     Tool(ToolMessage),
@@ -79,20 +361,100 @@ impl Message {
     pub fn system(content: impl Into<String>) -> Self {
         Message::System(SystemMessage::from(content.into()))
     }
+    pub fn developer(content: impl Into<String>) -> Self {
+        Message::Developer(DeveloperMessage::from(content.into()))
+    }
     pub fn user(content: impl Into<String>) -> Self {
         Message::User(UserMessage::from(content.into()))
     }
     pub fn assistant(content: impl Into<String>) -> Self {
         Message::Assistant(AssistantMessage::builder().content(content.into()).build())
     }
+    /// System message attributed to `name`; see [`validate_name`](super::participants::validate_name)
+    /// for the accepted characters.
+    pub fn system_named(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Message::System(
+            SystemMessage::builder()
+                .content(content)
+                .name(name.into())
+                .build(),
+        )
+    }
+    /// User message from the participant `name`, to tell several users apart in one history.
+    pub fn user_named(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Message::User(
+            UserMessage::builder()
+                .content(content)
+                .name(name.into())
+                .build(),
+        )
+    }
+    /// Assistant message from the agent `name`, for transcripts shared by several agents.
+    pub fn assistant_named(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Message::Assistant(
+            AssistantMessage::builder()
+                .content(content.into())
+                .name(name.into())
+                .build(),
+        )
+    }
+    pub fn role(&self) -> Role {
+        match self {
+            Message::System(_) => Role::System,
+            Message::Developer(_) => Role::Developer,
+            Message::User(_) => Role::User,
+            Message::Assistant(_) => Role::Assistant,
+            Message::Tool(_) => Role::Tool,
+        }
+    }
     pub fn content(&self) -> Option<&str> {
         match self {
             Message::System(msg) => Some(&msg.content),
+            Message::Developer(msg) => Some(&msg.content),
             Message::User(msg) => Some(&msg.content),
             Message::Assistant(msg) => msg.content.as_deref(),
             Message::Tool(msg) => Some(&msg.content),
         }
     }
+    /// Participant the message is attributed to, if any.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Message::System(msg) => msg.name.as_deref(),
+            Message::Developer(msg) => msg.name.as_deref(),
+            Message::User(msg) => msg.name.as_deref(),
+            Message::Assistant(msg) => msg.name.as_deref(),
+            Message::Tool(_) => None,
+        }
+    }
+    /// Refusal text if this is an assistant message declining the request.
+    pub fn refusal(&self) -> Option<&str> {
+        match self {
+            Message::Assistant(msg) => msg.refusal.as_deref(),
+            _ => None,
+        }
+    }
+    /// Replaces the text of the message, whatever its role.
+    pub fn set_content(&mut self, content: impl Into<String>) {
+        let content = content.into();
+        match self {
+            Message::System(msg) => msg.content = content,
+            Message::Developer(msg) => msg.content = content,
+            Message::User(msg) => msg.content = content,
+            Message::Assistant(msg) => msg.content = Some(content),
+            Message::Tool(msg) => msg.content = content,
+        }
+    }
+    /// Appends `text` to the message as is, e.g. a streamed delta. An assistant message without
+    /// text starts with `text`.
+    pub fn push_content(&mut self, text: &str) {
+        match self {
+            Message::System(msg) => msg.content.push_str(text),
+            Message::Developer(msg) => msg.content.push_str(text),
+            Message::User(msg) => msg.content.push_str(text),
+            Message::Assistant(msg) => msg.content.get_or_insert_with(String::new).push_str(text),
+            Message::Tool(msg) => msg.content.push_str(text),
+        }
+    }
 }
 
 impl From<SystemMessage> for Message {
@@ -101,6 +463,12 @@ impl From<SystemMessage> for Message {
     }
 }
 
+impl From<DeveloperMessage> for Message {
+    fn from(message: DeveloperMessage) -> Self {
+        Message::Developer(message)
+    }
+}
+
 impl From<UserMessage> for Message {
     fn from(message: UserMessage) -> Self {
         Message::User(message)
@@ -113,9 +481,59 @@ impl From<AssistantMessage> for Message {
     }
 }
 
+impl From<ToolMessage> for Message {
+    fn from(message: ToolMessage) -> Self {
+        Message::Tool(message)
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Messages(pub Vec<Message>);
 
+/// A message as a request sends it: assistant audio is referred to by its `id` alone, the API
+/// rejects the rest.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RequestMessage<'a> {
+    Message(&'a Message),
+    AssistantWithAudio {
+        role: Role,
+        #[serde(flatten)]
+        message: AssistantMessage,
+        audio: AudioRef<'a>,
+    },
+}
+
+#[derive(Serialize)]
+struct AudioRef<'a> {
+    id: &'a str,
+}
+
+/// Serializes `messages` for the body of a chat completion request, see [`RequestMessage`].
+pub(crate) fn serialize_request_messages<S: Serializer>(
+    messages: &Messages,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(messages.iter().map(|message| match message {
+        Message::Assistant(
+            message @ AssistantMessage {
+                audio: Some(audio), ..
+            },
+        ) => RequestMessage::AssistantWithAudio {
+            role: Role::Assistant,
+            message: AssistantMessage {
+                content: message.content.clone(),
+                name: message.name.clone(),
+                tool_calls: message.tool_calls.clone(),
+                refusal: message.refusal.clone(),
+                audio: None,
+            },
+            audio: AudioRef { id: &audio.id },
+        },
+        message => RequestMessage::Message(message),
+    }))
+}
+
 impl Deref for Messages {
     type Target = Vec<Message>;
 
@@ -130,12 +548,147 @@ impl DerefMut for Messages {
     }
 }
 
+impl Message {
+    /// Whether the message carries nothing for the model: no text, tool calls or refusal.
+    /// Tool results are never considered empty, their call would be left unanswered.
+    fn is_empty(&self) -> bool {
+        match self {
+            Message::System(msg) => msg.content.trim().is_empty(),
+            Message::Developer(msg) => msg.content.trim().is_empty(),
+            Message::User(msg) => msg.content.trim().is_empty() && msg.parts.is_empty(),
+            Message::Assistant(msg) => {
+                let has_text = msg.content.as_deref().is_some_and(|c| !c.trim().is_empty());
+                let has_calls = msg.tool_calls.as_ref().is_some_and(|c| !c.is_empty());
+                !has_text && !has_calls && msg.refusal.is_none()
+            }
+            Message::Tool(_) => false,
+        }
+    }
+
+    /// Appends `next` to this message if both are the same role. The name is kept only when
+    /// both messages come from the same participant. Returns `next` back when they can't be
+    /// merged.
+    fn merge(&mut self, next: Message) -> Option<Message> {
+        fn join(text: &mut String, more: &str) {
+            if !text.is_empty() && !more.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(more);
+        }
+        fn join_name(name: &mut Option<String>, other: Option<String>) {
+            if *name != other {
+                *name = None;
+            }
+        }
+        match (self, next) {
+            (Message::System(msg), Message::System(next)) => {
+                join(&mut msg.content, &next.content);
+                join_name(&mut msg.name, next.name);
+            }
+            (Message::Developer(msg), Message::Developer(next)) => {
+                join(&mut msg.content, &next.content);
+                join_name(&mut msg.name, next.name);
+            }
+            (Message::User(msg), Message::User(next)) => {
+                join(&mut msg.content, &next.content);
+                msg.parts.extend(next.parts);
+                join_name(&mut msg.name, next.name);
+            }
+            (Message::Assistant(msg), Message::Assistant(next)) => {
+                join_name(&mut msg.name, next.name);
+                if let Some(more) = next.content {
+                    join(msg.content.get_or_insert_with(String::new), &more);
+                }
+                if let Some(calls) = next.tool_calls {
+                    msg.tool_calls.get_or_insert_with(Vec::new).extend(calls);
+                }
+                if let Some(more) = next.refusal {
+                    join(msg.refusal.get_or_insert_with(String::new), &more);
+                }
+            }
+            (_, next) => return Some(next),
+        }
+        None
+    }
+}
+
+impl Messages {
+    /// Merges adjacent messages of the same role and drops empty ones.
+    ///
+    /// Some OpenAI-compatible providers reject histories where roles don't alternate, which
+    /// agent loops produce easily. Messages from different participants are merged too, losing
+    /// their `name`. Tool results are kept as they are.
+    pub fn normalize(&mut self) {
+        let mut normalized: Vec<Message> = Vec::with_capacity(self.len());
+        for message in self.0.drain(..).filter(|message| !message.is_empty()) {
+            let rest = match normalized.last_mut() {
+                Some(last) => last.merge(message),
+                None => Some(message),
+            };
+            normalized.extend(rest);
+        }
+        self.0 = normalized;
+    }
+
+    /// Turns system messages into developer messages, for o-series models that reject the
+    /// `system` role.
+    pub fn system_to_developer(&mut self) {
+        for message in self.iter_mut() {
+            if let Message::System(msg) = message {
+                *message = Message::Developer(DeveloperMessage {
+                    content: std::mem::take(&mut msg.content),
+                    name: msg.name.take(),
+                });
+            }
+        }
+    }
+
+    /// Appends a tool message for every `(tool_call_id, result)` pair, see
+    /// [`ToolMessage::from_result`].
+    pub fn push_tool_results<I, S, R>(&mut self, results: I)
+    where
+        I: IntoIterator<Item = (S, R)>,
+        S: Into<String>,
+        R: Serialize,
+    {
+        self.0.extend(
+            results
+                .into_iter()
+                .map(|(id, result)| Message::Tool(ToolMessage::from_result(id, result))),
+        );
+    }
+}
+
 impl From<Message> for Messages {
     fn from(value: Message) -> Self {
         Messages(vec![value])
     }
 }
 
+impl From<Vec<Message>> for Messages {
+    fn from(value: Vec<Message>) -> Self {
+        Messages(value)
+    }
+}
+
+impl From<&str> for Messages {
+    fn from(value: &str) -> Self {
+        Messages::from(Message::user(value))
+    }
+}
+
+impl FromIterator<Message> for Messages {
+    fn from_iter<I: IntoIterator<Item = Message>>(iter: I) -> Self {
+        Messages(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Message> for Messages {
+    fn extend<I: IntoIterator<Item = Message>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
 impl IntoIterator for Messages {
     type Item = Message;
     type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -143,13 +696,24 @@ impl IntoIterator for Messages {
         self.0.into_iter()
     }
 }
+
+impl<'a> IntoIterator for &'a Messages {
+    type Item = &'a Message;
+    type IntoIter = std::slice::Iter<'a, Message>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
 #[cfg(test)]
 mod tests {
     use serde_json::json;
 
     use crate::chat::message::UserMessage;
 
-    use super::{AssistantMessage, Message, SystemMessage, ToolMessage};
+    use super::{
+        serialize_request_messages, AssistantMessage, ImageDetail, ImageUrl, InputAudioFormat,
+        Message, Messages, MultimodalContent, Role, SystemMessage, ToolCall, ToolMessage,
+    };
 
     #[test]
     fn test_assistant_message_deserialization() {
@@ -189,6 +753,81 @@ mod tests {
         assert_eq!(msg.content, "What is the weather?");
     }
 
+    #[test]
+    fn test_user_message_images() {
+        let message = UserMessage::from("What's in this image?".to_string())
+            .with_image(ImageUrl::new("https://example.com/cat.png").detail(ImageDetail::Low))
+            .with_image(ImageUrl::from_bytes(b"GIF89a", "image/gif"));
+        let json = serde_json::to_value(Message::User(message)).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What's in this image?"},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/cat.png", "detail": "low"}},
+                    {"type": "image_url", "image_url": {"url": "data:image/gif;base64,R0lGODlh"}}
+                ]
+            })
+        );
+
+        let Message::User(back) = serde_json::from_value(json).unwrap() else {
+            panic!("Expected user message");
+        };
+        assert_eq!(back.content, "What's in this image?");
+        assert!(matches!(
+            &back.parts[0],
+            MultimodalContent::ImageUrl { image_url } if image_url.detail == Some(ImageDetail::Low)
+        ));
+        assert_eq!(
+            serde_json::to_value(Message::user("Hi")).unwrap(),
+            json!({"role": "user", "content": "Hi"})
+        );
+    }
+
+    #[test]
+    fn test_audio_messages() {
+        let message = UserMessage::from(String::new()).with_audio(b"RIFF", InputAudioFormat::Wav);
+        assert_eq!(
+            serde_json::to_value(Message::User(message)).unwrap(),
+            json!({
+                "role": "user",
+                "content": [{"type": "input_audio", "input_audio": {"data": "UklGRg==", "format": "wav"}}]
+            })
+        );
+
+        let reply: AssistantMessage = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": null,
+            "audio": {
+                "id": "audio_1",
+                "data": "UklGRg==",
+                "expires_at": 1729018505,
+                "transcript": "Hello!"
+            }
+        }))
+        .unwrap();
+        let audio = reply.audio.as_ref().unwrap();
+        assert_eq!(audio.bytes().unwrap(), b"RIFF");
+        assert_eq!(audio.transcript, "Hello!");
+        let mut messages = Messages(vec![Message::Assistant(reply)]);
+        let sent = serialize_request_messages(&messages, serde_json::value::Serializer).unwrap();
+        assert_eq!(
+            sent,
+            json!([{"role": "assistant", "audio": {"id": "audio_1"}}])
+        );
+
+        // Everywhere else the audio is kept whole, so saved conversations keep it.
+        let saved = serde_json::to_string(&messages).unwrap();
+        messages = serde_json::from_str(&saved).unwrap();
+        let Message::Assistant(reply) = &messages[0] else {
+            panic!("expected an assistant message");
+        };
+        let audio = reply.audio.as_ref().unwrap();
+        assert_eq!(audio.expires_at, Some(1729018505));
+        assert_eq!(audio.transcript, "Hello!");
+    }
+
     #[test]
     fn test_tool_message_deserialization() {
         let json = json!({
@@ -202,6 +841,112 @@ mod tests {
         assert_eq!(msg.tool_call_id, "weather_123");
     }
 
+    #[test]
+    fn test_normalize() {
+        let mut messages = Messages(vec![
+            Message::system("Be brief."),
+            Message::user("Hi"),
+            Message::user(" "),
+            Message::user("Are you there?"),
+            Message::user_named("bob", "Hello"),
+            Message::assistant_named("ann", "Yes."),
+            Message::assistant_named("ann", ""),
+            Message::assistant_named("ann", "Hi bob."),
+            Message::user_named("bob", "Thanks"),
+            Message::assistant(""),
+            Message::assistant("Yes."),
+            Message::Assistant(
+                AssistantMessage::builder()
+                    .tool_calls(vec![ToolCall::new("call_1", "search", "{}")])
+                    .build(),
+            ),
+            Message::Tool(
+                ToolMessage::builder()
+                    .content("")
+                    .tool_call_id("call_1".to_string())
+                    .build(),
+            ),
+        ]);
+        messages.normalize();
+        let roles: Vec<_> = messages.iter().map(Message::role).collect();
+        assert_eq!(
+            roles,
+            [
+                Role::System,
+                Role::User,
+                Role::Assistant,
+                Role::User,
+                Role::Assistant,
+                Role::Tool
+            ]
+        );
+        assert_eq!(messages[1].content(), Some("Hi\n\nAre you there?\n\nHello"));
+        assert_eq!(messages[1].name(), None);
+        assert_eq!(messages[2].content(), Some("Yes.\n\nHi bob."));
+        assert_eq!(messages[2].name(), Some("ann"));
+        assert_eq!(messages[3].name(), Some("bob"));
+        let Message::Assistant(reply) = &messages[4] else {
+            panic!("Expected assistant message");
+        };
+        assert_eq!(reply.content.as_deref(), Some("Yes."));
+        assert_eq!(reply.tool_calls.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_tool_results() {
+        let reply: AssistantMessage = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}
+            }, {
+                "id": "call_2",
+                "type": "function",
+                "function": {"name": "get_time", "arguments": ""}
+            }]
+        }))
+        .unwrap();
+        let calls = reply.tool_calls();
+        assert_eq!(calls[0].name(), "get_weather");
+        assert_eq!(
+            calls[0].arguments::<serde_json::Value>().unwrap(),
+            json!({"city": "Oslo"})
+        );
+        assert_eq!(
+            calls[1].arguments::<serde_json::Value>().unwrap(),
+            json!({})
+        );
+
+        let mut messages = Messages(vec![Message::Assistant(reply)]);
+        messages.push_tool_results([("call_1", json!({"temp_c": 4})), ("call_2", json!("12:00"))]);
+        let Message::Tool(weather) = &messages[1] else {
+            panic!("Expected tool message");
+        };
+        assert_eq!(weather.tool_call_id, "call_1");
+        assert_eq!(weather.content, r#"{"temp_c":4}"#);
+        assert_eq!(messages[2].content(), Some("12:00"));
+        assert!(AssistantMessage::builder().build().tool_calls().is_empty());
+    }
+
+    #[test]
+    fn test_messages_collection() {
+        let mut messages: Messages = ["Hi", "Anyone?"].into_iter().map(Message::user).collect();
+        messages.extend([Message::assistant("Hello!")]);
+        assert_eq!(messages.len(), 3);
+
+        let mut roles = Vec::new();
+        for message in &messages {
+            roles.push(message.role());
+        }
+        assert_eq!(roles, [Role::User, Role::User, Role::Assistant]);
+
+        let from_str = Messages::from("Hi");
+        assert_eq!(from_str[0].role(), Role::User);
+        assert_eq!(Messages::from(vec![Message::system("Be brief.")]).len(), 1);
+    }
+
     #[test]
     fn test_message_deserialization() {
         let json = json!({
@@ -1,39 +1,90 @@
+pub mod accumulator;
+pub mod anthropic;
+pub mod cache;
+pub mod conversation;
+pub mod examples;
 pub mod message;
+pub mod partial;
+pub mod participants;
+pub mod recovery;
+#[cfg(feature = "redaction")]
+pub mod redaction;
+pub mod refusal;
+pub mod runner;
+pub mod tool;
+#[cfg(feature = "tokenizer")]
+pub mod truncation;
+
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign},
+    time::Duration,
+};
 
 use bon::Builder;
-use futures::{Stream, StreamExt};
-use serde::{Deserialize, Serialize};
+use futures::{stream::BoxStream, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
+use thiserror::Error;
 
-use crate::{ApiRequestError, ErrorResponse, OpenAi, BASE_URL};
-
-use self::message::{Message, Messages};
+use crate::{
+    auth::Authorize,
+    require_client,
+    responses::ReasoningEffort,
+    retry::SendRetrying,
+    sse::{idle_timeout, sse_events, SseEvent, StreamingBody},
+    ApiRequest, ApiRequestError, ApiRequestWithClient, OpenAi,
+};
 
-const API_URL: &str = "v1/chat/completions";
+#[cfg(feature = "tokenizer")]
+use crate::tokenizer::{
+    TokenCount, Tokenizer, REPLY_PRIMING_TOKENS, TOKENS_PER_MESSAGE, TOKENS_PER_NAME,
+};
+#[cfg(not(feature = "tokenizer"))]
+use crate::{chat::message::TOKENS_PER_MESSAGE, rate_limit::estimate_tokens};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[serde(rename = "text")]
-pub struct TextType;
+use self::{
+    message::{Message, Messages, Role},
+    participants::NameError,
+    tool::{ToolChoice, Tools},
+};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[serde(rename = "json_object")]
-pub struct JsonType;
+const API_URL: &str = "v1/chat/completions";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ResponseFormat {
-    Text {
-        #[serde(rename = "type")]
-        format_type: TextType,
-    },
-    Json {
-        #[serde(rename = "type")]
-        format_type: JsonType,
+    Text,
+    /// Any valid JSON object.
+    #[serde(rename = "json_object")]
+    Json,
+    /// JSON matching a schema, see [`crate::extract`].
+    JsonSchema {
+        json_schema: JsonSchemaFormat,
     },
 }
 
-#[derive(Debug, Clone, Serialize, Builder)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// A chat completion request.
+///
+/// Requests serialize to the API's JSON body and deserialize from it, e.g. from a config file.
+/// The client isn't part of the JSON: a deserialized request, or one built without `.openai(..)`,
+/// is sent with [`ApiRequest::send_with`].
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
 pub struct ChatCompletionRequest {
+    /// Sent with the audio of earlier replies cut down to its `id`.
     #[builder(into)]
+    #[serde(serialize_with = "message::serialize_request_messages")]
     pub messages: Messages,
     #[builder(into)]
     pub model: String,
@@ -45,39 +96,253 @@ pub struct ChatCompletionRequest {
     pub logprobs: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_logprobs: Option<u32>,
+    /// Deprecated in favor of `max_completion_tokens` and rejected by o-series models.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
+    /// Upper bound on generated tokens, reasoning tokens included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<u32>,
+    /// Output types the model should produce, e.g. text and audio with `gpt-4o-audio-preview`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub modalities: Option<Vec<Modality>>,
+    /// Voice and format of the reply, required when `modalities` includes audio.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioOutput>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub presence_penalty: Option<f64>,
+    /// How long reasoning models think before answering.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Groups requests sharing a long prefix so they are routed to the same prompt cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub prompt_cache_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response_format: Option<ResponseFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub seed: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Kept for compatibility and never sent: [`ChatCompletionRequest::stream`] asks for a
+    /// stream on its own and [`ChatCompletionRequest::send`] never does.
+    #[serde(skip)]
     pub stream: Option<bool>,
+    /// Only sent by [`ChatCompletionRequest::stream`] and its variants, the API rejects it on
+    /// regular requests.
+    #[serde(skip)]
+    pub stream_options: Option<StreamOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Value>,
+    #[builder(into)]
+    pub tools: Option<Tools>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Whether the model may call several tools in one reply, which it does by default. Run
+    /// them with [`tool::ToolRegistry::call_all`]. Only accepted together with `tools`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    /// Client used by `send` and `stream`, which fail with [`ApiRequestError::MissingClient`]
+    /// without one.
     #[serde(skip)]
-    pub openai: OpenAi,
+    pub openai: Option<OpenAi>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modality {
+    Text,
+    Audio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioOutputFormat {
+    Wav,
+    Mp3,
+    Flac,
+    Opus,
+    Pcm16,
+}
+
+/// Requested form of a spoken reply, see [`message::AssistantAudio`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Builder)]
+pub struct AudioOutput {
+    /// One of the built-in voices, e.g. `alloy`.
+    #[builder(into)]
+    pub voice: String,
+    pub format: AudioOutputFormat,
+}
+
+/// Options of a streamed chat completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Sends one more chunk before `[DONE]`, with no choices and the token usage of the whole
+    /// request in [`ChatCompletionChunkResponse::usage`].
+    pub include_usage: bool,
+}
+
+const MAX_TOP_LOGPROBS: u32 = 20;
+
+/// Parameter values the API would reject with a 400.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ChatCompletionRequestError {
+    #[error("{param} must be between {min} and {max}, got {value}")]
+    OutOfRange {
+        param: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    #[error("n must be at least 1")]
+    ZeroChoices,
+    #[error("top_logprobs must be at most {}, got {}", MAX_TOP_LOGPROBS, .0)]
+    TooManyTopLogprobs(u32),
+    #[error("top_logprobs requires logprobs to be enabled")]
+    TopLogprobsWithoutLogprobs,
+    #[error(transparent)]
+    InvalidName(#[from] NameError),
+    #[error("tool_choice names {0}, which is not one of the tools")]
+    UnknownToolChoice(String),
+    #[error("{model} does not support {param}")]
+    UnsupportedParameter { param: &'static str, model: String },
+    #[error("parallel_tool_calls requires tools")]
+    ParallelToolCallsWithoutTools,
+}
+
+impl From<ChatCompletionRequestError> for ApiRequestError {
+    fn from(e: ChatCompletionRequestError) -> Self {
+        let param = match e {
+            ChatCompletionRequestError::OutOfRange { param, .. } => param,
+            ChatCompletionRequestError::ZeroChoices => "n",
+            ChatCompletionRequestError::TooManyTopLogprobs(_)
+            | ChatCompletionRequestError::TopLogprobsWithoutLogprobs => "top_logprobs",
+            ChatCompletionRequestError::InvalidName(_) => "messages",
+            ChatCompletionRequestError::UnknownToolChoice(_) => "tool_choice",
+            ChatCompletionRequestError::UnsupportedParameter { param, .. } => param,
+            ChatCompletionRequestError::ParallelToolCallsWithoutTools => "parallel_tool_calls",
+        };
+        ApiRequestError::InvalidRequestError {
+            message: e.to_string(),
+            param: Some(param.to_string()),
+            code: None,
+        }
+    }
+}
+
+/// Valid but questionable parameter combinations, reported by
+/// [`ChatCompletionRequest::warnings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatCompletionWarning {
+    /// OpenAI recommends altering `temperature` or `top_p`, not both.
+    TemperatureWithTopP,
+}
+
+fn check_range(
+    param: &'static str,
+    value: Option<f64>,
+    min: f64,
+    max: f64,
+) -> Result<(), ChatCompletionRequestError> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => {
+            Err(ChatCompletionRequestError::OutOfRange {
+                param,
+                value,
+                min,
+                max,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+impl<S: chat_completion_request_builder::IsComplete> ChatCompletionRequestBuilder<S> {
+    pub fn build(self) -> Result<ChatCompletionRequest, ChatCompletionRequestError> {
+        let request = self.build_unchecked();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+impl ChatCompletionRequest {
+    /// Checks the parameters against the ranges documented by the API.
+    pub fn validate(&self) -> Result<(), ChatCompletionRequestError> {
+        check_range("temperature", self.temperature, 0.0, 2.0)?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_range("presence_penalty", self.presence_penalty, -2.0, 2.0)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2.0, 2.0)?;
+        if self.n == Some(0) {
+            return Err(ChatCompletionRequestError::ZeroChoices);
+        }
+        if let Some(top_logprobs) = self.top_logprobs {
+            if top_logprobs > MAX_TOP_LOGPROBS {
+                return Err(ChatCompletionRequestError::TooManyTopLogprobs(top_logprobs));
+            }
+            if self.logprobs != Some(true) {
+                return Err(ChatCompletionRequestError::TopLogprobsWithoutLogprobs);
+            }
+        }
+        self.messages.validate_names()?;
+        if is_reasoning_model(&self.model) {
+            let unsupported = [
+                ("temperature", self.temperature.is_some()),
+                ("top_p", self.top_p.is_some()),
+                ("max_tokens", self.max_tokens.is_some()),
+            ];
+            if let Some((param, _)) = unsupported.into_iter().find(|(_, set)| *set) {
+                return Err(ChatCompletionRequestError::UnsupportedParameter {
+                    param,
+                    model: self.model.clone(),
+                });
+            }
+        }
+        if let Some(ToolChoice::Function(name)) = &self.tool_choice {
+            if self
+                .tools
+                .as_ref()
+                .and_then(|tools| tools.get(name))
+                .is_none()
+            {
+                return Err(ChatCompletionRequestError::UnknownToolChoice(name.clone()));
+            }
+        }
+        let has_tools = self.tools.as_ref().is_some_and(|tools| !tools.is_empty());
+        if self.parallel_tool_calls.is_some() && !has_tools {
+            return Err(ChatCompletionRequestError::ParallelToolCallsWithoutTools);
+        }
+        Ok(())
+    }
+
+    pub fn warnings(&self) -> Vec<ChatCompletionWarning> {
+        let mut warnings = Vec::new();
+        if self.temperature.is_some() && self.top_p.is_some() {
+            warnings.push(ChatCompletionWarning::TemperatureWithTopP);
+        }
+        warnings
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FinishReason {
     Stop,
-    Limit,
+    /// The reply hit `max_completion_tokens` or the context window and is cut off.
+    Length,
     ContentFilter,
     ToolCalls,
+    /// Legacy `functions` call, sent by older models.
+    FunctionCall,
+    /// A reason this crate doesn't know yet.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -85,32 +350,128 @@ pub struct Choice {
     pub index: u32,
     pub message: Message,
     pub finish_reason: FinishReason,
-    pub logprobs: Option<serde_json::Value>,
+    pub logprobs: Option<LogprobContent>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Log probabilities of the tokens of a choice, requested with `logprobs`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogprobContent {
+    pub content: Option<Vec<TokenLogprob>>,
+    pub refusal: Option<Vec<TokenLogprob>>,
+}
+
+impl LogprobContent {
+    /// The content tokens, empty if the model refused.
+    pub fn tokens(&self) -> &[TokenLogprob] {
+        self.content.as_deref().unwrap_or_default()
+    }
+
+    /// Perplexity of the content, `exp` of the mean negative log probability. `None` without
+    /// tokens.
+    pub fn perplexity(&self) -> Option<f64> {
+        let tokens = self.tokens();
+        if tokens.is_empty() {
+            return None;
+        }
+        let sum: f64 = tokens.iter().map(|token| token.logprob).sum();
+        Some((-sum / tokens.len() as f64).exp())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    /// UTF-8 bytes of the token, for tokens that split a multi-byte character.
+    pub bytes: Option<Vec<u8>>,
+    /// The most likely tokens at this position, as many as `top_logprobs` asked for.
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+impl TokenLogprob {
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
+    pub bytes: Option<Vec<u8>>,
+}
+
+impl TopLogprob {
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FunctionCallDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+/// Fragment of a tool call. The first fragment for a given `index` carries the `id` and function
+/// name, later ones only append to `function.arguments`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    pub index: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub tool_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Delta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<FunctionCallDelta>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChoiceStreamed {
     pub index: u32,
+    #[serde(default)]
     pub delta: Delta,
+    #[serde(default)]
     pub finish_reason: Option<FinishReason>,
-    pub logprobs: Option<serde_json::Value>,
+    #[serde(default)]
+    pub logprobs: Option<LogprobContent>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Usage {
+    #[serde(default)]
     pub prompt_tokens: u32,
+    #[serde(default)]
     pub completion_tokens: u32,
-    pub completion_tokens_details: CompletionTokensDetails,
-    pub prompt_tokens_details: PromptTokensDetails,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    #[serde(default)]
     pub total_tokens: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CompletionTokensDetails {
     pub accepted_prediction_tokens: u32,
     pub audio_tokens: u32,
@@ -118,12 +479,84 @@ pub struct CompletionTokensDetails {
     pub rejected_prediction_tokens: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PromptTokensDetails {
     pub audio_tokens: u32,
     pub cached_tokens: u32,
 }
 
+fn add_details<T: Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+impl Add for CompletionTokensDetails {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        CompletionTokensDetails {
+            accepted_prediction_tokens: self.accepted_prediction_tokens
+                + rhs.accepted_prediction_tokens,
+            audio_tokens: self.audio_tokens + rhs.audio_tokens,
+            reasoning_tokens: self.reasoning_tokens + rhs.reasoning_tokens,
+            rejected_prediction_tokens: self.rejected_prediction_tokens
+                + rhs.rejected_prediction_tokens,
+        }
+    }
+}
+
+impl Add for PromptTokensDetails {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        PromptTokensDetails {
+            audio_tokens: self.audio_tokens + rhs.audio_tokens,
+            cached_tokens: self.cached_tokens + rhs.cached_tokens,
+        }
+    }
+}
+
+impl Add for Usage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Usage {
+            prompt_tokens: self.prompt_tokens + rhs.prompt_tokens,
+            completion_tokens: self.completion_tokens + rhs.completion_tokens,
+            completion_tokens_details: add_details(
+                self.completion_tokens_details,
+                rhs.completion_tokens_details,
+            ),
+            prompt_tokens_details: add_details(
+                self.prompt_tokens_details,
+                rhs.prompt_tokens_details,
+            ),
+            total_tokens: self.total_tokens + rhs.total_tokens,
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for Usage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Usage::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Usage> for Usage {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
     pub id: String,
@@ -135,20 +568,54 @@ pub struct ChatCompletionResponse {
     pub usage: Usage,
 }
 
-// impl From<ChatCompletionResponse> for String {
-//     fn from(response: ChatCompletionResponse) -> Self {
-//         response
-//             .choices
-//             .into_iter()
-//             .map(|c| match c.message {
-//                 Message::System(msg) => msg.content.clone(),
-//                 Message::User(msg) => msg.content.clone(),
-//                 Message::Assistant(msg) => msg.content.clone(),
-//                 Message::Tool(_) => String::new(),
-//             })
-//             .collect()
-//     }
-// }
+impl ChatCompletionResponse {
+    /// The choice with index 0, the only one unless `n` was set.
+    pub fn first_choice(&self) -> Option<&Choice> {
+        self.choices.iter().find(|choice| choice.index == 0)
+    }
+
+    /// Text of the first choice, `None` for a tool call or refusal without text.
+    pub fn text(&self) -> Option<&str> {
+        self.first_choice()?.message.content()
+    }
+
+    /// Text of the first choice. A refusal is returned as [`ApiRequestError::Refusal`].
+    pub fn into_text(self) -> Result<String, ApiRequestError> {
+        if let Some(refusal) = self.refusal() {
+            return Err(ApiRequestError::Refusal {
+                refusal: refusal.to_owned(),
+            });
+        }
+        self.text()
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+                status: None,
+                response: "response contains no text".to_string(),
+            })
+    }
+
+    /// Refusal of the first choice, if the model declined to answer.
+    pub fn refusal(&self) -> Option<&str> {
+        self.first_choice()?.message.refusal()
+    }
+
+    /// Deserializes the text of the first choice, e.g. a reply constrained by a JSON schema.
+    /// A refusal is returned as [`ApiRequestError::Refusal`].
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, ApiRequestError> {
+        if let Some(refusal) = self.refusal() {
+            return Err(ApiRequestError::Refusal {
+                refusal: refusal.to_owned(),
+            });
+        }
+        let content = self
+            .text()
+            .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+                status: None,
+                response: "response contains no text".to_string(),
+            })?;
+        Ok(crate::json::from_str(content)?)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionChunkResponse {
@@ -158,6 +625,9 @@ pub struct ChatCompletionChunkResponse {
     pub model: String,
     pub system_fingerprint: Option<String>,
     pub object: String,
+    /// Only set on the final chunk, and only when requested with [`StreamOptions`].
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
 impl From<ChatCompletionChunkResponse> for String {
@@ -170,109 +640,213 @@ impl From<ChatCompletionChunkResponse> for String {
     }
 }
 
+/// Parses the server-sent events of a streamed completion into chunks, ending at `[DONE]`.
+fn chunk_stream<S, B, E>(
+    body: S,
+) -> impl Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<ApiRequestError>,
+{
+    sse_events(body)
+        .take_while(|event| {
+            let done = matches!(event, Ok(event) if event.data == "[DONE]");
+            futures::future::ready(!done)
+        })
+        .map(|event| {
+            event.and_then(|event| {
+                crate::json::from_str::<ChatCompletionChunkResponse>(&event.data)
+                    .map_err(ApiRequestError::SerdeError)
+            })
+        })
+}
+
+/// Whether `model` is an o-series reasoning model, e.g. `o1`, `o3-mini` or `o4-mini`.
+pub fn is_reasoning_model(model: &str) -> bool {
+    let mut chars = model.chars();
+    chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
 impl ChatCompletionRequest {
+    /// Sends system messages as developer messages when the model is an o-series reasoning
+    /// model, which rejects the `system` role. Other models are left as they are.
+    pub fn adapt_roles_to_model(&mut self) {
+        if is_reasoning_model(&self.model) {
+            self.messages.system_to_developer();
+        }
+    }
+
     pub fn push_message(&mut self, message: impl Into<Message>) {
         self.messages.push(message.into());
     }
     pub async fn send(&self) -> Result<ChatCompletionResponse, ApiRequestError> {
-        let url = format!("{}/{}", BASE_URL, API_URL);
-        let req = self
-            .openai
+        self.send_with(require_client(&self.openai)?).await
+    }
+
+    /// Prompt tokens plus the most the reply may use, what the request costs of a token bucket.
+    fn estimated_tokens(&self) -> usize {
+        #[cfg(feature = "tokenizer")]
+        let prompt = self.token_count();
+        #[cfg(not(feature = "tokenizer"))]
+        let prompt: usize = self
+            .messages
+            .iter()
+            .map(|message| {
+                TOKENS_PER_MESSAGE
+                    + estimate_tokens(&self.model, message.content().unwrap_or_default())
+            })
+            .sum();
+        let reply = self.max_completion_tokens.or(self.max_tokens).unwrap_or(0);
+        prompt + reply as usize * self.n.unwrap_or(1) as usize
+    }
+
+    /// Sends the streaming request and returns the response once it has started successfully.
+    async fn start_stream(&self) -> Result<reqwest::Response, ApiRequestError> {
+        let openai = require_client(&self.openai)?;
+        openai
+            .inner
+            .acquire_tokens(|| self.estimated_tokens())
+            .await;
+        let url = openai.inner.model_url(API_URL, &self.model);
+        let res = openai
+            .inner
             .client
-            .post(&url)
-            .bearer_auth(&self.openai.api_key)
-            .json(self);
-        let res = req.send().await?;
+            .post(url)
+            .authorize(openai)
+            .await?
+            .json(&StreamingBody::new(self).options(self.stream_options))
+            .send_streaming(openai)
+            .await?;
         if res.status().is_success() {
-            let data: ChatCompletionResponse = res.json().await?;
-            Ok(data)
+            Ok(res)
         } else {
-            let error_response: ErrorResponse = res.json().await?;
-            Err(ApiRequestError::InvalidRequestError {
-                message: error_response.error.message,
-                param: error_response.error.param,
-                code: error_response.error.code,
-            })
+            Err(ApiRequestError::from_response(res).await)
         }
     }
 
+    fn stream_idle(&self) -> Option<Duration> {
+        self.openai
+            .as_ref()
+            .and_then(|openai| openai.inner.timeouts.stream_idle)
+    }
+
+    /// Streams the completion chunk by chunk. A failed or rejected request never panics: its
+    /// error is the first and only item of the stream.
     pub async fn stream(
         &self,
-    ) -> impl Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>> {
-        let url = format!("{}/{}", BASE_URL, API_URL);
-        let mut body = serde_json::to_value(self).unwrap();
-        body["stream"] = serde_json::Value::Bool(true);
+    ) -> BoxStream<'static, Result<ChatCompletionChunkResponse, ApiRequestError>> {
+        match self.start_stream().await {
+            Ok(res) => chunk_stream(idle_timeout(res.bytes_stream(), self.stream_idle())).boxed(),
+            Err(e) => futures::stream::once(async { Err(e) }).boxed(),
+        }
+    }
 
-        let stream = self
-            .openai
-            .client
-            .post(url)
-            .bearer_auth(&self.openai.api_key)
-            .json(&body)
-            .send()
-            .await
-            .unwrap()
-            .bytes_stream();
-
-        let filtered_stream = stream.flat_map(|chunk| {
-            let chunk = match chunk {
-                Ok(bytes) => String::from_utf8(bytes.to_vec())
-                    .map_err(|e| ApiRequestError::Stream(e.to_string())),
-                Err(e) => Err(ApiRequestError::Stream(e.to_string())),
-            };
-
-            let responses = chunk
-                .map(|data| match data.as_str() {
-                    "" => vec![],
-                    s if s.starts_with("data: ") => s
-                        .split("\n\n")
-                        .filter(|chunk| !chunk.is_empty() && chunk != &"data: [DONE]")
-                        .filter_map(|chunk| chunk.strip_prefix("data: "))
-                        .map(|json_str| {
-                            serde_json::from_str::<ChatCompletionChunkResponse>(json_str)
-                                .map_err(ApiRequestError::SerdeError)
-                        })
-                        .filter(|res| {
-                            res.as_ref().is_ok_and(|res| {
-                                !res.choices.iter().any(|choice| {
-                                    choice.delta.content.as_ref().is_some_and(|s| {
-                                        dbg!(s);
-                                        dbg!(s.is_empty())
-                                    })
-                                })
-                            })
-                        })
-                        .collect(),
-                    _ => vec![Err(ApiRequestError::Stream(format!(
-                        "Invalid event data: {}",
-                        data
-                    )))],
+    /// Like [`stream`](Self::stream), but ends with [`ApiRequestError::Cancelled`] as soon as
+    /// `token` is cancelled, whether the response has started or not.
+    #[cfg(feature = "cancellation")]
+    pub async fn stream_cancellable(
+        &self,
+        token: crate::CancellationToken,
+    ) -> BoxStream<'static, Result<ChatCompletionChunkResponse, ApiRequestError>> {
+        crate::cancel::cancellable_stream(token, self.stream()).await
+    }
+
+    /// Streams the untouched server-sent events, including the final `[DONE]` marker.
+    ///
+    /// Useful for debugging, proxying the stream to another client, or handling event types this
+    /// crate does not model yet.
+    pub async fn stream_raw(&self) -> impl Stream<Item = Result<SseEvent, ApiRequestError>> {
+        match self.start_stream().await {
+            Ok(res) => sse_events(idle_timeout(res.bytes_stream(), self.stream_idle())).boxed(),
+            Err(e) => futures::stream::once(async { Err(e) }).boxed(),
+        }
+    }
+}
+
+/// Text and tool calls of the message plus the per-message overhead. Images and audio are not
+/// counted.
+#[cfg(feature = "tokenizer")]
+impl TokenCount for Message {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        let calls: usize = match self {
+            Message::Assistant(message) => message
+                .tool_calls()
+                .iter()
+                .map(|call| {
+                    tokenizer.count(&call.function.name) + tokenizer.count(&call.function.arguments)
                 })
-                .unwrap_or_else(|e| vec![Err(e)]);
+                .sum(),
+            _ => 0,
+        };
+        let name = self
+            .name()
+            .map_or(0, |name| TOKENS_PER_NAME + tokenizer.count(name));
+        TOKENS_PER_MESSAGE
+            + self.content().token_count_with(tokenizer)
+            + self.refusal().token_count_with(tokenizer)
+            + name
+            + calls
+    }
+}
 
-            futures::stream::iter(responses)
-        });
+/// Every message plus the tokens priming the reply.
+#[cfg(feature = "tokenizer")]
+impl TokenCount for Messages {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        self.iter()
+            .map(|message| message.token_count_with(tokenizer))
+            .sum::<usize>()
+            + REPLY_PRIMING_TOKENS
+    }
+}
 
-        Box::pin(filtered_stream)
+/// Prompt tokens of the messages with the encoding of the request's model; tool definitions
+/// are not counted.
+#[cfg(feature = "tokenizer")]
+impl TokenCount for ChatCompletionRequest {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        self.messages.token_count_with(tokenizer)
+    }
+
+    fn token_count(&self) -> usize {
+        self.token_count_with(&Tokenizer::for_model(&self.model))
     }
 }
 
-// impl TokenCount for Message {
-//     fn token_count(&self) -> usize {
-//         match self {
-//             Message::System(message) => message.content.token_count(),
-//             Message::User(message) => message.content.token_count(),
-//             Message::Assistant(message) => message.content.token_count(),
-//             Message::Tool(message) => message.content.token_count(),
-//         }
-//     }
-// }
+#[async_trait::async_trait]
+impl ApiRequest for ChatCompletionRequest {
+    type Response = ChatCompletionResponse;
 
-// impl TokenCount for Messages {
-//     fn token_count(&self) -> usize {
-//         self.0.iter().map(|m| m.token_count()).sum()
-//     }
-// }
+    async fn send_with(&self, openai: &OpenAi) -> Result<ChatCompletionResponse, ApiRequestError> {
+        openai
+            .inner
+            .acquire_tokens(|| self.estimated_tokens())
+            .await;
+        let url = openai.inner.model_url(API_URL, &self.model);
+        let req = openai
+            .inner
+            .client
+            .post(&url)
+            .authorize(openai)
+            .await?
+            .json(self);
+        let res = req.send_retrying(openai).await?;
+        if res.status().is_success() {
+            let data: ChatCompletionResponse = res.json().await?;
+            Ok(data)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiRequestWithClient for ChatCompletionRequest {
+    async fn send(&self) -> Result<ChatCompletionResponse, ApiRequestError> {
+        self.send_with(require_client(&self.openai)?).await
+    }
+}
 
 impl OpenAi {
     pub fn chat_completion(
@@ -280,49 +854,376 @@ impl OpenAi {
     ) -> ChatCompletionRequestBuilder<chat_completion_request_builder::SetOpenai> {
         ChatCompletionRequest::builder().openai(self.clone())
     }
+
+    /// Sends `prompt` as a single user message and returns the reply text.
+    ///
+    /// For scripts and examples; anything beyond one prompt wants [`OpenAi::chat_completion`].
+    /// A refusal is returned as [`ApiRequestError::Refusal`].
+    pub async fn generate(
+        &self,
+        model: impl Into<String>,
+        prompt: impl Into<String>,
+    ) -> Result<String, ApiRequestError> {
+        self.chat_completion()
+            .model(model)
+            .messages(Message::user(prompt))
+            .build()?
+            .send()
+            .await?
+            .into_text()
+    }
 }
 
 #[cfg(test)]
 mod test {
 
     use futures::StreamExt;
+    use serde_json::{json, Value};
 
     use crate::{
-        chat::{message::Messages, Message},
-        OpenAi,
+        chat::{
+            message::Role, ChatCompletionChunkResponse, ChatCompletionRequest,
+            ChatCompletionRequestError, ChatCompletionResponse, ChatCompletionWarning,
+            FinishReason, LogprobContent, Message, Usage,
+        },
+        responses::ReasoningEffort,
+        ApiRequestError, OpenAi,
     };
 
+    #[test]
+    fn test_usage_without_details_and_sum() {
+        let a: Usage = serde_json::from_value(json!({
+            "prompt_tokens": 10,
+            "completion_tokens": 5,
+            "total_tokens": 15
+        }))
+        .unwrap();
+        let b: Usage = serde_json::from_value(json!({
+            "prompt_tokens": 20,
+            "completion_tokens": 10,
+            "total_tokens": 30,
+            "prompt_tokens_details": {"cached_tokens": 8}
+        }))
+        .unwrap();
+        assert!(a.prompt_tokens_details.is_none());
+
+        let mut total: Usage = [a, b].iter().sum();
+        assert_eq!(total.total_tokens, 45);
+        assert_eq!(total.prompt_tokens_details.unwrap().cached_tokens, 8);
+        assert!(total.completion_tokens_details.is_none());
+
+        total += a;
+        assert_eq!(total.prompt_tokens, 40);
+    }
+
+    #[test]
+    fn test_chunk_with_tool_call_delta() {
+        let chunk: ChatCompletionChunkResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "delta": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "index": 0,
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "weather", "arguments": ""}
+                    }],
+                    "annotations": []
+                },
+                "finish_reason": null
+            }]
+        }))
+        .unwrap();
+        let delta = &chunk.choices[0].delta;
+        assert_eq!(delta.role, Some(Role::Assistant));
+        let tool_call = &delta.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(tool_call.id.as_deref(), Some("call_1"));
+        assert_eq!(
+            tool_call.function.as_ref().unwrap().name.as_deref(),
+            Some("weather")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chunk_stream_across_tcp_chunks() {
+        let body = "data: {\"id\":\"c\",\"object\":\"chat.completion.chunk\",\"created\":1,\
+                    \"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"}}]}\r\n\r\n\
+                    : keep-alive\n\n\
+                    data: [DONE]\n\n";
+        let (first, second) = body.split_at(40);
+        let bytes = futures::stream::iter([Ok::<_, ApiRequestError>(first), Ok(second)]);
+        let chunks: Vec<_> = super::chunk_stream(bytes).collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(
+            String::from(chunks.into_iter().next().unwrap().unwrap()),
+            "Hi"
+        );
+    }
+
+    #[cfg(feature = "mock")]
     #[tokio::test]
     async fn test_chat_no_stream() {
-        let api_key = std::env::var("OPENAI_API_KEY").unwrap();
-        let client = reqwest::Client::new();
-        let openai = OpenAi::builder().api_key(api_key).client(client).build();
-        let res = openai
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push("v1/chat/completions", MockResponse::chat_text("Hi John!"));
+        let res = mock
+            .client()
             .chat_completion()
             .model("gpt-4o")
             .messages(Message::user("Hi, I'm John."))
             .build()
+            .unwrap()
             .send()
-            .await;
-        dbg!(&res);
+            .await
+            .unwrap();
+        assert_eq!(res.text(), Some("Hi John!"));
+
+        let sent: ChatCompletionRequest = mock.requests()[0].json().unwrap();
+        assert_eq!(sent.model, "gpt-4o");
+        assert_eq!(sent.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_request_validation() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let request = || {
+            openai
+                .chat_completion()
+                .model("gpt-4o")
+                .messages(Message::user("Hi"))
+        };
+        assert_eq!(
+            request().temperature(2.5).build().unwrap_err(),
+            ChatCompletionRequestError::OutOfRange {
+                param: "temperature",
+                value: 2.5,
+                min: 0.0,
+                max: 2.0
+            }
+        );
+        assert_eq!(
+            request().n(0).build().unwrap_err(),
+            ChatCompletionRequestError::ZeroChoices
+        );
+        assert_eq!(
+            request().top_logprobs(5).build().unwrap_err(),
+            ChatCompletionRequestError::TopLogprobsWithoutLogprobs
+        );
+        assert_eq!(
+            request()
+                .logprobs(true)
+                .top_logprobs(21)
+                .build()
+                .unwrap_err(),
+            ChatCompletionRequestError::TooManyTopLogprobs(21)
+        );
+        assert_eq!(
+            request().parallel_tool_calls(false).build().unwrap_err(),
+            ChatCompletionRequestError::ParallelToolCallsWithoutTools
+        );
+
+        let reasoning = || {
+            openai
+                .chat_completion()
+                .model("o4-mini")
+                .messages(Message::user("Hi"))
+        };
+        assert_eq!(
+            reasoning().temperature(0.2).build().unwrap_err(),
+            ChatCompletionRequestError::UnsupportedParameter {
+                param: "temperature",
+                model: "o4-mini".to_string()
+            }
+        );
+        let tuned = reasoning()
+            .reasoning_effort(ReasoningEffort::High)
+            .max_completion_tokens(4096)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&tuned).unwrap(),
+            json!({
+                "model": "o4-mini",
+                "messages": [{"role": "user", "content": "Hi"}],
+                "max_completion_tokens": 4096,
+                "reasoning_effort": "high"
+            })
+        );
+
+        let request = request().temperature(0.7).top_p(0.9).build().unwrap();
+        assert_eq!(
+            request.warnings(),
+            [ChatCompletionWarning::TemperatureWithTopP]
+        );
+    }
+
+    #[test]
+    fn test_finish_reason() {
+        let reasons: Vec<FinishReason> =
+            serde_json::from_value(json!(["stop", "length", "function_call", "pause_turn"]))
+                .unwrap();
+        assert_eq!(
+            reasons,
+            [
+                FinishReason::Stop,
+                FinishReason::Length,
+                FinishReason::FunctionCall,
+                FinishReason::Unknown
+            ]
+        );
+    }
+
+    #[test]
+    fn test_response_text() {
+        let response = |message: Value| -> ChatCompletionResponse {
+            serde_json::from_value(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "system_fingerprint": "fp",
+                "choices": [{"index": 0, "message": message, "finish_reason": "stop", "logprobs": null}],
+                "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+            }))
+            .unwrap()
+        };
+        let reply = response(json!({"role": "assistant", "content": "Hello"}));
+        assert_eq!(
+            reply.first_choice().unwrap().finish_reason,
+            FinishReason::Stop
+        );
+        assert_eq!(reply.text(), Some("Hello"));
+        assert_eq!(reply.into_text().unwrap(), "Hello");
+
+        let refusal = response(json!({"role": "assistant", "content": null, "refusal": "No."}));
+        assert_eq!(refusal.text(), None);
+        assert!(matches!(
+            refusal.into_text(),
+            Err(ApiRequestError::Refusal { .. })
+        ));
+    }
+
+    #[test]
+    fn test_logprobs() {
+        let logprobs: LogprobContent = serde_json::from_value(json!({
+            "content": [{
+                "token": "Hi",
+                "logprob": -0.1,
+                "bytes": [72, 105],
+                "top_logprobs": [
+                    {"token": "Hi", "logprob": -0.1, "bytes": [72, 105]},
+                    {"token": "Hello", "logprob": -2.4, "bytes": null}
+                ]
+            }, {
+                "token": "!",
+                "logprob": -0.3,
+                "bytes": [33],
+                "top_logprobs": []
+            }],
+            "refusal": null
+        }))
+        .unwrap();
+        let tokens = logprobs.tokens();
+        assert_eq!(tokens[0].bytes.as_deref(), Some(&b"Hi"[..]));
+        assert_eq!(tokens[0].top_logprobs[1].token, "Hello");
+        assert!((tokens[1].probability() - (-0.3f64).exp()).abs() < 1e-12);
+        assert!((logprobs.perplexity().unwrap() - 0.2f64.exp()).abs() < 1e-12);
+        assert_eq!(LogprobContent::default().perplexity(), None);
     }
 
+    #[test]
+    fn test_request_round_trip() {
+        let request = ChatCompletionRequest::builder()
+            .model("gpt-4o")
+            .messages(vec![Message::system("Be brief."), Message::user("Hi")])
+            .temperature(0.2)
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        let loaded: ChatCompletionRequest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.model, "gpt-4o");
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(loaded.temperature, Some(0.2));
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), json);
+        assert!(loaded.openai.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_send_without_client() {
+        let request = ChatCompletionRequest::builder()
+            .model("gpt-4o")
+            .messages(Message::user("Hi"))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            request.send().await,
+            Err(ApiRequestError::MissingClient)
+        ));
+        let mut stream = request.stream().await;
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ApiRequestError::MissingClient))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn test_developer_role_for_reasoning_models() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let request = |model: &str| {
+            let mut request = openai
+                .chat_completion()
+                .model(model)
+                .messages(vec![Message::system("Be brief."), Message::user("Hi")])
+                .build()
+                .unwrap();
+            request.adapt_roles_to_model();
+            request.messages[0].role()
+        };
+        assert_eq!(request("o3-mini"), Role::Developer);
+        assert_eq!(request("o1"), Role::Developer);
+        assert_eq!(request("gpt-4o"), Role::System);
+        assert_eq!(
+            serde_json::to_value(Message::developer("Be brief.")).unwrap(),
+            json!({"role": "developer", "content": "Be brief."})
+        );
+    }
+
+    #[cfg(feature = "mock")]
     #[tokio::test]
     async fn test_chat_stream() {
-        let api_key = std::env::var("OPENAI_API_KEY").unwrap();
-        let client = reqwest::Client::new();
-        let openai = OpenAi::builder().api_key(api_key).client(client).build();
-        let mut res = openai
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/chat/completions",
+            MockResponse::chat_stream_text(["Hi ", "John", "!"]),
+        );
+        let mut res = mock
+            .client()
             .chat_completion()
             .model("gpt-4o")
             .stream(true)
             .messages(Message::user("Hi, I'm John."))
             .build()
+            .unwrap()
             .stream()
             .await;
+        let mut text = String::new();
         while let Some(res) = res.next().await {
-            dbg!(String::from(res.unwrap()));
+            text.push_str(&String::from(res.unwrap()));
         }
+        assert_eq!(text, "Hi John!");
+
+        let sent: Value = mock.requests()[0].json().unwrap();
+        assert_eq!(sent["stream"], true);
     }
 
     // #[cfg(feature = "tools")]
@@ -0,0 +1,242 @@
+//! Incremental parsing of streamed JSON output.
+//!
+//! When a response is constrained to a JSON object, its top level fields arrive one after
+//! another. [`PartialJsonParser`] consumes the streamed text and reports every top level field as
+//! soon as its value is complete, so a UI can render e.g. a `title` long before the full object has
+//! been generated.
+
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::ApiRequestError;
+
+use super::{ChatCompletionChunkResponse, ChatCompletionRequest};
+
+/// Incremental scanner over a JSON object that arrives in pieces.
+#[derive(Debug, Default, Clone)]
+pub struct PartialJsonParser {
+    buffer: String,
+    pos: usize,
+    depth: usize,
+    in_string: bool,
+    escape: bool,
+    key_start: Option<usize>,
+    key: Option<String>,
+    value_start: Option<usize>,
+    fields: Map<String, Value>,
+}
+
+impl PartialJsonParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `chunk` and returns the top level fields completed by it, in order.
+    pub fn push(&mut self, chunk: &str) -> Vec<(String, Value)> {
+        self.buffer.push_str(chunk);
+        let mut completed = Vec::new();
+        let start = self.pos;
+        let chars: Vec<(usize, char)> = self.buffer[start..]
+            .char_indices()
+            .map(|(offset, c)| (start + offset, c))
+            .collect();
+        for (i, c) in chars {
+            if self.in_string {
+                if self.escape {
+                    self.escape = false;
+                } else if c == '\\' {
+                    self.escape = true;
+                } else if c == '"' {
+                    self.in_string = false;
+                    if self.depth == 1 && self.key.is_none() {
+                        if let Some(key_start) = self.key_start.take() {
+                            self.key = serde_json::from_str(&self.buffer[key_start..=i]).ok();
+                        }
+                    }
+                }
+                continue;
+            }
+            match c {
+                '"' => {
+                    self.in_string = true;
+                    if self.depth == 1 && self.key.is_none() {
+                        self.key_start = Some(i);
+                    }
+                }
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => {
+                    if self.depth == 1 {
+                        completed.extend(self.complete_field(i));
+                    }
+                    self.depth = self.depth.saturating_sub(1);
+                }
+                ':' if self.depth == 1 && self.key.is_some() && self.value_start.is_none() => {
+                    self.value_start = Some(i + 1);
+                }
+                ',' if self.depth == 1 => completed.extend(self.complete_field(i)),
+                _ => {}
+            }
+        }
+        self.pos = self.buffer.len();
+        completed
+    }
+
+    fn complete_field(&mut self, end: usize) -> Option<(String, Value)> {
+        self.key_start = None;
+        let key = self.key.take()?;
+        let start = self.value_start.take()?;
+        let value: Value = serde_json::from_str(self.buffer[start..end].trim()).ok()?;
+        self.fields.insert(key.clone(), value.clone());
+        Some((key, value))
+    }
+
+    /// Top level fields completed so far.
+    pub fn fields(&self) -> &Map<String, Value> {
+        &self.fields
+    }
+
+    /// Deserializes the completed fields into `P`, typically a struct whose fields are all
+    /// `Option`s mirroring the final type.
+    pub fn snapshot<P: DeserializeOwned>(&self) -> Result<P, serde_json::Error> {
+        serde_json::from_value(Value::Object(self.fields.clone()))
+    }
+
+    /// Text received so far.
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Parses the complete text into `T`.
+    pub fn finish<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(self.buffer.trim())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StructuredEvent<T> {
+    /// A top level field whose value has been fully received.
+    Field { name: String, value: Value },
+    /// The whole object, parsed once the stream finished.
+    Complete(T),
+}
+
+struct StructuredState<S> {
+    inner: S,
+    parser: PartialJsonParser,
+    pending: std::collections::VecDeque<(String, Value)>,
+    done: bool,
+}
+
+/// Turns a chunk stream into [`StructuredEvent`]s for the first choice.
+pub fn structured_events<T, S>(
+    stream: S,
+) -> impl Stream<Item = Result<StructuredEvent<T>, ApiRequestError>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>> + Unpin,
+{
+    let state = StructuredState {
+        inner: stream,
+        parser: PartialJsonParser::new(),
+        pending: Default::default(),
+        done: false,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some((name, value)) = state.pending.pop_front() {
+                return Some((Ok(StructuredEvent::Field { name, value }), state));
+            }
+            if state.done {
+                return None;
+            }
+            match state.inner.next().await {
+                Some(Ok(chunk)) => {
+                    let text = chunk
+                        .choices
+                        .into_iter()
+                        .find(|choice| choice.index == 0)
+                        .and_then(|choice| choice.delta.content)
+                        .unwrap_or_default();
+                    let fields = state.parser.push(&text);
+                    state.pending.extend(fields);
+                }
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+                None => {
+                    state.done = true;
+                    let item = state
+                        .parser
+                        .finish()
+                        .map(StructuredEvent::Complete)
+                        .map_err(ApiRequestError::SerdeError);
+                    return Some((item, state));
+                }
+            }
+        }
+    })
+}
+
+impl ChatCompletionRequest {
+    /// Streams a JSON response, yielding each top level field as soon as it is complete and the
+    /// fully parsed `T` at the end.
+    pub async fn stream_structured<T: DeserializeOwned>(
+        &self,
+    ) -> impl Stream<Item = Result<StructuredEvent<T>, ApiRequestError>> {
+        structured_events(self.stream().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Article {
+        title: String,
+        tags: Vec<String>,
+        meta: Value,
+    }
+
+    #[test]
+    fn test_fields_complete_incrementally() {
+        let mut parser = PartialJsonParser::new();
+        assert!(parser.push("{\"tit").is_empty());
+        assert!(parser.push("le\": \"Hello, \\\"world\\\"\"").is_empty());
+        assert_eq!(
+            parser.push(", \"tags\": [\"a\", "),
+            vec![("title".to_string(), json!("Hello, \"world\""))]
+        );
+        assert_eq!(
+            parser.push("\"b\"], \"meta\": {\"n\": 1, \"m\": [1,2]}"),
+            vec![("tags".to_string(), json!(["a", "b"]))]
+        );
+        assert_eq!(
+            parser.push("}"),
+            vec![("meta".to_string(), json!({"n": 1, "m": [1, 2]}))]
+        );
+        let article: Article = parser.finish().unwrap();
+        assert_eq!(article.title, "Hello, \"world\"");
+        assert_eq!(parser.fields().len(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_of_partial_object() {
+        #[derive(Debug, Deserialize)]
+        struct PartialArticle {
+            title: Option<String>,
+            tags: Option<Vec<String>>,
+        }
+
+        let mut parser = PartialJsonParser::new();
+        parser.push("{\"title\": \"Draft\", \"tags\": [\"x\"");
+        let partial: PartialArticle = parser.snapshot().unwrap();
+        assert_eq!(partial.title.as_deref(), Some("Draft"));
+        assert!(partial.tags.is_none());
+    }
+}
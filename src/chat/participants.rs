@@ -0,0 +1,154 @@
+//! Named participants and multi-agent transcripts.
+//!
+//! Messages carry an optional `name`, so several users or agents can share one history. An agent
+//! can only play the `assistant` role, so before handing a shared transcript to one agent,
+//! [`Messages::as_seen_by`] turns the other agents' turns into named user messages.
+//!
+//! ```
+//! use openai_ox::chat::message::{Message, Messages};
+//!
+//! let transcript = Messages(vec![
+//!     Message::user_named("alice", "Should we ship on Friday?"),
+//!     Message::assistant_named("optimist", "Yes, the tests are green."),
+//!     Message::assistant_named("skeptic", "Not before the load test."),
+//! ]);
+//! assert_eq!(transcript.participants(), ["alice", "optimist", "skeptic"]);
+//!
+//! let for_skeptic = transcript.as_seen_by("skeptic");
+//! assert_eq!(for_skeptic[1].name(), Some("optimist"));
+//! ```
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use super::message::{Message, Messages, UserMessage};
+
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum NameError {
+    #[error("Participant name is empty")]
+    Empty,
+    #[error("Participant name is longer than {} characters: {}", MAX_NAME_LENGTH, .0)]
+    TooLong(String),
+    #[error("Participant name {name} contains {invalid:?}, only ASCII letters, digits, '_' and '-' are allowed")]
+    InvalidChar { name: String, invalid: char },
+}
+
+/// Checks that `name` is 1-64 ASCII letters, digits, underscores or hyphens, as the API expects.
+pub fn validate_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(NameError::TooLong(name.to_string()));
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '-'))
+    {
+        Some(invalid) => Err(NameError::InvalidChar {
+            name: name.to_string(),
+            invalid,
+        }),
+        None => Ok(()),
+    }
+}
+
+impl Messages {
+    /// Distinct participant names in order of first appearance.
+    pub fn participants(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.iter()
+            .filter_map(Message::name)
+            .filter(|name| seen.insert(*name))
+            .collect()
+    }
+
+    /// Validates the name of every message.
+    pub fn validate_names(&self) -> Result<(), NameError> {
+        self.iter()
+            .filter_map(Message::name)
+            .try_for_each(validate_name)
+    }
+
+    /// The transcript from the point of view of the agent `name`.
+    ///
+    /// Replies of other named agents become user messages carrying their name. Their tool calls
+    /// are private to them, so calls without text are dropped together with the tool results
+    /// answering them.
+    pub fn as_seen_by(&self, name: &str) -> Messages {
+        let mut hidden_calls = HashSet::new();
+        self.iter()
+            .filter_map(|message| match message {
+                Message::Assistant(msg) if msg.name.as_deref().is_some_and(|n| n != name) => {
+                    hidden_calls.extend(msg.tool_calls().iter().map(|call| call.id.clone()));
+                    let content = msg.content.clone()?;
+                    Some(Message::User(
+                        UserMessage::builder()
+                            .content(content)
+                            .maybe_name(msg.name.clone())
+                            .build(),
+                    ))
+                }
+                Message::Tool(msg) if hidden_calls.contains(&msg.tool_call_id) => None,
+                message => Some(message.clone()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{
+        message::{AssistantMessage, Role, ToolMessage},
+        tool::ToolCall,
+    };
+
+    #[test]
+    fn test_names_and_perspective() {
+        assert_eq!(validate_name("agent_1-b"), Ok(()));
+        assert_eq!(validate_name(""), Err(NameError::Empty));
+        assert_eq!(
+            validate_name("John Doe"),
+            Err(NameError::InvalidChar {
+                name: "John Doe".to_string(),
+                invalid: ' '
+            })
+        );
+        assert!(matches!(
+            validate_name(&"a".repeat(65)),
+            Err(NameError::TooLong(_))
+        ));
+
+        let transcript = Messages(vec![
+            Message::user_named("alice", "Find flights to Oslo."),
+            Message::Assistant(
+                AssistantMessage::builder()
+                    .name("planner".to_string())
+                    .tool_calls(vec![ToolCall::new("call_1", "search_flights", "{}")])
+                    .build(),
+            ),
+            Message::Tool(
+                ToolMessage::builder()
+                    .content("[]")
+                    .tool_call_id("call_1".to_string())
+                    .build(),
+            ),
+            Message::assistant_named("planner", "No flights found."),
+            Message::assistant_named("critic", "Try nearby airports."),
+        ]);
+        assert_eq!(transcript.participants(), ["alice", "planner", "critic"]);
+        assert!(transcript.validate_names().is_ok());
+
+        let for_critic = transcript.as_seen_by("critic");
+        let roles: Vec<_> = for_critic.iter().map(Message::role).collect();
+        assert_eq!(roles, [Role::User, Role::User, Role::Assistant]);
+        assert_eq!(for_critic[1].name(), Some("planner"));
+        assert_eq!(for_critic[1].content(), Some("No flights found."));
+
+        assert_eq!(transcript.as_seen_by("planner").len(), 5);
+    }
+}
@@ -0,0 +1,159 @@
+//! Recovery from connections that drop in the middle of a streamed generation.
+//!
+//! When the stream fails (or ends without a `finish_reason`) the request is re-issued with the
+//! text generated so far appended as an assistant message, followed by an instruction to continue.
+//! The chunks of the continuation are forwarded on the same stream, so the consumer sees one
+//! uninterrupted generation.
+
+use bon::Builder;
+use futures::{stream::BoxStream, Stream, StreamExt};
+
+use crate::ApiRequestError;
+
+use super::{message::Message, ChatCompletionChunkResponse, ChatCompletionRequest};
+
+const DEFAULT_CONTINUATION: &str = "Your previous reply was cut off. Continue exactly where it \
+stopped, without repeating anything and without any preamble.";
+
+#[derive(Debug, Clone, Builder)]
+pub struct RecoveryOptions {
+    /// How many times a dropped stream is resumed before the error is returned.
+    #[builder(default = 2)]
+    pub max_attempts: u32,
+    /// User message sent after the partial assistant reply.
+    #[builder(into, default = DEFAULT_CONTINUATION.to_string())]
+    pub continuation_prompt: String,
+}
+
+impl Default for RecoveryOptions {
+    fn default() -> Self {
+        RecoveryOptions::builder().build()
+    }
+}
+
+struct RecoveryState {
+    request: ChatCompletionRequest,
+    options: RecoveryOptions,
+    inner: Option<BoxStream<'static, Result<ChatCompletionChunkResponse, ApiRequestError>>>,
+    prefix: String,
+    attempts: u32,
+    finished: bool,
+    done: bool,
+}
+
+impl RecoveryState {
+    fn continuation(&self) -> ChatCompletionRequest {
+        let mut request = self.request.clone();
+        if !self.prefix.is_empty() {
+            request.push_message(Message::assistant(self.prefix.clone()));
+            request.push_message(Message::user(self.options.continuation_prompt.clone()));
+        }
+        request
+    }
+
+    fn can_resume(&self) -> bool {
+        !self.finished && self.attempts < self.options.max_attempts
+    }
+}
+
+fn is_disconnect(error: &ApiRequestError) -> bool {
+    matches!(
+        error,
+        ApiRequestError::Stream(_) | ApiRequestError::ReqwestError(_)
+    )
+}
+
+impl ChatCompletionRequest {
+    /// Like [`ChatCompletionRequest::stream`], but transparently resumes the generation when the
+    /// connection drops before the model finished.
+    ///
+    /// Only the first choice is tracked, so this is meant for requests with `n` unset or `1`.
+    pub async fn stream_with_recovery(
+        &self,
+        options: RecoveryOptions,
+    ) -> impl Stream<Item = Result<ChatCompletionChunkResponse, ApiRequestError>> {
+        let state = RecoveryState {
+            request: self.clone(),
+            options,
+            inner: None,
+            prefix: String::new(),
+            attempts: 0,
+            finished: false,
+            done: false,
+        };
+        futures::stream::unfold(state, |mut state| async move {
+            loop {
+                if state.done {
+                    return None;
+                }
+                let inner = match state.inner.as_mut() {
+                    Some(inner) => inner,
+                    None => {
+                        let request = state.continuation();
+                        state.inner = Some(request.stream().await.boxed());
+                        continue;
+                    }
+                };
+                match inner.next().await {
+                    Some(Ok(chunk)) => {
+                        for choice in chunk.choices.iter().filter(|c| c.index == 0) {
+                            if let Some(content) = &choice.delta.content {
+                                state.prefix.push_str(content);
+                            }
+                            if choice.finish_reason.is_some() {
+                                state.finished = true;
+                            }
+                        }
+                        return Some((Ok(chunk), state));
+                    }
+                    Some(Err(e)) if is_disconnect(&e) && state.can_resume() => {
+                        state.attempts += 1;
+                        state.inner = None;
+                    }
+                    Some(Err(e)) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                    None if state.can_resume() => {
+                        state.attempts += 1;
+                        state.inner = None;
+                    }
+                    None => return None,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenAi;
+
+    #[test]
+    fn test_continuation_appends_prefix() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let request = openai
+            .chat_completion()
+            .model("gpt-4o")
+            .messages(Message::user("Write a long story."))
+            .build()
+            .unwrap();
+        let mut state = RecoveryState {
+            request,
+            options: RecoveryOptions::default(),
+            inner: None,
+            prefix: String::new(),
+            attempts: 0,
+            finished: false,
+            done: false,
+        };
+        assert_eq!(state.continuation().messages.len(), 1);
+
+        state.prefix.push_str("Once upon a time");
+        let messages = state.continuation().messages;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].content(), Some("Once upon a time"));
+        assert_eq!(messages[2].content(), Some(DEFAULT_CONTINUATION));
+    }
+}
@@ -0,0 +1,199 @@
+//! Scrubbed copies of conversations for logging and persistence.
+//!
+//! ```
+//! use openai_ox::chat::{
+//!     message::{Message, Messages},
+//!     redaction::RedactionPolicy,
+//! };
+//!
+//! let messages = Messages(vec![Message::user("Mail me at jane@example.com")]);
+//! let policy = RedactionPolicy::new().mask_pii().truncate_tool_output(2_000);
+//! let safe = messages.redacted(&policy);
+//! assert_eq!(safe[0].content(), Some("Mail me at [EMAIL]"));
+//! ```
+
+use std::{borrow::Cow, fmt, sync::OnceLock};
+
+use regex::Regex;
+
+use super::message::{Message, Messages};
+
+fn media_regex() -> &'static Regex {
+    static MEDIA: OnceLock<Regex> = OnceLock::new();
+    MEDIA.get_or_init(|| {
+        Regex::new(r"data:(image|audio|video)/[\w.+-]+;base64,[A-Za-z0-9+/=]+").unwrap()
+    })
+}
+
+/// Like `Regex::replace_all`, reusing `text` when nothing matches.
+fn replace_all(text: &mut String, pattern: &Regex, replacement: &str) {
+    let replaced = match pattern.replace_all(text, replacement) {
+        Cow::Owned(replaced) => Some(replaced),
+        Cow::Borrowed(_) => None,
+    };
+    if let Some(replaced) = replaced {
+        *text = replaced;
+    }
+}
+
+/// Built-in PII patterns, applied in order.
+const PII_PATTERNS: [(&str, &str); 4] = [
+    (r"[\w.+-]+@[\w-]+(\.[\w-]+)+", "[EMAIL]"),
+    (r"\bsk-[A-Za-z0-9_-]{16,}", "[API_KEY]"),
+    (r"\b(?:\d[ -]?){13,19}\b", "[CARD]"),
+    (
+        r"\+?\d{1,3}[ .-]?\(?\d{2,4}\)?[ .-]?\d{3,4}[ .-]?\d{3,4}\b",
+        "[PHONE]",
+    ),
+];
+
+/// What [`Messages::redacted`] scrubs. Nothing is scrubbed by default.
+#[derive(Clone, Default)]
+pub struct RedactionPolicy {
+    strip_media: bool,
+    patterns: Vec<(Regex, String)>,
+    max_tool_output: Option<usize>,
+}
+
+impl fmt::Debug for RedactionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let patterns: Vec<_> = self.patterns.iter().map(|(re, _)| re.as_str()).collect();
+        f.debug_struct("RedactionPolicy")
+            .field("strip_media", &self.strip_media)
+            .field("patterns", &patterns)
+            .field("max_tool_output", &self.max_tool_output)
+            .finish()
+    }
+}
+
+impl RedactionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces base64 data URLs of images, audio and video with a short placeholder.
+    pub fn strip_media(mut self) -> Self {
+        self.strip_media = true;
+        self
+    }
+
+    /// Replaces every match of `pattern` with `replacement`, which may use `$1`-style groups.
+    pub fn mask(mut self, pattern: Regex, replacement: impl Into<String>) -> Self {
+        self.patterns.push((pattern, replacement.into()));
+        self
+    }
+
+    /// Masks email addresses, OpenAI API keys, card numbers and phone numbers. Pattern based,
+    /// so it's a safety net for logs rather than a guarantee.
+    pub fn mask_pii(self) -> Self {
+        PII_PATTERNS.iter().fold(self, |policy, (pattern, label)| {
+            policy.mask(Regex::new(pattern).unwrap(), *label)
+        })
+    }
+
+    /// Cuts tool results down to `max_chars` characters.
+    pub fn truncate_tool_output(mut self, max_chars: usize) -> Self {
+        self.max_tool_output = Some(max_chars);
+        self
+    }
+
+    fn scrub(&self, text: &mut String) {
+        if self.strip_media {
+            replace_all(text, media_regex(), "[$1 removed]");
+        }
+        for (pattern, replacement) in &self.patterns {
+            replace_all(text, pattern, replacement);
+        }
+    }
+
+    fn truncate(&self, text: &mut String) {
+        let Some(max_chars) = self.max_tool_output else {
+            return;
+        };
+        if let Some((cut, _)) = text.char_indices().nth(max_chars) {
+            let dropped = text[cut..].chars().count();
+            text.truncate(cut);
+            text.push_str(&format!("… [{} chars truncated]", dropped));
+        }
+    }
+}
+
+impl Messages {
+    /// Copy of the conversation scrubbed according to `policy`, e.g. before logging it.
+    pub fn redacted(&self, policy: &RedactionPolicy) -> Messages {
+        let mut messages = self.clone();
+        for message in messages.iter_mut() {
+            match message {
+                Message::System(msg) => policy.scrub(&mut msg.content),
+                Message::Developer(msg) => policy.scrub(&mut msg.content),
+                Message::User(msg) => policy.scrub(&mut msg.content),
+                Message::Assistant(msg) => {
+                    msg.content.iter_mut().for_each(|text| policy.scrub(text));
+                    msg.refusal.iter_mut().for_each(|text| policy.scrub(text));
+                    msg.tool_calls
+                        .iter_mut()
+                        .flatten()
+                        .for_each(|call| policy.scrub(&mut call.function.arguments));
+                }
+                Message::Tool(msg) => {
+                    policy.truncate(&mut msg.content);
+                    policy.scrub(&mut msg.content);
+                }
+            }
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{
+        message::{AssistantMessage, ToolMessage},
+        tool::ToolCall,
+    };
+
+    #[test]
+    fn test_redacted() {
+        let messages = Messages(vec![
+            Message::user("Photo: data:image/png;base64,iVBORw0KGgo= call +1 555 123 4567"),
+            Message::Assistant(
+                AssistantMessage::builder()
+                    .tool_calls(vec![ToolCall::new(
+                        "call_1",
+                        "send_mail",
+                        r#"{"email":"bob@corp.io"}"#,
+                    )])
+                    .build(),
+            ),
+            Message::Tool(
+                ToolMessage::builder()
+                    .content("x".repeat(30))
+                    .tool_call_id("call_1".to_string())
+                    .build(),
+            ),
+        ]);
+        let policy = RedactionPolicy::new()
+            .strip_media()
+            .mask_pii()
+            .truncate_tool_output(10);
+        let redacted = messages.redacted(&policy);
+
+        assert_eq!(
+            redacted[0].content(),
+            Some("Photo: [image removed] call [PHONE]")
+        );
+        let Message::Assistant(call) = &redacted[1] else {
+            panic!("Expected assistant message");
+        };
+        assert_eq!(
+            call.tool_calls()[0].function.arguments,
+            r#"{"email":"[EMAIL]"}"#
+        );
+        assert_eq!(
+            redacted[2].content(),
+            Some("xxxxxxxxxx… [20 chars truncated]")
+        );
+        assert_eq!(messages[2].content().map(str::len), Some(30));
+    }
+}
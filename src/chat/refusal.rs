@@ -0,0 +1,125 @@
+//! Handling of safety refusals in chat completions.
+//!
+//! A refusal arrives in `AssistantMessage.refusal` with no `content`, which is easy to miss when
+//! only the text is read. [`ChatCompletionRequest::send_with_refusal_policy`] makes the caller
+//! choose what a refusal means: an error, a regular reply, or a reason to ask again.
+
+use crate::ApiRequestError;
+
+use super::{message::Message, ChatCompletionRequest, ChatCompletionResponse};
+
+const DEFAULT_SOFTENED_PROMPT: &str = "If any part of the request can be answered safely, \
+answer that part and briefly mention what you cannot help with.";
+
+#[derive(Debug, Clone, Default)]
+pub enum RefusalPolicy {
+    /// Return [`ApiRequestError::Refusal`].
+    #[default]
+    Error,
+    /// Move the refusal into the message content, so it reads like any other reply.
+    ReturnAsText,
+    /// Ask again with `prompt` appended after the refusal, up to `max_retries` times, then
+    /// return [`ApiRequestError::Refusal`].
+    Retry { max_retries: u32, prompt: String },
+}
+
+impl RefusalPolicy {
+    /// Retry policy with a default prompt asking the model to answer what it safely can.
+    pub fn retry(max_retries: u32) -> Self {
+        RefusalPolicy::Retry {
+            max_retries,
+            prompt: DEFAULT_SOFTENED_PROMPT.to_string(),
+        }
+    }
+}
+
+/// Copies refusals into the empty `content` of their messages.
+fn refusal_as_text(response: &mut ChatCompletionResponse) {
+    for choice in &mut response.choices {
+        if let Message::Assistant(message) = &mut choice.message {
+            if message.content.is_none() {
+                message.content = message.refusal.clone();
+            }
+        }
+    }
+}
+
+impl ChatCompletionRequest {
+    /// Sends the request and handles a refusal of the first choice according to `policy`.
+    pub async fn send_with_refusal_policy(
+        &self,
+        policy: &RefusalPolicy,
+    ) -> Result<ChatCompletionResponse, ApiRequestError> {
+        let mut response = self.send().await?;
+        let Some(refusal) = response.refusal().map(ToOwned::to_owned) else {
+            return Ok(response);
+        };
+        match policy {
+            RefusalPolicy::Error => Err(ApiRequestError::Refusal { refusal }),
+            RefusalPolicy::ReturnAsText => {
+                refusal_as_text(&mut response);
+                Ok(response)
+            }
+            RefusalPolicy::Retry {
+                max_retries,
+                prompt,
+            } => {
+                let mut request = self.clone();
+                let mut refusal = refusal;
+                for _ in 0..*max_retries {
+                    request.push_message(Message::assistant(refusal));
+                    request.push_message(Message::user(prompt.clone()));
+                    let response = request.send().await?;
+                    match response.refusal() {
+                        Some(again) => refusal = again.to_owned(),
+                        None => return Ok(response),
+                    }
+                }
+                Err(ApiRequestError::Refusal { refusal })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::chat::message::ContentPart;
+
+    #[test]
+    fn test_refusal_accessors() {
+        let mut response: ChatCompletionResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o",
+            "system_fingerprint": "fp_1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": null, "refusal": "I can't help with that."},
+                "finish_reason": "stop",
+                "logprobs": null
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 6, "total_tokens": 11}
+        }))
+        .unwrap();
+        assert_eq!(response.refusal(), Some("I can't help with that."));
+        let Message::Assistant(message) = &response.choices[0].message else {
+            panic!("Expected assistant message");
+        };
+        assert_eq!(
+            message.parts(),
+            [ContentPart::Refusal {
+                refusal: "I can't help with that.".to_string()
+            }]
+        );
+
+        refusal_as_text(&mut response);
+        assert_eq!(
+            response.choices[0].message.content(),
+            Some("I can't help with that.")
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Chat completions that run the model's tool calls until it answers.
+//!
+//! [`ToolRunner`] sends a request, executes the tool calls of the reply with a
+//! [`ToolRegistry`], appends the results and sends again, until the model replies without
+//! calling tools or the iteration limit is hit.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::chat::{
+//!     message::Message,
+//!     runner::ToolRunner,
+//!     tool::{Function, ToolRegistry},
+//! };
+//!
+//! let registry = ToolRegistry::new().register(
+//!     Function::builder().name("get_time").build(),
+//!     |_| async { Ok::<_, String>("12:00".to_string()) },
+//! );
+//! let request = openai
+//!     .chat_completion()
+//!     .model("gpt-4o")
+//!     .messages(Message::user("What time is it?"))
+//!     .build()?;
+//! let run = ToolRunner::new(registry).max_iterations(5).run(request).await?;
+//! println!("{}", run.response.text().unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::ApiRequestError;
+
+use super::{
+    message::{Message, Messages},
+    tool::ToolRegistry,
+    ChatCompletionRequest, ChatCompletionResponse, Usage,
+};
+
+const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+/// Outcome of [`ToolRunner::run`].
+#[derive(Debug)]
+pub struct ToolRun {
+    /// The request's messages followed by every assistant reply and tool result, the final
+    /// answer included.
+    pub messages: Messages,
+    /// The last response, whose reply calls no tools.
+    pub response: ChatCompletionResponse,
+    /// Tokens used by all requests of the run.
+    pub usage: Usage,
+    /// Requests sent, at least one.
+    pub iterations: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolRunner {
+    registry: ToolRegistry,
+    max_iterations: usize,
+}
+
+impl ToolRunner {
+    pub fn new(registry: ToolRegistry) -> Self {
+        ToolRunner {
+            registry,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        }
+    }
+
+    /// Most requests sent before giving up on a model that keeps calling tools, 10 by default.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Sends `request` with its client, running tool calls until the model answers. The
+    /// registry's tools are offered when the request has none of its own. Calls of one reply
+    /// run concurrently.
+    pub async fn run(
+        &self,
+        mut request: ChatCompletionRequest,
+    ) -> Result<ToolRun, ApiRequestError> {
+        if request.tools.is_none() && !self.registry.tools().is_empty() {
+            request.tools = Some(self.registry.tools().clone());
+        }
+        let mut usage = Usage::default();
+        for iteration in 1..=self.max_iterations {
+            let response = request.send().await?;
+            usage += response.usage;
+            let Some(choice) = response.choices.first() else {
+                return Err(ApiRequestError::UnexpectedResponse {
+                    status: None,
+                    response: "chat completion without choices".to_string(),
+                });
+            };
+            let calls = match &choice.message {
+                Message::Assistant(msg) => msg.tool_calls().to_vec(),
+                _ => Vec::new(),
+            };
+            request.messages.push(choice.message.clone());
+            if calls.is_empty() {
+                return Ok(ToolRun {
+                    messages: request.messages,
+                    response,
+                    usage,
+                    iterations: iteration,
+                });
+            }
+            let results = self.registry.call_all(&calls).await;
+            request
+                .messages
+                .extend(results.into_iter().map(Message::Tool));
+        }
+        Err(ApiRequestError::ToolIterationLimit {
+            iterations: self.max_iterations,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        chat::tool::Function,
+        mock::{MockOpenAi, MockResponse},
+    };
+
+    fn tool_call_reply(arguments: &str) -> MockResponse {
+        MockResponse::json(&json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "mock",
+            "system_fingerprint": "mock",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "add", "arguments": arguments}}]
+                },
+                "finish_reason": "tool_calls",
+                "logprobs": null
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_tool_runner() {
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/chat/completions",
+            tool_call_reply(r#"{"a": 2, "b": 3}"#),
+        )
+        .push("v1/chat/completions", MockResponse::chat_text("5"));
+        let registry = ToolRegistry::new().register(
+            Function::builder().name("add").build(),
+            |args: Value| async move {
+                let (a, b) = (args["a"].as_i64(), args["b"].as_i64());
+                Ok::<_, String>((a.unwrap_or_default() + b.unwrap_or_default()).to_string())
+            },
+        );
+        let request = mock
+            .client()
+            .chat_completion()
+            .model("gpt-4o-mini")
+            .messages(Message::user("2 + 3?"))
+            .build()
+            .unwrap();
+        let runner = ToolRunner::new(registry).max_iterations(2);
+
+        let run = runner.run(request.clone()).await.unwrap();
+        assert_eq!(run.iterations, 2);
+        assert_eq!(run.usage.total_tokens, 15);
+        assert_eq!(run.messages.len(), 4);
+        assert_eq!(run.messages[3].content(), Some("5"));
+        let second: ChatCompletionRequest = mock.requests()[1].json().unwrap();
+        assert_eq!(second.tools.unwrap().len(), 1);
+
+        mock.push("v1/chat/completions", tool_call_reply("{}"))
+            .push("v1/chat/completions", tool_call_reply("{}"));
+        assert!(matches!(
+            runner.run(request).await,
+            Err(ApiRequestError::ToolIterationLimit { iterations: 2 })
+        ));
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use bon::Builder;
+use futures::{
+    future::{join_all, BoxFuture},
+    FutureExt,
+};
+use serde::{
+    de::{self, DeserializeOwned},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::{json, Value};
+
+#[cfg(feature = "derive")]
+pub use openai_ox_derive::Tool;
+
+use super::message::ToolMessage;
+use crate::extract::JsonSchema;
+
+fn empty_parameters() -> Value {
+    json!({"type": "object", "properties": {}})
+}
+
+/// Definition of a function the model may call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+pub struct Function {
+    #[builder(into)]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub description: Option<String>,
+    /// JSON schema of the arguments object.
+    #[builder(default = empty_parameters())]
+    pub parameters: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Tool {
+    Function { function: Function },
+}
+
+impl Tool {
+    pub fn function(function: Function) -> Self {
+        Tool::Function { function }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Tool::Function { function } => &function.name,
+        }
+    }
+}
+
+impl From<Function> for Tool {
+    fn from(function: Function) -> Self {
+        Tool::function(function)
+    }
+}
+
+/// Arguments of a function tool, whose definition is generated from the type. With the
+/// `derive` feature the impl is generated as well:
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// use openai_ox::chat::tool::{Tool, ToolArgs};
+/// use serde::Deserialize;
+///
+/// /// Current weather for a city.
+/// #[derive(Deserialize, Tool)]
+/// struct GetWeather {
+///     /// City name, e.g. `Paris`.
+///     city: String,
+/// }
+///
+/// let tool = GetWeather::tool();
+/// assert_eq!(tool.name(), "get_weather");
+/// ```
+pub trait ToolArgs: JsonSchema + DeserializeOwned {
+    const NAME: &'static str;
+    const DESCRIPTION: Option<&'static str> = None;
+
+    /// Strict function definition with the schema of `Self` as parameters.
+    fn function() -> Function {
+        Function::builder()
+            .name(Self::NAME)
+            .maybe_description(Self::DESCRIPTION)
+            .parameters(Self::json_schema())
+            .strict(true)
+            .build()
+    }
+
+    fn tool() -> Tool {
+        Tool::function(Self::function())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tools(pub Vec<Tool>);
+
+impl Tools {
+    pub fn get(&self, name: &str) -> Option<&Tool> {
+        self.0.iter().find(|tool| tool.name() == name)
+    }
+}
+
+impl Deref for Tools {
+    type Target = Vec<Tool>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Tools {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Vec<Tool>> for Tools {
+    fn from(tools: Vec<Tool>) -> Self {
+        Tools(tools)
+    }
+}
+
+impl FromIterator<Tool> for Tools {
+    fn from_iter<I: IntoIterator<Item = Tool>>(iter: I) -> Self {
+        Tools(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Tools {
+    type Item = Tool;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl From<Tools> for Value {
+    fn from(tools: Tools) -> Self {
+        serde_json::to_value(tools.0).unwrap_or_default()
+    }
+}
+
+/// Whether and which tool the model must call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// Never call a tool, answer with text.
+    None,
+    /// Let the model decide, the default when tools are given.
+    Auto,
+    /// Call at least one tool.
+    Required,
+    /// Call the function with this name.
+    Function(String),
+}
+
+impl ToolChoice {
+    pub fn function(name: impl Into<String>) -> Self {
+        ToolChoice::Function(name.into())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionName {
+    name: String,
+}
+
+/// Wire format: a mode string, or an object naming the function.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceRepr {
+    Mode(String),
+    Function {
+        #[serde(rename = "type")]
+        tool_type: String,
+        function: FunctionName,
+    },
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            ToolChoice::None => ToolChoiceRepr::Mode("none".to_string()),
+            ToolChoice::Auto => ToolChoiceRepr::Mode("auto".to_string()),
+            ToolChoice::Required => ToolChoiceRepr::Mode("required".to_string()),
+            ToolChoice::Function(name) => ToolChoiceRepr::Function {
+                tool_type: function_type(),
+                function: FunctionName { name: name.clone() },
+            },
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match ToolChoiceRepr::deserialize(deserializer)? {
+            ToolChoiceRepr::Mode(mode) => match mode.as_str() {
+                "none" => Ok(ToolChoice::None),
+                "auto" => Ok(ToolChoice::Auto),
+                "required" => Ok(ToolChoice::Required),
+                other => Err(de::Error::unknown_variant(
+                    other,
+                    &["none", "auto", "required"],
+                )),
+            },
+            ToolChoiceRepr::Function { function, .. } => Ok(ToolChoice::Function(function.name)),
+        }
+    }
+}
+
+/// Function invocation inside a [`ToolCall`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    /// JSON encoded arguments, as produced by the model.
+    pub arguments: String,
+}
+
+/// A tool call requested by the model in an assistant message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub tool_type: String,
+    pub function: FunctionCall,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+impl ToolCall {
+    /// A function call with JSON encoded `arguments`.
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            tool_type: function_type(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments: arguments.into(),
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.function.name
+    }
+
+    /// Parses the arguments into `T`. Empty arguments are treated as `{}`.
+    pub fn arguments<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        match self.function.arguments.trim() {
+            "" => serde_json::from_str("{}"),
+            arguments => serde_json::from_str(arguments),
+        }
+    }
+}
+
+type ToolHandler = Arc<dyn Fn(Value) -> BoxFuture<'static, Result<String, String>> + Send + Sync>;
+
+/// Local implementations of function tools, keyed by name.
+///
+/// Handlers receive the parsed arguments and return the text sent back to the model. Errors are
+/// returned to the model as text as well, so it can correct itself instead of aborting the turn.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Tools,
+    handlers: HashMap<String, ToolHandler>,
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.tools)
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for `function`, replacing any tool with the same name.
+    pub fn register<F, Fut, E>(mut self, function: Function, handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        let name = function.name.clone();
+        self.tools.retain(|tool| tool.name() != name);
+        self.tools.push(Tool::function(function));
+        self.handlers.insert(
+            name,
+            Arc::new(move |arguments| {
+                handler(arguments)
+                    .map(|res| res.map_err(|e| e.to_string()))
+                    .boxed()
+            }),
+        );
+        self
+    }
+
+    /// Registers `handler` for the function defined by `T`, receiving the arguments parsed
+    /// into it. Arguments that don't parse are reported to the model.
+    pub fn register_args<T, F, Fut, E>(self, handler: F) -> Self
+    where
+        T: ToolArgs + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        let handler = Arc::new(handler);
+        self.register(T::function(), move |arguments| {
+            let handler = Arc::clone(&handler);
+            async move {
+                match serde_json::from_value::<T>(arguments) {
+                    Ok(arguments) => handler(arguments).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(format!("invalid arguments: {}", e)),
+                }
+            }
+        })
+    }
+
+    pub fn tools(&self) -> &Tools {
+        &self.tools
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.handlers.contains_key(name)
+    }
+
+    /// Runs the tool call and returns its output, or a description of the failure.
+    ///
+    /// `arguments` is the JSON encoded arguments string produced by the model.
+    pub async fn call(&self, name: &str, arguments: &str) -> String {
+        let Some(handler) = self.handlers.get(name) else {
+            return format!("Error: unknown tool `{}`", name);
+        };
+        let arguments = if arguments.trim().is_empty() {
+            Ok(json!({}))
+        } else {
+            serde_json::from_str(arguments)
+        };
+        match arguments {
+            Ok(arguments) => handler(arguments)
+                .await
+                .unwrap_or_else(|e| format!("Error: {}", e)),
+            Err(e) => format!("Error: invalid arguments: {}", e),
+        }
+    }
+
+    /// Runs all tool calls of a reply concurrently and returns their results in the order of
+    /// `calls`, ready to be appended after the assistant message.
+    pub async fn call_all(&self, calls: &[ToolCall]) -> Vec<ToolMessage> {
+        let outputs = join_all(
+            calls
+                .iter()
+                .map(|call| self.call(call.name(), &call.function.arguments)),
+        )
+        .await;
+        calls
+            .iter()
+            .zip(outputs)
+            .map(|(call, output)| ToolMessage::from_result(call.id.clone(), output))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tool_serialization() {
+        let tool = Tool::function(
+            Function::builder()
+                .name("get_weather")
+                .description("Current weather for a city")
+                .parameters(json!({
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"]
+                }))
+                .build(),
+        );
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap(),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": "Current weather for a city",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {"city": {"type": "string"}},
+                        "required": ["city"]
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_choice_serialization() {
+        assert_eq!(
+            serde_json::to_value(ToolChoice::Required).unwrap(),
+            "required"
+        );
+        let choice = ToolChoice::function("get_weather");
+        let value = serde_json::to_value(&choice).unwrap();
+        assert_eq!(
+            value,
+            json!({"type": "function", "function": {"name": "get_weather"}})
+        );
+        assert_eq!(serde_json::from_value::<ToolChoice>(value).unwrap(), choice);
+        assert!(serde_json::from_value::<ToolChoice>(json!("sometimes")).is_err());
+    }
+
+    #[derive(Deserialize)]
+    struct Add {
+        a: i64,
+        b: i64,
+    }
+
+    impl JsonSchema for Add {
+        fn json_schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": {"a": {"type": "integer"}, "b": {"type": "integer"}},
+                "required": ["a", "b"],
+                "additionalProperties": false,
+            })
+        }
+    }
+
+    impl ToolArgs for Add {
+        const NAME: &'static str = "add";
+        const DESCRIPTION: Option<&'static str> = Some("Adds two integers");
+    }
+
+    #[tokio::test]
+    async fn test_typed_registry() {
+        let tool = Add::tool();
+        assert_eq!(tool.name(), "add");
+        let Tool::Function { function } = &tool;
+        assert_eq!(function.description.as_deref(), Some("Adds two integers"));
+        assert_eq!(function.strict, Some(true));
+
+        let registry = ToolRegistry::new().register_args(|args: Add| async move {
+            Ok::<_, String>((args.a + args.b).to_string())
+        });
+        assert_eq!(registry.tools().get("add"), Some(&tool));
+        assert_eq!(registry.call("add", r#"{"a": 2, "b": 3}"#).await, "5");
+        assert!(registry
+            .call("add", r#"{"a": "two"}"#)
+            .await
+            .starts_with("Error: invalid arguments"));
+    }
+
+    #[tokio::test]
+    async fn test_registry_dispatch() {
+        let registry = ToolRegistry::new().register(
+            Function::builder().name("add").build(),
+            |args: Value| async move {
+                match (args["a"].as_i64(), args["b"].as_i64()) {
+                    (Some(a), Some(b)) => Ok((a + b).to_string()),
+                    _ => Err("expected integers `a` and `b`"),
+                }
+            },
+        );
+        assert_eq!(registry.tools().len(), 1);
+        assert_eq!(registry.call("add", r#"{"a": 2, "b": 3}"#).await, "5");
+        assert_eq!(
+            registry.call("add", "{}").await,
+            "Error: expected integers `a` and `b`"
+        );
+        assert!(registry
+            .call("sub", "{}")
+            .await
+            .starts_with("Error: unknown tool"));
+
+        let calls = [
+            ToolCall::new("call_1", "add", r#"{"a": 1, "b": 1}"#),
+            ToolCall::new("call_2", "add", r#"{"a": 2, "b": 2}"#),
+        ];
+        let results = registry.call_all(&calls).await;
+        let results: Vec<_> = results
+            .iter()
+            .map(|msg| (msg.tool_call_id.as_str(), msg.content.as_str()))
+            .collect();
+        assert_eq!(results, [("call_1", "2"), ("call_2", "4")]);
+    }
+}
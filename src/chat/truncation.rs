@@ -0,0 +1,183 @@
+//! Trimming a conversation to fit a model's context window.
+//!
+//! Messages are dropped oldest first until the prompt, counted with the model's encoding, fits
+//! in the budget. The latest message is always kept, and tool results never outlive the
+//! assistant message that called them.
+//!
+//! ```
+//! use openai_ox::chat::{
+//!     message::{Message, Messages},
+//!     truncation::TruncationStrategy,
+//! };
+//!
+//! let mut messages = Messages(vec![Message::system("Be brief.")]);
+//! for turn in 0..100 {
+//!     messages.push(Message::user(format!("Question {}", turn)));
+//!     messages.push(Message::assistant(format!("Answer {}", turn)));
+//! }
+//! let dropped = messages.truncate_to_tokens_with(200, "gpt-4o", TruncationStrategy::SummarizePlaceholder);
+//! assert!(dropped > 0);
+//! assert_eq!(messages[0].content(), Some("Be brief."));
+//! ```
+
+use super::message::{Message, Messages, Role};
+use crate::tokenizer::{TokenCount, Tokenizer, REPLY_PRIMING_TOKENS};
+
+/// What [`Messages::truncate_to_tokens_with`] drops to make the conversation fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Drops the oldest messages, system and developer messages included.
+    DropOldest,
+    /// Keeps system and developer messages and drops the oldest of the others.
+    #[default]
+    KeepSystem,
+    /// Like `KeepSystem`, and tells the model with a system message after the instructions how
+    /// many messages were left out.
+    SummarizePlaceholder,
+}
+
+fn placeholder(dropped: usize) -> Message {
+    Message::system(format!(
+        "[{} earlier messages were removed to fit the context window]",
+        dropped
+    ))
+}
+
+impl Messages {
+    /// Drops old messages until the prompt fits in `max_tokens` for `model`, keeping system and
+    /// developer messages. Returns how many messages were dropped.
+    ///
+    /// Leave room for the reply: `max_tokens` is the context window minus the tokens the reply
+    /// may take.
+    pub fn truncate_to_tokens(&mut self, max_tokens: usize, model: &str) -> usize {
+        self.truncate_to_tokens_with(max_tokens, model, TruncationStrategy::default())
+    }
+
+    /// Like [`Messages::truncate_to_tokens`], dropping messages according to `strategy`.
+    ///
+    /// The conversation can still exceed `max_tokens` when the messages that are never dropped
+    /// don't fit on their own.
+    pub fn truncate_to_tokens_with(
+        &mut self,
+        max_tokens: usize,
+        model: &str,
+        strategy: TruncationStrategy,
+    ) -> usize {
+        let tokenizer = Tokenizer::for_model(model);
+        let droppable = |message: &Message| {
+            strategy == TruncationStrategy::DropOldest || !message.role().is_instruction()
+        };
+        let placeholder_tokens = |dropped: usize| match strategy {
+            TruncationStrategy::SummarizePlaceholder if dropped > 0 => {
+                placeholder(dropped).token_count_with(&tokenizer)
+            }
+            _ => 0,
+        };
+
+        let mut counts: Vec<usize> = self
+            .iter()
+            .map(|message| message.token_count_with(&tokenizer))
+            .collect();
+        let mut total = counts.iter().sum::<usize>() + REPLY_PRIMING_TOKENS;
+        let mut dropped = 0;
+        while total + placeholder_tokens(dropped) > max_tokens {
+            let Some(at) = self.iter().position(droppable) else {
+                break;
+            };
+            // Tool results go together with the call they answer.
+            let end = at
+                + 1
+                + self.0[at + 1..]
+                    .iter()
+                    .take_while(|message| message.role() == Role::Tool)
+                    .count();
+            if end >= self.len() {
+                break;
+            }
+            self.drain(at..end);
+            total -= counts.drain(at..end).sum::<usize>();
+            dropped += end - at;
+        }
+
+        if strategy == TruncationStrategy::SummarizePlaceholder && dropped > 0 {
+            let at = self
+                .iter()
+                .take_while(|message| message.role().is_instruction())
+                .count();
+            self.insert(at, placeholder(dropped));
+        }
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{
+        message::{AssistantMessage, ToolMessage},
+        tool::ToolCall,
+    };
+
+    fn conversation() -> Messages {
+        Messages(vec![
+            Message::system("Be brief."),
+            Message::user("What's the weather in Paris?"),
+            Message::Assistant(
+                AssistantMessage::builder()
+                    .tool_calls(vec![ToolCall::new(
+                        "call_1",
+                        "weather",
+                        r#"{"city":"Paris"}"#,
+                    )])
+                    .build(),
+            ),
+            Message::Tool(
+                ToolMessage::builder()
+                    .content("18°C and sunny")
+                    .tool_call_id("call_1".to_string())
+                    .build(),
+            ),
+            Message::assistant("It's 18°C and sunny."),
+            Message::user("And tomorrow?"),
+        ])
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let mut messages = conversation();
+        let full = messages.token_count_with(&Tokenizer::for_model("gpt-4o"));
+        assert_eq!(messages.truncate_to_tokens(full, "gpt-4o"), 0);
+        assert_eq!(messages.len(), 6);
+
+        // Dropping the call drops its result too, leaving no orphaned tool message.
+        assert_eq!(messages.truncate_to_tokens(full - 1, "gpt-4o"), 1);
+        assert_eq!(messages.truncate_to_tokens(full - 20, "gpt-4o"), 2);
+        let roles: Vec<_> = messages.iter().map(Message::role).collect();
+        assert_eq!(roles, [Role::System, Role::Assistant, Role::User]);
+
+        let mut messages = conversation();
+        messages.truncate_to_tokens_with(0, "gpt-4o", TruncationStrategy::DropOldest);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content(), Some("And tomorrow?"));
+
+        let mut messages = conversation();
+        let dropped =
+            messages.truncate_to_tokens_with(0, "gpt-4o", TruncationStrategy::SummarizePlaceholder);
+        assert_eq!(dropped, 4);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[1].content(),
+            Some("[4 earlier messages were removed to fit the context window]")
+        );
+    }
+
+    #[test]
+    fn test_truncate_keeps_pending_tool_call() {
+        // A tool loop in progress: the call and its result are the latest messages.
+        let mut messages = conversation();
+        messages.truncate(4);
+        assert_eq!(messages.truncate_to_tokens(0, "gpt-4o"), 1);
+        let roles: Vec<_> = messages.iter().map(Message::role).collect();
+        assert_eq!(roles, [Role::System, Role::Assistant, Role::Tool]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Shared state behind [`OpenAi`], built by `OpenAi::builder()`.
+
+#[cfg(feature = "leaky-bucket")]
+use crate::RateLimiter;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use bon::Builder;
+
+use crate::{
+    auth::{ApiKeySource, AuthScheme},
+    azure::AzureConfig,
+    middleware::Middleware,
+    proxy::ProxyConfig,
+    rate_limit::{RateLimitCallback, RateLimitInfo},
+    retry::RetryPolicy,
+    timeout::Timeouts,
+    OpenAi, BASE_URL,
+};
+
+/// Configuration every clone of an [`OpenAi`] client points to.
+#[derive(Clone, Builder)]
+#[builder(
+    builder_type(name = OpenAiBuilder, vis = "pub"),
+    state_mod(name = open_ai_builder, vis = "pub"),
+    start_fn(name = builder, vis = "pub(crate)"),
+    finish_fn(name = build_inner, vis = "")
+)]
+pub struct Inner {
+    /// A key string, or any [`ApiKeySource`] for keys that are looked up or rotated at runtime.
+    /// Can be left out when `auth` doesn't send it; otherwise every request fails with
+    /// [`ApiKeyError::Missing`](crate::auth::ApiKeyError::Missing).
+    #[builder(into)]
+    pub(crate) api_key: Option<ApiKeySource>,
+    #[builder(default)]
+    pub(crate) auth: AuthScheme,
+    /// Root the endpoint paths are appended to. Point it at Azure OpenAI, OpenRouter, a local
+    /// llama.cpp or Ollama server or a test harness, e.g. `http://localhost:11434`. A trailing
+    /// slash or `/v1` is dropped, so the URLs these services document work as they are.
+    #[builder(into, default = BASE_URL.to_string())]
+    pub(crate) base_url: String,
+    /// Proxy for the default client. Ignored when a custom `client` is given.
+    pub(crate) proxy: Option<ProxyConfig>,
+    /// Unix domain socket the default client sends every request over, e.g. for llama.cpp or
+    /// vLLM served locally. The host of `base_url` is then only used for the `Host` header.
+    /// Ignored when a custom `client` is given. Only settable on Unix.
+    #[cfg_attr(unix, builder(into))]
+    #[cfg_attr(not(unix), builder(skip))]
+    pub(crate) unix_socket: Option<PathBuf>,
+    /// Routes chat, embeddings and audio requests to Azure OpenAI deployments. The key is then
+    /// sent in the `api-key` header, unless `auth` is set to something other than bearer.
+    pub(crate) azure: Option<AzureConfig>,
+    /// Time limits of every request. The connect timeout is ignored when a custom `client` is
+    /// given.
+    #[builder(default)]
+    pub(crate) timeouts: Timeouts,
+    /// HTTP client used for every endpoint. Pass your own to plug in a custom connector,
+    /// middleware or timeouts.
+    #[builder(default = default_client(proxy.as_ref(), unix_socket.as_deref(), timeouts.connect))]
+    pub(crate) client: reqwest::Client,
+    /// Retries of rate-limited and failed requests; without it every request is sent once.
+    pub(crate) retry: Option<RetryPolicy>,
+    /// Called with the rate limit headers of every response that carries them.
+    #[builder(with = |f: impl Fn(&RateLimitInfo) + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) on_rate_limit: Option<RateLimitCallback>,
+    /// Hooks run around every request; combine several with a tuple.
+    #[builder(with = |middleware: impl Middleware + 'static| Arc::new(middleware) as Arc<dyn Middleware>)]
+    pub(crate) middleware: Option<Arc<dyn Middleware>>,
+    /// Limiter every request, retries included, takes a permit of before it is sent.
+    #[cfg(feature = "leaky-bucket")]
+    pub(crate) leaky_bucket: Option<Arc<RateLimiter>>,
+    /// Limiter whose permits are tokens, e.g. refilled at the tokens-per-minute limit of the
+    /// model. Chat, completion and embedding requests take their estimated prompt tokens plus
+    /// `max_tokens` before they are sent; a request larger than the bucket takes all of it.
+    #[cfg(feature = "leaky-bucket")]
+    pub(crate) token_bucket: Option<Arc<RateLimiter>>,
+}
+
+impl<S: open_ai_builder::IsComplete> OpenAiBuilder<S> {
+    pub fn build(self) -> OpenAi {
+        let mut inner = self.build_inner();
+        inner.base_url = normalize_base_url(&inner.base_url).to_string();
+        if inner.azure.is_some() && matches!(inner.auth, AuthScheme::Bearer) {
+            inner.auth = AuthScheme::header("api-key");
+        }
+        OpenAi {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl Inner {
+    /// URL of the endpoint `path` serving `model`, on the model's deployment with Azure.
+    pub(crate) fn model_url(&self, path: &str, model: &str) -> String {
+        match &self.azure {
+            Some(azure) => azure.url(&self.base_url, path, model),
+            None => format!("{}/{}", self.base_url, path),
+        }
+    }
+
+    /// Waits for `tokens` permits of the token bucket. The estimate is only computed when a
+    /// bucket is set.
+    pub(crate) async fn acquire_tokens(&self, tokens: impl FnOnce() -> usize) {
+        #[cfg(feature = "leaky-bucket")]
+        if let Some(bucket) = &self.token_bucket {
+            bucket.acquire(tokens().min(bucket.max())).await;
+        }
+        #[cfg(not(feature = "leaky-bucket"))]
+        let _ = tokens;
+    }
+
+    /// Passes the rate limit headers of `res` to the `on_rate_limit` callback.
+    pub(crate) fn observe(&self, res: &reqwest::Response) {
+        if let Some(on_rate_limit) = &self.on_rate_limit {
+            if let Some(info) = RateLimitInfo::from_headers(res.headers()) {
+                on_rate_limit(&info);
+            }
+        }
+    }
+}
+
+/// Strips what every endpoint path adds again: trailing slashes and the `/v1` version prefix.
+fn normalize_base_url(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    url.strip_suffix("/v1").unwrap_or(url)
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenAi")
+            .field("api_key", &self.api_key)
+            .field("auth", &self.auth)
+            .field("base_url", &self.base_url)
+            .field("proxy", &self.proxy)
+            .field("unix_socket", &self.unix_socket)
+            .field("azure", &self.azure)
+            .field("client", &self.client)
+            .field("retry", &self.retry)
+            .field("timeouts", &self.timeouts)
+            .field("on_rate_limit", &self.on_rate_limit.is_some())
+            .field("middleware", &self.middleware.is_some())
+            .finish()
+    }
+}
+
+/// Client the builder falls back to when no `client` is given. Everything that can be
+/// misconfigured is checked before: proxies by [`ProxyConfigBuilder::build`] and Unix sockets by
+/// only being settable on Unix.
+///
+/// # Panics
+///
+/// Like `reqwest::Client::new`, if the TLS backend cannot be initialized.
+///
+/// [`ProxyConfigBuilder::build`]: crate::proxy::ProxyConfigBuilder::build
+fn default_client(
+    proxy: Option<&ProxyConfig>,
+    unix_socket: Option<&Path>,
+    connect_timeout: Option<Duration>,
+) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(proxy) = proxy {
+        builder = proxy.apply(builder);
+    }
+    #[cfg(unix)]
+    if let Some(path) = unix_socket {
+        builder = builder.unix_socket(path.to_path_buf());
+    }
+    #[cfg(not(unix))]
+    let _ = unix_socket;
+    builder
+        .build()
+        .expect("the TLS backend should initialize, the rest is checked up front")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_normalization() {
+        for (url, expected) in [
+            ("https://api.openai.com", "https://api.openai.com"),
+            ("http://localhost:11434/v1/", "http://localhost:11434"),
+            ("https://openrouter.ai/api/v1", "https://openrouter.ai/api"),
+            ("http://localhost:8080/", "http://localhost:8080"),
+        ] {
+            let openai = OpenAi::builder().base_url(url).build();
+            assert_eq!(openai.base_url(), expected);
+        }
+    }
+}
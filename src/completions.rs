@@ -0,0 +1,377 @@
+//! Legacy text completions (`/v1/completions`).
+//!
+//! The endpoint continues a prompt instead of answering messages. OpenAI only serves it for
+//! `gpt-3.5-turbo-instruct`, `davinci-002` and `babbage-002`, but many OpenAI-compatible local
+//! servers implement nothing else.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! let response = openai
+//!     .completion()
+//!     .model("gpt-3.5-turbo-instruct")
+//!     .prompt("fn fibonacci(n: u64) -> u64 {")
+//!     .suffix("}")
+//!     .max_tokens(64)
+//!     .build()?
+//!     .send()
+//!     .await?;
+//! println!("{}", response.text().unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use bon::Builder;
+use futures::{stream::BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    auth::Authorize,
+    chat::{FinishReason, Usage},
+    rate_limit::estimate_tokens,
+    require_client,
+    retry::SendRetrying,
+    sse::{error_stream, idle_timeout, sse_events, StreamingBody},
+    ApiRequest, ApiRequestError, ApiRequestWithClient, OpenAi,
+};
+
+const API_URL: &str = "v1/completions";
+
+const MAX_LOGPROBS: u32 = 5;
+
+/// Completion length the API defaults to when `max_tokens` is not set.
+const DEFAULT_MAX_TOKENS: u32 = 16;
+
+/// One prompt, or several completed in one request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Prompt {
+    Text(String),
+    Batch(Vec<String>),
+}
+
+impl From<String> for Prompt {
+    fn from(text: String) -> Self {
+        Prompt::Text(text)
+    }
+}
+
+impl From<&str> for Prompt {
+    fn from(text: &str) -> Self {
+        Prompt::Text(text.to_string())
+    }
+}
+
+impl From<Vec<String>> for Prompt {
+    fn from(prompts: Vec<String>) -> Self {
+        Prompt::Batch(prompts)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct CompletionRequest {
+    #[builder(into)]
+    pub model: String,
+    #[builder(into)]
+    pub prompt: Prompt,
+    /// Text that comes after the completion, for inserting text instead of appending it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// Completions generated server-side, of which the `n` most likely are returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_of: Option<u32>,
+    /// Returns the prompt in front of the completion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<bool>,
+    /// Number of most likely tokens, at most 5, whose log probabilities are returned for every
+    /// position.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub user: Option<String>,
+    /// Client used by `send` and `stream`, which fail with [`ApiRequestError::MissingClient`]
+    /// without one.
+    #[serde(skip)]
+    pub openai: Option<OpenAi>,
+}
+
+/// Parameter combinations the API would reject with a 400.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CompletionRequestError {
+    #[error("logprobs must be at most {}, got {}", MAX_LOGPROBS, .0)]
+    TooManyLogprobs(u32),
+    #[error("best_of ({best_of}) must be at least n ({n})")]
+    BestOfBelowN { best_of: u32, n: u32 },
+    #[error("best_of cannot be combined with streaming")]
+    StreamedBestOf,
+}
+
+impl From<CompletionRequestError> for ApiRequestError {
+    fn from(e: CompletionRequestError) -> Self {
+        let param = match e {
+            CompletionRequestError::TooManyLogprobs(_) => "logprobs",
+            CompletionRequestError::BestOfBelowN { .. }
+            | CompletionRequestError::StreamedBestOf => "best_of",
+        };
+        ApiRequestError::InvalidRequestError {
+            message: e.to_string(),
+            param: Some(param.to_string()),
+            code: None,
+        }
+    }
+}
+
+impl<S: completion_request_builder::IsComplete> CompletionRequestBuilder<S> {
+    pub fn build(self) -> Result<CompletionRequest, CompletionRequestError> {
+        let request = self.build_unchecked();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+impl CompletionRequest {
+    pub fn validate(&self) -> Result<(), CompletionRequestError> {
+        if let Some(logprobs) = self.logprobs.filter(|logprobs| *logprobs > MAX_LOGPROBS) {
+            return Err(CompletionRequestError::TooManyLogprobs(logprobs));
+        }
+        if let (Some(best_of), Some(n)) = (self.best_of, self.n) {
+            if best_of < n {
+                return Err(CompletionRequestError::BestOfBelowN { best_of, n });
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn send(&self) -> Result<CompletionResponse, ApiRequestError> {
+        self.send_with(require_client(&self.openai)?).await
+    }
+
+    /// Prompt tokens plus the most every requested completion may use.
+    fn estimated_tokens(&self) -> usize {
+        let prompt = match &self.prompt {
+            Prompt::Text(text) => estimate_tokens(&self.model, text),
+            Prompt::Batch(prompts) => prompts
+                .iter()
+                .map(|text| estimate_tokens(&self.model, text))
+                .sum(),
+        };
+        let completions = self.best_of.or(self.n).unwrap_or(1);
+        prompt + self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize * completions as usize
+    }
+
+    /// Streams the completion chunk by chunk, each chunk a [`CompletionResponse`] holding the
+    /// new text. A failed or rejected request is the first and only item of the stream.
+    pub async fn stream(&self) -> BoxStream<'static, Result<CompletionResponse, ApiRequestError>> {
+        if self.best_of.is_some() {
+            let e = ApiRequestError::from(CompletionRequestError::StreamedBestOf);
+            return futures::stream::once(async { Err(e) }).boxed();
+        }
+        let openai = match require_client(&self.openai) {
+            Ok(openai) => openai,
+            Err(e) => return futures::stream::once(async { Err(e) }).boxed(),
+        };
+        openai
+            .inner
+            .acquire_tokens(|| self.estimated_tokens())
+            .await;
+        let url = openai.inner.model_url(API_URL, &self.model);
+        let res = match openai.inner.client.post(url).authorize(openai).await {
+            Ok(request) => {
+                request
+                    .json(&StreamingBody::new(self))
+                    .send_streaming(openai)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        let stream = match res {
+            Ok(res) if res.status().is_success() => {
+                idle_timeout(res.bytes_stream(), openai.inner.timeouts.stream_idle)
+            }
+            res => return error_stream(res).await,
+        };
+        sse_events(stream)
+            .take_while(|event| {
+                let done = matches!(event, Ok(event) if event.data == "[DONE]");
+                futures::future::ready(!done)
+            })
+            .map(|event| -> Result<CompletionResponse, ApiRequestError> {
+                Ok(crate::json::from_str(&event?.data)?)
+            })
+            .boxed()
+    }
+
+    /// Like [`stream`](Self::stream), but ends with [`ApiRequestError::Cancelled`] as soon as
+    /// `token` is cancelled, whether the response has started or not.
+    #[cfg(feature = "cancellation")]
+    pub async fn stream_cancellable(
+        &self,
+        token: crate::CancellationToken,
+    ) -> BoxStream<'static, Result<CompletionResponse, ApiRequestError>> {
+        crate::cancel::cancellable_stream(token, self.stream()).await
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionResponse {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<CompletionChoice>,
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
+    /// Missing from stream chunks and from some compatible servers.
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+impl CompletionResponse {
+    /// Text of the choice with index 0.
+    pub fn text(&self) -> Option<&str> {
+        self.choices
+            .iter()
+            .find(|choice| choice.index == 0)
+            .map(|choice| choice.text.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionChoice {
+    pub text: String,
+    pub index: u32,
+    #[serde(default)]
+    pub logprobs: Option<CompletionLogprobs>,
+    /// `None` until the last chunk of a stream.
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+}
+
+/// Log probabilities per token position, in parallel arrays.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompletionLogprobs {
+    pub tokens: Vec<String>,
+    /// `None` for the first token of an echoed prompt, which has no probability.
+    pub token_logprobs: Vec<Option<f64>>,
+    pub top_logprobs: Option<Vec<Option<HashMap<String, f64>>>>,
+    /// Character offset of each token in the prompt and completion text.
+    pub text_offset: Vec<u32>,
+}
+
+#[async_trait::async_trait]
+impl ApiRequest for CompletionRequest {
+    type Response = CompletionResponse;
+
+    async fn send_with(&self, openai: &OpenAi) -> Result<CompletionResponse, ApiRequestError> {
+        openai
+            .inner
+            .acquire_tokens(|| self.estimated_tokens())
+            .await;
+        let url = openai.inner.model_url(API_URL, &self.model);
+        let res = openai
+            .inner
+            .client
+            .post(url)
+            .authorize(openai)
+            .await?
+            .json(self)
+            .send_retrying(openai)
+            .await?;
+        if res.status().is_success() {
+            Ok(crate::json::from_slice(&res.bytes().await?)?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiRequestWithClient for CompletionRequest {
+    async fn send(&self) -> Result<CompletionResponse, ApiRequestError> {
+        self.send_with(require_client(&self.openai)?).await
+    }
+}
+
+impl OpenAi {
+    pub fn completion(&self) -> CompletionRequestBuilder<completion_request_builder::SetOpenai> {
+        CompletionRequest::builder().openai(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_completion_request() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let request = || {
+            openai
+                .completion()
+                .model("gpt-3.5-turbo-instruct")
+                .prompt("Say this is a test")
+        };
+        let body = serde_json::to_value(request().echo(true).logprobs(2).build().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({"model": "gpt-3.5-turbo-instruct", "prompt": "Say this is a test", "echo": true, "logprobs": 2})
+        );
+        assert_eq!(
+            request().logprobs(6).build().unwrap_err(),
+            CompletionRequestError::TooManyLogprobs(6)
+        );
+        assert_eq!(
+            request().n(3).best_of(2).build().unwrap_err(),
+            CompletionRequestError::BestOfBelowN { best_of: 2, n: 3 }
+        );
+
+        let response: CompletionResponse = serde_json::from_value(json!({
+            "id": "cmpl-1",
+            "object": "text_completion",
+            "created": 1,
+            "model": "gpt-3.5-turbo-instruct",
+            "choices": [{
+                "text": "\n\nThis is indeed a test",
+                "index": 0,
+                "logprobs": {"tokens": ["\n", "This"], "token_logprobs": [null, -0.5], "top_logprobs": null, "text_offset": [18, 19]},
+                "finish_reason": "length"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12}
+        }))
+        .unwrap();
+        assert_eq!(response.text(), Some("\n\nThis is indeed a test"));
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason, Some(FinishReason::Length));
+        assert_eq!(
+            choice.logprobs.as_ref().unwrap().token_logprobs,
+            [None, Some(-0.5)]
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! Decorators adding cross-cutting behavior to any [`ApiRequest`].
+//!
+//! Each decorator is itself an [`ApiRequest`] with the same response type, so they stack:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use openai_ox::{
+//!     decorators::{ApiRequestExt, ResponseCache},
+//!     ApiRequest, OpenAi,
+//! };
+//!
+//! async fn send<R>(openai: &OpenAi, request: R, cache: ResponseCache<R::Response>)
+//! where
+//!     R: ApiRequest + serde::Serialize + Send + Sync,
+//!     R::Response: Clone + Send,
+//! {
+//!     let request = request
+//!         .retrying(3)
+//!         .cached(cache)
+//!         .timed(|elapsed: Duration| println!("took {:?}", elapsed));
+//!     let _response = request.send_with(openai).await;
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use serde::{Serialize, Serializer};
+
+use crate::{backoff::Backoff, ApiRequest, ApiRequestError, ApiRequestWithClient, OpenAi};
+
+type Sent<'a, T> = BoxFuture<'a, Result<T, ApiRequestError>>;
+
+/// Decorators serialize as the request they wrap, so a decorated request still has a
+/// [`Cached`] key and can be decorated further.
+macro_rules! serialize_as_request {
+    ($($ty:ident<$($param:ident),+>),+) => {
+        $(impl<$($param),+> Serialize for $ty<$($param),+>
+        where
+            R: Serialize + ApiRequest,
+        {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.request.serialize(serializer)
+            }
+        })+
+    };
+}
+
+/// Retries transient failures, see [`ApiRequestError::is_retryable`].
+#[derive(Debug, Clone)]
+pub struct Retrying<R> {
+    request: R,
+    max_retries: u32,
+    backoff: Backoff,
+}
+
+impl<R: ApiRequest + Sync> Retrying<R> {
+    pub fn new(request: R, max_retries: u32) -> Self {
+        Self {
+            request,
+            max_retries,
+            backoff: Backoff::default(),
+        }
+    }
+
+    /// Delays between attempts, [`Backoff::default`] unless set.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn into_inner(self) -> R {
+        self.request
+    }
+
+    async fn run<'a>(
+        &'a self,
+        send: impl Fn() -> Sent<'a, R::Response> + Send + Sync + 'a,
+    ) -> Result<R::Response, ApiRequestError> {
+        let mut delays = self.backoff.delays();
+        let mut attempt = 0;
+        loop {
+            match send().await {
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    attempt += 1;
+                    tokio::time::sleep(delays.next().unwrap_or_default()).await;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<R> ApiRequest for Retrying<R>
+where
+    R: ApiRequest + Send + Sync,
+    R::Response: Send,
+{
+    type Response = R::Response;
+
+    async fn send_with(&self, open_ai: &OpenAi) -> Result<R::Response, ApiRequestError> {
+        self.run(|| self.request.send_with(open_ai)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<R> ApiRequestWithClient for Retrying<R>
+where
+    R: ApiRequestWithClient + Send + Sync,
+    R::Response: Send,
+{
+    async fn send(&self) -> Result<R::Response, ApiRequestError> {
+        self.run(|| self.request.send()).await
+    }
+}
+
+/// Waits for a token from its own rate limiter before every send, on top of the client's.
+#[cfg(feature = "leaky-bucket")]
+#[derive(Debug, Clone)]
+pub struct RateLimited<R> {
+    request: R,
+    limiter: Arc<crate::RateLimiter>,
+}
+
+#[cfg(feature = "leaky-bucket")]
+impl<R> RateLimited<R> {
+    pub fn new(request: R, limiter: Arc<crate::RateLimiter>) -> Self {
+        Self { request, limiter }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.request
+    }
+}
+
+#[cfg(feature = "leaky-bucket")]
+#[async_trait::async_trait]
+impl<R> ApiRequest for RateLimited<R>
+where
+    R: ApiRequest + Send + Sync,
+    R::Response: Send,
+{
+    type Response = R::Response;
+
+    async fn send_with(&self, open_ai: &OpenAi) -> Result<R::Response, ApiRequestError> {
+        self.limiter.acquire_one().await;
+        self.request.send_with(open_ai).await
+    }
+}
+
+#[cfg(feature = "leaky-bucket")]
+#[async_trait::async_trait]
+impl<R> ApiRequestWithClient for RateLimited<R>
+where
+    R: ApiRequestWithClient + Send + Sync,
+    R::Response: Send,
+{
+    async fn send(&self) -> Result<R::Response, ApiRequestError> {
+        self.limiter.acquire_one().await;
+        self.request.send().await
+    }
+}
+
+/// Responses keyed by the JSON body of the request that produced them.
+///
+/// Cloning is cheap and clones share the entries, so one cache can serve many requests.
+pub struct ResponseCache<T> {
+    entries: Arc<Mutex<HashMap<String, T>>>,
+}
+
+impl<T> Clone for ResponseCache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: Arc::clone(&self.entries),
+        }
+    }
+}
+
+impl<T> Default for ResponseCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ResponseCache<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T> ResponseCache<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn get(&self, key: &str) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: String, value: T) {
+        self.entries.lock().unwrap().insert(key, value);
+    }
+}
+
+/// Answers from a [`ResponseCache`] when an identical request already succeeded.
+///
+/// Only successful responses are stored. Concurrent identical requests may all reach the API
+/// before the first one is cached.
+#[derive(Debug, Clone)]
+pub struct Cached<R: ApiRequest> {
+    request: R,
+    cache: ResponseCache<R::Response>,
+}
+
+impl<R> Cached<R>
+where
+    R: ApiRequest + Serialize + Sync,
+    R::Response: Clone,
+{
+    pub fn new(request: R, cache: ResponseCache<R::Response>) -> Self {
+        Self { request, cache }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.request
+    }
+
+    async fn run<'a>(
+        &'a self,
+        send: Sent<'a, R::Response>,
+    ) -> Result<R::Response, ApiRequestError> {
+        // A request that can't be serialized has no key and simply isn't cached.
+        let key = serde_json::to_string(&self.request).ok();
+        if let Some(hit) = key.as_deref().and_then(|key| self.cache.get(key)) {
+            return Ok(hit);
+        }
+        let res = send.await?;
+        if let Some(key) = key {
+            self.cache.insert(key, res.clone());
+        }
+        Ok(res)
+    }
+}
+
+#[async_trait::async_trait]
+impl<R> ApiRequest for Cached<R>
+where
+    R: ApiRequest + Serialize + Send + Sync,
+    R::Response: Clone + Send,
+{
+    type Response = R::Response;
+
+    async fn send_with(&self, open_ai: &OpenAi) -> Result<R::Response, ApiRequestError> {
+        self.run(self.request.send_with(open_ai)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<R> ApiRequestWithClient for Cached<R>
+where
+    R: ApiRequestWithClient + Serialize + Send + Sync,
+    R::Response: Clone + Send,
+{
+    async fn send(&self) -> Result<R::Response, ApiRequestError> {
+        self.run(self.request.send()).await
+    }
+}
+
+/// Reports how long every send took, failed ones included.
+#[derive(Clone)]
+pub struct Timed<R, F> {
+    request: R,
+    on_done: F,
+}
+
+impl<R: fmt::Debug, F> fmt::Debug for Timed<R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timed")
+            .field("request", &self.request)
+            .finish()
+    }
+}
+
+impl<R, F: Fn(Duration)> Timed<R, F> {
+    pub fn new(request: R, on_done: F) -> Self {
+        Self { request, on_done }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.request
+    }
+
+    async fn run<T>(&self, send: Sent<'_, T>) -> Result<T, ApiRequestError> {
+        let started = Instant::now();
+        let res = send.await;
+        (self.on_done)(started.elapsed());
+        res
+    }
+}
+
+#[async_trait::async_trait]
+impl<R, F> ApiRequest for Timed<R, F>
+where
+    R: ApiRequest + Send + Sync,
+    R::Response: Send,
+    F: Fn(Duration) + Send + Sync,
+{
+    type Response = R::Response;
+
+    async fn send_with(&self, open_ai: &OpenAi) -> Result<R::Response, ApiRequestError> {
+        self.run(self.request.send_with(open_ai)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<R, F> ApiRequestWithClient for Timed<R, F>
+where
+    R: ApiRequestWithClient + Send + Sync,
+    R::Response: Send,
+    F: Fn(Duration) + Send + Sync,
+{
+    async fn send(&self) -> Result<R::Response, ApiRequestError> {
+        self.run(self.request.send()).await
+    }
+}
+
+serialize_as_request!(Retrying<R>, Cached<R>, Timed<R, F>);
+#[cfg(feature = "leaky-bucket")]
+serialize_as_request!(RateLimited<R>);
+
+/// Wraps requests in the decorators of this module.
+pub trait ApiRequestExt: ApiRequest + Sized {
+    /// Retries transient failures up to `max_retries` times with the default backoff.
+    fn retrying(self, max_retries: u32) -> Retrying<Self>
+    where
+        Self: Sync,
+    {
+        Retrying::new(self, max_retries)
+    }
+
+    #[cfg(feature = "leaky-bucket")]
+    fn rate_limited(self, limiter: Arc<crate::RateLimiter>) -> RateLimited<Self> {
+        RateLimited::new(self, limiter)
+    }
+
+    fn cached(self, cache: ResponseCache<Self::Response>) -> Cached<Self>
+    where
+        Self: Serialize + Sync,
+        Self::Response: Clone,
+    {
+        Cached::new(self, cache)
+    }
+
+    fn timed<F: Fn(Duration)>(self, on_done: F) -> Timed<Self, F> {
+        Timed::new(self, on_done)
+    }
+}
+
+impl<R: ApiRequest> ApiRequestExt for R {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use reqwest::StatusCode;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::ApiError;
+
+    /// Fails with a rate limit error `failures` times, then echoes its prompt.
+    #[derive(Debug, Serialize)]
+    struct Flaky {
+        prompt: String,
+        #[serde(skip)]
+        failures: u32,
+        #[serde(skip)]
+        calls: Arc<AtomicU32>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    struct Echo(String);
+
+    #[async_trait::async_trait]
+    impl ApiRequest for Flaky {
+        type Response = Echo;
+
+        async fn send_with(&self, _open_ai: &OpenAi) -> Result<Echo, ApiRequestError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(ApiRequestError::RateLimited {
+                    error: ApiError {
+                        status: StatusCode::TOO_MANY_REQUESTS,
+                        message: "Rate limit reached".to_string(),
+                        kind: Some("requests".to_string()),
+                        param: None,
+                        code: Some("rate_limit_exceeded".to_string()),
+                    },
+                    retry_after: None,
+                });
+            }
+            Ok(Echo(self.prompt.clone()))
+        }
+    }
+
+    fn flaky(failures: u32, calls: &Arc<AtomicU32>) -> Flaky {
+        Flaky {
+            prompt: "hi".to_string(),
+            failures,
+            calls: Arc::clone(calls),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decorators_compose() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let calls = Arc::new(AtomicU32::new(0));
+        let timings = Arc::new(AtomicU32::new(0));
+        let cache = ResponseCache::new();
+        let backoff = Backoff::builder().initial(Duration::ZERO).build();
+
+        let counted = Arc::clone(&timings);
+        let request = flaky(2, &calls)
+            .retrying(2)
+            .backoff(backoff)
+            .cached(cache.clone())
+            .timed(move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+            });
+        assert_eq!(request.send_with(&openai).await.unwrap(), Echo("hi".into()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.len(), 1);
+
+        // Served from the cache, the inner request isn't called again.
+        assert!(request.send_with(&openai).await.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(timings.load(Ordering::SeqCst), 2);
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let request = flaky(5, &calls).retrying(1).backoff(backoff);
+        assert!(request.send_with(&openai).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
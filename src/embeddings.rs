@@ -1,21 +1,117 @@
-use std::borrow::Cow;
-
+use base64::{engine::general_purpose::STANDARD, Engine};
 use bon::Builder;
-use serde::{Deserialize, Serialize};
+use futures::{StreamExt, TryStreamExt};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    auth::Authorize, json, rate_limit::estimate_tokens, require_client, retry::SendRetrying,
+    ApiRequest, ApiRequestError, ApiRequestWithClient, OpenAi,
+};
+
+const API_URL: &str = "v1/embeddings";
 
-use crate::{ApiRequestError, ErrorResponse, OpenAi};
+/// Most inputs the API accepts in one request.
+pub const MAX_INPUTS: usize = 2048;
+/// Most tokens the API accepts in one request, summed over all inputs.
+pub const MAX_TOKENS: usize = 300_000;
 
-#[derive(Debug, Serialize, Builder)]
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
 pub struct EmbeddingRequest {
     #[builder(into)]
     model: String,
-    input: Vec<String>,
+    #[builder(into)]
+    input: EmbeddingInput,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     dimensions: Option<u32>,
+    /// Wire format of the vectors. Base64 responses are about four times smaller and decoded
+    /// into the same [`EmbeddingData::embedding`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding_format: Option<EncodingFormat>,
     #[serde(skip)]
-    openai: OpenAi,
+    openai: Option<OpenAi>,
+}
+
+/// Text or token ids to embed, one vector per string or token array.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Text(String),
+    Texts(Vec<String>),
+    /// Pre-tokenized text, with the encoding of the embedding model.
+    Tokens(Vec<u32>),
+    TokenBatches(Vec<Vec<u32>>),
+}
+
+impl EmbeddingInput {
+    /// Number of vectors the input produces.
+    pub fn len(&self) -> usize {
+        match self {
+            EmbeddingInput::Text(_) | EmbeddingInput::Tokens(_) => 1,
+            EmbeddingInput::Texts(texts) => texts.len(),
+            EmbeddingInput::TokenBatches(batches) => batches.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<String> for EmbeddingInput {
+    fn from(text: String) -> Self {
+        EmbeddingInput::Text(text)
+    }
+}
+
+impl From<&str> for EmbeddingInput {
+    fn from(text: &str) -> Self {
+        EmbeddingInput::Text(text.to_string())
+    }
+}
+
+impl From<Vec<String>> for EmbeddingInput {
+    fn from(texts: Vec<String>) -> Self {
+        EmbeddingInput::Texts(texts)
+    }
+}
+
+impl From<Vec<&str>> for EmbeddingInput {
+    fn from(texts: Vec<&str>) -> Self {
+        texts.as_slice().into()
+    }
+}
+
+impl From<&[&str]> for EmbeddingInput {
+    fn from(texts: &[&str]) -> Self {
+        EmbeddingInput::Texts(texts.iter().map(|text| text.to_string()).collect())
+    }
+}
+
+impl From<&[String]> for EmbeddingInput {
+    fn from(texts: &[String]) -> Self {
+        EmbeddingInput::Texts(texts.to_vec())
+    }
+}
+
+impl From<Vec<u32>> for EmbeddingInput {
+    fn from(tokens: Vec<u32>) -> Self {
+        EmbeddingInput::Tokens(tokens)
+    }
+}
+
+impl From<Vec<Vec<u32>>> for EmbeddingInput {
+    fn from(batches: Vec<Vec<u32>>) -> Self {
+        EmbeddingInput::TokenBatches(batches)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingFormat {
+    Float,
+    Base64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,16 +125,89 @@ pub struct EmbeddingResponse {
 #[derive(Debug, Deserialize)]
 pub struct EmbeddingData {
     pub object: String,
+    #[serde(deserialize_with = "deserialize_embedding")]
     pub embedding: Vec<f32>,
     pub index: usize,
 }
 
-#[derive(Debug, Deserialize)]
+/// An array of floats, or the base64 encoded little-endian `f32`s of `encoding_format: base64`.
+fn deserialize_embedding<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Embedding {
+        Float(Vec<f32>),
+        Base64(String),
+    }
+
+    match Embedding::deserialize(deserializer)? {
+        Embedding::Float(embedding) => Ok(embedding),
+        Embedding::Base64(encoded) => {
+            let bytes = STANDARD.decode(encoded).map_err(de::Error::custom)?;
+            if bytes.len() % 4 != 0 {
+                return Err(de::Error::custom(
+                    "base64 embedding is not a whole number of f32s",
+                ));
+            }
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: usize,
     pub total_tokens: usize,
 }
 
+/// How [`EmbeddingRequest::send_batched`] splits its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Builder)]
+pub struct BatchLimits {
+    #[builder(default = MAX_INPUTS)]
+    pub max_inputs: usize,
+    /// Counted exactly with the `tokenizer` feature, estimated from the text length otherwise.
+    #[builder(default = MAX_TOKENS)]
+    pub max_tokens: usize,
+    /// Requests in flight at once.
+    #[builder(default = 4)]
+    pub concurrency: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        BatchLimits::builder().build()
+    }
+}
+
+/// Splits `items` into runs within `limits`. An item over the token limit on its own gets a
+/// batch of its own, for the API to reject.
+fn split_batches<T: Clone>(
+    items: &[T],
+    tokens: impl Fn(&T) -> usize,
+    limits: &BatchLimits,
+) -> Vec<Vec<T>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_tokens = 0;
+    for item in items {
+        let item_tokens = tokens(item);
+        let full = batch.len() >= limits.max_inputs.max(1)
+            || batch_tokens + item_tokens > limits.max_tokens;
+        if full && !batch.is_empty() {
+            batches.push(std::mem::take(&mut batch));
+            batch_tokens = 0;
+        }
+        batch_tokens += item_tokens;
+        batch.push(item.clone());
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum EmbeddingRequestBuilderError {
     #[error("Missing required field: model")]
@@ -48,59 +217,445 @@ pub enum EmbeddingRequestBuilderError {
 }
 
 impl EmbeddingRequest {
+    /// Tokens of the input, estimated for text and counted for token arrays.
+    fn estimated_tokens(&self) -> usize {
+        match &self.input {
+            EmbeddingInput::Text(text) => estimate_tokens(&self.model, text),
+            EmbeddingInput::Texts(texts) => texts
+                .iter()
+                .map(|text| estimate_tokens(&self.model, text))
+                .sum(),
+            EmbeddingInput::Tokens(tokens) => tokens.len(),
+            EmbeddingInput::TokenBatches(batches) => batches.iter().map(Vec::len).sum(),
+        }
+    }
+
     pub async fn send(&self) -> Result<EmbeddingResponse, ApiRequestError> {
-        #[cfg(feature = "leaky-bucket")]
-        if let Some(rate_limiter) = self.openai.leaky_bucket.as_ref() {
-            rate_limiter.acquire_one().await;
+        self.send_with(require_client(&self.openai)?).await
+    }
+
+    /// Sends an input of any size as requests within `limits`, several at once, and merges the
+    /// responses into one. Vectors keep the index of their input and usage is summed.
+    pub async fn send_batched(
+        &self,
+        limits: BatchLimits,
+    ) -> Result<EmbeddingResponse, ApiRequestError> {
+        let inputs: Vec<EmbeddingInput> = match &self.input {
+            EmbeddingInput::Texts(texts) => {
+                split_batches(texts, |text| estimate_tokens(&self.model, text), &limits)
+                    .into_iter()
+                    .map(EmbeddingInput::Texts)
+                    .collect()
+            }
+            EmbeddingInput::TokenBatches(batches) => split_batches(batches, Vec::len, &limits)
+                .into_iter()
+                .map(EmbeddingInput::TokenBatches)
+                .collect(),
+            input => vec![input.clone()],
+        };
+        let offsets: Vec<usize> = inputs
+            .iter()
+            .scan(0, |offset, input| {
+                let start = *offset;
+                *offset += input.len();
+                Some(start)
+            })
+            .collect();
+        let responses: Vec<EmbeddingResponse> = futures::stream::iter(inputs)
+            .map(|input| {
+                let request = EmbeddingRequest {
+                    input,
+                    ..self.clone()
+                };
+                async move { request.send().await }
+            })
+            .buffered(limits.concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        let mut merged = EmbeddingResponse {
+            object: "list".to_string(),
+            data: Vec::with_capacity(self.input.len()),
+            model: self.model.clone(),
+            usage: Usage::default(),
+        };
+        for (response, offset) in responses.into_iter().zip(offsets) {
+            merged.model = response.model;
+            merged.usage.prompt_tokens += response.usage.prompt_tokens;
+            merged.usage.total_tokens += response.usage.total_tokens;
+            merged
+                .data
+                .extend(response.data.into_iter().map(|data| EmbeddingData {
+                    index: data.index + offset,
+                    ..data
+                }));
+        }
+        merged.data.sort_by_key(|data| data.index);
+        Ok(merged)
+    }
+
+    /// Sends the request and stores it in `outbox` if it fails with a retryable error.
+    ///
+    /// Returns `Ok(None)` when the request was queued; the response will be delivered by the
+    /// [`OutboxDriver`](crate::outbox::OutboxDriver) once a retry succeeds.
+    #[cfg(feature = "outbox")]
+    pub async fn send_or_enqueue(
+        &self,
+        outbox: &crate::outbox::Outbox,
+        key: Option<String>,
+    ) -> Result<Option<EmbeddingResponse>, crate::outbox::OutboxError> {
+        match self.send().await {
+            Ok(res) => Ok(Some(res)),
+            Err(e) if e.is_retryable() => {
+                outbox
+                    .enqueue(API_URL, serde_json::to_value(self)?, key)
+                    .await?;
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
         }
+    }
+}
 
-        let url = "https://api.openai.com/v1/embeddings";
-        let response = self
-            .openai
+#[async_trait::async_trait]
+impl ApiRequest for EmbeddingRequest {
+    type Response = EmbeddingResponse;
+
+    async fn send_with(&self, openai: &OpenAi) -> Result<EmbeddingResponse, ApiRequestError> {
+        openai
+            .inner
+            .acquire_tokens(|| self.estimated_tokens())
+            .await;
+        let url = openai.inner.model_url(API_URL, &self.model);
+        let response = openai
+            .inner
             .client
             .post(url)
             .header("Content-Type", "application/json")
-            .bearer_auth(&self.openai.api_key)
+            .authorize(openai)
+            .await?
             .json(&self)
-            .send()
+            .send_retrying(openai)
             .await?;
 
         if response.status().is_success() {
-            let data: EmbeddingResponse = response.json().await?;
+            let data: EmbeddingResponse = json::from_slice(&response.bytes().await?)?;
             Ok(data)
         } else {
-            let error_response: ErrorResponse = response.json().await?;
-            Err(ApiRequestError::InvalidRequestError {
-                message: error_response.error.message,
-                param: error_response.error.param,
-                code: error_response.error.code,
-            })
+            Err(ApiRequestError::from_response(response).await)
         }
     }
 }
 
+#[async_trait::async_trait]
+impl ApiRequestWithClient for EmbeddingRequest {
+    async fn send(&self) -> Result<EmbeddingResponse, ApiRequestError> {
+        self.send_with(require_client(&self.openai)?).await
+    }
+}
+
 impl OpenAi {
     pub fn embeddings(&self) -> EmbeddingRequestBuilder<embedding_request_builder::SetOpenai> {
         EmbeddingRequest::builder().openai(self.clone())
     }
+
+    /// Embeds any number of `texts` with [`EmbeddingRequest::send_batched`].
+    pub async fn embed_many(
+        &self,
+        model: impl Into<String>,
+        texts: impl IntoIterator<Item = impl Into<String>>,
+        limits: BatchLimits,
+    ) -> Result<EmbeddingResponse, ApiRequestError> {
+        let texts: Vec<String> = texts.into_iter().map(Into::into).collect();
+        self.embeddings()
+            .model(model)
+            .input(texts)
+            .build()
+            .send_batched(limits)
+            .await
+    }
+
+    /// Embeds a single `text` and returns its vector.
+    pub async fn quick_embed(
+        &self,
+        model: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Result<Vec<f32>, ApiRequestError> {
+        let res = self
+            .embeddings()
+            .model(model)
+            .input(EmbeddingInput::Text(text.into()))
+            .build()
+            .send()
+            .await?;
+        res.data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+                status: None,
+                response: "response contains no embeddings".to_string(),
+            })
+    }
+}
+
+/// Dot product of two vectors, `None` if they differ in length, e.g. embeddings of different
+/// models or dimensions.
+pub fn dot(a: &[f32], b: &[f32]) -> Option<f32> {
+    (a.len() == b.len()).then(|| a.iter().zip(b).map(|(a, b)| a * b).sum())
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Cosine of the angle between two vectors, `0.0` when either is all zeros and `None` if they
+/// differ in length. OpenAI embeddings are normalized to length 1, so for them this equals
+/// [`dot`].
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    let dot = dot(a, b)?;
+    let norms = norm(a) * norm(b);
+    Some(if norms == 0.0 { 0.0 } else { dot / norms })
+}
+
+/// Scales `vector` to length 1, e.g. after shortening it by hand. A zero vector stays as it is.
+pub fn normalize(vector: &mut [f32]) {
+    let norm = norm(vector);
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Indices and cosine similarities of the `k` candidates most similar to `query`, most similar
+/// first. Candidates that differ from `query` in length are skipped.
+pub fn top_k<V: AsRef<[f32]>>(query: &[f32], candidates: &[V], k: usize) -> Vec<(usize, f32)> {
+    let mut scored: Vec<(usize, f32)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, candidate)| Some((i, cosine_similarity(query, candidate.as_ref())?)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+    scored
+}
+
+impl AsRef<[f32]> for EmbeddingData {
+    fn as_ref(&self) -> &[f32] {
+        &self.embedding
+    }
+}
+
+impl EmbeddingData {
+    pub fn cosine_similarity(&self, other: &[f32]) -> Option<f32> {
+        cosine_similarity(&self.embedding, other)
+    }
+
+    pub fn dot(&self, other: &[f32]) -> Option<f32> {
+        dot(&self.embedding, other)
+    }
+
+    pub fn normalize(&mut self) {
+        normalize(&mut self.embedding);
+    }
 }
+
+impl EmbeddingResponse {
+    /// The `k` vectors of the response most similar to `query`, as indices into `data` with
+    /// their cosine similarity.
+    pub fn top_k(&self, query: &[f32], k: usize) -> Vec<(usize, f32)> {
+        top_k(query, &self.data, k)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+
     use super::*;
 
+    #[test]
+    fn test_base64_embedding() {
+        let vector = [0.5f32, -1.25, 3.0];
+        let bytes: Vec<u8> = vector.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let data = |embedding| -> Result<EmbeddingData, _> {
+            serde_json::from_value(
+                json!({"object": "embedding", "embedding": embedding, "index": 0}),
+            )
+        };
+        assert_eq!(
+            data(json!(STANDARD.encode(&bytes))).unwrap().embedding,
+            vector
+        );
+        assert_eq!(data(json!(vector)).unwrap().embedding, vector);
+        assert!(data(json!(STANDARD.encode(&bytes[..5]))).is_err());
+
+        let request = OpenAi::builder()
+            .api_key(String::new())
+            .build()
+            .embeddings()
+            .model("text-embedding-3-small")
+            .input("Hello")
+            .encoding_format(EncodingFormat::Base64)
+            .build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["encoding_format"],
+            "base64"
+        );
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(dot(&[1.0, 2.0], &[3.0, 4.0]), Some(11.0));
+        let cosine = cosine_similarity(&[1.0, 0.0], &[1.0, 1.0]).unwrap();
+        assert!((cosine - 0.5f32.sqrt()).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), Some(0.0));
+
+        let mut vector = [3.0, 4.0];
+        normalize(&mut vector);
+        assert_eq!(vector, [0.6, 0.8]);
+
+        let candidates = [vec![0.0, 1.0], vec![1.0, 0.0], vec![0.7, 0.7]];
+        let best = top_k(&[1.0, 0.1], &candidates, 2);
+        assert_eq!(best.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+        assert!(best[0].1 > best[1].1);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        assert_eq!(dot(&[1.0, 0.0], &[1.0, 0.0, 0.0]), None);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]), None);
+
+        let candidates = [vec![1.0, 0.0, 0.0], vec![0.0, 1.0]];
+        assert_eq!(top_k(&[0.0, 1.0], &candidates, 2), [(1, 1.0)]);
+    }
+
+    #[test]
+    fn test_split_batches() {
+        let limits = BatchLimits::builder().max_inputs(2).max_tokens(10).build();
+        let texts = ["aaaa", "bb", "cccccc", "d", "eeeeeeeeeeee"];
+        let batches = split_batches(&texts, |text| text.len(), &limits);
+        assert_eq!(
+            batches,
+            [
+                vec!["aaaa", "bb"],
+                vec!["cccccc", "d"],
+                vec!["eeeeeeeeeeee"]
+            ]
+        );
+        assert!(split_batches(&[] as &[&str], |text| text.len(), &limits).is_empty());
+    }
+
+    #[test]
+    fn test_embedding_input() {
+        let cases = [
+            (EmbeddingInput::from("hi"), json!("hi"), 1),
+            (EmbeddingInput::from(vec!["a", "b"]), json!(["a", "b"]), 2),
+            (EmbeddingInput::from(vec![1u32, 2]), json!([1, 2]), 1),
+            (
+                EmbeddingInput::from(vec![vec![1u32], vec![2]]),
+                json!([[1], [2]]),
+                2,
+            ),
+        ];
+        for (input, json, len) in cases {
+            assert_eq!(serde_json::to_value(&input).unwrap(), json);
+            assert_eq!(
+                serde_json::from_value::<EmbeddingInput>(json).unwrap(),
+                input
+            );
+            assert_eq!(input.len(), len);
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_send_batched() {
+        use serde_json::Value;
+
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        // Each batch answers in reverse order, with vectors holding the batch number.
+        let response = |batch: f32, len: usize, tokens: u32| {
+            let data: Vec<_> = (0..len)
+                .rev()
+                .map(|index| json!({"object": "embedding", "embedding": [batch, index as f32], "index": index}))
+                .collect();
+            MockResponse::json(&json!({
+                "object": "list",
+                "data": data,
+                "model": "text-embedding-3-small",
+                "usage": {"prompt_tokens": tokens, "total_tokens": tokens}
+            }))
+        };
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push("v1/embeddings", response(0.0, 2, 3))
+            .push("v1/embeddings", response(1.0, 2, 4))
+            .push("v1/embeddings", response(2.0, 1, 5));
+        let response = mock
+            .client()
+            .embeddings()
+            .model("text-embedding-3-small")
+            .input(vec!["a", "b", "c", "d", "e"])
+            .build()
+            .send_batched(BatchLimits::builder().max_inputs(2).concurrency(1).build())
+            .await
+            .unwrap();
+
+        let inputs: Vec<Value> = mock
+            .requests()
+            .iter()
+            .map(|request| request.json::<Value>().unwrap()["input"].clone())
+            .collect();
+        assert_eq!(inputs, [json!(["a", "b"]), json!(["c", "d"]), json!(["e"])]);
+        let vectors: Vec<(usize, &[f32])> = response
+            .data
+            .iter()
+            .map(|data| (data.index, data.embedding.as_slice()))
+            .collect();
+        assert_eq!(
+            vectors,
+            [
+                (0, &[0.0, 0.0][..]),
+                (1, &[0.0, 1.0]),
+                (2, &[1.0, 0.0]),
+                (3, &[1.0, 1.0]),
+                (4, &[2.0, 0.0]),
+            ]
+        );
+        assert_eq!(response.usage.prompt_tokens, 12);
+        assert_eq!(response.usage.total_tokens, 12);
+    }
+
+    #[cfg(feature = "mock")]
     #[tokio::test]
     async fn test_embedding_request() {
-        let openai_api_key = std::env::var("OPENAI_API_KEY").unwrap();
-        let openai = OpenAi::builder().api_key(openai_api_key).build();
-        let request = openai
+        use serde_json::Value;
+
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/embeddings",
+            MockResponse::json(&json!({
+                "object": "list",
+                "data": [{"object": "embedding", "embedding": [0.6, 0.8], "index": 0}],
+                "model": "text-embedding-3-small",
+                "usage": {"prompt_tokens": 2, "total_tokens": 2}
+            })),
+        );
+        let request = mock
+            .client()
             .embeddings()
             .model("text-embedding-3-small")
-            .dimensions(256)
-            .input(vec!["Hello world".to_string()])
+            .dimensions(2)
+            .input("Hello world")
             .build();
 
         let response = request.send().await.unwrap();
-        dbg!(&response.data[0].embedding);
-        // dbg!(response);
+        assert_eq!(response.data[0].embedding.as_slice(), &[0.6, 0.8]);
+
+        let sent: Value = mock.requests()[0].json().unwrap();
+        assert_eq!(sent["dimensions"], 2);
+        assert_eq!(sent["input"], "Hello world");
     }
 }
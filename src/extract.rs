@@ -0,0 +1,353 @@
+//! Typed extraction of structured data from unstructured text.
+//!
+//! A type implementing [`Extract`] knows its JSON schema, so the model is asked for exactly that
+//! shape with a strict `json_schema` response format and the reply deserializes straight into
+//! it. With the `derive` feature the impl is generated:
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! use openai_ox::extract::Extract;
+//! use serde::Deserialize;
+//!
+//! /// An invoice found in the document.
+//! #[derive(Debug, Deserialize, Extract)]
+//! struct Invoice {
+//!     /// Invoice number as printed.
+//!     number: String,
+//!     total: f64,
+//!     due_date: Option<String>,
+//! }
+//!
+//! # async fn example(openai: openai_ox::OpenAi, text: &str) -> Result<(), openai_ox::ApiRequestError> {
+//! let invoice = Invoice::extract(&openai, text).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+#[cfg(feature = "derive")]
+pub use openai_ox_derive::Extract;
+#[doc(hidden)]
+pub use serde_json;
+
+use crate::{
+    chat::{
+        message::{Message, Messages},
+        ChatCompletionRequest, ChatCompletionRequestError, JsonSchemaFormat, ResponseFormat,
+    },
+    ApiRequestError, OpenAi,
+};
+
+/// Model used by [`Extract::extract`].
+pub const DEFAULT_EXTRACT_MODEL: &str = "gpt-4o-mini";
+
+const EXTRACT_PROMPT: &str = "Extract the requested information from the user's text. \
+Use null for anything the text does not mention, never guess.";
+
+/// Types that can describe themselves as a JSON schema accepted by structured outputs.
+pub trait JsonSchema {
+    fn json_schema() -> Value;
+}
+
+macro_rules! schema_type {
+    ($kind:literal: $($ty:ty),+) => {
+        $(impl JsonSchema for $ty {
+            fn json_schema() -> Value {
+                json!({"type": $kind})
+            }
+        })+
+    };
+}
+
+schema_type!("string": String, char);
+schema_type!("boolean": bool);
+schema_type!("integer": i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+schema_type!("number": f32, f64);
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> Value {
+        json!({"anyOf": [T::json_schema(), {"type": "null"}]})
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> Value {
+        json!({"type": "array", "items": T::json_schema()})
+    }
+}
+
+impl<T: JsonSchema + Ord> JsonSchema for BTreeSet<T> {
+    fn json_schema() -> Value {
+        Vec::<T>::json_schema()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Box<T> {
+    fn json_schema() -> Value {
+        T::json_schema()
+    }
+}
+
+/// Adds `description` to `schema`, used by the derive for doc comments.
+pub fn with_description(mut schema: Value, description: Option<&str>) -> Value {
+    if let (Some(description), Value::Object(map)) = (description, &mut schema) {
+        map.insert("description".to_string(), description.into());
+    }
+    schema
+}
+
+/// Schema name for `T`: its type name without module path or generics.
+fn schema_name<T>() -> String {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    let name: String = name
+        .rsplit("::")
+        .next()
+        .unwrap_or(name)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    if name.is_empty() {
+        "response".to_string()
+    } else {
+        name
+    }
+}
+
+impl ResponseFormat {
+    /// Strict `json_schema` format for `T` under `name`.
+    pub fn json_schema<T: JsonSchema>(name: impl Into<String>) -> Self {
+        ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema: T::json_schema(),
+                strict: Some(true),
+            },
+        }
+    }
+}
+
+impl ChatCompletionRequest {
+    /// Sends the request constrained to the schema of `T` and deserializes the reply into it.
+    /// Any `response_format` already set is replaced.
+    pub async fn send_structured<T: JsonSchema + DeserializeOwned>(
+        &self,
+    ) -> Result<T, ApiRequestError> {
+        let mut request = self.clone();
+        request.response_format = Some(ResponseFormat::json_schema::<T>(schema_name::<T>()));
+        request.send().await?.parse()
+    }
+}
+
+/// A type that can be pulled out of free text by the model.
+#[async_trait::async_trait]
+pub trait Extract: JsonSchema + DeserializeOwned + Send {
+    /// Schema name sent to the API: letters, digits, `_` and `-` only.
+    const NAME: &'static str;
+
+    fn response_format() -> ResponseFormat {
+        ResponseFormat::json_schema::<Self>(Self::NAME)
+    }
+
+    /// The extraction request without sending it, to adjust it or run it in a batch.
+    fn extraction_request(
+        openai: &OpenAi,
+        model: &str,
+        text: &str,
+    ) -> Result<ChatCompletionRequest, ChatCompletionRequestError> {
+        openai
+            .chat_completion()
+            .model(model)
+            .messages(Messages(vec![
+                Message::system(EXTRACT_PROMPT),
+                Message::user(text),
+            ]))
+            .response_format(Self::response_format())
+            .build()
+    }
+
+    /// Extracts `Self` from `text` with [`DEFAULT_EXTRACT_MODEL`].
+    async fn extract(openai: &OpenAi, text: &str) -> Result<Self, ApiRequestError> {
+        Self::extract_with(openai, DEFAULT_EXTRACT_MODEL, text).await
+    }
+
+    async fn extract_with(
+        openai: &OpenAi,
+        model: &str,
+        text: &str,
+    ) -> Result<Self, ApiRequestError> {
+        Self::extraction_request(openai, model, text)?
+            .send()
+            .await?
+            .parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Contact {
+        name: String,
+        emails: Vec<String>,
+        age: Option<u32>,
+    }
+
+    impl JsonSchema for Contact {
+        fn json_schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": with_description(String::json_schema(), Some("Full name")),
+                    "emails": Vec::<String>::json_schema(),
+                    "age": Option::<u32>::json_schema(),
+                },
+                "required": ["name", "emails", "age"],
+                "additionalProperties": false,
+            })
+        }
+    }
+
+    impl Extract for Contact {
+        const NAME: &'static str = "Contact";
+    }
+
+    #[test]
+    fn test_extraction_request() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let request = Contact::extraction_request(&openai, "gpt-4o", "Jane, 34").unwrap();
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["name"], "Contact");
+        assert_eq!(body["response_format"]["json_schema"]["strict"], true);
+        assert_eq!(
+            body["response_format"]["json_schema"]["schema"]["properties"]["age"],
+            json!({"anyOf": [{"type": "integer"}, {"type": "null"}]})
+        );
+
+        assert_eq!(schema_name::<Contact>(), "Contact");
+        assert_eq!(schema_name::<Vec<Contact>>(), "Vec");
+
+        let contact: Contact =
+            crate::json::from_str(r#"{"name":"Jane","emails":[],"age":34}"#).unwrap();
+        assert_eq!((contact.name.as_str(), contact.age), ("Jane", Some(34)));
+        assert!(contact.emails.is_empty());
+    }
+
+    #[cfg(feature = "derive")]
+    mod derive {
+        use serde::Serialize;
+
+        use super::*;
+
+        macro_rules! renamed {
+            ($record:ident, $choice:ident, $rule:literal) => {
+                #[derive(Default, Serialize, Deserialize, Extract)]
+                #[serde(rename_all = $rule)]
+                struct $record {
+                    foo_bar: u32,
+                    r#type: String,
+                    #[serde(rename = "kept")]
+                    renamed: bool,
+                    #[serde(skip)]
+                    _skipped: bool,
+                }
+
+                #[derive(Serialize, Deserialize, Extract)]
+                #[serde(rename_all = $rule)]
+                enum $choice {
+                    FooBar,
+                    Type,
+                    HTTPCode,
+                }
+
+                impl $choice {
+                    const ALL: [$choice; 3] = [$choice::FooBar, $choice::Type, $choice::HTTPCode];
+                }
+            };
+        }
+
+        renamed!(Lower, LowerChoice, "lowercase");
+        renamed!(Upper, UpperChoice, "UPPERCASE");
+        renamed!(Pascal, PascalChoice, "PascalCase");
+        renamed!(Camel, CamelChoice, "camelCase");
+        renamed!(Snake, SnakeChoice, "snake_case");
+        renamed!(ScreamingSnake, ScreamingSnakeChoice, "SCREAMING_SNAKE_CASE");
+        renamed!(Kebab, KebabChoice, "kebab-case");
+        renamed!(ScreamingKebab, ScreamingKebabChoice, "SCREAMING-KEBAB-CASE");
+
+        /// Checks the schema against the keys serde writes and reads back.
+        fn assert_fields_match<T: Extract + Serialize + Default>() -> Vec<String> {
+            let value = serde_json::to_value(T::default()).unwrap();
+            let keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            let schema = T::json_schema();
+            let properties: Vec<String> = schema["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            assert_eq!(keys, properties, "{}", T::NAME);
+            let mut required: Vec<String> =
+                serde_json::from_value(schema["required"].clone()).unwrap();
+            required.sort();
+            assert_eq!(keys, required, "{}", T::NAME);
+            serde_json::from_value::<T>(value).unwrap();
+            keys
+        }
+
+        /// Checks the schema against the strings serde writes and reads back.
+        fn assert_variants_match<T: Extract + Serialize>(variants: &[T]) -> Vec<String> {
+            let names: Vec<String> = variants
+                .iter()
+                .map(|variant| {
+                    serde_json::to_value(variant)
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect();
+            assert_eq!(T::json_schema()["enum"], json!(names), "{}", T::NAME);
+            for name in &names {
+                serde_json::from_value::<T>(json!(name)).unwrap();
+            }
+            names
+        }
+
+        #[test]
+        fn test_derived_schema_matches_serde() {
+            assert_eq!(assert_fields_match::<Lower>(), ["foo_bar", "kept", "type"]);
+            assert_eq!(assert_fields_match::<Upper>(), ["FOO_BAR", "TYPE", "kept"]);
+            assert_fields_match::<Pascal>();
+            assert_fields_match::<Camel>();
+            assert_fields_match::<Snake>();
+            assert_fields_match::<ScreamingSnake>();
+            assert_eq!(assert_fields_match::<Kebab>(), ["foo-bar", "kept", "type"]);
+            assert_fields_match::<ScreamingKebab>();
+
+            assert_eq!(
+                assert_variants_match(&LowerChoice::ALL),
+                ["foobar", "type", "httpcode"]
+            );
+            assert_variants_match(&UpperChoice::ALL);
+            assert_variants_match(&PascalChoice::ALL);
+            assert_variants_match(&CamelChoice::ALL);
+            assert_eq!(
+                assert_variants_match(&SnakeChoice::ALL),
+                ["foo_bar", "type", "h_t_t_p_code"]
+            );
+            assert_variants_match(&ScreamingSnakeChoice::ALL);
+            assert_variants_match(&KebabChoice::ALL);
+            assert_variants_match(&ScreamingKebabChoice::ALL);
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Uploading, listing, deleting and downloading files (`/v1/files`), plus files created inside
+//! code interpreter containers (`/v1/containers/{id}/files`).
+
+use std::collections::HashSet;
+
+use bon::Builder;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{auth::Authorize, retry::SendRetrying, ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/files";
+const CONTAINERS_URL: &str = "v1/containers";
+
+const MIN_EXPIRATION_SECS: u64 = 3600;
+const MAX_EXPIRATION_SECS: u64 = 30 * 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilePurpose {
+    #[serde(rename = "assistants")]
+    Assistants,
+    #[serde(rename = "batch")]
+    Batch,
+    #[serde(rename = "fine-tune")]
+    FineTune,
+    #[serde(rename = "vision")]
+    Vision,
+    #[serde(rename = "user_data")]
+    UserData,
+    #[serde(rename = "evals")]
+    Evals,
+}
+
+impl FilePurpose {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilePurpose::Assistants => "assistants",
+            FilePurpose::Batch => "batch",
+            FilePurpose::FineTune => "fine-tune",
+            FilePurpose::Vision => "vision",
+            FilePurpose::UserData => "user_data",
+            FilePurpose::Evals => "evals",
+        }
+    }
+
+    /// File extensions the API accepts for this purpose, `None` when any type is allowed.
+    pub fn extensions(self) -> Option<&'static [&'static str]> {
+        match self {
+            FilePurpose::Batch | FilePurpose::FineTune | FilePurpose::Evals => Some(&["jsonl"]),
+            FilePurpose::Vision => Some(&["png", "jpg", "jpeg", "gif", "webp"]),
+            FilePurpose::Assistants | FilePurpose::UserData => None,
+        }
+    }
+
+    /// Checks the extension of `filename` against [`extensions`](FilePurpose::extensions).
+    pub fn validate_filename(self, filename: &str) -> Result<(), FileRequestError> {
+        let Some(extensions) = self.extensions() else {
+            return Ok(());
+        };
+        let extension = filename
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase());
+        match extension {
+            Some(extension) if extensions.contains(&extension.as_str()) => Ok(()),
+            _ => Err(FileRequestError::UnsupportedFileType {
+                purpose: self,
+                filename: filename.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FileRequestError {
+    #[error("{filename} cannot be uploaded with purpose {}", .purpose.as_str())]
+    UnsupportedFileType {
+        purpose: FilePurpose,
+        filename: String,
+    },
+    #[error(
+        "Expiration must be between {} and {} seconds, got {}",
+        MIN_EXPIRATION_SECS,
+        MAX_EXPIRATION_SECS,
+        .0
+    )]
+    InvalidExpiration(u64),
+}
+
+impl From<FileRequestError> for ApiRequestError {
+    fn from(e: FileRequestError) -> Self {
+        let param = match e {
+            FileRequestError::UnsupportedFileType { .. } => "file",
+            FileRequestError::InvalidExpiration(_) => "expires_after",
+        };
+        ApiRequestError::InvalidRequestError {
+            message: e.to_string(),
+            param: Some(param.to_string()),
+            code: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpirationAnchor {
+    CreatedAt,
+}
+
+/// Deletes the file `seconds` after `anchor`, between one hour and 30 days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiresAfter {
+    pub anchor: ExpirationAnchor,
+    pub seconds: u64,
+}
+
+impl ExpiresAfter {
+    pub fn seconds(seconds: u64) -> Self {
+        ExpiresAfter {
+            anchor: ExpirationAnchor::CreatedAt,
+            seconds,
+        }
+    }
+
+    pub fn days(days: u64) -> Self {
+        Self::seconds(days * 24 * 3600)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileObject {
+    pub id: String,
+    pub object: String,
+    pub bytes: u64,
+    pub created_at: u64,
+    /// When the file will be deleted, if it was uploaded with an expiration.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    pub filename: String,
+    /// Uploaded files carry a [`FilePurpose`]; files created by the API use output purposes
+    /// such as `batch_output`.
+    pub purpose: String,
+}
+
+/// A page of files, newest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileList {
+    pub object: String,
+    pub data: Vec<FileObject>,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletedFile {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct UploadFileRequest {
+    #[builder(into)]
+    pub filename: String,
+    pub bytes: Vec<u8>,
+    pub purpose: FilePurpose,
+    pub expires_after: Option<ExpiresAfter>,
+    pub openai: OpenAi,
+}
+
+impl<S: upload_file_request_builder::IsComplete> UploadFileRequestBuilder<S> {
+    /// Builds the request, checking the file type and expiration.
+    pub fn build(self) -> Result<UploadFileRequest, FileRequestError> {
+        let request = self.build_unchecked();
+        request.purpose.validate_filename(&request.filename)?;
+        if let Some(ExpiresAfter { seconds, .. }) = request.expires_after {
+            if !(MIN_EXPIRATION_SECS..=MAX_EXPIRATION_SECS).contains(&seconds) {
+                return Err(FileRequestError::InvalidExpiration(seconds));
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Reference to a file produced by code interpreter, found in run or response output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileRef {
+    pub file_id: String,
+    /// Set for files that live in a Responses API container rather than in `/v1/files`.
+    pub container_id: Option<String>,
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedFile {
+    pub file: FileRef,
+    pub bytes: Vec<u8>,
+}
+
+impl UploadFileRequest {
+    pub async fn send(&self) -> Result<FileObject, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let mut form = reqwest::multipart::Form::new()
+            .text("purpose", self.purpose.as_str())
+            .part(
+                "file",
+                reqwest::multipart::Part::bytes(self.bytes.clone())
+                    .file_name(self.filename.clone()),
+            );
+        if let Some(expires_after) = self.expires_after {
+            form = form
+                .text("expires_after[anchor]", "created_at")
+                .text("expires_after[seconds]", expires_after.seconds.to_string());
+        }
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .multipart(form)
+            .send_observed(&self.openai)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+}
+
+impl OpenAi {
+    pub fn file_upload(&self) -> UploadFileRequestBuilder<upload_file_request_builder::SetOpenai> {
+        UploadFileRequest::builder().openai(self.clone())
+    }
+
+    /// Uploads `bytes` as `filename` for the given `purpose`, without an expiration.
+    pub async fn upload_file(
+        &self,
+        filename: impl Into<String>,
+        bytes: Vec<u8>,
+        purpose: FilePurpose,
+    ) -> Result<FileObject, ApiRequestError> {
+        self.file_upload()
+            .filename(filename)
+            .bytes(bytes)
+            .purpose(purpose)
+            .build()?
+            .send()
+            .await
+    }
+
+    /// Files of the organization, newest first, optionally only those uploaded for `purpose`.
+    /// Pass the last id of a page as `after` to get the next one.
+    pub async fn list_files(
+        &self,
+        purpose: Option<FilePurpose>,
+        after: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<FileList, ApiRequestError> {
+        let url = format!("{}/{}", self.inner.base_url, API_URL);
+        let mut query = Vec::new();
+        if let Some(purpose) = purpose {
+            query.push(("purpose", purpose.as_str().to_string()));
+        }
+        if let Some(after) = after {
+            query.push(("after", after.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .query(&query)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+
+    pub async fn retrieve_file(&self, file_id: &str) -> Result<FileObject, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, file_id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+
+    pub async fn delete_file(&self, file_id: &str) -> Result<DeletedFile, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, file_id);
+        let res = self
+            .inner
+            .client
+            .delete(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+
+    /// Raw contents of an uploaded or generated file.
+    pub async fn file_content(&self, file_id: &str) -> Result<Vec<u8>, ApiRequestError> {
+        let url = format!("{}/{}/{}/content", self.inner.base_url, API_URL, file_id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.bytes().await?.to_vec())
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+
+    /// Raw contents of a file created in a code interpreter container.
+    pub async fn container_file_content(
+        &self,
+        container_id: &str,
+        file_id: &str,
+    ) -> Result<Vec<u8>, ApiRequestError> {
+        let url = format!(
+            "{}/{}/{}/files/{}/content",
+            self.inner.base_url, CONTAINERS_URL, container_id, file_id
+        );
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.bytes().await?.to_vec())
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+
+    /// Downloads every referenced file concurrently, skipping duplicate ids.
+    pub async fn download_files(
+        &self,
+        files: impl IntoIterator<Item = FileRef>,
+    ) -> Result<Vec<GeneratedFile>, ApiRequestError> {
+        let mut seen = HashSet::new();
+        let downloads = files
+            .into_iter()
+            .filter(|file| seen.insert(file.file_id.clone()))
+            .map(|file| async move {
+                let bytes = match &file.container_id {
+                    Some(container_id) => {
+                        self.container_file_content(container_id, &file.file_id)
+                            .await?
+                    }
+                    None => self.file_content(&file.file_id).await?,
+                };
+                Ok::<_, ApiRequestError>(GeneratedFile { file, bytes })
+            });
+        try_join_all(downloads).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upload_validation() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let upload = |filename: &str, purpose, expires_after| {
+            openai
+                .file_upload()
+                .filename(filename)
+                .bytes(Vec::new())
+                .purpose(purpose)
+                .maybe_expires_after(expires_after)
+                .build()
+        };
+        assert!(upload("train.jsonl", FilePurpose::FineTune, None).is_ok());
+        assert!(upload(
+            "chart.PNG",
+            FilePurpose::Vision,
+            Some(ExpiresAfter::days(7))
+        )
+        .is_ok());
+        assert!(upload("notes.pdf", FilePurpose::UserData, None).is_ok());
+        assert!(matches!(
+            upload("train.csv", FilePurpose::Batch, None),
+            Err(FileRequestError::UnsupportedFileType { .. })
+        ));
+        assert!(matches!(
+            upload(
+                "a.jsonl",
+                FilePurpose::Batch,
+                Some(ExpiresAfter::seconds(60))
+            ),
+            Err(FileRequestError::InvalidExpiration(60))
+        ));
+        assert_eq!(
+            serde_json::to_value(FilePurpose::FineTune).unwrap(),
+            serde_json::json!("fine-tune")
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_list_retrieve_and_delete_files() {
+        use serde_json::json;
+
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let file = |id: &str| {
+            json!({
+                "id": id,
+                "object": "file",
+                "bytes": 120,
+                "created_at": 1,
+                "filename": "train.jsonl",
+                "purpose": "fine-tune"
+            })
+        };
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/files",
+            MockResponse::json(&json!({
+                "object": "list",
+                "data": [file("file-2"), file("file-1")],
+                "first_id": "file-2",
+                "last_id": "file-1",
+                "has_more": false
+            })),
+        )
+        .push("v1/files/file-1", MockResponse::json(&file("file-1")))
+        .push(
+            "v1/files/file-1",
+            MockResponse::json(&json!({"id": "file-1", "object": "file", "deleted": true})),
+        )
+        .push(
+            "v1/files/file-9",
+            MockResponse::error(404, "not_found", "No such File object: file-9"),
+        );
+        let openai = mock.client();
+
+        let page = openai
+            .list_files(Some(FilePurpose::FineTune), Some("file-3"), Some(2))
+            .await
+            .unwrap();
+        assert_eq!(
+            page.data.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(),
+            ["file-2", "file-1"]
+        );
+        assert!(!page.has_more);
+
+        let retrieved = openai.retrieve_file("file-1").await.unwrap();
+        assert_eq!(retrieved.filename, "train.jsonl");
+        assert_eq!(retrieved.expires_at, None);
+
+        let deleted = openai.delete_file("file-1").await.unwrap();
+        assert!(deleted.deleted);
+        assert!(matches!(
+            openai.retrieve_file("file-9").await,
+            Err(ApiRequestError::Rejected(error)) if error.code.as_deref() == Some("not_found")
+        ));
+
+        let requests = mock.requests();
+        assert_eq!(
+            requests[0].path,
+            "/v1/files?purpose=fine-tune&after=file-3&limit=2"
+        );
+        assert_eq!(
+            requests
+                .iter()
+                .map(|r| r.method.as_str())
+                .collect::<Vec<_>>(),
+            ["GET", "GET", "DELETE", "GET"]
+        );
+    }
+}
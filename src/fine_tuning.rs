@@ -0,0 +1,585 @@
+//! Fine-tuning jobs (`/v1/fine_tuning/jobs`).
+//!
+//! The training method is chosen with [`Method`]: supervised fine-tuning, direct preference
+//! optimization or reinforcement fine-tuning, each with its own hyperparameters. Hyperparameters
+//! left unset are chosen automatically by the API.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), Box<dyn std::error::Error>> {
+//! use openai_ox::fine_tuning::{DpoHyperparameters, Method};
+//!
+//! let job = openai
+//!     .create_fine_tuning_job()
+//!     .model("gpt-4.1-mini-2025-04-14")
+//!     .training_file("file-abc123")
+//!     .method(Method::dpo(DpoHyperparameters::builder().beta(0.1).build()))
+//!     .build()?
+//!     .send()
+//!     .await?;
+//! println!("{} is {:?}", job.id, job.status);
+//! # Ok(())
+//! # }
+//! ```
+
+use bon::Builder;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    auth::Authorize, responses::ReasoningEffort, retry::SendRetrying, ApiRequestError, OpenAi,
+};
+
+const API_URL: &str = "v1/fine_tuning/jobs";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FineTuningRequestError {
+    #[error("`hyperparameters` is deprecated and cannot be combined with `method`")]
+    HyperparametersWithMethod,
+    #[error("Invalid value for hyperparameter `{0}`")]
+    InvalidHyperparameter(&'static str),
+}
+
+/// A hyperparameter the API picks itself, or a fixed value. Jobs report the hyperparameters
+/// left unset as `"auto"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hyperparameter<T> {
+    Auto,
+    Value(T),
+}
+
+impl<T> Hyperparameter<T> {
+    /// The fixed value, `None` when the API picks it.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Hyperparameter::Auto => None,
+            Hyperparameter::Value(value) => Some(value),
+        }
+    }
+}
+
+impl<T> From<T> for Hyperparameter<T> {
+    fn from(value: T) -> Self {
+        Hyperparameter::Value(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum HyperparameterRepr<T> {
+    Value(T),
+    Mode(String),
+}
+
+impl<T: Serialize + Copy> Serialize for Hyperparameter<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Hyperparameter::Auto => HyperparameterRepr::<T>::Mode("auto".to_string()),
+            Hyperparameter::Value(value) => HyperparameterRepr::Value(value),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Hyperparameter<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match HyperparameterRepr::deserialize(deserializer)? {
+            HyperparameterRepr::Value(value) => Ok(Hyperparameter::Value(value)),
+            HyperparameterRepr::Mode(mode) if mode == "auto" => Ok(Hyperparameter::Auto),
+            HyperparameterRepr::Mode(mode) => Err(de::Error::unknown_variant(&mode, &["auto"])),
+        }
+    }
+}
+
+/// Hyperparameters shared by every method.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct Hyperparameters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub batch_size: Option<Hyperparameter<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub learning_rate_multiplier: Option<Hyperparameter<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub n_epochs: Option<Hyperparameter<u32>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct DpoHyperparameters {
+    /// Weight of the penalty keeping the model close to the reference; higher is more
+    /// conservative.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub beta: Option<Hyperparameter<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub batch_size: Option<Hyperparameter<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub learning_rate_multiplier: Option<Hyperparameter<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub n_epochs: Option<Hyperparameter<u32>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct ReinforcementHyperparameters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub batch_size: Option<Hyperparameter<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub learning_rate_multiplier: Option<Hyperparameter<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub n_epochs: Option<Hyperparameter<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub compute_multiplier: Option<Hyperparameter<f64>>,
+    /// Training steps between evaluation runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub eval_interval: Option<Hyperparameter<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub eval_samples: Option<Hyperparameter<u32>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SupervisedMethod {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hyperparameters: Option<Hyperparameters>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DpoMethod {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hyperparameters: Option<DpoHyperparameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReinforcementMethod {
+    /// Grader scoring the model's samples, e.g. a `string_check` or `score_model` grader.
+    pub grader: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hyperparameters: Option<ReinforcementHyperparameters>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Method {
+    Supervised { supervised: SupervisedMethod },
+    Dpo { dpo: DpoMethod },
+    Reinforcement { reinforcement: ReinforcementMethod },
+}
+
+impl Method {
+    pub fn supervised(hyperparameters: Hyperparameters) -> Self {
+        Method::Supervised {
+            supervised: SupervisedMethod {
+                hyperparameters: Some(hyperparameters),
+            },
+        }
+    }
+
+    pub fn dpo(hyperparameters: DpoHyperparameters) -> Self {
+        Method::Dpo {
+            dpo: DpoMethod {
+                hyperparameters: Some(hyperparameters),
+            },
+        }
+    }
+
+    pub fn reinforcement(grader: Value, hyperparameters: ReinforcementHyperparameters) -> Self {
+        Method::Reinforcement {
+            reinforcement: ReinforcementMethod {
+                grader,
+                hyperparameters: Some(hyperparameters),
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<(), FineTuningRequestError> {
+        match self {
+            Method::Supervised { supervised } => supervised
+                .hyperparameters
+                .as_ref()
+                .map_or(Ok(()), Hyperparameters::validate),
+            Method::Dpo { dpo } => match &dpo.hyperparameters {
+                Some(h) => {
+                    if value(h.beta).is_some_and(|beta| !(beta > 0.0 && beta <= 2.0)) {
+                        return Err(FineTuningRequestError::InvalidHyperparameter("beta"));
+                    }
+                    validate_common(h.batch_size, h.learning_rate_multiplier, h.n_epochs)
+                }
+                None => Ok(()),
+            },
+            Method::Reinforcement { reinforcement } => match &reinforcement.hyperparameters {
+                Some(h) => {
+                    if value(h.compute_multiplier).is_some_and(|m| m <= 0.0) {
+                        return Err(FineTuningRequestError::InvalidHyperparameter(
+                            "compute_multiplier",
+                        ));
+                    }
+                    validate_common(h.batch_size, h.learning_rate_multiplier, h.n_epochs)
+                }
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+fn value<T>(hyperparameter: Option<Hyperparameter<T>>) -> Option<T> {
+    hyperparameter.and_then(Hyperparameter::value)
+}
+
+fn validate_common(
+    batch_size: Option<Hyperparameter<u32>>,
+    learning_rate_multiplier: Option<Hyperparameter<f64>>,
+    n_epochs: Option<Hyperparameter<u32>>,
+) -> Result<(), FineTuningRequestError> {
+    let (batch_size, learning_rate_multiplier, n_epochs) = (
+        value(batch_size),
+        value(learning_rate_multiplier),
+        value(n_epochs),
+    );
+    if batch_size == Some(0) {
+        return Err(FineTuningRequestError::InvalidHyperparameter("batch_size"));
+    }
+    if learning_rate_multiplier.is_some_and(|m| m <= 0.0) {
+        return Err(FineTuningRequestError::InvalidHyperparameter(
+            "learning_rate_multiplier",
+        ));
+    }
+    if n_epochs.is_some_and(|n| !(1..=50).contains(&n)) {
+        return Err(FineTuningRequestError::InvalidHyperparameter("n_epochs"));
+    }
+    Ok(())
+}
+
+impl Hyperparameters {
+    fn validate(&self) -> Result<(), FineTuningRequestError> {
+        validate_common(
+            self.batch_size,
+            self.learning_rate_multiplier,
+            self.n_epochs,
+        )
+    }
+}
+
+/// Weights & Biases run the job reports its metrics to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+pub struct WandbIntegration {
+    #[builder(into)]
+    pub project: String,
+    /// Display name of the run, the job id by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub name: Option<String>,
+    /// Team or user owning the project, the API key's default entity when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub entity: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Integration {
+    Wandb { wandb: WandbIntegration },
+}
+
+impl From<WandbIntegration> for Integration {
+    fn from(wandb: WandbIntegration) -> Self {
+        Integration::Wandb { wandb }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct CreateFineTuningJobRequest {
+    #[builder(into)]
+    pub model: String,
+    /// Id of an uploaded JSONL file with purpose `fine-tune`.
+    #[builder(into)]
+    pub training_file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub validation_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<Method>,
+    /// Deprecated in favour of `method`; applies to supervised fine-tuning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyperparameters: Option<Hyperparameters>,
+    /// Up to 64 characters added to the fine-tuned model name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Experiment trackers that receive the training metrics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrations: Option<Vec<Integration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+impl<S: create_fine_tuning_job_request_builder::IsComplete> CreateFineTuningJobRequestBuilder<S> {
+    pub fn build(self) -> Result<CreateFineTuningJobRequest, FineTuningRequestError> {
+        let request = self.build_unchecked();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FineTuningJobStatus {
+    ValidatingFiles,
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl FineTuningJobStatus {
+    /// `true` once the job will not change anymore.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            FineTuningJobStatus::Succeeded
+                | FineTuningJobStatus::Failed
+                | FineTuningJobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FineTuningJob {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub model: String,
+    pub status: FineTuningJobStatus,
+    pub training_file: String,
+    #[serde(default)]
+    pub validation_file: Option<String>,
+    /// Name of the resulting model, once the job has succeeded.
+    #[serde(default)]
+    pub fine_tuned_model: Option<String>,
+    #[serde(default)]
+    pub method: Option<Method>,
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    #[serde(default)]
+    pub trained_tokens: Option<u64>,
+    #[serde(default)]
+    pub result_files: Vec<String>,
+    #[serde(default)]
+    pub error: Option<Value>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub integrations: Option<Vec<Integration>>,
+}
+
+impl CreateFineTuningJobRequest {
+    /// Checks that hyperparameters are in range and that the deprecated top-level
+    /// `hyperparameters` is not combined with `method`.
+    pub fn validate(&self) -> Result<(), FineTuningRequestError> {
+        if let Some(hyperparameters) = &self.hyperparameters {
+            if self.method.is_some() {
+                return Err(FineTuningRequestError::HyperparametersWithMethod);
+            }
+            hyperparameters.validate()?;
+        }
+        self.method.as_ref().map_or(Ok(()), Method::validate)
+    }
+
+    pub async fn send(&self) -> Result<FineTuningJob, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .json(self)
+            .send_observed(&self.openai)
+            .await?;
+        parse_job(res).await
+    }
+}
+
+async fn parse_job(res: reqwest::Response) -> Result<FineTuningJob, ApiRequestError> {
+    if res.status().is_success() {
+        Ok(res.json().await?)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
+
+impl OpenAi {
+    pub fn create_fine_tuning_job(
+        &self,
+    ) -> CreateFineTuningJobRequestBuilder<create_fine_tuning_job_request_builder::SetOpenai> {
+        CreateFineTuningJobRequest::builder().openai(self.clone())
+    }
+
+    pub async fn retrieve_fine_tuning_job(
+        &self,
+        job_id: &str,
+    ) -> Result<FineTuningJob, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, job_id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_job(res).await
+    }
+
+    pub async fn cancel_fine_tuning_job(
+        &self,
+        job_id: &str,
+    ) -> Result<FineTuningJob, ApiRequestError> {
+        let url = format!("{}/{}/{}/cancel", self.inner.base_url, API_URL, job_id);
+        let res = self
+            .inner
+            .client
+            .post(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_job(res).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_method_serialization_and_validation() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .create_fine_tuning_job()
+            .model("gpt-4.1-mini")
+            .training_file("file-1")
+            .method(Method::dpo(
+                DpoHyperparameters::builder().beta(0.1).n_epochs(2).build(),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "model": "gpt-4.1-mini",
+                "training_file": "file-1",
+                "method": {
+                    "type": "dpo",
+                    "dpo": {"hyperparameters": {"beta": 0.1, "n_epochs": 2}}
+                }
+            })
+        );
+
+        let err = openai
+            .create_fine_tuning_job()
+            .model("gpt-4.1-mini")
+            .training_file("file-1")
+            .method(Method::supervised(Hyperparameters::default()))
+            .hyperparameters(Hyperparameters::builder().n_epochs(3).build())
+            .build()
+            .unwrap_err();
+        assert_eq!(err, FineTuningRequestError::HyperparametersWithMethod);
+
+        let err = openai
+            .create_fine_tuning_job()
+            .model("o4-mini")
+            .training_file("file-1")
+            .method(Method::reinforcement(
+                json!({"type": "string_check", "name": "exact", "operation": "eq"}),
+                ReinforcementHyperparameters::builder()
+                    .reasoning_effort(ReasoningEffort::Medium)
+                    .learning_rate_multiplier(0.0)
+                    .build(),
+            ))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            FineTuningRequestError::InvalidHyperparameter("learning_rate_multiplier")
+        );
+    }
+
+    #[test]
+    fn test_auto_hyperparameters() {
+        let job: FineTuningJob = serde_json::from_value(json!({
+            "id": "ftjob-1",
+            "object": "fine_tuning.job",
+            "created_at": 1,
+            "model": "gpt-4.1-mini",
+            "status": "queued",
+            "training_file": "file-1",
+            "method": {
+                "type": "dpo",
+                "dpo": {"hyperparameters": {"beta": "auto", "batch_size": "auto", "learning_rate_multiplier": 0.5, "n_epochs": "auto"}}
+            }
+        }))
+        .unwrap();
+        let Some(Method::Dpo { dpo }) = &job.method else {
+            panic!("expected a DPO method, got {:?}", job.method);
+        };
+        let hyperparameters = dpo.hyperparameters.as_ref().unwrap();
+        assert_eq!(hyperparameters.beta, Some(Hyperparameter::Auto));
+        assert_eq!(hyperparameters.n_epochs, Some(Hyperparameter::Auto));
+        assert_eq!(
+            hyperparameters.learning_rate_multiplier,
+            Some(Hyperparameter::Value(0.5))
+        );
+        assert_eq!(
+            serde_json::to_value(hyperparameters).unwrap(),
+            json!({"beta": "auto", "batch_size": "auto", "learning_rate_multiplier": 0.5, "n_epochs": "auto"})
+        );
+        assert!(serde_json::from_value::<Hyperparameters>(json!({"n_epochs": "many"})).is_err());
+    }
+
+    #[test]
+    fn test_wandb_integration() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .create_fine_tuning_job()
+            .model("gpt-4.1-mini")
+            .training_file("file-1")
+            .integrations(vec![WandbIntegration::builder()
+                .project("support-bot")
+                .entity("acme")
+                .tags(vec!["v2".to_string()])
+                .build()
+                .into()])
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["integrations"],
+            json!([{
+                "type": "wandb",
+                "wandb": {"project": "support-bot", "entity": "acme", "tags": ["v2"]}
+            }])
+        );
+    }
+}
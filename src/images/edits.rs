@@ -0,0 +1,170 @@
+//! Image edits (`/v1/images/edits`): change existing images according to a prompt.
+//!
+//! `dall-e-2` edits a single square PNG, optionally limited to the transparent area of `mask`.
+//! `gpt-image-1` accepts several reference images and combines them.
+
+use bon::Builder;
+use reqwest::multipart;
+
+use super::{
+    form_value, send_form, Background, ImageFile, ImageQuality, ImageRequestError,
+    ImageResponseFormat, ImageSize, ImagesResponse, OutputFormat, DALL_E_2, GPT_IMAGE_1,
+};
+use crate::{ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/images/edits";
+
+#[derive(Debug, Clone, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct ImageEditRequest {
+    pub image: Vec<ImageFile>,
+    #[builder(into)]
+    pub prompt: String,
+    /// PNG whose fully transparent pixels mark where `image` is edited.
+    pub mask: Option<ImageFile>,
+    #[builder(into)]
+    pub model: Option<String>,
+    pub n: Option<u32>,
+    pub size: Option<ImageSize>,
+    pub quality: Option<ImageQuality>,
+    pub background: Option<Background>,
+    pub output_format: Option<OutputFormat>,
+    pub response_format: Option<ImageResponseFormat>,
+    #[builder(into)]
+    pub user: Option<String>,
+    pub openai: OpenAi,
+}
+
+impl<S: image_edit_request_builder::IsComplete> ImageEditRequestBuilder<S> {
+    pub fn build(self) -> Result<ImageEditRequest, ImageRequestError> {
+        let request = self.build_unchecked();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+impl ImageEditRequest {
+    /// Checks the image count and the parameters only `gpt-image-1` supports.
+    pub fn validate(&self) -> Result<(), ImageRequestError> {
+        let model = self.model.as_deref().unwrap_or(DALL_E_2);
+        if self.image.is_empty() {
+            return Err(ImageRequestError::MissingImage);
+        }
+        if model == GPT_IMAGE_1 {
+            if self.response_format.is_some() {
+                return Err(ImageRequestError::UnsupportedParameter {
+                    model: model.to_string(),
+                    parameter: "response_format",
+                });
+            }
+            return Ok(());
+        }
+        if self.image.len() > 1 {
+            return Err(ImageRequestError::TooManyImages {
+                model: model.to_string(),
+                count: self.image.len(),
+            });
+        }
+        for (parameter, set) in [
+            ("quality", self.quality.is_some()),
+            ("background", self.background.is_some()),
+            ("output_format", self.output_format.is_some()),
+        ] {
+            if set {
+                return Err(ImageRequestError::UnsupportedParameter {
+                    model: model.to_string(),
+                    parameter,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn form(&self) -> Result<multipart::Form, ApiRequestError> {
+        let mut form = multipart::Form::new().text("prompt", self.prompt.clone());
+        let field = if self.image.len() > 1 {
+            "image[]"
+        } else {
+            "image"
+        };
+        for image in &self.image {
+            form = form.part(field, image.part()?);
+        }
+        if let Some(mask) = &self.mask {
+            form = form.part("mask", mask.part()?);
+        }
+        if let Some(model) = &self.model {
+            form = form.text("model", model.clone());
+        }
+        if let Some(n) = self.n {
+            form = form.text("n", n.to_string());
+        }
+        let options = [
+            ("size", self.size.map(form_value)),
+            ("quality", self.quality.map(form_value)),
+            ("background", self.background.map(form_value)),
+            ("output_format", self.output_format.map(form_value)),
+            ("response_format", self.response_format.map(form_value)),
+            ("user", self.user.clone()),
+        ];
+        for (name, value) in options {
+            if let Some(value) = value {
+                form = form.text(name, value);
+            }
+        }
+        Ok(form)
+    }
+
+    pub async fn send(&self) -> Result<ImagesResponse, ApiRequestError> {
+        send_form(&self.openai, API_URL, self.form()?).await
+    }
+}
+
+impl OpenAi {
+    pub fn image_edit(&self) -> ImageEditRequestBuilder<image_edit_request_builder::SetOpenai> {
+        ImageEditRequest::builder().openai(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_validation() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let images = vec![
+            ImageFile::new("room.png", Vec::new()),
+            ImageFile::new("lamp.jpg", Vec::new()),
+        ];
+        assert!(openai
+            .image_edit()
+            .model("gpt-image-1")
+            .image(images.clone())
+            .prompt("Put the lamp in the room")
+            .build()
+            .is_ok());
+        assert_eq!(
+            openai
+                .image_edit()
+                .image(images)
+                .prompt("Put the lamp in the room")
+                .build()
+                .unwrap_err(),
+            ImageRequestError::TooManyImages {
+                model: "dall-e-2".into(),
+                count: 2
+            }
+        );
+        assert_eq!(
+            openai
+                .image_edit()
+                .image(Vec::new())
+                .prompt("Anything")
+                .build()
+                .unwrap_err(),
+            ImageRequestError::MissingImage
+        );
+        assert_eq!(ImageFile::new("lamp.JPG", Vec::new()).mime(), "image/jpeg");
+    }
+}
@@ -0,0 +1,575 @@
+//! Images API: generations (`/v1/images/generations`), [`edits`] and [`variations`].
+//!
+//! With `gpt-image-1`, [`ImageGenerationRequest::stream`] yields up to `partial_images`
+//! progressively refined previews before the final image, so UIs can render something while
+//! generation is still running.
+
+pub mod edits;
+pub mod variations;
+
+use std::path::Path;
+
+use bon::Builder;
+use futures::{stream::BoxStream, StreamExt};
+use reqwest::multipart;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    auth::Authorize,
+    retry::SendRetrying,
+    sse::{error_stream, idle_timeout, sse_events, StreamingBody},
+    ApiRequestError, OpenAi,
+};
+
+const API_URL: &str = "v1/images/generations";
+const DALL_E_2: &str = "dall-e-2";
+const DALL_E_3: &str = "dall-e-3";
+const GPT_IMAGE_1: &str = "gpt-image-1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageSize {
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "256x256")]
+    S256x256,
+    #[serde(rename = "512x512")]
+    S512x512,
+    #[serde(rename = "1024x1024")]
+    S1024x1024,
+    #[serde(rename = "1792x1024")]
+    S1792x1024,
+    #[serde(rename = "1024x1792")]
+    S1024x1792,
+    #[serde(rename = "1536x1024")]
+    S1536x1024,
+    #[serde(rename = "1024x1536")]
+    S1024x1536,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageQuality {
+    Auto,
+    Standard,
+    Hd,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageStyle {
+    Vivid,
+    Natural,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+    Auto,
+    Transparent,
+    Opaque,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    Url,
+    B64Json,
+}
+
+/// An image uploaded for an edit or variation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageFile {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+impl ImageFile {
+    pub fn new(filename: impl Into<String>, bytes: Vec<u8>) -> Self {
+        Self {
+            filename: filename.into(),
+            bytes,
+        }
+    }
+
+    /// MIME type guessed from the file extension, PNG if unknown.
+    pub fn mime(&self) -> &'static str {
+        let extension = Path::new(&self.filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            Some("gif") => "image/gif",
+            _ => "image/png",
+        }
+    }
+
+    fn part(&self) -> Result<multipart::Part, ApiRequestError> {
+        Ok(multipart::Part::bytes(self.bytes.clone())
+            .file_name(self.filename.clone())
+            .mime_str(self.mime())?)
+    }
+}
+
+/// Sends a multipart image request and parses the response.
+async fn send_form(
+    openai: &OpenAi,
+    path: &str,
+    form: multipart::Form,
+) -> Result<ImagesResponse, ApiRequestError> {
+    let url = format!("{}/{}", openai.inner.base_url, path);
+    let res = openai
+        .inner
+        .client
+        .post(url)
+        .authorize(openai)
+        .await?
+        .multipart(form)
+        .send_observed(openai)
+        .await?;
+    if res.status().is_success() {
+        Ok(res.json().await?)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
+
+/// Value of an enum parameter as sent in a multipart form.
+fn form_value(value: impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(value)) => value,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ImageRequestError {
+    #[error("Size {size:?} is not supported by {model}")]
+    UnsupportedSize { model: String, size: ImageSize },
+    #[error("Quality {quality:?} is not supported by {model}")]
+    UnsupportedQuality {
+        model: String,
+        quality: ImageQuality,
+    },
+    #[error("Parameter `{parameter}` is not supported by {model}")]
+    UnsupportedParameter {
+        model: String,
+        parameter: &'static str,
+    },
+    #[error("{model} can generate between 1 and {max} images, got {n}")]
+    InvalidCount { model: String, n: u32, max: u32 },
+    #[error("partial_images must be between 0 and 3, got {0}")]
+    InvalidPartialImages(u32),
+    #[error("Transparent backgrounds require png or webp output")]
+    TransparentJpeg,
+    #[error("At least one image is required")]
+    MissingImage,
+    #[error("{model} accepts a single input image, got {count}")]
+    TooManyImages { model: String, count: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct ImageGenerationRequest {
+    #[builder(into)]
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<ImageSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<ImageQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<ImageStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Background>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ImageResponseFormat>,
+    /// Number of previews (0-3) sent before the final image when streaming.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_images: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub user: Option<String>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageData {
+    #[serde(default)]
+    pub b64_json: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub revised_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImagesResponse {
+    pub created: u64,
+    #[serde(default)]
+    pub data: Vec<ImageData>,
+    #[serde(default)]
+    pub usage: Option<Value>,
+}
+
+/// Event of a streamed image generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ImageStreamEvent {
+    #[serde(rename = "image_generation.partial_image")]
+    PartialImage {
+        b64_json: String,
+        partial_image_index: u32,
+        #[serde(default)]
+        created_at: Option<u64>,
+        #[serde(default)]
+        size: Option<String>,
+        #[serde(default)]
+        quality: Option<String>,
+        #[serde(default)]
+        background: Option<String>,
+        #[serde(default)]
+        output_format: Option<String>,
+    },
+    #[serde(rename = "image_generation.completed")]
+    Completed {
+        b64_json: String,
+        #[serde(default)]
+        created_at: Option<u64>,
+        #[serde(default)]
+        size: Option<String>,
+        #[serde(default)]
+        quality: Option<String>,
+        #[serde(default)]
+        background: Option<String>,
+        #[serde(default)]
+        output_format: Option<String>,
+        #[serde(default)]
+        usage: Option<Value>,
+    },
+}
+
+impl ImageStreamEvent {
+    /// Base64 encoded image carried by the event.
+    pub fn b64_json(&self) -> &str {
+        match self {
+            ImageStreamEvent::PartialImage { b64_json, .. } => b64_json,
+            ImageStreamEvent::Completed { b64_json, .. } => b64_json,
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(self, ImageStreamEvent::Completed { .. })
+    }
+}
+
+impl<S: image_generation_request_builder::IsComplete> ImageGenerationRequestBuilder<S> {
+    /// Builds the request, checking parameters against what the chosen model supports.
+    ///
+    /// Requests for models other than `dall-e-2`, `dall-e-3` and `gpt-image-1` are not checked.
+    pub fn build(self) -> Result<ImageGenerationRequest, ImageRequestError> {
+        let request = self.build_unchecked();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+impl ImageGenerationRequest {
+    /// Checks the parameters against the rules of the selected model, `dall-e-2` if unset.
+    pub fn validate(&self) -> Result<(), ImageRequestError> {
+        let model = self.model.as_deref().unwrap_or(DALL_E_2);
+        let (sizes, qualities, max_n): (&[ImageSize], &[ImageQuality], u32) = match model {
+            DALL_E_2 => (
+                &[
+                    ImageSize::S256x256,
+                    ImageSize::S512x512,
+                    ImageSize::S1024x1024,
+                ],
+                &[ImageQuality::Standard],
+                10,
+            ),
+            DALL_E_3 => (
+                &[
+                    ImageSize::S1024x1024,
+                    ImageSize::S1792x1024,
+                    ImageSize::S1024x1792,
+                ],
+                &[ImageQuality::Standard, ImageQuality::Hd],
+                1,
+            ),
+            GPT_IMAGE_1 => (
+                &[
+                    ImageSize::Auto,
+                    ImageSize::S1024x1024,
+                    ImageSize::S1536x1024,
+                    ImageSize::S1024x1536,
+                ],
+                &[
+                    ImageQuality::Auto,
+                    ImageQuality::Low,
+                    ImageQuality::Medium,
+                    ImageQuality::High,
+                ],
+                10,
+            ),
+            _ => return Ok(()),
+        };
+        let unsupported = |parameter| ImageRequestError::UnsupportedParameter {
+            model: model.to_string(),
+            parameter,
+        };
+
+        if let Some(size) = self.size.filter(|size| !sizes.contains(size)) {
+            return Err(ImageRequestError::UnsupportedSize {
+                model: model.to_string(),
+                size,
+            });
+        }
+        if let Some(quality) = self.quality.filter(|quality| !qualities.contains(quality)) {
+            return Err(ImageRequestError::UnsupportedQuality {
+                model: model.to_string(),
+                quality,
+            });
+        }
+        if let Some(n) = self.n.filter(|n| !(1..=max_n).contains(n)) {
+            return Err(ImageRequestError::InvalidCount {
+                model: model.to_string(),
+                n,
+                max: max_n,
+            });
+        }
+        if self.style.is_some() && model != DALL_E_3 {
+            return Err(unsupported("style"));
+        }
+        if model == GPT_IMAGE_1 {
+            if self.response_format.is_some() {
+                return Err(unsupported("response_format"));
+            }
+            if let Some(partial_images) = self.partial_images.filter(|n| *n > 3) {
+                return Err(ImageRequestError::InvalidPartialImages(partial_images));
+            }
+            if self.background == Some(Background::Transparent)
+                && self.output_format == Some(OutputFormat::Jpeg)
+            {
+                return Err(ImageRequestError::TransparentJpeg);
+            }
+        } else {
+            if self.background.is_some() {
+                return Err(unsupported("background"));
+            }
+            if self.output_format.is_some() {
+                return Err(unsupported("output_format"));
+            }
+            if self.partial_images.is_some() {
+                return Err(unsupported("partial_images"));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn send(&self) -> Result<ImagesResponse, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .json(self)
+            .send_observed(&self.openai)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+
+    /// Streams partial images followed by the final one.
+    pub async fn stream(&self) -> BoxStream<'static, Result<ImageStreamEvent, ApiRequestError>> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = match self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await
+        {
+            Ok(request) => {
+                request
+                    .json(&StreamingBody::new(self))
+                    .send_streaming(&self.openai)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        match res {
+            Ok(res) if res.status().is_success() => sse_events(idle_timeout(
+                res.bytes_stream(),
+                self.openai.inner.timeouts.stream_idle,
+            ))
+            .filter(|event| {
+                let keep = !matches!(event, Ok(event) if event.data == "[DONE]");
+                async move { keep }
+            })
+            .map(|event| -> Result<ImageStreamEvent, ApiRequestError> {
+                Ok(crate::json::from_str(&event?.data)?)
+            })
+            .boxed(),
+            res => error_stream(res).await,
+        }
+    }
+}
+
+impl OpenAi {
+    pub fn image_generation(
+        &self,
+    ) -> ImageGenerationRequestBuilder<image_generation_request_builder::SetOpenai> {
+        ImageGenerationRequest::builder().openai(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::sse::SseParser;
+
+    use super::*;
+
+    #[test]
+    fn test_partial_image_events() {
+        let mut parser = SseParser::new();
+        let events = parser.push(
+            concat!(
+                "event: image_generation.partial_image\n",
+                "data: {\"type\":\"image_generation.partial_image\",\"b64_json\":\"AAA\",\"partial_image_index\":0,\"size\":\"1024x1024\"}\n\n",
+                "event: image_generation.completed\n",
+                "data: {\"type\":\"image_generation.completed\",\"b64_json\":\"BBB\",\"usage\":{\"total_tokens\":10}}\n\n",
+            )
+            .as_bytes(),
+        );
+        let parsed: Vec<ImageStreamEvent> = events
+            .iter()
+            .map(|event| serde_json::from_str(&event.data).unwrap())
+            .collect();
+        assert_eq!(parsed[0].b64_json(), "AAA");
+        assert!(!parsed[0].is_final());
+        assert!(matches!(
+            &parsed[1],
+            ImageStreamEvent::Completed { usage: Some(usage), .. } if usage == &json!({"total_tokens": 10})
+        ));
+
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .image_generation()
+            .model("gpt-image-1")
+            .prompt("A lighthouse at dusk")
+            .partial_images(2)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"prompt": "A lighthouse at dusk", "model": "gpt-image-1", "partial_images": 2})
+        );
+    }
+
+    #[test]
+    fn test_per_model_validation() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .image_generation()
+            .model("dall-e-3")
+            .prompt("A cat")
+            .size(ImageSize::S1792x1024)
+            .quality(ImageQuality::Hd)
+            .style(ImageStyle::Vivid)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "prompt": "A cat",
+                "model": "dall-e-3",
+                "size": "1792x1024",
+                "quality": "hd",
+                "style": "vivid"
+            })
+        );
+
+        assert_eq!(
+            openai
+                .image_generation()
+                .model("gpt-image-1")
+                .prompt("A cat")
+                .size(ImageSize::S1792x1024)
+                .build()
+                .unwrap_err(),
+            ImageRequestError::UnsupportedSize {
+                model: "gpt-image-1".into(),
+                size: ImageSize::S1792x1024
+            }
+        );
+        assert_eq!(
+            openai
+                .image_generation()
+                .model("dall-e-3")
+                .prompt("A cat")
+                .n(2)
+                .build()
+                .unwrap_err(),
+            ImageRequestError::InvalidCount {
+                model: "dall-e-3".into(),
+                n: 2,
+                max: 1
+            }
+        );
+        assert!(matches!(
+            openai
+                .image_generation()
+                .prompt("A cat")
+                .background(Background::Transparent)
+                .build(),
+            Err(ImageRequestError::UnsupportedParameter {
+                parameter: "background",
+                ..
+            })
+        ));
+        assert_eq!(
+            openai
+                .image_generation()
+                .model("gpt-image-1")
+                .prompt("A cat")
+                .background(Background::Transparent)
+                .output_format(OutputFormat::Jpeg)
+                .build()
+                .unwrap_err(),
+            ImageRequestError::TransparentJpeg
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Image variations (`/v1/images/variations`), `dall-e-2` only.
+
+use bon::Builder;
+use reqwest::multipart;
+
+use super::{
+    form_value, send_form, ImageFile, ImageRequestError, ImageResponseFormat, ImageSize,
+    ImagesResponse, DALL_E_2,
+};
+use crate::{ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/images/variations";
+const MAX_VARIATIONS: u32 = 10;
+
+#[derive(Debug, Clone, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct ImageVariationRequest {
+    /// Square PNG smaller than 4 MB.
+    pub image: ImageFile,
+    #[builder(into)]
+    pub model: Option<String>,
+    pub n: Option<u32>,
+    pub size: Option<ImageSize>,
+    pub response_format: Option<ImageResponseFormat>,
+    #[builder(into)]
+    pub user: Option<String>,
+    pub openai: OpenAi,
+}
+
+impl<S: image_variation_request_builder::IsComplete> ImageVariationRequestBuilder<S> {
+    pub fn build(self) -> Result<ImageVariationRequest, ImageRequestError> {
+        let request = self.build_unchecked();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+impl ImageVariationRequest {
+    pub fn validate(&self) -> Result<(), ImageRequestError> {
+        let model = self.model.as_deref().unwrap_or(DALL_E_2);
+        if let Some(size) = self.size.filter(|size| {
+            ![
+                ImageSize::S256x256,
+                ImageSize::S512x512,
+                ImageSize::S1024x1024,
+            ]
+            .contains(size)
+        }) {
+            return Err(ImageRequestError::UnsupportedSize {
+                model: model.to_string(),
+                size,
+            });
+        }
+        if let Some(n) = self.n.filter(|n| !(1..=MAX_VARIATIONS).contains(n)) {
+            return Err(ImageRequestError::InvalidCount {
+                model: model.to_string(),
+                n,
+                max: MAX_VARIATIONS,
+            });
+        }
+        Ok(())
+    }
+
+    fn form(&self) -> Result<multipart::Form, ApiRequestError> {
+        let mut form = multipart::Form::new().part("image", self.image.part()?);
+        if let Some(model) = &self.model {
+            form = form.text("model", model.clone());
+        }
+        if let Some(n) = self.n {
+            form = form.text("n", n.to_string());
+        }
+        if let Some(size) = self.size {
+            form = form.text("size", form_value(size));
+        }
+        if let Some(response_format) = self.response_format {
+            form = form.text("response_format", form_value(response_format));
+        }
+        if let Some(user) = &self.user {
+            form = form.text("user", user.clone());
+        }
+        Ok(form)
+    }
+
+    pub async fn send(&self) -> Result<ImagesResponse, ApiRequestError> {
+        send_form(&self.openai, API_URL, self.form()?).await
+    }
+}
+
+impl OpenAi {
+    pub fn image_variation(
+        &self,
+    ) -> ImageVariationRequestBuilder<image_variation_request_builder::SetOpenai> {
+        ImageVariationRequest::builder().openai(self.clone())
+    }
+}
@@ -0,0 +1,48 @@
+//! JSON decoding for hot paths: stream chunks and large response bodies.
+//!
+//! With the `simd-json` feature these parse with simd-json, which pays off in high-throughput
+//! streaming proxies; otherwise they are thin wrappers around `serde_json`. Errors are always
+//! `serde_json::Error`, so callers don't depend on the feature.
+
+use serde::de::DeserializeOwned;
+
+pub(crate) fn from_str<T: DeserializeOwned>(data: &str) -> Result<T, serde_json::Error> {
+    from_slice(data.as_bytes())
+}
+
+#[cfg(not(feature = "simd-json"))]
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error> {
+    serde_json::from_slice(bytes)
+}
+
+#[cfg(feature = "simd-json")]
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error> {
+    // simd-json parses in place, so it needs its own copy of the input.
+    let mut bytes = bytes.to_vec();
+    simd_json::serde::from_slice(&mut bytes)
+        .map_err(<serde_json::Error as serde::de::Error>::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Chunk {
+            id: String,
+            index: u32,
+        }
+        assert_eq!(
+            from_str::<Chunk>(r#"{"id":"chatcmpl-1","index":2}"#).unwrap(),
+            Chunk {
+                id: "chatcmpl-1".to_string(),
+                index: 2
+            }
+        );
+        assert!(from_str::<Chunk>(r#"{"id":1}"#).is_err());
+    }
+}
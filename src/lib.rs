@@ -1,34 +1,97 @@
-use bon::Builder;
+use std::time::Duration;
+
+use reqwest::StatusCode;
 use serde::Deserialize;
 use thiserror::Error;
 
+pub mod assistants;
 pub mod audio;
+pub mod auth;
+pub mod azure;
+pub mod backoff;
+pub mod batch;
+#[cfg(feature = "cancellation")]
+pub mod cancel;
+#[cfg(feature = "cassette")]
+pub mod cassette;
 pub mod chat;
+mod client;
+pub mod completions;
+pub mod decorators;
 pub mod embeddings;
+pub mod extract;
+pub mod files;
+pub mod fine_tuning;
+pub mod images;
+mod json;
+#[cfg(feature = "mcp")]
+pub mod mcp;
+pub mod middleware;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod models;
+pub mod moderations;
+pub mod openapi;
+#[cfg(feature = "outbox")]
+pub mod outbox;
+pub mod pipeline;
+pub mod provider;
+pub mod proxy;
+pub mod rate_limit;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod responses;
+pub mod retry;
+pub mod sse;
+pub mod timeout;
+#[cfg(feature = "tokenizer")]
+pub mod tokenizer;
+pub mod vector_stores;
+pub mod videos;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+
+// The derive macros expand to `::openai_ox::...` paths; this makes them resolve in our own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as openai_ox;
+
 const BASE_URL: &str = "https://api.openai.com";
 
+use auth::ApiKeySource;
+#[cfg(feature = "cancellation")]
+pub use cancel::CancellationToken;
+pub use client::{open_ai_builder, OpenAiBuilder};
 #[cfg(feature = "leaky-bucket")]
 pub use leaky_bucket::RateLimiter;
-use std::fmt;
-#[cfg(feature = "leaky-bucket")]
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-#[derive(Clone, Builder)]
+/// Client for the OpenAI API.
+///
+/// Cloning is cheap: every clone shares the same configuration, connection pool and rate
+/// limiter, so it's fine to hand one to each request.
+#[derive(Clone)]
 pub struct OpenAi {
-    api_key: String,
-    #[builder(default)]
-    client: reqwest::Client,
-    #[cfg(feature = "leaky-bucket")]
-    leaky_bucket: Option<Arc<RateLimiter>>,
+    inner: Arc<client::Inner>,
 }
 
 impl fmt::Debug for OpenAi {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("OpenAi")
-            .field("api_key", &"[REDACTED]")
-            .field("client", &self.client)
-            .finish()
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl OpenAi {
+    pub fn builder() -> OpenAiBuilder {
+        client::Inner::builder()
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.inner.base_url
+    }
+
+    /// Source the client takes its key from; call [`ApiKeySource::refresh`] to rotate it.
+    pub fn api_key_source(&self) -> Option<&ApiKeySource> {
+        self.inner.api_key.as_ref()
     }
 }
 
@@ -40,29 +103,227 @@ struct ErrorResponse {
 #[derive(Debug, Deserialize)]
 pub struct ApiErrorDetail {
     message: String,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
     #[serde(default)]
     param: Option<String>,
     #[serde(default)]
     code: Option<String>,
 }
 
+/// A request the API answered with an error: the HTTP status and the `error` object of the
+/// body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    /// `type` of the error, e.g. `invalid_request_error`, `insufficient_quota` or
+    /// `server_error`.
+    pub kind: Option<String>,
+    pub param: Option<String>,
+    pub code: Option<String>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, detail: ApiErrorDetail) -> Self {
+        ApiError {
+            status,
+            message: detail.message,
+            kind: detail.kind,
+            param: detail.param,
+            code: detail.code,
+        }
+    }
+
+    /// Sorts the error into the variant callers are likely to handle differently.
+    /// `retry_after` is how long the response asked to wait.
+    fn into_request_error(self, retry_after: Option<Duration>) -> ApiRequestError {
+        let is =
+            |name: &str| self.code.as_deref() == Some(name) || self.kind.as_deref() == Some(name);
+        if is("insufficient_quota") {
+            ApiRequestError::InsufficientQuota(self)
+        } else if is("context_length_exceeded") {
+            ApiRequestError::ContextLengthExceeded(self)
+        } else if self.status == StatusCode::TOO_MANY_REQUESTS {
+            ApiRequestError::RateLimited {
+                error: self,
+                retry_after,
+            }
+        } else if self.status == StatusCode::UNAUTHORIZED {
+            ApiRequestError::AuthenticationError(self)
+        } else if self.status.is_server_error() {
+            ApiRequestError::ServerError(self)
+        } else {
+            ApiRequestError::Rejected(self)
+        }
+    }
+
+    /// Sorts an error that arrived without an HTTP status of its own, e.g. as an event of a
+    /// stream that started with `200 OK`. The status is inferred from its code or type.
+    pub(crate) fn without_status(detail: ApiErrorDetail) -> ApiRequestError {
+        let is = |name: &str| {
+            detail.code.as_deref() == Some(name) || detail.kind.as_deref() == Some(name)
+        };
+        let status = if is("rate_limit_exceeded") {
+            StatusCode::TOO_MANY_REQUESTS
+        } else if is("server_error") {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        ApiError::new(status, detail).into_request_error(None)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ApiRequestError {
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
     #[error(transparent)]
     SerdeError(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The client's API key could not be fetched from its source.
+    #[error(transparent)]
+    ApiKey(#[from] auth::ApiKeyError),
 
+    /// A request rejected before it was sent, or a failed line of a batch.
     #[error("Invalid request error: {message}")]
     InvalidRequestError {
         message: String,
         param: Option<String>,
         code: Option<String>,
     },
-    #[error("Unexpected response from API: {response}")]
-    UnexpectedResponse { response: String },
+    /// Rejected by the API for a reason none of the variants below cover, e.g. a bad parameter
+    /// or an unknown model.
+    #[error("Request rejected with {}: {}", .0.status, .0.message)]
+    Rejected(ApiError),
+    /// Status 429 for sending too many requests or tokens. `retry_after` is how long the server
+    /// asked to wait.
+    #[error("Rate limited: {}", .error.message)]
+    RateLimited {
+        error: ApiError,
+        retry_after: Option<Duration>,
+    },
+    /// Status 401: a missing, invalid or revoked key.
+    #[error("Authentication failed: {}", .0.message)]
+    AuthenticationError(ApiError),
+    /// The organization ran out of credits or hit its spending limit; retrying won't help.
+    #[error("Insufficient quota: {}", .0.message)]
+    InsufficientQuota(ApiError),
+    /// The prompt plus the requested completion is longer than the model's context window.
+    #[error("Context length exceeded: {}", .0.message)]
+    ContextLengthExceeded(ApiError),
+    /// Status 5xx: the API failed to handle a valid request.
+    #[error("Server error {}: {}", .0.status, .0.message)]
+    ServerError(ApiError),
+    /// A response this crate can't make sense of, e.g. an HTML page a proxy answered a 502 with
+    /// or a reply missing its content. `status` is set when the response was an HTTP error,
+    /// `response` then holds its raw body.
+    #[error(
+        "Unexpected response from API{}: {response}",
+        .status.map(|status| format!(" ({})", status)).unwrap_or_default()
+    )]
+    UnexpectedResponse {
+        status: Option<StatusCode>,
+        response: String,
+    },
     #[error("Stream error: {0}")]
     Stream(String),
+    /// No data arrived for the client's stream idle timeout, see
+    /// [`Timeouts::stream_idle`](timeout::Timeouts::stream_idle).
+    #[error("No data received for {0:?}")]
+    StreamIdle(Duration),
+    #[error("Model refused the request: {refusal}")]
+    Refusal { refusal: String },
+    #[error("Model was still calling tools after {iterations} iterations")]
+    ToolIterationLimit { iterations: usize },
+    /// The request or stream was given up on through its cancellation token.
+    #[error("Request was cancelled")]
+    Cancelled,
+    /// The request was deserialized or built without a client; send it with
+    /// [`ApiRequest::send_with`].
+    #[error("Request has no client to send it with")]
+    MissingClient,
+}
+
+impl ApiRequestError {
+    /// Returns `true` for transient failures worth retrying: network errors, rate limits, server
+    /// errors and broken streams.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiRequestError::ReqwestError(e) => {
+                e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+            }
+            ApiRequestError::RateLimited { .. } | ApiRequestError::ServerError(_) => true,
+            ApiRequestError::Stream(_) | ApiRequestError::StreamIdle(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` when the request or stream timed out, on this side or in reqwest.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            ApiRequestError::ReqwestError(e) => e.is_timeout(),
+            ApiRequestError::StreamIdle(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` when no connection to the server could be opened.
+    pub fn is_connect(&self) -> bool {
+        matches!(self, ApiRequestError::ReqwestError(e) if e.is_connect())
+    }
+
+    /// The error the API answered with, `None` for errors that happened on this side.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            ApiRequestError::Rejected(error)
+            | ApiRequestError::RateLimited { error, .. }
+            | ApiRequestError::AuthenticationError(error)
+            | ApiRequestError::InsufficientQuota(error)
+            | ApiRequestError::ContextLengthExceeded(error)
+            | ApiRequestError::ServerError(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of the error response, or of the response a body failed to download from.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ApiRequestError::ReqwestError(e) => e.status(),
+            ApiRequestError::UnexpectedResponse { status, .. } => *status,
+            e => e.api_error().map(|error| error.status),
+        }
+    }
+
+    /// Error of a non-2xx response, read from its JSON body. Bodies that are not an API error,
+    /// such as the HTML or plain text pages of proxies and load balancers, are kept as they are
+    /// in [`ApiRequestError::UnexpectedResponse`].
+    pub(crate) async fn from_response(res: reqwest::Response) -> Self {
+        let status = res.status();
+        let retry_after = retry::retry_after(res.headers());
+        match res.bytes().await {
+            Ok(body) => Self::from_body(status, retry_after, &body),
+            Err(e) => e.into(),
+        }
+    }
+
+    pub(crate) fn from_body(
+        status: StatusCode,
+        retry_after: Option<Duration>,
+        body: &[u8],
+    ) -> Self {
+        match json::from_slice::<ErrorResponse>(body) {
+            Ok(error_response) => {
+                ApiError::new(status, error_response.error).into_request_error(retry_after)
+            }
+            Err(_) => ApiRequestError::UnexpectedResponse {
+                status: Some(status),
+                response: String::from_utf8_lossy(body).into_owned(),
+            },
+        }
+    }
 }
 
 /// `ApiRequest` trait allows sending any prepared request by explicitly providing OpenAI client.
@@ -73,6 +334,24 @@ pub enum ApiRequestError {
 ///
 /// - `Response`: A type that implements the `DeserializeOwned` trait from Serde. This type
 /// represents the deserialized response that you expect back from the API call.
+///
+/// Implemented by the chat, embedding, transcription and speech requests, so they can be sent
+/// through another client than the one they were built with, or handled generically:
+///
+/// ```no_run
+/// use openai_ox::{ApiRequest, ApiRequestError, OpenAi};
+///
+/// async fn send_all<R: ApiRequest + Sync>(
+///     openai: &OpenAi,
+///     requests: &[R],
+/// ) -> Result<Vec<R::Response>, ApiRequestError> {
+///     let mut responses = Vec::new();
+///     for request in requests {
+///         responses.push(request.send_with(openai).await?);
+///     }
+///     Ok(responses)
+/// }
+/// ```
 #[async_trait::async_trait]
 pub trait ApiRequest {
     type Response: serde::de::DeserializeOwned;
@@ -94,3 +373,59 @@ pub trait ApiRequestWithClient: ApiRequest {
     /// sends off the API request.
     async fn send(&self) -> Result<Self::Response, ApiRequestError>;
 }
+
+/// The client a request was built with, [`ApiRequestError::MissingClient`] without one.
+pub(crate) fn require_client(openai: &Option<OpenAi>) -> Result<&OpenAi, ApiRequestError> {
+    openai.as_ref().ok_or(ApiRequestError::MissingClient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_body() {
+        let body = r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error","param":null,"code":"invalid_api_key"}}"#;
+        match ApiRequestError::from_body(StatusCode::UNAUTHORIZED, None, body.as_bytes()) {
+            ApiRequestError::AuthenticationError(error) => {
+                assert_eq!(error.message, "Incorrect API key provided");
+                assert_eq!(error.kind.as_deref(), Some("invalid_request_error"));
+                assert_eq!(error.code.as_deref(), Some("invalid_api_key"));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let body = r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#;
+        let e = ApiRequestError::from_body(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(Duration::from_secs(2)),
+            body.as_bytes(),
+        );
+        assert!(matches!(
+            &e,
+            ApiRequestError::RateLimited { retry_after: Some(after), .. } if after.as_secs() == 2
+        ));
+        assert_eq!(e.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+        assert!(e.is_retryable());
+        let body = r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota","code":"insufficient_quota"}}"#;
+        assert!(matches!(
+            ApiRequestError::from_body(StatusCode::TOO_MANY_REQUESTS, None, body.as_bytes()),
+            ApiRequestError::InsufficientQuota(_)
+        ));
+        let body = r#"{"error":{"message":"maximum context length is 8192 tokens","type":"invalid_request_error","param":"messages","code":"context_length_exceeded"}}"#;
+        assert!(matches!(
+            ApiRequestError::from_body(StatusCode::BAD_REQUEST, None, body.as_bytes()),
+            ApiRequestError::ContextLengthExceeded(_)
+        ));
+        assert!(matches!(
+            ApiRequestError::from_body(StatusCode::BAD_GATEWAY, None, "<html>502 Bad Gateway</html>".as_bytes()),
+            ApiRequestError::UnexpectedResponse { status: Some(StatusCode::BAD_GATEWAY), response }
+                if response == "<html>502 Bad Gateway</html>"
+        ));
+
+        let detail: ApiErrorDetail =
+            json::from_str(r#"{"message":"Slow down","code":"rate_limit_exceeded"}"#).unwrap();
+        let e = ApiError::without_status(detail);
+        assert!(matches!(e, ApiRequestError::RateLimited { .. }));
+        assert!(e.is_retryable());
+    }
+}
@@ -0,0 +1,422 @@
+//! Model Context Protocol client.
+//!
+//! Connects to an MCP server over stdio or streamable HTTP, lists its tools as chat [`Tools`] and
+//! forwards tool calls made by the model back to the server.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), openai_ox::mcp::McpError> {
+//! use openai_ox::mcp::McpClient;
+//!
+//! let mcp = McpClient::stdio("npx", ["-y", "@modelcontextprotocol/server-everything"]).await?;
+//! let tools = mcp.tools().await?;
+//! // ... send `tools` with a chat request, then for every tool call:
+//! let output = mcp.call("echo", r#"{"message": "hi"}"#).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    ffi::OsStr,
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, ChildStdout},
+    sync::Mutex,
+};
+
+use crate::{
+    chat::tool::{Function, Tool, Tools},
+    sse::SseParser,
+};
+
+const PROTOCOL_VERSION: &str = "2025-03-26";
+const SESSION_HEADER: &str = "mcp-session-id";
+
+#[derive(Debug, Error)]
+pub enum McpError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+    #[error("MCP error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("MCP protocol error: {0}")]
+    Protocol(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "inputSchema", default)]
+    pub input_schema: Value,
+}
+
+impl From<McpTool> for Tool {
+    fn from(tool: McpTool) -> Self {
+        let parameters = if tool.input_schema.is_object() {
+            tool.input_schema
+        } else {
+            json!({"type": "object", "properties": {}})
+        };
+        Tool::function(
+            Function::builder()
+                .name(tool.name)
+                .maybe_description(tool.description)
+                .parameters(parameters)
+                .build(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: Value,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallToolResult {
+    #[serde(default)]
+    pub content: Vec<McpContent>,
+    #[serde(rename = "isError", default)]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// Text parts of the result joined with newlines, non-text parts are serialized as JSON.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .map(|content| match content {
+                McpContent::Text { text } => text.clone(),
+                other => serde_json::to_string(other).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+enum Transport {
+    Stdio {
+        _child: Child,
+        stdin: ChildStdin,
+        stdout: BufReader<ChildStdout>,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        session_id: Option<String>,
+    },
+}
+
+pub struct McpClient {
+    transport: Mutex<Transport>,
+    next_id: AtomicU64,
+    server_info: Value,
+}
+
+impl std::fmt::Debug for McpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpClient")
+            .field("server_info", &self.server_info)
+            .finish()
+    }
+}
+
+fn rpc_result(message: Value) -> Result<Value, McpError> {
+    if let Some(error) = message.get("error") {
+        return Err(McpError::Rpc {
+            code: error
+                .get("code")
+                .and_then(Value::as_i64)
+                .unwrap_or_default(),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    Ok(message.get("result").cloned().unwrap_or(Value::Null))
+}
+
+impl Transport {
+    async fn send(&mut self, message: &Value, id: Option<u64>) -> Result<Option<Value>, McpError> {
+        match self {
+            Transport::Stdio { stdin, stdout, .. } => {
+                let mut line = serde_json::to_vec(message)?;
+                line.push(b'\n');
+                stdin.write_all(&line).await?;
+                stdin.flush().await?;
+                let Some(id) = id else {
+                    return Ok(None);
+                };
+                let mut buf = String::new();
+                loop {
+                    buf.clear();
+                    if stdout.read_line(&mut buf).await? == 0 {
+                        return Err(McpError::Protocol("server closed stdout".to_string()));
+                    }
+                    let Ok(message) = serde_json::from_str::<Value>(buf.trim()) else {
+                        continue;
+                    };
+                    if message.get("id").and_then(Value::as_u64) == Some(id)
+                        && message.get("method").is_none()
+                    {
+                        return Ok(Some(message));
+                    }
+                }
+            }
+            Transport::Http {
+                client,
+                url,
+                session_id,
+            } => {
+                let mut req = client
+                    .post(url.as_str())
+                    .header("Accept", "application/json, text/event-stream")
+                    .json(message);
+                if let Some(session_id) = session_id.as_ref() {
+                    req = req.header(SESSION_HEADER, session_id);
+                }
+                let res = req.send().await?;
+                if let Some(value) = res.headers().get(SESSION_HEADER) {
+                    *session_id = value.to_str().ok().map(ToOwned::to_owned);
+                }
+                let status = res.status();
+                let is_sse = res
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.starts_with("text/event-stream"));
+                let body = res.bytes().await?;
+                if !status.is_success() {
+                    return Err(McpError::Protocol(format!(
+                        "HTTP {}: {}",
+                        status,
+                        String::from_utf8_lossy(&body)
+                    )));
+                }
+                let Some(id) = id else {
+                    return Ok(None);
+                };
+                let messages: Vec<Value> = if is_sse {
+                    let mut parser = SseParser::new();
+                    let mut events = parser.push(&body);
+                    events.extend(parser.finish());
+                    events
+                        .iter()
+                        .filter_map(|event| serde_json::from_str(&event.data).ok())
+                        .collect()
+                } else {
+                    vec![serde_json::from_slice(&body)?]
+                };
+                messages
+                    .into_iter()
+                    .find(|m| m.get("id").and_then(Value::as_u64) == Some(id))
+                    .map(Some)
+                    .ok_or_else(|| McpError::Protocol("missing response".to_string()))
+            }
+        }
+    }
+}
+
+impl McpClient {
+    /// Spawns `command` and talks to it over stdin/stdout.
+    pub async fn stdio<I, S>(command: impl AsRef<OsStr>, args: I) -> Result<Self, McpError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut child = tokio::process::Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| McpError::Protocol("stdin not captured".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| McpError::Protocol("stdout not captured".to_string()))?;
+        Self::connect(Transport::Stdio {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+        .await
+    }
+
+    /// Connects to a server using the streamable HTTP transport.
+    pub async fn http(url: impl Into<String>) -> Result<Self, McpError> {
+        Self::http_with_client(url, reqwest::Client::new()).await
+    }
+
+    pub async fn http_with_client(
+        url: impl Into<String>,
+        client: reqwest::Client,
+    ) -> Result<Self, McpError> {
+        Self::connect(Transport::Http {
+            client,
+            url: url.into(),
+            session_id: None,
+        })
+        .await
+    }
+
+    async fn connect(transport: Transport) -> Result<Self, McpError> {
+        let mut client = McpClient {
+            transport: Mutex::new(transport),
+            next_id: AtomicU64::new(1),
+            server_info: Value::Null,
+        };
+        client.server_info = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {"name": "openai-ox", "version": env!("CARGO_PKG_VERSION")},
+                }),
+            )
+            .await?;
+        client
+            .notify("notifications/initialized", json!({}))
+            .await?;
+        Ok(client)
+    }
+
+    /// Result of the `initialize` handshake, including `serverInfo` and `capabilities`.
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = self.transport.lock().await.send(&message, Some(id)).await?;
+        rpc_result(response.unwrap_or(Value::Null))
+    }
+
+    pub async fn notify(&self, method: &str, params: Value) -> Result<(), McpError> {
+        let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        self.transport.lock().await.send(&message, None).await?;
+        Ok(())
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>, McpError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({"cursor": cursor}),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+            if let Some(page) = result.get("tools") {
+                tools.extend(serde_json::from_value::<Vec<McpTool>>(page.clone())?);
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Server tools as chat tool definitions.
+    pub async fn tools(&self) -> Result<Tools, McpError> {
+        Ok(self
+            .list_tools()
+            .await?
+            .into_iter()
+            .map(Tool::from)
+            .collect())
+    }
+
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .request("tools/call", json!({"name": name, "arguments": arguments}))
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Forwards a tool call made by the model and returns the textual result.
+    ///
+    /// `arguments` is the JSON encoded arguments string produced by the model. Results flagged
+    /// with `isError` are still returned as text so the model can react to them.
+    pub async fn call(&self, name: &str, arguments: &str) -> Result<String, McpError> {
+        let arguments = if arguments.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(arguments)?
+        };
+        Ok(self.call_tool(name, arguments).await?.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAKE_SERVER: &str = r#"
+read l
+printf '%s\n' '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{},"serverInfo":{"name":"fake","version":"1"}}}'
+read l
+read l
+printf '%s\n' '{"jsonrpc":"2.0","method":"notifications/message","params":{}}'
+printf '%s\n' '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"echo","description":"Echo","inputSchema":{"type":"object","properties":{"message":{"type":"string"}}}}]}}'
+read l
+printf '%s\n' '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"hi"}]}}'
+"#;
+
+    #[tokio::test]
+    async fn test_stdio_round_trip() {
+        let mcp = McpClient::stdio("sh", ["-c", FAKE_SERVER]).await.unwrap();
+        assert_eq!(mcp.server_info()["serverInfo"]["name"], "fake");
+
+        let tools = mcp.tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name(), "echo");
+
+        let output = mcp.call("echo", r#"{"message":"hi"}"#).await.unwrap();
+        assert_eq!(output, "hi");
+    }
+}
@@ -0,0 +1,148 @@
+//! Hooks run around every HTTP request the client sends.
+//!
+//! A [`Middleware`] sees each request right before it goes out, and may change it, then the
+//! response or error together with how long the request took. Retried requests run the hooks
+//! once per attempt. Use it for audit logs, latency metrics or headers a corporate proxy
+//! expects.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use openai_ox::{
+//!     middleware::{redact_headers, Middleware, ResponseInfo},
+//!     OpenAi,
+//! };
+//!
+//! struct AuditLog;
+//!
+//! impl Middleware for AuditLog {
+//!     fn on_request(&self, request: &mut reqwest::Request) {
+//!         request
+//!             .headers_mut()
+//!             .insert("x-team", reqwest::header::HeaderValue::from_static("search"));
+//!         eprintln!("{} {} {:?}", request.method(), request.url(), redact_headers(request.headers()));
+//!     }
+//!
+//!     fn on_response(&self, info: &ResponseInfo<'_>) {
+//!         eprintln!("{} {:?} in {:?}", info.url, info.status(), info.elapsed);
+//!     }
+//! }
+//!
+//! let openai = OpenAi::builder().api_key("sk-...").middleware(AuditLog).build();
+//! ```
+
+use std::time::Duration;
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Method, StatusCode, Url,
+};
+
+/// Headers carrying credentials, replaced by [`redact_headers`].
+pub(crate) const SECRET_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "api-key",
+    "x-api-key",
+];
+
+pub trait Middleware: Send + Sync {
+    /// Called right before `request` is sent, with the credentials already set.
+    fn on_request(&self, request: &mut reqwest::Request) {
+        let _ = request;
+    }
+
+    /// Called once the response headers arrived or the request failed.
+    fn on_response(&self, info: &ResponseInfo<'_>) {
+        let _ = info;
+    }
+}
+
+/// Runs `A` then `B`, to combine several middlewares on one client.
+impl<A: Middleware, B: Middleware> Middleware for (A, B) {
+    fn on_request(&self, request: &mut reqwest::Request) {
+        self.0.on_request(request);
+        self.1.on_request(request);
+    }
+
+    fn on_response(&self, info: &ResponseInfo<'_>) {
+        self.0.on_response(info);
+        self.1.on_response(info);
+    }
+}
+
+/// Outcome of one request.
+#[derive(Debug)]
+pub struct ResponseInfo<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub response: Result<&'a reqwest::Response, &'a reqwest::Error>,
+    /// Time until the response headers arrived, the body is read later.
+    pub elapsed: Duration,
+}
+
+impl ResponseInfo<'_> {
+    /// Status of the response, `None` when the request failed before getting one.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.response.ok().map(reqwest::Response::status)
+    }
+}
+
+/// Copy of `headers` with the values of credential headers replaced, safe to log. Besides the
+/// usual ones, that's every header marked sensitive, like the one of
+/// [`AuthScheme::Header`](crate::auth::AuthScheme::Header).
+pub fn redact_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut redacted = headers.clone();
+    for (name, value) in redacted.iter_mut() {
+        if SECRET_HEADERS.contains(&name.as_str()) || value.is_sensitive() {
+            *value = HeaderValue::from_static("[REDACTED]");
+        }
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        auth::{AuthScheme, Authorize},
+        OpenAi,
+    };
+
+    #[test]
+    fn test_redact_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer sk-secret"),
+        );
+        headers.insert("api-key", HeaderValue::from_static("secret"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["authorization"], "[REDACTED]");
+        assert_eq!(redacted["api-key"], "[REDACTED]");
+        assert_eq!(redacted["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_redact_custom_auth_header() {
+        let client = reqwest::Client::new();
+        let openai = OpenAi::builder()
+            .api_key("sk-secret")
+            .auth(AuthScheme::header("x-gateway-key"))
+            .client(client.clone())
+            .build();
+        let request = client
+            .get("http://localhost")
+            .authorize(&openai)
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["x-gateway-key"], "sk-secret");
+        assert_eq!(
+            redact_headers(request.headers())["x-gateway-key"],
+            "[REDACTED]"
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Local stand-in for the API, for tests that run without network access or an API key.
+//!
+//! [`MockOpenAi`] serves canned responses from a server on `127.0.0.1`, queued per endpoint and
+//! returned in order, and records every request it received. The client it hands out goes
+//! through the same code as one talking to `api.openai.com`, retries and middleware included.
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use openai_ox::mock::{MockOpenAi, MockResponse};
+//!
+//! let mock = MockOpenAi::start().await?;
+//! mock.push("v1/chat/completions", MockResponse::chat_text("Hello!"));
+//!
+//! let reply = mock.client().generate("gpt-4o-mini", "Hi").await?;
+//! assert_eq!(reply, "Hello!");
+//! assert_eq!(mock.requests()[0].path, "/v1/chat/completions");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::OpenAi;
+
+/// A response the mock server sends back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub content_type: String,
+    /// Headers sent besides `content-type`, e.g. `retry-after`.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// `200 OK` with `body` serialized as JSON.
+    ///
+    /// # Panics
+    ///
+    /// If `body` fails to serialize.
+    pub fn json(body: &impl Serialize) -> Self {
+        MockResponse {
+            status: 200,
+            content_type: "application/json".to_string(),
+            headers: Vec::new(),
+            body: serde_json::to_vec(body).expect("mock response body should serialize"),
+        }
+    }
+
+    /// An error in the API's format, e.g. `429` with `rate_limit_exceeded`.
+    pub fn error(status: u16, code: &str, message: &str) -> Self {
+        MockResponse {
+            status,
+            ..Self::json(&json!({
+                "error": {"message": message, "type": "invalid_request_error", "param": null, "code": code}
+            }))
+        }
+    }
+
+    /// A server-sent event stream of `events` as `data:` lines, ended by `data: [DONE]`.
+    ///
+    /// # Panics
+    ///
+    /// If an event fails to serialize.
+    pub fn sse<T: Serialize>(events: impl IntoIterator<Item = T>) -> Self {
+        let mut body = String::new();
+        for event in events {
+            let data = serde_json::to_string(&event).expect("mock event should serialize");
+            body.push_str(&format!("data: {}\n\n", data));
+        }
+        body.push_str("data: [DONE]\n\n");
+        MockResponse {
+            status: 200,
+            content_type: "text/event-stream".to_string(),
+            headers: Vec::new(),
+            body: body.into_bytes(),
+        }
+    }
+
+    /// The same response with the header `name` added.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// A chat completion whose only choice replies with `text`.
+    pub fn chat_text(text: &str) -> Self {
+        Self::json(&json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "mock",
+            "system_fingerprint": "mock",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": text},
+                "finish_reason": "stop",
+                "logprobs": null
+            }],
+            "usage": {"prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0}
+        }))
+    }
+
+    /// A chat completion stream sending `pieces` as consecutive content deltas.
+    pub fn chat_stream_text<'a>(pieces: impl IntoIterator<Item = &'a str>) -> Self {
+        let chunk = |delta, finish_reason| {
+            json!({
+                "id": "chatcmpl-mock",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": "mock",
+                "system_fingerprint": null,
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+            })
+        };
+        let deltas = pieces
+            .into_iter()
+            .map(|piece| chunk(json!({"content": piece}), None));
+        Self::sse(deltas.chain([chunk(json!({}), Some("stop"))]))
+    }
+}
+
+/// A request the mock server received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path with the query string, e.g. `/v1/files?purpose=batch`.
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The body parsed as JSON, e.g. a [`ChatCompletionRequest`](crate::chat::ChatCompletionRequest).
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    responses: HashMap<String, VecDeque<MockResponse>>,
+    requests: Vec<MockRequest>,
+    upstream: Option<String>,
+    forwarded: Vec<(MockRequest, MockResponse)>,
+}
+
+/// A mock server, shut down when dropped.
+#[derive(Debug)]
+pub struct MockOpenAi {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl MockOpenAi {
+    /// Starts a server on a free port of `127.0.0.1`.
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, Arc::clone(&state)));
+                }
+            }
+        });
+        Ok(MockOpenAi { addr, state, task })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A client pointed at the server. Use [`MockOpenAi::base_url`] with [`OpenAi::builder`]
+    /// to test other client settings.
+    pub fn client(&self) -> OpenAi {
+        OpenAi::builder()
+            .api_key("sk-mock")
+            .base_url(self.base_url())
+            .build()
+    }
+
+    /// Queues `response` for the next request to `path`, e.g. `v1/chat/completions`. Requests
+    /// to a path without queued responses get a `404` error.
+    pub fn push(&self, path: &str, response: MockResponse) -> &Self {
+        let path = path.trim_start_matches('/').to_string();
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .entry(path)
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Sends requests to a path without queued responses on to `base_url`, e.g. the real API,
+    /// headers included, instead of answering them with a `404`.
+    pub fn forward_to(&self, base_url: impl Into<String>) -> &Self {
+        self.state.lock().unwrap().upstream = Some(base_url.into());
+        self
+    }
+
+    /// Forwarded requests with the responses they got, oldest first.
+    pub fn forwarded(&self) -> Vec<(MockRequest, MockResponse)> {
+        self.state.lock().unwrap().forwarded.clone()
+    }
+}
+
+impl Drop for MockOpenAi {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut stream = BufReader::new(stream);
+    let Ok(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let route = request.path.split('?').next().unwrap_or_default();
+    let route = route.trim_start_matches('/').to_string();
+    let (response, upstream) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let response = state
+            .responses
+            .get_mut(&route)
+            .and_then(VecDeque::pop_front);
+        (response, state.upstream.clone())
+    };
+    let response = match (response, upstream) {
+        (Some(response), _) => response,
+        (None, Some(upstream)) => {
+            let response = forward(&upstream, &request).await.unwrap_or_else(|e| {
+                MockResponse::error(502, "bad_gateway", &format!("forwarding failed: {}", e))
+            });
+            let mut state = state.lock().unwrap();
+            state.forwarded.push((request, response.clone()));
+            response
+        }
+        (None, None) => MockResponse::error(
+            404,
+            "unknown_url",
+            &format!(
+                "no mock response queued for {} {}",
+                request.method, request.path
+            ),
+        ),
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let stream = stream.get_mut();
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&response.body).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Sends `request` to `upstream` and reads the whole response.
+async fn forward(upstream: &str, request: &MockRequest) -> reqwest::Result<MockResponse> {
+    let method = request.method.parse().unwrap_or(reqwest::Method::POST);
+    let url = format!("{}{}", upstream.trim_end_matches('/'), request.path);
+    let mut builder = reqwest::Client::new().request(method, url);
+    for (name, value) in &request.headers {
+        if !["host", "content-length", "connection"].contains(&name.as_str()) {
+            builder = builder.header(name, value);
+        }
+    }
+    let res = builder.body(request.body.clone()).send().await?;
+    let status = res.status().as_u16();
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+    Ok(MockResponse {
+        status,
+        content_type,
+        headers: Vec::new(),
+        body: res.bytes().await?.to_vec(),
+    })
+}
+
+/// Reads an HTTP/1.1 request with a `content-length` or chunked body.
+async fn read_request(stream: &mut BufReader<TcpStream>) -> io::Result<MockRequest> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed request");
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(invalid)?.to_string();
+    let path = parts.next().ok_or_else(invalid)?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        stream.read_line(&mut line).await?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':').ok_or_else(invalid)?;
+        headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut body = Vec::new();
+    if header("transfer-encoding").is_some_and(|value| value.contains("chunked")) {
+        loop {
+            line.clear();
+            stream.read_line(&mut line).await?;
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16).map_err(|_| invalid())?;
+            let start = body.len();
+            body.resize(start + size + 2, 0);
+            stream.read_exact(&mut body[start..]).await?;
+            body.truncate(start + size);
+            if size == 0 {
+                break;
+            }
+        }
+    } else if let Some(len) = header("content-length") {
+        let len = len.parse().map_err(|_| invalid())?;
+        body.resize(len, 0);
+        stream.read_exact(&mut body).await?;
+    }
+    Ok(MockRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{
+        chat::{message::Message, ChatCompletionRequest},
+        ApiRequestError,
+    };
+
+    #[tokio::test]
+    async fn test_mock_chat() {
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push("v1/chat/completions", MockResponse::chat_text("Hello!"))
+            .push(
+                "v1/chat/completions",
+                MockResponse::chat_stream_text(["Hel", "lo!"]),
+            )
+            .push(
+                "v1/chat/completions",
+                MockResponse::error(400, "invalid_value", "bad request"),
+            );
+        let request = mock
+            .client()
+            .chat_completion()
+            .model("gpt-4o-mini")
+            .messages(Message::user("Hi"))
+            .build()
+            .unwrap();
+
+        let res = request.send().await.unwrap();
+        assert_eq!(res.text(), Some("Hello!"));
+
+        let mut text = String::new();
+        let mut stream = request.stream().await;
+        while let Some(chunk) = stream.next().await {
+            text.extend(chunk.unwrap().choices[0].delta.content.clone());
+        }
+        assert_eq!(text, "Hello!");
+
+        match request.send().await {
+            Err(ApiRequestError::Rejected(error)) => {
+                assert_eq!(error.code.as_deref(), Some("invalid_value"));
+            }
+            res => panic!("expected an API error, got {:?}", res),
+        }
+        assert!(mock.client().generate("gpt-4o-mini", "Hi").await.is_err());
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer sk-mock"));
+        let sent: ChatCompletionRequest = requests[0].json().unwrap();
+        assert_eq!(sent.model, "gpt-4o-mini");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_request_body() {
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push("v1/files", MockResponse::json(&json!({})));
+        // A streamed body has no length and goes out with `transfer-encoding: chunked`.
+        let chunks = futures::stream::iter([Ok::<_, io::Error>("{\"purpose\":"), Ok("\"batch\"}")]);
+        let res = reqwest::Client::new()
+            .post(format!("{}/v1/files", mock.base_url()))
+            .body(reqwest::Body::wrap_stream(chunks))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+
+        let request = &mock.requests()[0];
+        assert_eq!(request.header("transfer-encoding"), Some("chunked"));
+        assert_eq!(
+            request.json::<serde_json::Value>().unwrap(),
+            json!({"purpose": "batch"})
+        );
+    }
+}
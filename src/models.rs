@@ -1,4 +1,4 @@
-use crate::{ApiRequestError, OpenAi};
+use crate::{auth::Authorize, retry::SendRetrying, ApiRequestError, OpenAi};
 
 use serde::{Deserialize, Serialize};
 
@@ -24,28 +24,36 @@ impl From<Model> for String {
 
 impl OpenAi {
     pub async fn get_models(&self) -> Result<ModelList, ApiRequestError> {
-        let url = "https://api.openai.com/v1/models";
-        let response = self
+        let url = format!("{}/v1/models", self.inner.base_url);
+        let res = self
+            .inner
             .client
             .get(url)
-            .bearer_auth(&self.api_key)
-            .send()
+            .authorize(self)
             .await?
-            .json::<ModelList>()
+            .send_retrying(self)
             .await?;
-        Ok(response)
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
     }
 
     pub async fn get_model(&self, model_id: &str) -> Result<Model, ApiRequestError> {
-        let url = format!("https://api.openai.com/v1/models/{}", model_id);
-        let response = self
+        let url = format!("{}/v1/models/{}", self.inner.base_url, model_id);
+        let res = self
+            .inner
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
+            .authorize(self)
             .await?
-            .json::<Model>()
+            .send_retrying(self)
             .await?;
-        Ok(response)
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
     }
 }
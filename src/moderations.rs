@@ -0,0 +1,266 @@
+//! Content classification (`/v1/moderations`), commonly run on user input before it reaches a
+//! chat model.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! let res = openai
+//!     .moderations()
+//!     .input("I want to hurt them.")
+//!     .build()
+//!     .send()
+//!     .await?;
+//! if res.flagged() {
+//!     println!("{:?}", res.results[0].flagged_categories());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use bon::Builder;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{auth::Authorize, json, retry::SendRetrying, ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/moderations";
+
+/// Text to classify, one string or several classified separately.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModerationInput {
+    Text(String),
+    Texts(Vec<String>),
+}
+
+impl From<String> for ModerationInput {
+    fn from(text: String) -> Self {
+        ModerationInput::Text(text)
+    }
+}
+
+impl From<&str> for ModerationInput {
+    fn from(text: &str) -> Self {
+        ModerationInput::Text(text.to_string())
+    }
+}
+
+impl From<Vec<String>> for ModerationInput {
+    fn from(texts: Vec<String>) -> Self {
+        ModerationInput::Texts(texts)
+    }
+}
+
+impl From<Vec<&str>> for ModerationInput {
+    fn from(texts: Vec<&str>) -> Self {
+        ModerationInput::Texts(texts.into_iter().map(str::to_string).collect())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Builder)]
+pub struct ModerationRequest {
+    #[builder(into)]
+    pub input: ModerationInput,
+    /// `omni-moderation-latest` when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub model: Option<String>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+/// One value per moderation category: flags in [`ModerationResult::categories`], scores
+/// between 0 and 1 in [`ModerationResult::category_scores`].
+///
+/// Categories a model doesn't classify, like `illicit` for the legacy text models, are left at
+/// their default, whether they're missing or `null`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, bound(deserialize = "T: Deserialize<'de> + Default"))]
+pub struct ModerationCategories<T> {
+    #[serde(deserialize_with = "null_as_default")]
+    pub harassment: T,
+    #[serde(
+        rename = "harassment/threatening",
+        deserialize_with = "null_as_default"
+    )]
+    pub harassment_threatening: T,
+    #[serde(deserialize_with = "null_as_default")]
+    pub hate: T,
+    #[serde(rename = "hate/threatening", deserialize_with = "null_as_default")]
+    pub hate_threatening: T,
+    #[serde(deserialize_with = "null_as_default")]
+    pub illicit: T,
+    #[serde(rename = "illicit/violent", deserialize_with = "null_as_default")]
+    pub illicit_violent: T,
+    #[serde(rename = "self-harm", deserialize_with = "null_as_default")]
+    pub self_harm: T,
+    #[serde(rename = "self-harm/intent", deserialize_with = "null_as_default")]
+    pub self_harm_intent: T,
+    #[serde(
+        rename = "self-harm/instructions",
+        deserialize_with = "null_as_default"
+    )]
+    pub self_harm_instructions: T,
+    #[serde(deserialize_with = "null_as_default")]
+    pub sexual: T,
+    #[serde(rename = "sexual/minors", deserialize_with = "null_as_default")]
+    pub sexual_minors: T,
+    #[serde(deserialize_with = "null_as_default")]
+    pub violence: T,
+    #[serde(rename = "violence/graphic", deserialize_with = "null_as_default")]
+    pub violence_graphic: T,
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+impl<T: Copy> ModerationCategories<T> {
+    /// Every category with its API name, in the order of the fields.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, T)> {
+        [
+            ("harassment", self.harassment),
+            ("harassment/threatening", self.harassment_threatening),
+            ("hate", self.hate),
+            ("hate/threatening", self.hate_threatening),
+            ("illicit", self.illicit),
+            ("illicit/violent", self.illicit_violent),
+            ("self-harm", self.self_harm),
+            ("self-harm/intent", self.self_harm_intent),
+            ("self-harm/instructions", self.self_harm_instructions),
+            ("sexual", self.sexual),
+            ("sexual/minors", self.sexual_minors),
+            ("violence", self.violence),
+            ("violence/graphic", self.violence_graphic),
+        ]
+        .into_iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    pub categories: ModerationCategories<bool>,
+    pub category_scores: ModerationCategories<f64>,
+}
+
+impl ModerationResult {
+    /// API names of the categories this input was flagged for.
+    pub fn flagged_categories(&self) -> Vec<&'static str> {
+        self.categories
+            .iter()
+            .filter(|(_, flagged)| *flagged)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// The category with the highest score and that score.
+    pub fn top_category(&self) -> (&'static str, f64) {
+        self.category_scores
+            .iter()
+            .fold(("harassment", f64::MIN), |top, score| {
+                if score.1 > top.1 {
+                    score
+                } else {
+                    top
+                }
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    /// One result per input, in the same order.
+    pub results: Vec<ModerationResult>,
+}
+
+impl ModerationResponse {
+    /// `true` if any of the inputs was flagged.
+    pub fn flagged(&self) -> bool {
+        self.results.iter().any(|result| result.flagged)
+    }
+}
+
+impl ModerationRequest {
+    pub async fn send(&self) -> Result<ModerationResponse, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let response = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .json(&self)
+            .send_observed(&self.openai)
+            .await?;
+
+        if response.status().is_success() {
+            Ok(json::from_slice(&response.bytes().await?)?)
+        } else {
+            Err(ApiRequestError::from_response(response).await)
+        }
+    }
+}
+
+impl OpenAi {
+    pub fn moderations(&self) -> ModerationRequestBuilder<moderation_request_builder::SetOpenai> {
+        ModerationRequest::builder().openai(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_moderation_response() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai.moderations().input(vec!["first", "second"]).build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({ "input": ["first", "second"] })
+        );
+
+        let res: ModerationResponse = serde_json::from_value(json!({
+            "id": "modr-123",
+            "model": "text-moderation-007",
+            "results": [{
+                "flagged": true,
+                "categories": {
+                    "harassment": false,
+                    "harassment/threatening": false,
+                    "hate": false,
+                    "hate/threatening": false,
+                    "illicit": null,
+                    "illicit/violent": null,
+                    "self-harm": false,
+                    "self-harm/intent": false,
+                    "self-harm/instructions": false,
+                    "sexual": false,
+                    "sexual/minors": false,
+                    "violence": true,
+                    "violence/graphic": false
+                },
+                "category_scores": {
+                    "harassment": 0.12,
+                    "illicit": null,
+                    "violence": 0.91,
+                    "violence/graphic": 0.02
+                }
+            }]
+        }))
+        .unwrap();
+        assert!(res.flagged());
+        let result = &res.results[0];
+        assert_eq!(result.flagged_categories(), ["violence"]);
+        assert_eq!(result.top_category(), ("violence", 0.91));
+        assert!(!result.categories.illicit);
+    }
+}
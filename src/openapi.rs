@@ -0,0 +1,463 @@
+//! Expose a REST API described by an OpenAPI document as chat tools.
+//!
+//! [`OpenApiToolset`] turns every operation of an OpenAPI 3 document into a function [`Tool`]
+//! whose parameters are the operation's path, query and header parameters plus an optional `body`
+//! argument for the JSON request body. Tool calls returned by the model can then be executed with
+//! [`OpenApiToolset::call`], which performs the matching HTTP request.
+
+use std::collections::HashMap;
+
+use reqwest::{header::HeaderMap, Method};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::chat::tool::{Function, Tool, Tools};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+const MAX_REF_DEPTH: usize = 16;
+const MAX_DESCRIPTION_LENGTH: usize = 1024;
+
+#[derive(Debug, Error)]
+pub enum OpenApiError {
+    #[error("Invalid OpenAPI document: {0}")]
+    InvalidSpec(String),
+    #[error("Unknown operation: {0}")]
+    UnknownOperation(String),
+    #[error("Missing required parameter: {0}")]
+    MissingParameter(String),
+    #[error("Request failed with status {status}: {body}")]
+    Http { status: u16, body: String },
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+}
+
+#[derive(Debug, Clone)]
+struct Parameter {
+    name: String,
+    location: ParameterLocation,
+    required: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    method: Method,
+    path: String,
+    parameters: Vec<Parameter>,
+    has_body: bool,
+    tool: Tool,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenApiToolset {
+    base_url: String,
+    operations: HashMap<String, Operation>,
+    order: Vec<String>,
+    client: reqwest::Client,
+    headers: HeaderMap,
+}
+
+fn resolve(spec: &Value, value: &Value, depth: usize) -> Value {
+    if depth > MAX_REF_DEPTH {
+        return json!({});
+    }
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get("$ref") {
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| spec.pointer(pointer))
+                    .cloned()
+                    .unwrap_or_else(|| json!({}));
+                return resolve(spec, &target, depth + 1);
+            }
+            Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), resolve(spec, v, depth)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|v| resolve(spec, v, depth)).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+/// Function names must match `^[a-zA-Z0-9_-]{1,64}$`.
+fn sanitize_name(name: &str) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    out = out.trim_matches('_').to_string();
+    out.truncate(64);
+    if out.is_empty() {
+        out.push_str("operation");
+    }
+    out
+}
+
+fn encode_path_segment(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl OpenApiToolset {
+    pub fn from_json(spec: &str) -> Result<Self, OpenApiError> {
+        Self::from_value(serde_json::from_str(spec)?)
+    }
+
+    pub fn from_value(spec: Value) -> Result<Self, OpenApiError> {
+        let paths = spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or_else(|| OpenApiError::InvalidSpec("missing `paths` object".to_string()))?;
+        let base_url = spec
+            .pointer("/servers/0/url")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_string();
+
+        let mut operations = HashMap::new();
+        let mut order = Vec::new();
+        for (path, item) in paths {
+            let item = resolve(&spec, item, 0);
+            let shared_parameters = item
+                .get("parameters")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for method in METHODS {
+                let Some(op) = item.get(method) else {
+                    continue;
+                };
+                let mut name = op
+                    .get("operationId")
+                    .and_then(Value::as_str)
+                    .map(sanitize_name)
+                    .unwrap_or_else(|| sanitize_name(&format!("{}_{}", method, path)));
+                while operations.contains_key(&name) {
+                    name.push('_');
+                }
+
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                let mut parameters = Vec::new();
+                let op_parameters = op
+                    .get("parameters")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                for param in shared_parameters.iter().chain(op_parameters.iter()) {
+                    let Some(param_name) = param.get("name").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    let location = match param.get("in").and_then(Value::as_str) {
+                        Some("path") => ParameterLocation::Path,
+                        Some("query") => ParameterLocation::Query,
+                        Some("header") => ParameterLocation::Header,
+                        _ => continue,
+                    };
+                    let is_required = location == ParameterLocation::Path
+                        || param.get("required").and_then(Value::as_bool) == Some(true);
+                    let mut schema = param
+                        .get("schema")
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "string"}));
+                    if let (Some(description), Value::Object(schema)) =
+                        (param.get("description"), &mut schema)
+                    {
+                        schema.insert("description".to_string(), description.clone());
+                    }
+                    properties.insert(param_name.to_string(), schema);
+                    if is_required {
+                        required.push(Value::String(param_name.to_string()));
+                    }
+                    parameters.retain(|p: &Parameter| p.name != param_name);
+                    parameters.push(Parameter {
+                        name: param_name.to_string(),
+                        location,
+                        required: is_required,
+                    });
+                }
+
+                let body_schema = op.pointer("/requestBody/content/application~1json/schema");
+                if let Some(schema) = body_schema {
+                    properties.insert("body".to_string(), schema.clone());
+                    if op.pointer("/requestBody/required").and_then(Value::as_bool) == Some(true) {
+                        required.push(Value::String("body".to_string()));
+                    }
+                }
+
+                let description = [op.get("summary"), op.get("description")]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                let description: String =
+                    description.chars().take(MAX_DESCRIPTION_LENGTH).collect();
+
+                let function = Function::builder()
+                    .name(name.clone())
+                    .maybe_description((!description.is_empty()).then_some(description))
+                    .parameters(json!({
+                        "type": "object",
+                        "properties": properties,
+                        "required": required,
+                    }))
+                    .build();
+
+                let method = Method::from_bytes(method.to_uppercase().as_bytes())
+                    .map_err(|e| OpenApiError::InvalidSpec(e.to_string()))?;
+                order.push(name.clone());
+                operations.insert(
+                    name,
+                    Operation {
+                        method,
+                        path: path.clone(),
+                        parameters,
+                        has_body: body_schema.is_some(),
+                        tool: Tool::function(function),
+                    },
+                );
+            }
+        }
+
+        Ok(OpenApiToolset {
+            base_url,
+            operations,
+            order,
+            client: reqwest::Client::new(),
+            headers: HeaderMap::new(),
+        })
+    }
+
+    /// Overrides the server url taken from the document.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Adds a header sent with every request, typically authentication.
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, OpenApiError> {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| OpenApiError::InvalidSpec(e.to_string()))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| OpenApiError::InvalidSpec(e.to_string()))?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Tool definitions, ordered by path.
+    pub fn tools(&self) -> Tools {
+        self.order
+            .iter()
+            .filter_map(|name| self.operations.get(name))
+            .map(|op| op.tool.clone())
+            .collect()
+    }
+
+    fn request(
+        &self,
+        name: &str,
+        arguments: &Value,
+    ) -> Result<reqwest::RequestBuilder, OpenApiError> {
+        let op = self
+            .operations
+            .get(name)
+            .ok_or_else(|| OpenApiError::UnknownOperation(name.to_string()))?;
+        let mut path = op.path.clone();
+        let mut query = Vec::new();
+        let mut headers = self.headers.clone();
+        for param in &op.parameters {
+            let Some(value) = arguments.get(&param.name).filter(|v| !v.is_null()) else {
+                if param.required {
+                    return Err(OpenApiError::MissingParameter(param.name.clone()));
+                }
+                continue;
+            };
+            let value = value_to_string(value);
+            match param.location {
+                ParameterLocation::Path => {
+                    path =
+                        path.replace(&format!("{{{}}}", param.name), &encode_path_segment(&value))
+                }
+                ParameterLocation::Query => query.push((param.name.clone(), value)),
+                ParameterLocation::Header => {
+                    if let (Ok(k), Ok(v)) = (
+                        reqwest::header::HeaderName::from_bytes(param.name.as_bytes()),
+                        reqwest::header::HeaderValue::from_str(&value),
+                    ) {
+                        headers.insert(k, v);
+                    }
+                }
+            }
+        }
+        let mut req = self
+            .client
+            .request(op.method.clone(), format!("{}{}", self.base_url, path))
+            .headers(headers)
+            .query(&query);
+        if let Some(body) = arguments.get("body").filter(|_| op.has_body) {
+            req = req.json(body);
+        }
+        Ok(req)
+    }
+
+    /// Executes a tool call and returns the response body, suitable as tool message content.
+    ///
+    /// `arguments` is the JSON encoded arguments string produced by the model.
+    pub async fn call(&self, name: &str, arguments: &str) -> Result<String, OpenApiError> {
+        let arguments: Value = if arguments.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(arguments)?
+        };
+        let res = self.request(name, &arguments)?.send().await?;
+        let status = res.status();
+        let body = res.text().await?;
+        if status.is_success() {
+            Ok(body)
+        } else {
+            Err(OpenApiError::Http {
+                status: status.as_u16(),
+                body,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn spec() -> Value {
+        json!({
+            "openapi": "3.0.0",
+            "servers": [{"url": "https://pets.example.com/v1/"}],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [{"name": "petId", "in": "path", "schema": {"type": "string"}}],
+                    "get": {
+                        "operationId": "getPet",
+                        "summary": "Fetch a pet",
+                        "parameters": [{"name": "verbose", "in": "query", "schema": {"type": "boolean"}}]
+                    }
+                },
+                "/pets": {
+                    "post": {
+                        "operationId": "create pet",
+                        "requestBody": {
+                            "required": true,
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}}
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_tools_from_spec() {
+        let toolset = OpenApiToolset::from_value(spec()).unwrap();
+        let tools = serde_json::to_value(toolset.tools()).unwrap();
+        assert_eq!(
+            tools,
+            json!([
+                {
+                    "type": "function",
+                    "function": {
+                        "name": "create_pet",
+                        "parameters": {
+                            "type": "object",
+                            "properties": {
+                                "body": {"type": "object", "properties": {"name": {"type": "string"}}}
+                            },
+                            "required": ["body"]
+                        }
+                    }
+                },
+                {
+                    "type": "function",
+                    "function": {
+                        "name": "getPet",
+                        "description": "Fetch a pet",
+                        "parameters": {
+                            "type": "object",
+                            "properties": {
+                                "petId": {"type": "string"},
+                                "verbose": {"type": "boolean"}
+                            },
+                            "required": ["petId"]
+                        }
+                    }
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_request_building() {
+        let toolset = OpenApiToolset::from_value(spec()).unwrap();
+        let req = toolset
+            .request("getPet", &json!({"petId": "a b", "verbose": true}))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.method(), Method::GET);
+        assert_eq!(
+            req.url().as_str(),
+            "https://pets.example.com/v1/pets/a%20b?verbose=true"
+        );
+        assert!(matches!(
+            toolset.request("getPet", &json!({})),
+            Err(OpenApiError::MissingParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,483 @@
+//! Durable retry queue for non-interactive requests.
+//!
+//! Requests that failed with a transient error (network failure, rate limit, server error) can be
+//! stored in an [`Outbox`] backed by a JSON file and retried later, including after the process
+//! has been restarted. An [`OutboxDriver`] periodically drains the queue and hands successful
+//! responses to a user supplied callback.
+//!
+//! Embeddings and batch submissions queue themselves through
+//! [`EmbeddingRequest::send_or_enqueue`](crate::embeddings::EmbeddingRequest::send_or_enqueue) and
+//! [`CreateBatchRequest::send_or_enqueue`](crate::batch::CreateBatchRequest::send_or_enqueue).
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{auth::Authorize, retry::SendRetrying, ApiRequestError, OpenAi};
+
+#[derive(Debug, Error)]
+pub enum OutboxError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+    #[error(transparent)]
+    Api(#[from] ApiRequestError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Failed,
+}
+
+/// A single stored request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: u64,
+    /// Optional caller supplied key used to correlate the eventual response.
+    pub key: Option<String>,
+    /// Endpoint path relative to the API base url, e.g. `v1/embeddings`.
+    pub endpoint: String,
+    pub body: Value,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: u64,
+    pub status: OutboxStatus,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct OutboxState {
+    next_id: u64,
+    entries: Vec<OutboxEntry>,
+}
+
+/// File backed queue of requests waiting to be retried.
+///
+/// Every mutation is written to disk, on tokio's blocking threads, before it returns and only
+/// takes effect once the write succeeded, so the queue survives process restarts and never
+/// holds changes the file lacks. Cloning an `Outbox` yields a handle to the same underlying
+/// queue.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    path: Arc<PathBuf>,
+    state: Arc<Mutex<OutboxState>>,
+    /// Held while a change is written, so changes are applied one at a time.
+    write: Arc<Mutex<()>>,
+}
+
+/// Result of a single pass over the queue.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrainReport {
+    pub succeeded: usize,
+    pub retried: usize,
+    pub failed: usize,
+}
+
+enum Attempt {
+    Success(Value),
+    Retry(String),
+    Fail(String),
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl Outbox {
+    /// Opens the queue stored at `path`, creating an empty one if the file does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, OutboxError> {
+        let path = path.as_ref().to_path_buf();
+        let state = match fs::read(&path) {
+            Ok(bytes) if !bytes.is_empty() => serde_json::from_slice(&bytes)?,
+            Ok(_) => OutboxState::default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => OutboxState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Arc::new(path),
+            state: Arc::new(Mutex::new(state)),
+            write: Arc::default(),
+        })
+    }
+
+    fn persist(&self, state: &OutboxState) -> Result<(), OutboxError> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec(state)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.path.as_ref())?;
+        Ok(())
+    }
+
+    /// Applies `f` to a copy of the queue, writes it and only then replaces the queue with it.
+    async fn update<T, F>(&self, f: F) -> Result<T, OutboxError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut OutboxState) -> T + Send + 'static,
+    {
+        let outbox = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let _write = outbox.write.lock().unwrap_or_else(|e| e.into_inner());
+            let mut state = outbox.lock().clone();
+            let out = f(&mut state);
+            outbox.persist(&state)?;
+            *outbox.lock() = state;
+            Ok(out)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, OutboxState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Stores a request body for `endpoint` and returns the id of the new entry.
+    pub async fn enqueue(
+        &self,
+        endpoint: impl Into<String>,
+        body: Value,
+        key: Option<String>,
+    ) -> Result<u64, OutboxError> {
+        let endpoint = endpoint.into();
+        self.update(|state| {
+            let id = state.next_id;
+            state.next_id += 1;
+            state.entries.push(OutboxEntry {
+                id,
+                key,
+                endpoint,
+                body,
+                attempts: 0,
+                last_error: None,
+                created_at: now(),
+                status: OutboxStatus::Pending,
+            });
+            id
+        })
+        .await
+    }
+
+    /// Removes an entry regardless of its status.
+    pub async fn remove(&self, id: u64) -> Result<Option<OutboxEntry>, OutboxError> {
+        self.update(move |state| {
+            let pos = state.entries.iter().position(|e| e.id == id)?;
+            Some(state.entries.remove(pos))
+        })
+        .await
+    }
+
+    pub fn entries(&self) -> Vec<OutboxEntry> {
+        self.lock().entries.clone()
+    }
+
+    pub fn pending(&self) -> Vec<OutboxEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|e| e.status == OutboxStatus::Pending)
+            .collect()
+    }
+
+    /// Entries that exhausted their attempts or were rejected by the API.
+    pub fn failed(&self) -> Vec<OutboxEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|e| e.status == OutboxStatus::Failed)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn attempt(openai: &OpenAi, entry: &OutboxEntry) -> Attempt {
+        // Routed like the request was when it was sent, to its deployment with Azure.
+        let url = match entry.body["model"].as_str() {
+            Some(model) => openai.inner.model_url(&entry.endpoint, model),
+            None => format!("{}/{}", openai.inner.base_url, entry.endpoint),
+        };
+        let res = match openai.inner.client.post(url).authorize(openai).await {
+            Ok(request) => request.json(&entry.body).send_observed(openai).await,
+            // Says nothing about the entry, the key source may be back on the next pass.
+            Err(e) => return Attempt::Retry(e.to_string()),
+        };
+        let error = match res {
+            Ok(res) if res.status().is_success() => {
+                return match res.json::<Value>().await {
+                    Ok(data) => Attempt::Success(data),
+                    Err(e) => Attempt::Retry(e.to_string()),
+                };
+            }
+            Ok(res) => ApiRequestError::from_response(res).await,
+            Err(e) => e.into(),
+        };
+        if error.is_retryable() {
+            Attempt::Retry(error.to_string())
+        } else {
+            Attempt::Fail(error.to_string())
+        }
+    }
+
+    /// Makes one attempt for every pending entry.
+    ///
+    /// Successful entries are removed from the queue and passed to `on_success` together with the
+    /// raw JSON response. Entries rejected by the API, or that reached `max_attempts`, are marked
+    /// as [`OutboxStatus::Failed`] and kept for inspection.
+    pub async fn drain<F>(
+        &self,
+        openai: &OpenAi,
+        max_attempts: u32,
+        mut on_success: F,
+    ) -> Result<DrainReport, OutboxError>
+    where
+        F: FnMut(&OutboxEntry, Value),
+    {
+        let mut report = DrainReport::default();
+        for entry in self.pending() {
+            let (error, fatal) = match Self::attempt(openai, &entry).await {
+                Attempt::Success(data) => {
+                    self.remove(entry.id).await?;
+                    on_success(&entry, data);
+                    report.succeeded += 1;
+                    continue;
+                }
+                Attempt::Retry(error) => (error, false),
+                Attempt::Fail(error) => (error, true),
+            };
+            let id = entry.id;
+            let failed = self
+                .update(move |state| {
+                    let Some(e) = state.entries.iter_mut().find(|e| e.id == id) else {
+                        return false;
+                    };
+                    e.attempts += 1;
+                    e.last_error = Some(error);
+                    if fatal || e.attempts >= max_attempts {
+                        e.status = OutboxStatus::Failed;
+                    }
+                    e.status == OutboxStatus::Failed
+                })
+                .await?;
+            if failed {
+                report.failed += 1;
+            } else {
+                report.retried += 1;
+            }
+        }
+        Ok(report)
+    }
+}
+
+type SuccessHandler = Arc<dyn Fn(&OutboxEntry, Value) + Send + Sync>;
+type ErrorHandler = Arc<dyn Fn(&OutboxError) + Send + Sync>;
+
+/// Background task that periodically drains an [`Outbox`].
+#[derive(Builder)]
+pub struct OutboxDriver {
+    outbox: Outbox,
+    openai: OpenAi,
+    #[builder(default = Duration::from_secs(30))]
+    interval: Duration,
+    #[builder(default = 5)]
+    max_attempts: u32,
+    #[builder(with = |f: impl Fn(&OutboxEntry, Value) + Send + Sync + 'static| Arc::new(f))]
+    on_success: Option<SuccessHandler>,
+    /// Called with the error of a failed pass, after which the driver keeps going.
+    #[builder(with = |f: impl Fn(&OutboxError) + Send + Sync + 'static| Arc::new(f))]
+    on_error: Option<ErrorHandler>,
+}
+
+impl OutboxDriver {
+    /// Runs a single drain pass.
+    pub async fn tick(&self) -> Result<DrainReport, OutboxError> {
+        let handler = self.on_success.clone();
+        self.outbox
+            .drain(&self.openai, self.max_attempts, |entry, data| {
+                if let Some(handler) = handler.as_ref() {
+                    handler(entry, data);
+                }
+            })
+            .await
+    }
+
+    /// Drains the queue forever, sleeping `interval` between passes.
+    ///
+    /// A failed pass, e.g. the queue file could not be written, is passed to `on_error`. Without
+    /// that handler the driver stops and returns the error.
+    pub async fn run(self) -> OutboxError {
+        loop {
+            if let Err(e) = self.tick().await {
+                match self.on_error.as_ref() {
+                    Some(handler) => handler(&e),
+                    None => return e,
+                }
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Spawns [`OutboxDriver::run`] on the current tokio runtime.
+    pub fn spawn(self) -> tokio::task::JoinHandle<OutboxError> {
+        tokio::spawn(self.run())
+    }
+}
+
+impl OpenAi {
+    pub fn outbox_driver(
+        &self,
+        outbox: Outbox,
+    ) -> OutboxDriverBuilder<outbox_driver_builder::SetOpenai<outbox_driver_builder::SetOutbox>>
+    {
+        OutboxDriver::builder().outbox(outbox).openai(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("openai-ox-{}-{}.json", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_outbox_survives_reopen() {
+        let path = temp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let outbox = Outbox::open(&path).unwrap();
+        let id = outbox
+            .enqueue(
+                "v1/embeddings",
+                json!({"input": ["hi"]}),
+                Some("doc-1".into()),
+            )
+            .await
+            .unwrap();
+        outbox
+            .enqueue("v1/embeddings", json!({}), None)
+            .await
+            .unwrap();
+        drop(outbox);
+
+        let outbox = Outbox::open(&path).unwrap();
+        assert_eq!(outbox.len(), 2);
+        assert_eq!(outbox.pending()[0].key.as_deref(), Some("doc-1"));
+
+        outbox.remove(id).await.unwrap();
+        let outbox = Outbox::open(&path).unwrap();
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(
+            outbox
+                .enqueue("v1/embeddings", json!({}), None)
+                .await
+                .unwrap(),
+            2
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_keeps_queue() {
+        let path = temp_path("missing-dir").join("outbox.json");
+        let outbox = Outbox::open(&path).unwrap();
+        let res = outbox.enqueue("v1/embeddings", json!({}), None).await;
+        assert!(matches!(res, Err(OutboxError::Io(_))));
+        assert!(outbox.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_driver_reports_failed_passes() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = temp_path("driver");
+        fs::create_dir_all(&dir).unwrap();
+        let outbox = Outbox::open(dir.join("outbox.json")).unwrap();
+        outbox
+            .enqueue("v1/embeddings", json!({"input": ["hi"]}), None)
+            .await
+            .unwrap();
+        // Recording the attempt fails once the queue file has nowhere to go.
+        fs::remove_dir_all(&dir).unwrap();
+        let openai = OpenAi::builder()
+            .api_key("sk-test")
+            .base_url("http://127.0.0.1:1")
+            .build();
+
+        let driver = openai
+            .outbox_driver(outbox.clone())
+            .interval(Duration::ZERO)
+            .build();
+        assert!(matches!(driver.run().await, OutboxError::Io(_)));
+
+        let errors = Arc::new(AtomicUsize::new(0));
+        let counter = errors.clone();
+        let handle = openai
+            .outbox_driver(outbox)
+            .interval(Duration::ZERO)
+            .on_error(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .spawn();
+        while errors.load(Ordering::SeqCst) < 2 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!handle.is_finished());
+        handle.abort();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_drain_keeps_only_retryable_entries() {
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let path = temp_path("drain");
+        let _ = fs::remove_file(&path);
+        let mock = MockOpenAi::start().await.unwrap();
+        mock.push(
+            "v1/embeddings",
+            MockResponse::error(429, "insufficient_quota", "You exceeded your current quota"),
+        )
+        .push(
+            "v1/embeddings",
+            MockResponse::error(503, "overloaded", "Try again"),
+        );
+
+        let outbox = Outbox::open(&path).unwrap();
+        for key in ["quota", "overloaded"] {
+            outbox
+                .enqueue("v1/embeddings", json!({"input": ["hi"]}), Some(key.into()))
+                .await
+                .unwrap();
+        }
+        let report = outbox.drain(&mock.client(), 5, |_, _| {}).await.unwrap();
+        assert_eq!((report.failed, report.retried), (1, 1));
+        let pending = outbox.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].key.as_deref(), Some("overloaded"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
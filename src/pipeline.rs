@@ -0,0 +1,223 @@
+//! Composable multi-step LLM workflows.
+//!
+//! A [`Pipeline`] is a lazily evaluated chain of steps sharing one client. Every chat step records
+//! its token usage, errors short-circuit the remaining steps, and nothing is sent until
+//! [`Pipeline::run`] is awaited.
+//!
+//! ```no_run
+//! # use openai_ox::{chat::message::Message, OpenAi};
+//! # async fn example(openai: OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! #[derive(serde::Deserialize)]
+//! struct Outline {
+//!     sections: Vec<String>,
+//! }
+//!
+//! let output = openai
+//!     .pipeline("gpt-4o", Message::user("Outline an essay about rust as JSON {\"sections\": []}"))
+//!     .then_parse::<Outline>()
+//!     .then_chat("gpt-4o", |outline| {
+//!         Message::user(format!("Write the intro for: {}", outline.sections.join(", ")))
+//!     })
+//!     .run()
+//!     .await?;
+//! println!("{} ({} steps)", output.value, output.steps.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    chat::{message::Messages, Usage},
+    ApiRequestError, OpenAi,
+};
+
+/// Token usage recorded for one chat step of a pipeline.
+#[derive(Debug, Clone)]
+pub struct StepUsage {
+    pub step: usize,
+    pub model: String,
+    pub usage: Usage,
+}
+
+#[derive(Debug)]
+pub struct PipelineOutput<T> {
+    pub value: T,
+    pub steps: Vec<StepUsage>,
+}
+
+impl<T> PipelineOutput<T> {
+    /// Usage summed over every chat step.
+    pub fn total_usage(&self) -> Usage {
+        self.steps.iter().map(|step| step.usage).sum()
+    }
+}
+
+type StepFuture<T> = BoxFuture<'static, Result<(T, Vec<StepUsage>), ApiRequestError>>;
+
+pub struct Pipeline<T> {
+    openai: OpenAi,
+    run: Box<dyn FnOnce(OpenAi) -> StepFuture<T> + Send>,
+}
+
+async fn chat_step(
+    openai: &OpenAi,
+    model: String,
+    messages: Messages,
+    steps: &mut Vec<StepUsage>,
+) -> Result<String, ApiRequestError> {
+    let res = openai
+        .chat_completion()
+        .model(model.clone())
+        .messages(messages)
+        .build()?
+        .send()
+        .await?;
+    steps.push(StepUsage {
+        step: steps.len(),
+        model,
+        usage: res.usage,
+    });
+    res.choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content().map(ToOwned::to_owned))
+        .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+            status: None,
+            response: "chat step returned no content".to_string(),
+        })
+}
+
+/// Strips a surrounding markdown code fence, which models like to add around JSON.
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    let Some(inner) = text.strip_prefix("```") else {
+        return text;
+    };
+    let inner = inner.strip_suffix("```").unwrap_or(inner);
+    match inner.split_once('\n') {
+        Some((lang, body)) if !lang.trim().contains(char::is_whitespace) => body.trim(),
+        _ => inner.trim(),
+    }
+}
+
+impl<T: Send + 'static> Pipeline<T> {
+    /// Starts a pipeline from an already known value.
+    pub fn start(openai: &OpenAi, value: T) -> Self {
+        Pipeline {
+            openai: openai.clone(),
+            run: Box::new(move |_| Box::pin(async move { Ok((value, Vec::new())) })),
+        }
+    }
+
+    /// Transforms the output of the previous step without calling the API.
+    pub fn then<U, F>(self, f: F) -> Pipeline<U>
+    where
+        U: Send + 'static,
+        F: FnOnce(T) -> Result<U, ApiRequestError> + Send + 'static,
+    {
+        let run = self.run;
+        Pipeline {
+            openai: self.openai,
+            run: Box::new(move |openai| {
+                Box::pin(async move {
+                    let (value, steps) = run(openai).await?;
+                    Ok((f(value)?, steps))
+                })
+            }),
+        }
+    }
+
+    /// Sends the messages built from the previous output to `model` and yields the reply text.
+    pub fn then_chat<F, M>(self, model: impl Into<String>, f: F) -> Pipeline<String>
+    where
+        F: FnOnce(T) -> M + Send + 'static,
+        M: Into<Messages>,
+    {
+        let run = self.run;
+        let model = model.into();
+        Pipeline {
+            openai: self.openai,
+            run: Box::new(move |openai| {
+                Box::pin(async move {
+                    let (value, mut steps) = run(openai.clone()).await?;
+                    let text = chat_step(&openai, model, f(value).into(), &mut steps).await?;
+                    Ok((text, steps))
+                })
+            }),
+        }
+    }
+
+    /// Executes every step in order.
+    pub async fn run(self) -> Result<PipelineOutput<T>, ApiRequestError> {
+        let (value, steps) = (self.run)(self.openai).await?;
+        Ok(PipelineOutput { value, steps })
+    }
+}
+
+impl Pipeline<String> {
+    /// Starts a pipeline with a chat step.
+    pub fn chat(openai: &OpenAi, model: impl Into<String>, messages: impl Into<Messages>) -> Self {
+        let messages = messages.into();
+        Pipeline::start(openai, ()).then_chat(model, move |_| messages)
+    }
+
+    /// Parses the previous reply as JSON, tolerating a surrounding markdown code fence.
+    pub fn then_parse<U>(self) -> Pipeline<U>
+    where
+        U: DeserializeOwned + Send + 'static,
+    {
+        self.then(|text| Ok(serde_json::from_str(strip_code_fence(&text))?))
+    }
+}
+
+impl OpenAi {
+    pub fn pipeline(
+        &self,
+        model: impl Into<String>,
+        messages: impl Into<Messages>,
+    ) -> Pipeline<String> {
+        Pipeline::chat(self, model, messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn test_strip_code_fence() {
+        assert_eq!(strip_code_fence("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(strip_code_fence("```\n[1]\n```"), "[1]");
+        assert_eq!(strip_code_fence(" {} "), "{}");
+    }
+
+    #[tokio::test]
+    async fn test_offline_steps() {
+        let openai = OpenAi::builder().api_key(String::new()).build();
+        let output = Pipeline::start(&openai, "```json\n{\"x\": 1, \"y\": 2}\n```".to_string())
+            .then_parse::<Point>()
+            .then(|p| Ok(p.x + p.y))
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(output.value, 3);
+        assert!(output.steps.is_empty());
+
+        let err = Pipeline::start(&openai, "not json".to_string())
+            .then_parse::<Point>()
+            .run()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApiRequestError::SerdeError(_)));
+    }
+}
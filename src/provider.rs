@@ -0,0 +1,225 @@
+//! Provider agnostic traits shared with sibling `*-ox` crates.
+//!
+//! Code written against [`ChatProvider`] and [`EmbeddingProvider`] can switch between
+//! `openai-ox`, `anthropic-ox` and other implementations without touching call sites.
+//!
+//! ```no_run
+//! use openai_ox::provider::{AnyRoleMessage, ChatMessage, ChatProvider};
+//!
+//! async fn ask<P: ChatProvider>(provider: &P, model: &str, question: &str) -> Option<String> {
+//!     let messages = vec![P::Message::user(question)];
+//!     let reply = provider.chat(model, messages).await.ok()?;
+//!     reply.content().map(ToOwned::to_owned)
+//! }
+//! ```
+
+use crate::{
+    chat::message::{
+        AssistantMessage, DeveloperMessage, Message, Role, SystemMessage, ToolMessage, UserMessage,
+    },
+    ApiRequestError, OpenAi,
+};
+
+/// Minimal view of a chat message that every provider can offer.
+///
+/// Implemented by [`Message`] and by each role's message type, so content is read and changed
+/// the same way whatever the role.
+pub trait ChatMessage {
+    fn role(&self) -> Role;
+    fn content(&self) -> Option<&str>;
+    /// Replaces the text of the message.
+    fn set_content(&mut self, content: impl Into<String>);
+    /// Appends `text` as is, starting the content if the message has none.
+    fn push_content(&mut self, text: &str);
+}
+
+/// A message type that can hold a turn of any role, as conversation histories do.
+pub trait AnyRoleMessage: ChatMessage + Sized {
+    fn system(content: impl Into<String>) -> Self;
+    fn user(content: impl Into<String>) -> Self;
+    fn assistant(content: impl Into<String>) -> Self;
+}
+
+impl ChatMessage for Message {
+    fn role(&self) -> Role {
+        Message::role(self)
+    }
+    fn content(&self) -> Option<&str> {
+        Message::content(self)
+    }
+    fn set_content(&mut self, content: impl Into<String>) {
+        Message::set_content(self, content)
+    }
+    fn push_content(&mut self, text: &str) {
+        Message::push_content(self, text)
+    }
+}
+
+impl AnyRoleMessage for Message {
+    fn system(content: impl Into<String>) -> Self {
+        Message::system(content)
+    }
+    fn user(content: impl Into<String>) -> Self {
+        Message::user(content)
+    }
+    fn assistant(content: impl Into<String>) -> Self {
+        Message::assistant(content)
+    }
+}
+
+macro_rules! text_message {
+    ($($ty:ty => $role:ident),+) => {
+        $(impl ChatMessage for $ty {
+            fn role(&self) -> Role {
+                Role::$role
+            }
+            fn content(&self) -> Option<&str> {
+                Some(&self.content)
+            }
+            fn set_content(&mut self, content: impl Into<String>) {
+                self.content = content.into();
+            }
+            fn push_content(&mut self, text: &str) {
+                self.content.push_str(text);
+            }
+        })+
+    };
+}
+
+text_message!(
+    SystemMessage => System,
+    DeveloperMessage => Developer,
+    UserMessage => User,
+    ToolMessage => Tool
+);
+
+impl ChatMessage for AssistantMessage {
+    fn role(&self) -> Role {
+        Role::Assistant
+    }
+    fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+    fn set_content(&mut self, content: impl Into<String>) {
+        self.content = Some(content.into());
+    }
+    fn push_content(&mut self, text: &str) {
+        self.content.get_or_insert_with(String::new).push_str(text);
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ChatProvider: Send + Sync {
+    type Message: AnyRoleMessage + Send + Sync;
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Sends the conversation to `model` and returns the assistant reply.
+    async fn chat(
+        &self,
+        model: &str,
+        messages: Vec<Self::Message>,
+    ) -> Result<Self::Message, Self::Error>;
+}
+
+#[async_trait::async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Embeds every input and returns the vectors in input order.
+    async fn embed(&self, model: &str, input: Vec<String>) -> Result<Vec<Vec<f32>>, Self::Error>;
+}
+
+#[async_trait::async_trait]
+impl ChatProvider for OpenAi {
+    type Message = Message;
+    type Error = ApiRequestError;
+
+    async fn chat(&self, model: &str, messages: Vec<Message>) -> Result<Message, ApiRequestError> {
+        let res = self
+            .chat_completion()
+            .model(model)
+            .messages(crate::chat::message::Messages(messages))
+            .build()?
+            .send()
+            .await?;
+        res.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| ApiRequestError::UnexpectedResponse {
+                status: None,
+                response: "response contains no choices".to_string(),
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for OpenAi {
+    type Error = ApiRequestError;
+
+    async fn embed(
+        &self,
+        model: &str,
+        input: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ApiRequestError> {
+        let mut res = self
+            .embeddings()
+            .model(model)
+            .input(input)
+            .build()
+            .send()
+            .await?;
+        res.data.sort_by_key(|data| data.index);
+        Ok(res.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnyRoleMessage, ChatMessage};
+    use crate::chat::message::{AssistantMessage, Message, Role, ToolMessage};
+
+    fn roles<M: ChatMessage>(messages: &[M]) -> Vec<Role> {
+        messages.iter().map(ChatMessage::role).collect()
+    }
+
+    #[test]
+    fn test_generic_message_construction() {
+        let messages = vec![
+            <Message as AnyRoleMessage>::system("Be brief."),
+            <Message as AnyRoleMessage>::user("Hi"),
+            <Message as AnyRoleMessage>::assistant("Hello"),
+        ];
+        assert_eq!(
+            roles(&messages),
+            vec![Role::System, Role::User, Role::Assistant]
+        );
+        assert_eq!(ChatMessage::content(&messages[1]), Some("Hi"));
+    }
+
+    #[test]
+    fn test_content_mutation() {
+        fn stream_into<M: ChatMessage>(message: &mut M) {
+            for delta in ["Hel", "lo"] {
+                message.push_content(delta);
+            }
+        }
+
+        let mut reply = AssistantMessage::builder().build();
+        stream_into(&mut reply);
+        assert_eq!(ChatMessage::content(&reply), Some("Hello"));
+
+        let mut result = ToolMessage::builder()
+            .content("42")
+            .tool_call_id("call_1".to_string())
+            .build();
+        stream_into(&mut result);
+        assert_eq!(ChatMessage::content(&result), Some("42Hello"));
+
+        let mut messages = [Message::system("Be "), Message::user("")];
+        messages.iter_mut().for_each(stream_into);
+        assert_eq!(messages[0].content(), Some("Be Hello"));
+        messages[1].set_content("Hi");
+        assert_eq!(messages[1].content(), Some("Hi"));
+    }
+}
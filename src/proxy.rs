@@ -0,0 +1,133 @@
+//! Proxy settings for the HTTP client built by `OpenAi::builder()`.
+//!
+//! ```no_run
+//! # fn example() -> Result<(), openai_ox::proxy::ProxyError> {
+//! use openai_ox::{proxy::ProxyConfig, OpenAi};
+//!
+//! let proxy = ProxyConfig::builder()
+//!     .url("http://proxy.corp.example:3128")
+//!     .no_proxy(vec!["localhost".to_string(), ".corp.example".to_string()])
+//!     .username("alice")
+//!     .password("secret")
+//!     .build()?;
+//! let openai = OpenAi::builder().api_key("sk-...").proxy(proxy).build();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use bon::Builder;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ProxyError {
+    #[error("Invalid proxy URL {url}: {source}")]
+    InvalidUrl { url: String, source: reqwest::Error },
+    #[error("Proxy password given without a username")]
+    PasswordWithoutUsername,
+}
+
+/// Which requests go through the proxy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProxyScope {
+    #[default]
+    All,
+    Http,
+    Https,
+}
+
+/// Proxy for every request of the client.
+///
+/// `url` may use the `http`, `https` or, with the `socks` feature, `socks5` and `socks5h` schemes.
+#[derive(Clone, Builder)]
+#[builder(finish_fn(name = build_unchecked, vis = ""))]
+pub struct ProxyConfig {
+    #[builder(into)]
+    pub url: String,
+    #[builder(default)]
+    pub scope: ProxyScope,
+    /// Hosts, domains (`.example.com`) or CIDR ranges reached directly.
+    #[builder(default)]
+    pub no_proxy: Vec<String>,
+    #[builder(into)]
+    pub username: Option<String>,
+    #[builder(into)]
+    pub password: Option<String>,
+}
+
+impl<S: proxy_config_builder::IsComplete> ProxyConfigBuilder<S> {
+    pub fn build(self) -> Result<ProxyConfig, ProxyError> {
+        let config = self.build_unchecked();
+        if config.password.is_some() && config.username.is_none() {
+            return Err(ProxyError::PasswordWithoutUsername);
+        }
+        config.to_proxy()?;
+        Ok(config)
+    }
+}
+
+impl fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("url", &self.url)
+            .field("scope", &self.scope)
+            .field("no_proxy", &self.no_proxy)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "[REDACTED]"))
+            .finish()
+    }
+}
+
+impl ProxyConfig {
+    pub(crate) fn to_proxy(&self) -> Result<reqwest::Proxy, ProxyError> {
+        let proxy = match self.scope {
+            ProxyScope::All => reqwest::Proxy::all(&self.url),
+            ProxyScope::Http => reqwest::Proxy::http(&self.url),
+            ProxyScope::Https => reqwest::Proxy::https(&self.url),
+        }
+        .map_err(|source| ProxyError::InvalidUrl {
+            url: self.url.clone(),
+            source,
+        })?;
+        let proxy = match &self.username {
+            Some(username) => {
+                proxy.basic_auth(username, self.password.as_deref().unwrap_or_default())
+            }
+            None => proxy,
+        };
+        Ok(proxy.no_proxy(reqwest::NoProxy::from_string(&self.no_proxy.join(","))))
+    }
+
+    /// Routes every request of the client being built through this proxy.
+    pub(crate) fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        builder.proxy(self.to_proxy().expect("proxy config is validated on build"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_validation() {
+        let proxy = ProxyConfig::builder()
+            .url("http://proxy.local:3128")
+            .no_proxy(vec!["localhost".to_string()])
+            .username("alice")
+            .password("secret")
+            .build()
+            .unwrap();
+        assert_eq!(proxy.scope, ProxyScope::All);
+
+        let err = ProxyConfig::builder().url("not a url").build().unwrap_err();
+        assert!(matches!(err, ProxyError::InvalidUrl { .. }));
+
+        let err = ProxyConfig::builder()
+            .url("http://proxy.local:3128")
+            .password("secret")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, ProxyError::PasswordWithoutUsername));
+    }
+}
@@ -0,0 +1,175 @@
+//! Rate limit state reported in the `x-ratelimit-*` response headers.
+//!
+//! Register a callback with [`OpenAiBuilder::on_rate_limit`](crate::OpenAiBuilder::on_rate_limit)
+//! to see the state after every response, e.g. to slow down before hitting the limit instead of
+//! after.
+//!
+//! ```
+//! use openai_ox::OpenAi;
+//!
+//! let openai = OpenAi::builder()
+//!     .api_key("sk-...")
+//!     .on_rate_limit(|info| {
+//!         if info.remaining_tokens.is_some_and(|tokens| tokens < 1_000) {
+//!             eprintln!("token budget nearly spent, resets in {:?}", info.reset_tokens);
+//!         }
+//!     })
+//!     .build();
+//! ```
+//!
+//! With the `leaky-bucket` feature the client can also wait before sending instead: every
+//! request takes a permit of `leaky_bucket`, and chat, completion and embedding requests take
+//! their estimated token count from `token_bucket`, counted with the model's tokenizer when the
+//! `tokenizer` feature is enabled.
+//!
+//! ```
+//! # #[cfg(feature = "leaky-bucket")]
+//! # {
+//! use std::{sync::Arc, time::Duration};
+//!
+//! use openai_ox::{OpenAi, RateLimiter};
+//!
+//! let openai = OpenAi::builder()
+//!     .api_key("sk-...")
+//!     .leaky_bucket(Arc::new(
+//!         RateLimiter::builder().max(500).refill(500).interval(Duration::from_secs(60)).build(),
+//!     ))
+//!     .token_bucket(Arc::new(
+//!         RateLimiter::builder()
+//!             .max(30_000)
+//!             .initial(30_000)
+//!             .refill(500)
+//!             .interval(Duration::from_secs(1))
+//!             .build(),
+//!     ))
+//!     .build();
+//! # }
+//! ```
+
+use std::{sync::Arc, time::Duration};
+
+use reqwest::header::HeaderMap;
+
+pub(crate) type RateLimitCallback = Arc<dyn Fn(&RateLimitInfo) + Send + Sync>;
+
+/// Limits of the organization for the model a request went to, and how much of them is left.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    pub limit_requests: Option<u64>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// Time until the request limit is back to full.
+    pub reset_requests: Option<Duration>,
+    /// Time until the token limit is back to full.
+    pub reset_tokens: Option<Duration>,
+}
+
+impl RateLimitInfo {
+    /// Reads the `x-ratelimit-*` headers, `None` when the response carries none of them, as with
+    /// most servers other than OpenAI.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok();
+        let number = |name: &str| header(name)?.trim().parse().ok();
+        let duration = |name: &str| parse_reset(header(name)?);
+        let info = RateLimitInfo {
+            limit_requests: number("x-ratelimit-limit-requests"),
+            limit_tokens: number("x-ratelimit-limit-tokens"),
+            remaining_requests: number("x-ratelimit-remaining-requests"),
+            remaining_tokens: number("x-ratelimit-remaining-tokens"),
+            reset_requests: duration("x-ratelimit-reset-requests"),
+            reset_tokens: duration("x-ratelimit-reset-tokens"),
+        };
+        (info != RateLimitInfo::default()).then_some(info)
+    }
+}
+
+/// Tokens `text` encodes to with the tokenizer of `model`.
+#[cfg(feature = "tokenizer")]
+pub(crate) fn estimate_tokens(model: &str, text: &str) -> usize {
+    crate::tokenizer::Tokenizer::for_model(model).count(text)
+}
+
+/// Without a tokenizer, see [`approximate_tokens`].
+#[cfg(not(feature = "tokenizer"))]
+pub(crate) fn estimate_tokens(_model: &str, text: &str) -> usize {
+    approximate_tokens(text)
+}
+
+/// Token count of `text` guessed from its length, for when no tokenizer is at hand: a token per
+/// three bytes, more than English text averages, so budgets and limits err on the safe side.
+pub fn approximate_tokens(text: &str) -> usize {
+    text.len().div_ceil(3)
+}
+
+/// Parses reset times in the Go duration format the API uses, e.g. `1s`, `6m0s` or `120ms`.
+fn parse_reset(value: &str) -> Option<Duration> {
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut nanos = 0;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let unit_nanos = match unit {
+            "h" => 3_600e9,
+            "m" => 60e9,
+            "s" => 1e9,
+            "ms" => 1e6,
+            "us" | "µs" => 1e3,
+            "ns" => 1.0,
+            _ => return None,
+        };
+        nanos += (number * unit_nanos).round() as u64;
+        rest = tail;
+    }
+    Some(Duration::from_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_headers() {
+        assert_eq!(parse_reset("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_reset("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(
+            parse_reset("1h2m3.5s"),
+            Some(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(parse_reset("120ms"), Some(Duration::from_millis(120)));
+        assert_eq!(parse_reset("soon"), None);
+
+        assert_eq!(RateLimitInfo::from_headers(&HeaderMap::new()), None);
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("x-ratelimit-limit-requests", "5000"),
+            ("x-ratelimit-remaining-requests", "4999"),
+            ("x-ratelimit-remaining-tokens", "159976"),
+            ("x-ratelimit-reset-tokens", "9ms"),
+        ] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+        let info = RateLimitInfo::from_headers(&headers).unwrap();
+        assert_eq!(info.limit_requests, Some(5000));
+        assert_eq!(info.remaining_requests, Some(4999));
+        assert_eq!(info.remaining_tokens, Some(159976));
+        assert_eq!(info.limit_tokens, None);
+        assert_eq!(info.reset_tokens, Some(Duration::from_millis(9)));
+
+        assert_eq!(estimate_tokens("gpt-4o", ""), 0);
+        assert!(estimate_tokens("gpt-4o", "Hello world") > 0);
+        assert_eq!(approximate_tokens("Hello world"), 4);
+    }
+}
@@ -0,0 +1,141 @@
+//! WebSocket connection to `wss://api.openai.com/v1/realtime`.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::realtime::{ClientEvent, ServerEvent, SessionConfig};
+//!
+//! let mut conn = openai.realtime("gpt-4o-realtime-preview").await?;
+//! conn.update_session(SessionConfig::builder().instructions("Be brief.").build())
+//!     .await?;
+//! conn.send_all([ClientEvent::user_text("Hello!"), ClientEvent::response_create()])
+//!     .await?;
+//! while let Some(event) = conn.next_event().await {
+//!     match event? {
+//!         ServerEvent::ResponseTextDelta { delta, .. } => print!("{}", delta),
+//!         ServerEvent::ResponseDone { .. } => break,
+//!         _ => {}
+//!     }
+//! }
+//! conn.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest, http::HeaderValue, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use super::{ClientEvent, ServerEvent, SessionConfig};
+use crate::{auth::Authorize, ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/realtime";
+
+fn realtime_url(base_url: &str, model: &str) -> String {
+    let base_url = match base_url.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some(("http", rest)) => format!("ws://{}", rest),
+        _ => base_url.to_string(),
+    };
+    format!("{}/{}?model={}", base_url, API_URL, model)
+}
+
+fn socket_error(e: tungstenite::Error) -> ApiRequestError {
+    ApiRequestError::Stream(e.to_string())
+}
+
+/// An open realtime session. Events are sent and received in order; the connection does not
+/// reconnect on its own.
+pub struct RealtimeConnection {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl std::fmt::Debug for RealtimeConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RealtimeConnection").finish_non_exhaustive()
+    }
+}
+
+impl RealtimeConnection {
+    pub async fn send(&mut self, event: &ClientEvent) -> Result<(), ApiRequestError> {
+        let text = serde_json::to_string(event)?;
+        self.socket
+            .send(Message::Text(text))
+            .await
+            .map_err(socket_error)
+    }
+
+    pub async fn send_all(
+        &mut self,
+        events: impl IntoIterator<Item = ClientEvent>,
+    ) -> Result<(), ApiRequestError> {
+        for event in events {
+            self.send(&event).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn update_session(&mut self, session: SessionConfig) -> Result<(), ApiRequestError> {
+        self.send(&ClientEvent::SessionUpdate { session }).await
+    }
+
+    /// Appends raw audio, in the session's input format, to the input buffer.
+    pub async fn append_audio(&mut self, audio: impl AsRef<[u8]>) -> Result<(), ApiRequestError> {
+        self.send(&ClientEvent::append_audio(audio)).await
+    }
+
+    /// The next server event, `None` once the server closed the connection. Pings are answered
+    /// by the underlying socket and never surface here.
+    pub async fn next_event(&mut self) -> Option<Result<ServerEvent, ApiRequestError>> {
+        loop {
+            match self.socket.next().await? {
+                Ok(Message::Text(text)) => {
+                    return Some(crate::json::from_str(&text).map_err(ApiRequestError::from))
+                }
+                Ok(Message::Close(_)) => return None,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(socket_error(e))),
+            }
+        }
+    }
+
+    pub async fn close(mut self) -> Result<(), ApiRequestError> {
+        self.socket.close(None).await.map_err(socket_error)
+    }
+}
+
+impl OpenAi {
+    /// Opens a realtime session with `model`, authenticated like every other request.
+    pub async fn realtime(&self, model: &str) -> Result<RealtimeConnection, ApiRequestError> {
+        let mut request = realtime_url(&self.inner.base_url, model)
+            .into_client_request()
+            .map_err(socket_error)?;
+        request
+            .headers_mut()
+            .insert("OpenAI-Beta", HeaderValue::from_static("realtime=v1"));
+        let (socket, _) = connect_async(request.authorize(self).await?)
+            .await
+            .map_err(socket_error)?;
+        Ok(RealtimeConnection { socket })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_realtime_url() {
+        assert_eq!(
+            realtime_url("https://api.openai.com", "gpt-4o-realtime-preview"),
+            "wss://api.openai.com/v1/realtime?model=gpt-4o-realtime-preview"
+        );
+        assert_eq!(
+            realtime_url("http://localhost:8080", "local"),
+            "ws://localhost:8080/v1/realtime?model=local"
+        );
+    }
+}
@@ -0,0 +1,495 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::session::SessionConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    InputText {
+        text: String,
+    },
+    InputAudio {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        audio: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transcript: Option<String>,
+    },
+    Text {
+        text: String,
+    },
+    Audio {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        audio: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transcript: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConversationItem {
+    Message {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        role: ItemRole,
+        #[serde(default)]
+        content: Vec<ContentPart>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    FunctionCall {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        call_id: String,
+        name: String,
+        #[serde(default)]
+        arguments: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    FunctionCallOutput {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        call_id: String,
+        output: String,
+    },
+}
+
+/// A completed function call requested by the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCallRequest {
+    pub call_id: String,
+    pub name: Option<String>,
+    /// JSON encoded arguments.
+    pub arguments: String,
+}
+
+/// Overrides for a single `response.create`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<super::session::Modality>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<Value>,
+    #[serde(flatten, default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientEvent {
+    #[serde(rename = "session.update")]
+    SessionUpdate { session: SessionConfig },
+    #[serde(rename = "input_audio_buffer.append")]
+    InputAudioBufferAppend {
+        /// Base64 encoded audio in the session's input format.
+        audio: String,
+    },
+    #[serde(rename = "input_audio_buffer.commit")]
+    InputAudioBufferCommit,
+    #[serde(rename = "input_audio_buffer.clear")]
+    InputAudioBufferClear,
+    #[serde(rename = "conversation.item.create")]
+    ConversationItemCreate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous_item_id: Option<String>,
+        item: ConversationItem,
+    },
+    #[serde(rename = "conversation.item.truncate")]
+    ConversationItemTruncate {
+        item_id: String,
+        content_index: u32,
+        audio_end_ms: u32,
+    },
+    #[serde(rename = "conversation.item.delete")]
+    ConversationItemDelete { item_id: String },
+    #[serde(rename = "response.create")]
+    ResponseCreate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        response: Option<ResponseConfig>,
+    },
+    #[serde(rename = "response.cancel")]
+    ResponseCancel,
+}
+
+impl ClientEvent {
+    /// `input_audio_buffer.append` with the raw audio bytes base64 encoded.
+    pub fn append_audio(audio: impl AsRef<[u8]>) -> Self {
+        ClientEvent::InputAudioBufferAppend {
+            audio: STANDARD.encode(audio),
+        }
+    }
+
+    /// `conversation.item.create` adding a user text message.
+    pub fn user_text(text: impl Into<String>) -> Self {
+        ClientEvent::ConversationItemCreate {
+            previous_item_id: None,
+            item: ConversationItem::Message {
+                id: None,
+                role: ItemRole::User,
+                content: vec![ContentPart::InputText { text: text.into() }],
+                status: None,
+            },
+        }
+    }
+
+    pub fn response_create() -> Self {
+        ClientEvent::ResponseCreate { response: None }
+    }
+
+    /// `conversation.item.create` carrying the result of a function call.
+    pub fn function_output(call_id: impl Into<String>, output: impl Into<String>) -> Self {
+        ClientEvent::ConversationItemCreate {
+            previous_item_id: None,
+            item: ConversationItem::FunctionCallOutput {
+                id: None,
+                call_id: call_id.into(),
+                output: output.into(),
+            },
+        }
+    }
+
+    /// The function output followed by `response.create`, so the model continues speaking with
+    /// the result.
+    pub fn function_output_and_respond(
+        call_id: impl Into<String>,
+        output: impl Into<String>,
+    ) -> [Self; 2] {
+        [
+            ClientEvent::function_output(call_id, output),
+            ClientEvent::response_create(),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealtimeError {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub param: Option<String>,
+    #[serde(default)]
+    pub event_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealtimeResponse {
+    pub id: String,
+    pub status: String,
+    #[serde(default)]
+    pub status_details: Option<Value>,
+    #[serde(default)]
+    pub output: Vec<ConversationItem>,
+    #[serde(default)]
+    pub usage: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub name: String,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerEvent {
+    #[serde(rename = "error")]
+    Error { error: RealtimeError },
+    #[serde(rename = "session.created")]
+    SessionCreated { session: SessionConfig },
+    #[serde(rename = "session.updated")]
+    SessionUpdated { session: SessionConfig },
+    #[serde(rename = "conversation.created")]
+    ConversationCreated { conversation: Value },
+    #[serde(rename = "conversation.item.created")]
+    ConversationItemCreated {
+        #[serde(default)]
+        previous_item_id: Option<String>,
+        item: ConversationItem,
+    },
+    #[serde(rename = "conversation.item.input_audio_transcription.completed")]
+    InputAudioTranscriptionCompleted {
+        item_id: String,
+        content_index: u32,
+        transcript: String,
+    },
+    #[serde(rename = "conversation.item.input_audio_transcription.failed")]
+    InputAudioTranscriptionFailed {
+        item_id: String,
+        content_index: u32,
+        error: Value,
+    },
+    #[serde(rename = "conversation.item.truncated")]
+    ConversationItemTruncated {
+        item_id: String,
+        content_index: u32,
+        audio_end_ms: u32,
+    },
+    #[serde(rename = "conversation.item.deleted")]
+    ConversationItemDeleted { item_id: String },
+    #[serde(rename = "input_audio_buffer.committed")]
+    InputAudioBufferCommitted {
+        #[serde(default)]
+        previous_item_id: Option<String>,
+        item_id: String,
+    },
+    #[serde(rename = "input_audio_buffer.cleared")]
+    InputAudioBufferCleared,
+    #[serde(rename = "input_audio_buffer.speech_started")]
+    InputAudioBufferSpeechStarted {
+        audio_start_ms: u32,
+        item_id: String,
+    },
+    #[serde(rename = "input_audio_buffer.speech_stopped")]
+    InputAudioBufferSpeechStopped { audio_end_ms: u32, item_id: String },
+    #[serde(rename = "response.created")]
+    ResponseCreated { response: RealtimeResponse },
+    #[serde(rename = "response.done")]
+    ResponseDone { response: RealtimeResponse },
+    #[serde(rename = "response.output_item.added")]
+    ResponseOutputItemAdded {
+        response_id: String,
+        output_index: u32,
+        item: ConversationItem,
+    },
+    #[serde(rename = "response.output_item.done")]
+    ResponseOutputItemDone {
+        response_id: String,
+        output_index: u32,
+        item: ConversationItem,
+    },
+    #[serde(rename = "response.content_part.added")]
+    ResponseContentPartAdded {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        part: ContentPart,
+    },
+    #[serde(rename = "response.content_part.done")]
+    ResponseContentPartDone {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        part: ContentPart,
+    },
+    #[serde(rename = "response.text.delta")]
+    ResponseTextDelta {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+    #[serde(rename = "response.text.done")]
+    ResponseTextDone {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        text: String,
+    },
+    #[serde(rename = "response.audio_transcript.delta")]
+    ResponseAudioTranscriptDelta {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+    #[serde(rename = "response.audio_transcript.done")]
+    ResponseAudioTranscriptDone {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        transcript: String,
+    },
+    #[serde(rename = "response.audio.delta")]
+    ResponseAudioDelta {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        /// Base64 encoded audio chunk.
+        delta: String,
+    },
+    #[serde(rename = "response.audio.done")]
+    ResponseAudioDone {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+    },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    ResponseFunctionCallArgumentsDelta {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        call_id: String,
+        delta: String,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    ResponseFunctionCallArgumentsDone {
+        response_id: String,
+        item_id: String,
+        output_index: u32,
+        call_id: String,
+        #[serde(default)]
+        name: Option<String>,
+        arguments: String,
+    },
+    #[serde(rename = "rate_limits.updated")]
+    RateLimitsUpdated { rate_limits: Vec<RateLimit> },
+    /// Any event type not modelled above.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ServerEvent {
+    /// Decodes the audio carried by a `response.audio.delta` event.
+    pub fn audio_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            ServerEvent::ResponseAudioDelta { delta, .. } => STANDARD.decode(delta).ok(),
+            _ => None,
+        }
+    }
+
+    /// The function call completed by this event, if any.
+    pub fn function_call(&self) -> Option<FunctionCallRequest> {
+        match self {
+            ServerEvent::ResponseFunctionCallArgumentsDone {
+                call_id,
+                name,
+                arguments,
+                ..
+            } => Some(FunctionCallRequest {
+                call_id: call_id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+            }),
+            ServerEvent::ResponseOutputItemDone {
+                item:
+                    ConversationItem::FunctionCall {
+                        call_id,
+                        name,
+                        arguments,
+                        ..
+                    },
+                ..
+            } => Some(FunctionCallRequest {
+                call_id: call_id.clone(),
+                name: Some(name.clone()),
+                arguments: arguments.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_client_event_serialization() {
+        assert_eq!(
+            serde_json::to_value(ClientEvent::user_text("Hello")).unwrap(),
+            json!({
+                "type": "conversation.item.create",
+                "item": {
+                    "type": "message",
+                    "role": "user",
+                    "content": [{"type": "input_text", "text": "Hello"}]
+                }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ClientEvent::append_audio([0u8, 1, 2])).unwrap(),
+            json!({"type": "input_audio_buffer.append", "audio": "AAEC"})
+        );
+        assert_eq!(
+            serde_json::to_value(ClientEvent::InputAudioBufferCommit).unwrap(),
+            json!({"type": "input_audio_buffer.commit"})
+        );
+    }
+
+    #[test]
+    fn test_server_event_deserialization() {
+        let event: ServerEvent = serde_json::from_value(json!({
+            "event_id": "event_1",
+            "type": "response.audio.delta",
+            "response_id": "resp_1",
+            "item_id": "item_1",
+            "output_index": 0,
+            "content_index": 0,
+            "delta": "AAEC"
+        }))
+        .unwrap();
+        assert_eq!(event.audio_bytes(), Some(vec![0, 1, 2]));
+
+        let event: ServerEvent = serde_json::from_value(json!({
+            "type": "input_audio_buffer.speech_started",
+            "audio_start_ms": 120,
+            "item_id": "item_2"
+        }))
+        .unwrap();
+        assert!(matches!(
+            event,
+            ServerEvent::InputAudioBufferSpeechStarted {
+                audio_start_ms: 120,
+                ..
+            }
+        ));
+
+        let event: ServerEvent = serde_json::from_value(json!({
+            "type": "response.function_call_arguments.done",
+            "response_id": "resp_1",
+            "item_id": "item_3",
+            "output_index": 0,
+            "call_id": "call_1",
+            "name": "get_weather",
+            "arguments": "{\"city\":\"Paris\"}"
+        }))
+        .unwrap();
+        let call = event.function_call().unwrap();
+        assert_eq!(call.name.as_deref(), Some("get_weather"));
+        let [output, respond] = ClientEvent::function_output_and_respond(call.call_id, "18C");
+        assert_eq!(
+            serde_json::to_value(output).unwrap(),
+            json!({
+                "type": "conversation.item.create",
+                "item": {"type": "function_call_output", "call_id": "call_1", "output": "18C"}
+            })
+        );
+        assert_eq!(respond, ClientEvent::response_create());
+
+        let event: ServerEvent =
+            serde_json::from_value(json!({"type": "output_audio_buffer.started"})).unwrap();
+        assert_eq!(event, ServerEvent::Unknown);
+    }
+}
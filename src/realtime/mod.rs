@@ -0,0 +1,13 @@
+//! Types for the Realtime API.
+//!
+//! The Realtime API is a bidirectional event protocol: the client sends [`ClientEvent`]s and the
+//! server answers with [`ServerEvent`]s, both serialized as JSON objects tagged by `type`.
+//! [`OpenAi::realtime`](crate::OpenAi::realtime) opens the WebSocket that carries them.
+
+pub mod connection;
+pub mod events;
+pub mod session;
+
+pub use connection::RealtimeConnection;
+pub use events::{ClientEvent, ServerEvent};
+pub use session::SessionConfig;
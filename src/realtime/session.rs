@@ -0,0 +1,124 @@
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::chat::tool::{Function, Tool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modality {
+    Text,
+    Audio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    Pcm16,
+    G711Ulaw,
+    G711Alaw,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+pub struct InputAudioTranscription {
+    #[builder(into)]
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TurnDetection {
+    ServerVad {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        threshold: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prefix_padding_ms: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        silence_duration_ms: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        create_response: Option<bool>,
+    },
+    SemanticVad {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        eagerness: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        create_response: Option<bool>,
+    },
+}
+
+/// Function definition in the flat shape used by the Realtime API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RealtimeTool {
+    Function {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        parameters: Value,
+    },
+}
+
+impl From<Function> for RealtimeTool {
+    fn from(function: Function) -> Self {
+        RealtimeTool::Function {
+            name: function.name,
+            description: function.description,
+            parameters: function.parameters,
+        }
+    }
+}
+
+impl From<Tool> for RealtimeTool {
+    fn from(tool: Tool) -> Self {
+        match tool {
+            Tool::Function { function } => function.into(),
+        }
+    }
+}
+
+/// Session configuration sent with `session.update` and returned in `session.created` /
+/// `session.updated`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct SessionConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<Modality>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_format: Option<AudioFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_audio_format: Option<AudioFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_transcription: Option<InputAudioTranscription>,
+    /// Unset keeps the server default; insert `"turn_detection": null` into
+    /// [`SessionConfig::extra`] to disable voice activity detection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn_detection: Option<TurnDetection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Either an integer or `"inf"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_output_tokens: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<RealtimeTool>>,
+    /// `"auto"`, `"none"`, `"required"` or `{"type": "function", "name": ...}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    /// Additional fields not modelled above.
+    #[serde(flatten, default, skip_serializing_if = "serde_json::Map::is_empty")]
+    #[builder(default)]
+    pub extra: serde_json::Map<String, Value>,
+}
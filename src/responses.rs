@@ -0,0 +1,1156 @@
+//! Types for the Responses API (`/v1/responses`).
+//!
+//! Turns can be chained either server-side with `previous_response_id` or by passing
+//! [`Response::output_as_input`] plus new [`InputItem`]s as the next input. Besides function
+//! tools, the built-in web search, file search, image generation and MCP tools are modelled.
+//!
+//! Requests created with `background(true)` return immediately with a `queued` response that can
+//! be retrieved, cancelled, or awaited with [`Response::poll_until_complete`] instead of holding
+//! the HTTP connection open for the whole generation.
+
+use std::collections::HashMap;
+
+use bon::Builder;
+use futures::{stream::BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    auth::Authorize,
+    backoff::Backoff,
+    chat::tool::{Function, Tool},
+    files::{FileRef, GeneratedFile},
+    images::{Background, ImageQuality, ImageSize, OutputFormat},
+    retry::SendRetrying,
+    sse::{error_stream, idle_timeout, sse_events, StreamingBody},
+    ApiRequestError, OpenAi,
+};
+
+const API_URL: &str = "v1/responses";
+
+/// Either a plain prompt or a list of input items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseInput {
+    Text(String),
+    Items(Vec<Value>),
+}
+
+impl From<String> for ResponseInput {
+    fn from(text: String) -> Self {
+        ResponseInput::Text(text)
+    }
+}
+
+impl From<&str> for ResponseInput {
+    fn from(text: &str) -> Self {
+        ResponseInput::Text(text.to_string())
+    }
+}
+
+impl From<Vec<Value>> for ResponseInput {
+    fn from(items: Vec<Value>) -> Self {
+        ResponseInput::Items(items)
+    }
+}
+
+impl From<Vec<InputItem>> for ResponseInput {
+    fn from(items: Vec<InputItem>) -> Self {
+        ResponseInput::Items(items.into_iter().map(Value::from).collect())
+    }
+}
+
+/// Typed input item. Anything else, such as output items carried over from a previous
+/// response, can be passed as raw JSON through [`ResponseInput::Items`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputItem {
+    /// `role` is `user`, `assistant`, `system` or `developer`.
+    Message { role: String, content: String },
+    /// Result of a `function_call` output item, matched by its `call_id`.
+    FunctionCallOutput { call_id: String, output: String },
+}
+
+impl InputItem {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::message("user", content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::message("assistant", content)
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::message("system", content)
+    }
+
+    pub fn developer(content: impl Into<String>) -> Self {
+        Self::message("developer", content)
+    }
+
+    fn message(role: &str, content: impl Into<String>) -> Self {
+        InputItem::Message {
+            role: role.to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn function_call_output(call_id: impl Into<String>, output: impl Into<String>) -> Self {
+        InputItem::FunctionCallOutput {
+            call_id: call_id.into(),
+            output: output.into(),
+        }
+    }
+}
+
+impl From<InputItem> for Value {
+    fn from(item: InputItem) -> Self {
+        serde_json::to_value(item).expect("input items serialize to JSON")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningSummary {
+    Auto,
+    Concise,
+    Detailed,
+}
+
+/// Reasoning configuration for o-series models.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct Reasoning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<ReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ReasoningSummary>,
+}
+
+/// Options of the built-in `image_generation` tool, which lets the model draw images with
+/// `gpt-image-1` in the middle of a text response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct ImageGenerationTool {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<ImageSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<ImageQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Background>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    /// 0-100, for `jpeg` and `webp` output.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_compression: Option<u8>,
+    /// Number of previews (0-3) streamed before the final image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_images: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpApprovalMode {
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpToolNames {
+    pub tool_names: Vec<String>,
+}
+
+/// Which calls to a remote MCP server need an explicit approval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum McpApproval {
+    Mode(McpApprovalMode),
+    PerTool {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        always: Option<McpToolNames>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        never: Option<McpToolNames>,
+    },
+}
+
+impl McpApproval {
+    /// Approval required for every tool except `tool_names`.
+    pub fn never_for<I, S>(tool_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        McpApproval::PerTool {
+            always: None,
+            never: Some(McpToolNames {
+                tool_names: tool_names.into_iter().map(Into::into).collect(),
+            }),
+        }
+    }
+}
+
+impl From<McpApprovalMode> for McpApproval {
+    fn from(mode: McpApprovalMode) -> Self {
+        McpApproval::Mode(mode)
+    }
+}
+
+/// Remote MCP server the model may call directly during the response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+pub struct McpTool {
+    /// Name the server's tools are namespaced under.
+    #[builder(into)]
+    pub server_label: String,
+    #[builder(into)]
+    pub server_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub server_description: Option<String>,
+    /// Restricts the imported tools; all tools are available when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Defaults to requiring approval for every call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub require_approval: Option<McpApproval>,
+    /// Extra HTTP headers, e.g. `Authorization`, sent to the server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchContextSize {
+    Low,
+    Medium,
+    High,
+}
+
+/// Options of the built-in `web_search` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Builder)]
+pub struct WebSearchTool {
+    /// How much retrieved content goes into the context window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_context_size: Option<SearchContextSize>,
+    /// Approximate location used to localize results, e.g.
+    /// `{"type": "approximate", "country": "GB", "city": "London"}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_location: Option<Value>,
+}
+
+/// Options of the built-in `file_search` tool, which searches the given vector stores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Builder)]
+pub struct FileSearchTool {
+    pub vector_store_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_num_results: Option<u32>,
+    /// Attribute filter applied before ranking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Value>,
+}
+
+/// Tool definition in the flat shape used by the Responses API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseTool {
+    Function {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        parameters: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+    ImageGeneration(ImageGenerationTool),
+    Mcp(McpTool),
+    WebSearch(WebSearchTool),
+    FileSearch(FileSearchTool),
+}
+
+impl From<Function> for ResponseTool {
+    fn from(function: Function) -> Self {
+        ResponseTool::Function {
+            name: function.name,
+            description: function.description,
+            parameters: function.parameters,
+            strict: function.strict,
+        }
+    }
+}
+
+impl From<Tool> for ResponseTool {
+    fn from(tool: Tool) -> Self {
+        match tool {
+            Tool::Function { function } => function.into(),
+        }
+    }
+}
+
+impl From<McpTool> for ResponseTool {
+    fn from(tool: McpTool) -> Self {
+        ResponseTool::Mcp(tool)
+    }
+}
+
+impl From<ImageGenerationTool> for ResponseTool {
+    fn from(tool: ImageGenerationTool) -> Self {
+        ResponseTool::ImageGeneration(tool)
+    }
+}
+
+impl From<WebSearchTool> for ResponseTool {
+    fn from(tool: WebSearchTool) -> Self {
+        ResponseTool::WebSearch(tool)
+    }
+}
+
+impl From<FileSearchTool> for ResponseTool {
+    fn from(tool: FileSearchTool) -> Self {
+        ResponseTool::FileSearch(tool)
+    }
+}
+
+/// `include` value asking for the encrypted reasoning trace, needed to chain reasoning items
+/// when `store` is disabled.
+pub const INCLUDE_REASONING_ENCRYPTED_CONTENT: &str = "reasoning.encrypted_content";
+
+#[derive(Debug, Clone, Serialize, Builder)]
+pub struct ResponseRequest {
+    #[builder(into)]
+    pub model: String,
+    #[builder(into)]
+    pub input: ResponseInput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub previous_response_id: Option<String>,
+    /// Run the response asynchronously; requires `store` to be left enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Reasoning>,
+    /// Additional output data to return, e.g. [`INCLUDE_REASONING_ENCRYPTED_CONTENT`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ResponseTool>>,
+    /// `"auto"`, `"none"`, `"required"` or an object forcing a specific tool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub user: Option<String>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Failed,
+    Cancelled,
+    Incomplete,
+}
+
+impl ResponseStatus {
+    /// `true` once the response will not change anymore.
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, ResponseStatus::Queued | ResponseStatus::InProgress)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputContent {
+    OutputText {
+        text: String,
+        #[serde(default)]
+        annotations: Vec<Value>,
+    },
+    Refusal {
+        refusal: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SummaryPart {
+    SummaryText { text: String },
+}
+
+/// A call to an MCP server waiting for the caller's approval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpApprovalRequest {
+    pub id: String,
+    pub server_label: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+impl McpApprovalRequest {
+    /// Input item answering this request; send it with `previous_response_id` set.
+    pub fn response(&self, approve: bool) -> Value {
+        json!({
+            "type": "mcp_approval_response",
+            "approval_request_id": self.id,
+            "approve": approve,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputItem {
+    Message {
+        id: String,
+        role: String,
+        #[serde(default)]
+        content: Vec<OutputContent>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    FunctionCall {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    Reasoning {
+        id: String,
+        #[serde(default)]
+        summary: Vec<SummaryPart>,
+        /// Opaque reasoning trace; pass the item back unchanged to continue reasoning statelessly.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encrypted_content: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    McpListTools {
+        id: String,
+        server_label: String,
+        #[serde(default)]
+        tools: Vec<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<Value>,
+    },
+    McpApprovalRequest(McpApprovalRequest),
+    McpCall {
+        id: String,
+        server_label: String,
+        name: String,
+        arguments: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        approval_request_id: Option<String>,
+    },
+    ImageGenerationCall {
+        id: String,
+        /// Base64-encoded image, once generation has finished.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        result: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        revised_prompt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    WebSearchCall {
+        id: String,
+        /// The search that was run, e.g. `{"type": "search", "query": "..."}`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        action: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    FileSearchCall {
+        id: String,
+        #[serde(default)]
+        queries: Vec<String>,
+        /// Matched chunks, present when `file_search_call.results` is included.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        results: Option<Vec<Value>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    CodeInterpreterCall {
+        id: String,
+        /// Container holding the files the code wrote.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        container_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        outputs: Option<Vec<Value>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<String>,
+    },
+    /// Any item type not modelled above.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Event of a streamed response. Only the events needed to follow text and image generation are
+/// modelled; the rest arrive as [`ResponseStreamEvent::Other`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseStreamEvent {
+    #[serde(rename = "response.created")]
+    Created { response: Response },
+    #[serde(rename = "response.in_progress")]
+    InProgress { response: Response },
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded { output_index: u32, item: OutputItem },
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone { output_index: u32, item: OutputItem },
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+    /// Fragment of the arguments of a `function_call` item; the complete call arrives in
+    /// [`ResponseStreamEvent::OutputItemDone`].
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: u32,
+        delta: String,
+    },
+    #[serde(rename = "response.image_generation_call.partial_image")]
+    ImageGenerationPartialImage {
+        item_id: String,
+        output_index: u32,
+        partial_image_index: u32,
+        /// Base64-encoded preview.
+        partial_image_b64: String,
+    },
+    #[serde(rename = "response.completed")]
+    Completed { response: Response },
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: Response },
+    #[serde(rename = "response.failed")]
+    Failed { response: Response },
+    #[serde(rename = "error")]
+    Error {
+        #[serde(default)]
+        code: Option<String>,
+        message: String,
+    },
+    /// Any event type not modelled above.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputTokensDetails {
+    pub reasoning_tokens: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens_details: Option<OutputTokensDetails>,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub status: ResponseStatus,
+    pub model: String,
+    #[serde(default)]
+    pub output: Vec<OutputItem>,
+    #[serde(default)]
+    pub background: Option<bool>,
+    #[serde(default)]
+    pub error: Option<ResponseError>,
+    #[serde(default)]
+    pub incomplete_details: Option<Value>,
+    #[serde(default)]
+    pub usage: Option<ResponseUsage>,
+}
+
+impl Response {
+    /// Concatenated text of all `output_text` parts.
+    pub fn output_text(&self) -> String {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::Message { content, .. } => Some(content),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|part| match part {
+                OutputContent::OutputText { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// MCP calls the model wants to make, waiting for approval.
+    pub fn approval_requests(&self) -> Vec<&McpApprovalRequest> {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::McpApprovalRequest(request) => Some(request),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Input items answering every pending approval request with the result of `decide`.
+    pub fn approval_responses(
+        &self,
+        mut decide: impl FnMut(&McpApprovalRequest) -> bool,
+    ) -> Vec<Value> {
+        self.approval_requests()
+            .into_iter()
+            .map(|request| request.response(decide(request)))
+            .collect()
+    }
+
+    /// Base64-encoded images produced by the `image_generation` tool.
+    pub fn images(&self) -> Vec<&str> {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::ImageGenerationCall { result, .. } => result.as_deref(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Reasoning summaries joined by blank lines.
+    pub fn reasoning_summary(&self) -> String {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::Reasoning { summary, .. } => Some(summary),
+                _ => None,
+            })
+            .flatten()
+            .map(|SummaryPart::SummaryText { text }| text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Files code interpreter created, from `container_file_citation` annotations.
+    pub fn generated_files(&self) -> Vec<FileRef> {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::Message { content, .. } => Some(content),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|part| match part {
+                OutputContent::OutputText { annotations, .. } => Some(annotations),
+                _ => None,
+            })
+            .flatten()
+            .filter(|annotation| annotation["type"] == "container_file_citation")
+            .filter_map(|annotation| {
+                Some(FileRef {
+                    file_id: annotation["file_id"].as_str()?.to_string(),
+                    container_id: Some(annotation["container_id"].as_str()?.to_string()),
+                    filename: annotation["filename"].as_str().map(ToOwned::to_owned),
+                })
+            })
+            .collect()
+    }
+
+    /// Downloads every file returned by [`generated_files`](Response::generated_files).
+    pub async fn download_files(
+        &self,
+        openai: &OpenAi,
+    ) -> Result<Vec<GeneratedFile>, ApiRequestError> {
+        openai.download_files(self.generated_files()).await
+    }
+
+    /// Output items in the shape expected by `input`, for carrying the turn (including
+    /// reasoning items) into the next request without relying on `previous_response_id`.
+    pub fn output_as_input(&self) -> Vec<Value> {
+        self.output
+            .iter()
+            .filter(|item| !matches!(item, OutputItem::Unknown))
+            .filter_map(|item| serde_json::to_value(item).ok())
+            .collect()
+    }
+
+    /// Re-fetches the response until it reaches a terminal status, sleeping according to
+    /// `backoff` between attempts.
+    ///
+    /// Wrap the call in `tokio::time::timeout` to bound the total wait.
+    pub async fn poll_until_complete(
+        self,
+        openai: &OpenAi,
+        backoff: Backoff,
+    ) -> Result<Response, ApiRequestError> {
+        let mut response = self;
+        let mut delays = backoff.delays();
+        while !response.status.is_terminal() {
+            if let Some(delay) = delays.next() {
+                tokio::time::sleep(delay).await;
+            }
+            response = openai.retrieve_response(&response.id).await?;
+        }
+        Ok(response)
+    }
+}
+
+async fn parse_response(res: reqwest::Response) -> Result<Response, ApiRequestError> {
+    if res.status().is_success() {
+        Ok(res.json().await?)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
+
+impl ResponseRequest {
+    pub async fn send(&self) -> Result<Response, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .json(self)
+            .send_observed(&self.openai)
+            .await?;
+        parse_response(res).await
+    }
+
+    /// Streams the response as it is generated, including partial images from the
+    /// `image_generation` tool.
+    pub async fn stream(&self) -> BoxStream<'static, Result<ResponseStreamEvent, ApiRequestError>> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = match self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await
+        {
+            Ok(request) => {
+                request
+                    .json(&StreamingBody::new(self))
+                    .send_streaming(&self.openai)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        match res {
+            Ok(res) if res.status().is_success() => sse_events(idle_timeout(
+                res.bytes_stream(),
+                self.openai.inner.timeouts.stream_idle,
+            ))
+            .map(|event| -> Result<ResponseStreamEvent, ApiRequestError> {
+                Ok(crate::json::from_str(&event?.data)?)
+            })
+            .boxed(),
+            res => error_stream(res).await,
+        }
+    }
+}
+
+impl OpenAi {
+    pub fn responses(&self) -> ResponseRequestBuilder<response_request_builder::SetOpenai> {
+        ResponseRequest::builder().openai(self.clone())
+    }
+
+    /// `GET /v1/responses/{id}`.
+    pub async fn retrieve_response(&self, id: &str) -> Result<Response, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_response(res).await
+    }
+
+    /// Cancels a background response. Only responses created with `background: true` can be
+    /// cancelled.
+    pub async fn cancel_response(&self, id: &str) -> Result<Response, ApiRequestError> {
+        let url = format!("{}/{}/{}/cancel", self.inner.base_url, API_URL, id);
+        let res = self
+            .inner
+            .client
+            .post(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_response(res).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_background_request_and_response() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .responses()
+            .model("o3")
+            .input("Write a long essay.")
+            .background(true)
+            .build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"model": "o3", "input": "Write a long essay.", "background": true})
+        );
+
+        let queued: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "queued",
+            "model": "o3",
+            "output": [],
+            "background": true
+        }))
+        .unwrap();
+        assert!(!queued.status.is_terminal());
+
+        let done: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "completed",
+            "model": "o3",
+            "output": [
+                {"type": "web_search_call", "id": "ws_1", "status": "completed"},
+                {
+                    "type": "message",
+                    "id": "msg_1",
+                    "role": "assistant",
+                    "content": [{"type": "output_text", "text": "Hello", "annotations": []}]
+                }
+            ],
+            "usage": {"input_tokens": 5, "output_tokens": 1, "total_tokens": 6}
+        }))
+        .unwrap();
+        assert!(done.status.is_terminal());
+        assert!(matches!(&done.output[0], OutputItem::WebSearchCall { id, .. } if id == "ws_1"));
+        assert_eq!(done.output_text(), "Hello");
+    }
+
+    #[test]
+    fn test_search_tools_and_input_items() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .responses()
+            .model("gpt-4.1")
+            .input(vec![
+                InputItem::developer("Cite your sources."),
+                InputItem::function_call_output("call_1", "{\"temp\":21}"),
+            ])
+            .previous_response_id("resp_0")
+            .tools(vec![
+                WebSearchTool::builder()
+                    .search_context_size(SearchContextSize::Low)
+                    .build()
+                    .into(),
+                FileSearchTool::builder()
+                    .vector_store_ids(vec!["vs_1".to_string()])
+                    .max_num_results(5)
+                    .build()
+                    .into(),
+            ])
+            .build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "model": "gpt-4.1",
+                "input": [
+                    {"type": "message", "role": "developer", "content": "Cite your sources."},
+                    {"type": "function_call_output", "call_id": "call_1", "output": "{\"temp\":21}"}
+                ],
+                "previous_response_id": "resp_0",
+                "tools": [
+                    {"type": "web_search", "search_context_size": "low"},
+                    {"type": "file_search", "vector_store_ids": ["vs_1"], "max_num_results": 5}
+                ]
+            })
+        );
+
+        let event: ResponseStreamEvent = serde_json::from_value(json!({
+            "type": "response.function_call_arguments.delta",
+            "item_id": "fc_1",
+            "output_index": 0,
+            "delta": "{\"ci"
+        }))
+        .unwrap();
+        assert!(
+            matches!(event, ResponseStreamEvent::FunctionCallArgumentsDelta { delta, .. } if delta == "{\"ci")
+        );
+    }
+
+    #[test]
+    fn test_reasoning_items_round_trip() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .responses()
+            .model("o4-mini")
+            .input("Why is the sky blue?")
+            .store(false)
+            .reasoning(
+                Reasoning::builder()
+                    .effort(ReasoningEffort::High)
+                    .summary(ReasoningSummary::Auto)
+                    .build(),
+            )
+            .include(vec![INCLUDE_REASONING_ENCRYPTED_CONTENT.to_string()])
+            .build();
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            body["reasoning"],
+            json!({"effort": "high", "summary": "auto"})
+        );
+        assert_eq!(body["include"], json!(["reasoning.encrypted_content"]));
+
+        let reasoning = json!({
+            "type": "reasoning",
+            "id": "rs_1",
+            "summary": [
+                {"type": "summary_text", "text": "Rayleigh scattering."},
+                {"type": "summary_text", "text": "Shorter wavelengths scatter more."}
+            ],
+            "encrypted_content": "gAAAA"
+        });
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "completed",
+            "model": "o4-mini",
+            "output": [reasoning.clone()],
+            "usage": {
+                "input_tokens": 5,
+                "output_tokens": 64,
+                "output_tokens_details": {"reasoning_tokens": 60},
+                "total_tokens": 69
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            response.reasoning_summary(),
+            "Rayleigh scattering.\n\nShorter wavelengths scatter more."
+        );
+        assert_eq!(response.output_as_input(), vec![reasoning]);
+    }
+
+    #[test]
+    fn test_code_interpreter_files() {
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "completed",
+            "model": "gpt-4.1",
+            "output": [
+                {
+                    "type": "code_interpreter_call",
+                    "id": "ci_1",
+                    "container_id": "cntr_1",
+                    "code": "df.to_csv('out.csv')",
+                    "status": "completed"
+                },
+                {
+                    "type": "message",
+                    "id": "msg_1",
+                    "role": "assistant",
+                    "content": [{
+                        "type": "output_text",
+                        "text": "Here is the file.",
+                        "annotations": [
+                            {"type": "url_citation", "url": "https://example.com"},
+                            {
+                                "type": "container_file_citation",
+                                "container_id": "cntr_1",
+                                "file_id": "cfile_1",
+                                "filename": "out.csv"
+                            }
+                        ]
+                    }]
+                }
+            ]
+        }))
+        .unwrap();
+        assert!(matches!(
+            &response.output[0],
+            OutputItem::CodeInterpreterCall { container_id: Some(id), .. } if id == "cntr_1"
+        ));
+        assert_eq!(
+            response.generated_files(),
+            [FileRef {
+                file_id: "cfile_1".to_string(),
+                container_id: Some("cntr_1".to_string()),
+                filename: Some("out.csv".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_image_generation_tool() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .responses()
+            .model("gpt-4.1")
+            .input("Draw a cat and describe it.")
+            .tools(vec![ImageGenerationTool::builder()
+                .size(ImageSize::S1024x1024)
+                .partial_images(2)
+                .build()
+                .into()])
+            .build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["tools"],
+            json!([{"type": "image_generation", "size": "1024x1024", "partial_images": 2}])
+        );
+
+        let event: ResponseStreamEvent = serde_json::from_value(json!({
+            "type": "response.image_generation_call.partial_image",
+            "item_id": "ig_1",
+            "output_index": 0,
+            "partial_image_index": 0,
+            "partial_image_b64": "iVBORw0"
+        }))
+        .unwrap();
+        assert!(matches!(
+            event,
+            ResponseStreamEvent::ImageGenerationPartialImage {
+                partial_image_index: 0,
+                ..
+            }
+        ));
+        let event: ResponseStreamEvent =
+            serde_json::from_value(json!({"type": "response.web_search_call.searching"})).unwrap();
+        assert_eq!(event, ResponseStreamEvent::Other);
+
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "completed",
+            "model": "gpt-4.1",
+            "output": [
+                {"type": "image_generation_call", "id": "ig_1", "status": "completed", "result": "iVBORw0KGgo"},
+                {
+                    "type": "message",
+                    "id": "msg_1",
+                    "role": "assistant",
+                    "content": [{"type": "output_text", "text": "A sleepy cat."}]
+                }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(response.images(), ["iVBORw0KGgo"]);
+        assert_eq!(response.output_text(), "A sleepy cat.");
+    }
+
+    #[test]
+    fn test_mcp_tool_and_approvals() {
+        let tool: ResponseTool = McpTool::builder()
+            .server_label("deepwiki")
+            .server_url("https://mcp.deepwiki.com/mcp")
+            .allowed_tools(vec!["ask_question".to_string()])
+            .require_approval(McpApproval::never_for(["ask_question"]))
+            .build()
+            .into();
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap(),
+            json!({
+                "type": "mcp",
+                "server_label": "deepwiki",
+                "server_url": "https://mcp.deepwiki.com/mcp",
+                "allowed_tools": ["ask_question"],
+                "require_approval": {"never": {"tool_names": ["ask_question"]}}
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(McpApproval::from(McpApprovalMode::Never)).unwrap(),
+            json!("never")
+        );
+
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1,
+            "status": "completed",
+            "model": "gpt-4.1",
+            "output": [
+                {"type": "mcp_list_tools", "id": "mcpl_1", "server_label": "deepwiki", "tools": []},
+                {
+                    "type": "mcp_approval_request",
+                    "id": "mcpr_1",
+                    "server_label": "deepwiki",
+                    "name": "read_wiki_structure",
+                    "arguments": "{\"repoName\":\"rust-lang/rust\"}"
+                }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(response.approval_requests()[0].name, "read_wiki_structure");
+        assert_eq!(
+            response.approval_responses(|request| request.server_label == "deepwiki"),
+            [json!({
+                "type": "mcp_approval_response",
+                "approval_request_id": "mcpr_1",
+                "approve": true
+            })]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Automatic retries of rate-limited and failed HTTP requests.
+//!
+//! Enabled per client with [`OpenAiBuilder::retry`](crate::OpenAiBuilder::retry). Responses with
+//! status 429, 500, 502 or 503 and connection errors or timeouts are retried after the delay the
+//! server asks for in `retry-after-ms` / `Retry-After`, at most
+//! [`RetryPolicy::max_retry_after`], or else after the next backoff delay. A 429 for an exhausted
+//! quota is not retried, since waiting doesn't bring credits back. Requests with a streaming
+//! body, such as multipart uploads, cannot be replayed and are sent once.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use openai_ox::{backoff::Backoff, retry::RetryPolicy, OpenAi};
+//!
+//! let openai = OpenAi::builder()
+//!     .api_key("sk-...")
+//!     .retry(
+//!         RetryPolicy::builder()
+//!             .max_attempts(5)
+//!             .backoff(Backoff::builder().initial(Duration::from_millis(250)).jitter(0.5).build())
+//!             .build(),
+//!     )
+//!     .build();
+//! ```
+
+use std::time::{Duration, Instant};
+
+use bon::Builder;
+use reqwest::{header::HeaderMap, StatusCode};
+
+use crate::{backoff::Backoff, middleware::ResponseInfo, ApiRequestError, OpenAi};
+
+const RETRY_STATUSES: [StatusCode; 4] = [
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Builder)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included.
+    #[builder(default = 3)]
+    pub max_attempts: u32,
+    /// Delays between attempts when the server doesn't say how long to wait.
+    #[builder(default = Backoff::builder()
+        .initial(Duration::from_millis(500))
+        .max(Duration::from_secs(20))
+        .jitter(0.5)
+        .build())]
+    pub backoff: Backoff,
+    /// Longest the server may make an attempt wait with `Retry-After`.
+    #[builder(default = Duration::from_secs(60))]
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::builder().build()
+    }
+}
+
+/// How long the server asked to wait, from `retry-after-ms` or `Retry-After` in seconds.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+    header("retry-after-ms")
+        .map(|ms| ms / 1000.0)
+        .or_else(|| header("retry-after"))
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+pub(crate) trait SendRetrying {
+    /// Sends a request once through the client's middleware, reporting the rate limit headers of
+    /// the response to the client's `on_rate_limit` callback. Waits for a permit of the client's
+    /// `leaky_bucket` first, so every attempt counts against it.
+    async fn send_observed(self, openai: &OpenAi) -> reqwest::Result<reqwest::Response>;
+
+    /// Sends a request like `send_observed`, retrying it according to the client's
+    /// [`RetryPolicy`].
+    async fn send_retrying(self, openai: &OpenAi) -> Result<reqwest::Response, ApiRequestError>;
+
+    /// Sends a request whose body is read as a stream, retrying it like `send_retrying` but
+    /// without the client's request timeout, which would cut off long streams. Each attempt
+    /// waits for the response headers at most the client's stream idle timeout; wrap the body
+    /// in [`idle_timeout`](crate::sse::idle_timeout) too.
+    async fn send_streaming(self, openai: &OpenAi) -> Result<reqwest::Response, ApiRequestError>;
+}
+
+impl SendRetrying for reqwest::RequestBuilder {
+    async fn send_observed(self, openai: &OpenAi) -> reqwest::Result<reqwest::Response> {
+        send_once(with_request_timeout(self, openai), openai).await
+    }
+
+    async fn send_retrying(self, openai: &OpenAi) -> Result<reqwest::Response, ApiRequestError> {
+        send_with_retries(with_request_timeout(self, openai), openai, None).await
+    }
+
+    async fn send_streaming(self, openai: &OpenAi) -> Result<reqwest::Response, ApiRequestError> {
+        send_with_retries(self, openai, openai.inner.timeouts.stream_idle).await
+    }
+}
+
+fn with_request_timeout(
+    request: reqwest::RequestBuilder,
+    openai: &OpenAi,
+) -> reqwest::RequestBuilder {
+    match openai.inner.timeouts.request {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    }
+}
+
+async fn send_once(
+    request: reqwest::RequestBuilder,
+    openai: &OpenAi,
+) -> reqwest::Result<reqwest::Response> {
+    #[cfg(feature = "leaky-bucket")]
+    if let Some(rate_limiter) = &openai.inner.leaky_bucket {
+        rate_limiter.acquire_one().await;
+    }
+    let res = match &openai.inner.middleware {
+        None => request.send().await,
+        Some(middleware) => {
+            let (client, request) = request.build_split();
+            let mut request = request?;
+            middleware.on_request(&mut request);
+            let method = request.method().clone();
+            let url = request.url().clone();
+            let start = Instant::now();
+            let res = client.execute(request).await;
+            middleware.on_response(&ResponseInfo {
+                method: &method,
+                url: &url,
+                response: res.as_ref(),
+                elapsed: start.elapsed(),
+            });
+            res
+        }
+    };
+    res.inspect(|res| openai.inner.observe(res))
+}
+
+/// Sends a request according to the client's [`RetryPolicy`], giving every attempt at most
+/// `header_timeout` to receive the response headers.
+async fn send_with_retries(
+    request: reqwest::RequestBuilder,
+    openai: &OpenAi,
+    header_timeout: Option<Duration>,
+) -> Result<reqwest::Response, ApiRequestError> {
+    let send = |request| async move {
+        match header_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, send_once(request, openai))
+                .await
+                .map_err(|_| ApiRequestError::StreamIdle(timeout))?
+                .map_err(ApiRequestError::from),
+            None => Ok(send_once(request, openai).await?),
+        }
+    };
+    let Some(policy) = openai.inner.retry else {
+        return send(request).await;
+    };
+    let mut delays = policy.backoff.delays();
+    for _ in 1..policy.max_attempts {
+        let Some(attempt) = request.try_clone() else {
+            break;
+        };
+        let delay = delays.next().unwrap_or_default();
+        match send(attempt).await {
+            Ok(res) if RETRY_STATUSES.contains(&res.status()) => {
+                let delay = retry_after(res.headers())
+                    .map_or(delay, |after| after.min(policy.max_retry_after));
+                if res.status() == StatusCode::TOO_MANY_REQUESTS {
+                    let e = ApiRequestError::from_response(res).await;
+                    if matches!(e, ApiRequestError::InsufficientQuota(_)) {
+                        return Err(e);
+                    }
+                }
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) if e.is_timeout() || e.is_connect() => tokio::time::sleep(delay).await,
+            res => return res,
+        }
+    }
+    send(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert("retry-after", HeaderValue::from_static("2"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert("retry-after-ms", HeaderValue::from_static("150"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(150)));
+
+        let mut date = HeaderMap::new();
+        date.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&date), None);
+        assert_eq!(RetryPolicy::default().max_attempts, 3);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_streaming_header_timeout() {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // Accepts the connection and never answers.
+            let _connection = listener.accept().await;
+            std::future::pending::<()>().await
+        });
+        let openai = OpenAi::builder()
+            .base_url(format!("http://{}", addr))
+            .timeouts(
+                crate::timeout::Timeouts::builder()
+                    .stream_idle(Duration::from_millis(50))
+                    .build(),
+            )
+            .build();
+        let err = openai
+            .inner
+            .client
+            .get(openai.base_url())
+            .send_streaming(&openai)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApiRequestError::StreamIdle(_)));
+        assert!(err.is_timeout() && err.is_retryable());
+        server.abort();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_send_with_retries() {
+        use crate::mock::{MockOpenAi, MockResponse};
+
+        let mock = MockOpenAi::start().await.unwrap();
+        let openai = OpenAi::builder()
+            .api_key("sk-mock")
+            .base_url(mock.base_url())
+            .retry(
+                RetryPolicy::builder()
+                    .backoff(Backoff::builder().initial(Duration::from_millis(1)).build())
+                    .max_retry_after(Duration::from_millis(10))
+                    .build(),
+            )
+            .build();
+        let send = || async {
+            openai
+                .inner
+                .client
+                .get(format!("{}/v1/models", openai.base_url()))
+                .send_retrying(&openai)
+                .await
+        };
+
+        // An hour of `Retry-After` is cut down to the policy's maximum.
+        let rate_limited = MockResponse::error(429, "rate_limit_exceeded", "Slow down")
+            .header("retry-after", "3600");
+        mock.push("v1/models", rate_limited)
+            .push(
+                "v1/models",
+                MockResponse::error(503, "overloaded", "Try again"),
+            )
+            .push(
+                "v1/models",
+                MockResponse::json(&serde_json::json!({"data": []})),
+            );
+        let start = Instant::now();
+        let res = send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(mock.requests().len(), 3);
+
+        mock.push(
+            "v1/models",
+            MockResponse::error(429, "insufficient_quota", "You exceeded your current quota"),
+        )
+        .push(
+            "v1/models",
+            MockResponse::json(&serde_json::json!({"data": []})),
+        );
+        let err = send().await.unwrap_err();
+        assert!(matches!(err, ApiRequestError::InsufficientQuota(_)));
+        assert_eq!(mock.requests().len(), 4);
+    }
+}
@@ -0,0 +1,254 @@
+//! Incremental parser for `text/event-stream` bodies.
+
+use std::time::Duration;
+
+use futures::{stream::BoxStream, Stream, StreamExt};
+use serde::Serialize;
+
+use crate::{chat::StreamOptions, ApiRequestError};
+
+/// A single server-sent event as it appeared on the wire.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Value of the `event:` field, `None` for the default `message` event.
+    pub event: Option<String>,
+    /// All `data:` lines of the event joined with `\n`.
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<u64>,
+}
+
+/// Buffers raw bytes and yields complete events, regardless of how the body is split into
+/// chunks.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk of the body and returns the events it completed.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let line = String::from_utf8_lossy(&line);
+            events.extend(self.process_line(&line));
+        }
+        events
+    }
+
+    /// Flushes an event that was not terminated by a blank line before the body ended.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::take(&mut self.buffer);
+            let line = String::from_utf8_lossy(&buffer)
+                .trim_end_matches('\r')
+                .to_string();
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            "retry" => self.retry = value.parse().ok(),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() && self.event.is_none() {
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+            retry: self.retry,
+        })
+    }
+}
+
+/// Converts a response body stream into a stream of [`SseEvent`]s.
+pub fn sse_events<S, B, E>(stream: S) -> impl Stream<Item = Result<SseEvent, ApiRequestError>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<ApiRequestError>,
+{
+    futures::stream::unfold(
+        (stream, SseParser::new(), false),
+        |(mut stream, mut parser, done)| async move {
+            if done {
+                return None;
+            }
+            let items: Vec<Result<SseEvent, ApiRequestError>> = match stream.next().await {
+                Some(Ok(bytes)) => parser.push(bytes.as_ref()).into_iter().map(Ok).collect(),
+                Some(Err(e)) => vec![Err(e.into())],
+                None => {
+                    let last: Vec<_> = parser.finish().into_iter().map(Ok).collect();
+                    return Some((futures::stream::iter(last), (stream, parser, true)));
+                }
+            };
+            Some((futures::stream::iter(items), (stream, parser, false)))
+        },
+    )
+    .flatten()
+}
+
+/// Ends `stream` with [`ApiRequestError::StreamIdle`] when no item arrives within `idle`, e.g.
+/// on a connection that stalled halfway through a response. Without `idle` items only have their
+/// errors converted.
+pub fn idle_timeout<S, T, E>(
+    stream: S,
+    idle: Option<Duration>,
+) -> BoxStream<'static, Result<T, ApiRequestError>>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: Send + 'static,
+    E: Into<ApiRequestError>,
+{
+    let stream = stream.map(|item| item.map_err(Into::into)).boxed();
+    let Some(idle) = idle else {
+        return stream;
+    };
+    futures::stream::unfold(Some(stream), move |stream| async move {
+        let mut stream = stream?;
+        match tokio::time::timeout(idle, stream.next()).await {
+            Ok(item) => Some((item?, Some(stream))),
+            Err(_) => Some((Err(ApiRequestError::StreamIdle(idle)), None)),
+        }
+    })
+    .boxed()
+}
+
+/// A stream yielding only the error of a failed streaming request, so callers see it as the
+/// first item instead of the request panicking.
+pub(crate) async fn error_stream<T: Send + 'static>(
+    res: Result<reqwest::Response, ApiRequestError>,
+) -> BoxStream<'static, Result<T, ApiRequestError>> {
+    let err = match res {
+        Ok(res) => ApiRequestError::from_response(res).await,
+        Err(e) => e,
+    };
+    futures::stream::once(async { Err(err) }).boxed()
+}
+
+/// Request body with `"stream": true` added next to the request's own fields, so streaming
+/// requests serialize in one pass straight into the HTTP body.
+#[derive(Debug, Serialize)]
+pub(crate) struct StreamingBody<'a, T> {
+    #[serde(flatten)]
+    request: &'a T,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+impl<'a, T: Serialize> StreamingBody<'a, T> {
+    pub(crate) fn new(request: &'a T) -> Self {
+        StreamingBody {
+            request,
+            stream: true,
+            stream_options: None,
+        }
+    }
+
+    pub(crate) fn options(mut self, stream_options: Option<StreamOptions>) -> Self {
+        self.stream_options = stream_options;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let chunks = futures::stream::iter([Ok::<_, ApiRequestError>("data: 1\n\n")])
+            .chain(futures::stream::pending());
+        let mut stream = idle_timeout(chunks, Some(Duration::from_millis(10)));
+        assert_eq!(stream.next().await.unwrap().unwrap(), "data: 1\n\n");
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(
+            matches!(err, ApiRequestError::StreamIdle(idle) if idle == Duration::from_millis(10))
+        );
+        assert!(err.is_timeout());
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn test_streaming_body() {
+        #[derive(Serialize)]
+        struct Request {
+            model: &'static str,
+        }
+        let body =
+            serde_json::to_string(&StreamingBody::new(&Request { model: "gpt-4o" })).unwrap();
+        assert_eq!(body, r#"{"model":"gpt-4o","stream":true}"#);
+        let body = serde_json::to_string(
+            &StreamingBody::new(&Request { model: "gpt-4o" }).options(Some(StreamOptions {
+                include_usage: true,
+            })),
+        )
+        .unwrap();
+        assert_eq!(
+            body,
+            r#"{"model":"gpt-4o","stream":true,"stream_options":{"include_usage":true}}"#
+        );
+    }
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"event: response.created\r\nda").is_empty());
+        assert!(parser.push(b"ta: {\"a\":").is_empty());
+        let events = parser.push(b" 1}\r\n\r\n: keep-alive\n\ndata: x\ndata: y\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("response.created".into()),
+                    data: "{\"a\": 1}".into(),
+                    ..Default::default()
+                },
+                SseEvent {
+                    data: "x\ny".into(),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert!(parser.push(b"data: [DONE]").is_empty());
+        assert_eq!(parser.finish().unwrap().data, "[DONE]");
+    }
+}
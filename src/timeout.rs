@@ -0,0 +1,108 @@
+//! Time limits for connecting, for whole requests and for pauses in streamed responses.
+//!
+//! Set them for every request of a client with
+//! [`OpenAiBuilder::timeouts`](crate::OpenAiBuilder::timeouts), and override them for single
+//! requests by building those with a client from [`OpenAi::with_timeouts`]. Long speech
+//! synthesis or reasoning requests need far more time than embeddings, and a stream may take
+//! minutes in total while a pause of a few seconds already means the connection is stuck.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use openai_ox::{timeout::Timeouts, OpenAi};
+//!
+//! let openai = OpenAi::builder()
+//!     .api_key("sk-...")
+//!     .timeouts(
+//!         Timeouts::builder()
+//!             .connect(Duration::from_secs(5))
+//!             .request(Duration::from_secs(30))
+//!             .stream_idle(Duration::from_secs(15))
+//!             .build(),
+//!     )
+//!     .build();
+//!
+//! // The same client, but with time for a slow reasoning model.
+//! let patient = openai.with_timeouts(Timeouts::builder().request(Duration::from_secs(600)).build());
+//! ```
+
+use std::{sync::Arc, time::Duration};
+
+use bon::Builder;
+
+use crate::OpenAi;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Builder)]
+pub struct Timeouts {
+    /// Time to open a connection, the TLS handshake included. Only applies to the default
+    /// client and can't be changed per request, since connections are pooled.
+    pub connect: Option<Duration>,
+    /// Time from sending a request to the end of its response body, per attempt when requests
+    /// are retried. Streamed responses are limited by `stream_idle` instead.
+    pub request: Option<Duration>,
+    /// Longest pause between two chunks of a streamed response, before the first one included,
+    /// after which the stream ends with an error.
+    pub stream_idle: Option<Duration>,
+}
+
+impl Timeouts {
+    /// These timeouts, with the ones set in `overrides` replaced.
+    pub fn merge(self, overrides: Timeouts) -> Self {
+        Timeouts {
+            connect: overrides.connect.or(self.connect),
+            request: overrides.request.or(self.request),
+            stream_idle: overrides.stream_idle.or(self.stream_idle),
+        }
+    }
+}
+
+impl OpenAi {
+    pub fn timeouts(&self) -> Timeouts {
+        self.inner.timeouts
+    }
+
+    /// A client sharing this one's connections, rate limiters and settings, with the timeouts
+    /// set in `timeouts` replaced. Give it to the requests that need other limits. The connect
+    /// timeout of the shared connection pool stays as it is.
+    pub fn with_timeouts(&self, timeouts: Timeouts) -> OpenAi {
+        let mut inner = (*self.inner).clone();
+        inner.timeouts = inner.timeouts.merge(Timeouts {
+            connect: None,
+            ..timeouts
+        });
+        OpenAi {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_timeouts() {
+        let openai = OpenAi::builder()
+            .timeouts(
+                Timeouts::builder()
+                    .connect(Duration::from_secs(5))
+                    .request(Duration::from_secs(30))
+                    .build(),
+            )
+            .build();
+        let slow = openai.with_timeouts(
+            Timeouts::builder()
+                .request(Duration::from_secs(600))
+                .build(),
+        );
+        assert_eq!(
+            slow.timeouts(),
+            Timeouts {
+                connect: Some(Duration::from_secs(5)),
+                request: Some(Duration::from_secs(600)),
+                stream_idle: None,
+            }
+        );
+        assert_eq!(openai.timeouts().request, Some(Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,208 @@
+//! Token counting with the BPE encodings of OpenAI models.
+//!
+//! [`Tokenizer::for_model`] picks the encoding a model uses: `o200k_base` for GPT-4o and newer,
+//! `cl100k_base` for GPT-4, GPT-3.5 and the embedding models. Counts include the overhead the
+//! chat format adds around every message, so
+//! [`Messages::token_count`](crate::chat::message::Messages) is what the prompt costs before
+//! any tool definitions.
+//!
+//! ```
+//! use openai_ox::{
+//!     chat::message::{Message, Messages},
+//!     tokenizer::{TokenCount, Tokenizer},
+//! };
+//!
+//! let tokenizer = Tokenizer::for_model("gpt-4o");
+//! let tokens = tokenizer.encode("Hello world");
+//! assert_eq!(tokenizer.decode(&tokens).unwrap(), "Hello world");
+//!
+//! let messages = Messages(vec![Message::system("Be brief."), Message::user("Hi")]);
+//! assert!(messages.token_count_with(&Tokenizer::for_model("gpt-4")) < 20);
+//! ```
+
+use std::{fmt, sync::OnceLock};
+
+use thiserror::Error;
+use tiktoken_rs::CoreBPE;
+
+pub use crate::chat::message::TOKENS_PER_MESSAGE;
+/// Extra token of a message carrying a participant name.
+pub const TOKENS_PER_NAME: usize = 1;
+/// Tokens priming the assistant reply, counted once per request.
+pub const REPLY_PRIMING_TOKENS: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// GPT-4, GPT-3.5 and the `text-embedding-*` models.
+    Cl100kBase,
+    /// GPT-4o, GPT-4.1, GPT-5 and the o-series.
+    #[default]
+    O200kBase,
+}
+
+impl Encoding {
+    /// Encoding of `model`, including fine-tuned `ft:` models. Unknown models get
+    /// `o200k_base`, the encoding of every model released since GPT-4o.
+    pub fn for_model(model: &str) -> Self {
+        let model = model.strip_prefix("ft:").unwrap_or(model);
+        let cl100k = ["gpt-4", "gpt-3.5", "text-embedding-"];
+        let o200k = ["gpt-4o", "gpt-4.1", "gpt-4.5", "chatgpt-4o"];
+        if o200k.iter().any(|prefix| model.starts_with(prefix)) {
+            Encoding::O200kBase
+        } else if cl100k.iter().any(|prefix| model.starts_with(prefix)) {
+            Encoding::Cl100kBase
+        } else {
+            Encoding::O200kBase
+        }
+    }
+
+    fn bpe(self) -> &'static CoreBPE {
+        static CL100K: OnceLock<CoreBPE> = OnceLock::new();
+        static O200K: OnceLock<CoreBPE> = OnceLock::new();
+        match self {
+            Encoding::Cl100kBase => CL100K.get_or_init(|| {
+                tiktoken_rs::cl100k_base().expect("cl100k_base is bundled with tiktoken-rs")
+            }),
+            Encoding::O200kBase => O200K.get_or_init(|| {
+                tiktoken_rs::o200k_base().expect("o200k_base is bundled with tiktoken-rs")
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Tokens don't decode to text: {0}")]
+pub struct DecodeError(String);
+
+/// Encoder for one [`Encoding`]. The BPE tables are loaded once per encoding and shared, so
+/// tokenizers are cheap to create and copy.
+#[derive(Clone, Copy, Default)]
+pub struct Tokenizer {
+    encoding: Encoding,
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokenizer")
+            .field("encoding", &self.encoding)
+            .finish()
+    }
+}
+
+impl Tokenizer {
+    pub fn new(encoding: Encoding) -> Self {
+        Tokenizer { encoding }
+    }
+
+    pub fn for_model(model: &str) -> Self {
+        Tokenizer::new(Encoding::for_model(model))
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Tokens of `text`. Special tokens such as `<|endoftext|>` are encoded as plain text.
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        self.encoding.bpe().encode_ordinary(text)
+    }
+
+    pub fn decode(&self, tokens: &[u32]) -> Result<String, DecodeError> {
+        self.encoding
+            .bpe()
+            .decode(tokens.to_vec())
+            .map_err(|e| DecodeError(e.to_string()))
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        self.encode(text).len()
+    }
+}
+
+pub trait TokenCount {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize;
+
+    /// Token count with `o200k_base`, the encoding of current models.
+    fn token_count(&self) -> usize {
+        self.token_count_with(&Tokenizer::default())
+    }
+}
+
+impl TokenCount for str {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        tokenizer.count(self)
+    }
+}
+
+impl TokenCount for String {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        tokenizer.count(self)
+    }
+}
+
+impl<T: TokenCount + ?Sized> TokenCount for &T {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        (**self).token_count_with(tokenizer)
+    }
+}
+
+impl<T: TokenCount> TokenCount for Option<T> {
+    fn token_count_with(&self, tokenizer: &Tokenizer) -> usize {
+        self.as_ref()
+            .map_or(0, |value| value.token_count_with(tokenizer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{
+        message::{AssistantMessage, Message, Messages},
+        tool::ToolCall,
+    };
+
+    #[test]
+    fn test_encoding_for_model() {
+        assert_eq!(Encoding::for_model("gpt-4o-mini"), Encoding::O200kBase);
+        assert_eq!(
+            Encoding::for_model("ft:gpt-4o-mini:acme::abc123"),
+            Encoding::O200kBase
+        );
+        assert_eq!(Encoding::for_model("o3-mini"), Encoding::O200kBase);
+        assert_eq!(Encoding::for_model("gpt-4-turbo"), Encoding::Cl100kBase);
+        assert_eq!(Encoding::for_model("gpt-3.5-turbo"), Encoding::Cl100kBase);
+        assert_eq!(
+            Encoding::for_model("text-embedding-3-small"),
+            Encoding::Cl100kBase
+        );
+
+        let tokenizer = Tokenizer::for_model("gpt-4");
+        let tokens = tokenizer.encode("hello world");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokenizer.decode(&tokens).unwrap(), "hello world");
+    }
+
+    #[test]
+    fn test_token_count() {
+        assert_eq!("hello world".token_count(), 2);
+        assert_eq!(Message::user("hello world").token_count(), 5);
+        assert_eq!(
+            Message::user_named("bob", "hello world").token_count(),
+            5 + TOKENS_PER_NAME + "bob".token_count()
+        );
+        let call = Message::Assistant(
+            AssistantMessage::builder()
+                .tool_calls(vec![ToolCall::new("call_1", "search", "{}")])
+                .build(),
+        );
+        assert_eq!(
+            call.token_count(),
+            TOKENS_PER_MESSAGE + "search".token_count() + "{}".token_count()
+        );
+        let messages = Messages(vec![Message::user("hello world"), call.clone()]);
+        assert_eq!(
+            messages.token_count(),
+            5 + call.token_count() + REPLY_PRIMING_TOKENS
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Searching vector stores (`/v1/vector_stores/{id}/search`).
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::vector_stores::Filter;
+//!
+//! let store = openai.vector_store("vs_123");
+//! let results = store
+//!     .search("refund policy", 5, Some(Filter::eq("region", "eu")))
+//!     .await?;
+//! let messages = results.to_messages("How long do refunds take?");
+//! # Ok(())
+//! # }
+//! ```
+
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    auth::Authorize,
+    chat::message::{Message, Messages},
+    retry::SendRetrying,
+    ApiRequestError, OpenAi,
+};
+
+const API_URL: &str = "v1/vector_stores";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComparisonOperator {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompoundOperator {
+    And,
+    Or,
+}
+
+/// Filter on file attributes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Filter {
+    Comparison {
+        key: String,
+        #[serde(rename = "type")]
+        operator: ComparisonOperator,
+        value: Value,
+    },
+    Compound {
+        #[serde(rename = "type")]
+        operator: CompoundOperator,
+        filters: Vec<Filter>,
+    },
+}
+
+impl Filter {
+    pub fn compare(
+        key: impl Into<String>,
+        operator: ComparisonOperator,
+        value: impl Into<Value>,
+    ) -> Self {
+        Filter::Comparison {
+            key: key.into(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(key: impl Into<String>, value: impl Into<Value>) -> Self {
+        Filter::compare(key, ComparisonOperator::Eq, value)
+    }
+
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::Compound {
+            operator: CompoundOperator::And,
+            filters: filters.into_iter().collect(),
+        }
+    }
+
+    pub fn or(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::Compound {
+            operator: CompoundOperator::Or,
+            filters: filters.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Builder)]
+pub struct VectorStoreSearchRequest {
+    #[serde(skip)]
+    #[builder(into)]
+    pub vector_store_id: String,
+    #[builder(into)]
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_num_results: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewrite_query: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranking_options: Option<Value>,
+    #[serde(skip)]
+    pub openai: OpenAi,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChunkContent {
+    Text { text: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredChunk {
+    pub file_id: String,
+    pub filename: String,
+    pub score: f64,
+    #[serde(default)]
+    pub attributes: Option<Value>,
+    #[serde(default)]
+    pub content: Vec<ChunkContent>,
+}
+
+impl ScoredChunk {
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .map(|ChunkContent::Text { text }| text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorStoreSearchResponse {
+    pub object: String,
+    #[serde(default)]
+    pub search_query: Value,
+    pub data: Vec<ScoredChunk>,
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default)]
+    pub next_page: Option<String>,
+}
+
+impl VectorStoreSearchResponse {
+    /// Chunks formatted as numbered sources, ready to be pasted into a prompt.
+    pub fn to_context(&self) -> String {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| format!("[{}] {}\n{}", i + 1, chunk.filename, chunk.text()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// A system message carrying the retrieved sources followed by `question` as the user turn.
+    pub fn to_messages(&self, question: impl Into<String>) -> Messages {
+        let system = format!(
+            "Answer the user's question using only the sources below. Cite sources by their \
+             number, e.g. [1]. If the sources do not contain the answer, say so.\n\n{}",
+            self.to_context()
+        );
+        Messages(vec![Message::system(system), Message::user(question)])
+    }
+}
+
+impl VectorStoreSearchRequest {
+    pub async fn send(&self) -> Result<VectorStoreSearchResponse, ApiRequestError> {
+        let url = format!(
+            "{}/{}/{}/search",
+            self.openai.inner.base_url, API_URL, self.vector_store_id
+        );
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .json(self)
+            .send_observed(&self.openai)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+}
+
+/// Handle to a single vector store.
+#[derive(Debug, Clone)]
+pub struct VectorStore {
+    openai: OpenAi,
+    id: String,
+}
+
+impl VectorStore {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the `k` best matching chunks for `query`.
+    pub async fn search(
+        &self,
+        query: impl Into<String>,
+        k: u32,
+        filters: Option<Filter>,
+    ) -> Result<VectorStoreSearchResponse, ApiRequestError> {
+        self.openai
+            .search_vector_store()
+            .vector_store_id(self.id.clone())
+            .query(query)
+            .max_num_results(k)
+            .maybe_filters(filters)
+            .build()
+            .send()
+            .await
+    }
+
+    /// Searches for `question` and returns a chat prompt grounded in the top `k` results.
+    pub async fn stuffed_prompt(
+        &self,
+        question: impl Into<String>,
+        k: u32,
+        filters: Option<Filter>,
+    ) -> Result<Messages, ApiRequestError> {
+        let question = question.into();
+        let results = self.search(question.clone(), k, filters).await?;
+        Ok(results.to_messages(question))
+    }
+}
+
+impl OpenAi {
+    pub fn search_vector_store(
+        &self,
+    ) -> VectorStoreSearchRequestBuilder<vector_store_search_request_builder::SetOpenai> {
+        VectorStoreSearchRequest::builder().openai(self.clone())
+    }
+
+    pub fn vector_store(&self, id: impl Into<String>) -> VectorStore {
+        VectorStore {
+            openai: self.clone(),
+            id: id.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_search_request_and_results() {
+        let openai = OpenAi::builder().api_key("sk-test".to_string()).build();
+        let request = openai
+            .search_vector_store()
+            .vector_store_id("vs_1")
+            .query("refunds")
+            .max_num_results(2)
+            .filters(Filter::and([
+                Filter::eq("region", "eu"),
+                Filter::compare("year", ComparisonOperator::Gte, 2024),
+            ]))
+            .build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "query": "refunds",
+                "max_num_results": 2,
+                "filters": {
+                    "type": "and",
+                    "filters": [
+                        {"key": "region", "type": "eq", "value": "eu"},
+                        {"key": "year", "type": "gte", "value": 2024}
+                    ]
+                }
+            })
+        );
+
+        let results: VectorStoreSearchResponse = serde_json::from_value(json!({
+            "object": "vector_store.search_results.page",
+            "search_query": "refunds",
+            "data": [{
+                "file_id": "file_1",
+                "filename": "policy.md",
+                "score": 0.92,
+                "attributes": {"region": "eu"},
+                "content": [{"type": "text", "text": "Refunds take 14 days."}]
+            }],
+            "has_more": false,
+            "next_page": null
+        }))
+        .unwrap();
+        assert_eq!(results.to_context(), "[1] policy.md\nRefunds take 14 days.");
+        let messages = results.to_messages("How long?");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content(), Some("How long?"));
+    }
+}
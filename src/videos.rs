@@ -0,0 +1,403 @@
+//! Video generation with Sora (`/v1/videos`).
+//!
+//! Generation runs as a job: create it, wait until it completes, then download the MP4. A
+//! finished video can be remixed with a new prompt, which keeps its structure and motion.
+//!
+//! ```no_run
+//! # async fn example(openai: openai_ox::OpenAi) -> Result<(), openai_ox::ApiRequestError> {
+//! use openai_ox::{
+//!     backoff::Backoff,
+//!     videos::{VideoSize, VideoVariant},
+//! };
+//!
+//! let video = openai
+//!     .create_video()
+//!     .model("sora-2")
+//!     .prompt("A paper boat drifting down a rainy street, close-up")
+//!     .size(VideoSize::Landscape720p)
+//!     .seconds(8)
+//!     .build()
+//!     .send()
+//!     .await?
+//!     .wait(&openai, Backoff::default())
+//!     .await?;
+//! let mp4 = openai.video_content(&video.id, VideoVariant::Video).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use bon::Builder;
+use reqwest::multipart;
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::Authorize, backoff::Backoff, retry::SendRetrying, ApiRequestError, OpenAi};
+
+const API_URL: &str = "v1/videos";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl VideoStatus {
+    /// `true` once the video will not change anymore.
+    pub fn is_terminal(self) -> bool {
+        matches!(self, VideoStatus::Completed | VideoStatus::Failed)
+    }
+}
+
+/// Output resolution, width by height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VideoSize {
+    #[serde(rename = "720x1280")]
+    Portrait720p,
+    #[serde(rename = "1280x720")]
+    Landscape720p,
+    /// `sora-2-pro` only.
+    #[serde(rename = "1024x1792")]
+    PortraitHd,
+    /// `sora-2-pro` only.
+    #[serde(rename = "1792x1024")]
+    LandscapeHd,
+}
+
+impl VideoSize {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VideoSize::Portrait720p => "720x1280",
+            VideoSize::Landscape720p => "1280x720",
+            VideoSize::PortraitHd => "1024x1792",
+            VideoSize::LandscapeHd => "1792x1024",
+        }
+    }
+}
+
+/// Asset of a completed video to download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoVariant {
+    /// The MP4 itself.
+    Video,
+    /// A still image of the video.
+    Thumbnail,
+    /// A grid of frames across the video.
+    Spritesheet,
+}
+
+impl VideoVariant {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VideoVariant::Video => "video",
+            VideoVariant::Thumbnail => "thumbnail",
+            VideoVariant::Spritesheet => "spritesheet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VideoError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Video {
+    pub id: String,
+    pub object: String,
+    pub model: String,
+    pub status: VideoStatus,
+    /// Completion estimate between 0 and 100.
+    #[serde(default)]
+    pub progress: Option<u32>,
+    pub created_at: u64,
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+    /// When the assets stop being downloadable.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub size: Option<VideoSize>,
+    /// Duration as returned by the API, e.g. `"8"`.
+    #[serde(default)]
+    pub seconds: Option<String>,
+    #[serde(default)]
+    pub remixed_from_video_id: Option<String>,
+    #[serde(default)]
+    pub error: Option<VideoError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoList {
+    pub object: String,
+    pub data: Vec<Video>,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletedVideo {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
+
+/// Image or video the generation starts from. It must match the requested size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputReference {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+impl InputReference {
+    pub fn new(filename: impl Into<String>, bytes: Vec<u8>) -> Self {
+        Self {
+            filename: filename.into(),
+            bytes,
+        }
+    }
+
+    /// MIME type guessed from the file extension.
+    pub fn mime(&self) -> &'static str {
+        let extension = Path::new(&self.filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("png") => "image/png",
+            Some("webp") => "image/webp",
+            Some("mp4") => "video/mp4",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct CreateVideoRequest {
+    /// `sora-2` or `sora-2-pro`.
+    #[builder(into)]
+    pub model: String,
+    #[builder(into)]
+    pub prompt: String,
+    pub size: Option<VideoSize>,
+    /// Clip length in seconds, 4, 8 or 12.
+    pub seconds: Option<u32>,
+    pub input_reference: Option<InputReference>,
+    pub openai: OpenAi,
+}
+
+async fn parse_video<T: serde::de::DeserializeOwned>(
+    res: reqwest::Response,
+) -> Result<T, ApiRequestError> {
+    if res.status().is_success() {
+        Ok(res.json().await?)
+    } else {
+        Err(ApiRequestError::from_response(res).await)
+    }
+}
+
+impl CreateVideoRequest {
+    fn form(&self) -> Result<multipart::Form, ApiRequestError> {
+        let mut form = multipart::Form::new()
+            .text("model", self.model.clone())
+            .text("prompt", self.prompt.clone());
+        if let Some(size) = self.size {
+            form = form.text("size", size.as_str());
+        }
+        if let Some(seconds) = self.seconds {
+            form = form.text("seconds", seconds.to_string());
+        }
+        if let Some(reference) = &self.input_reference {
+            let part = multipart::Part::bytes(reference.bytes.clone())
+                .file_name(reference.filename.clone())
+                .mime_str(reference.mime())?;
+            form = form.part("input_reference", part);
+        }
+        Ok(form)
+    }
+
+    pub async fn send(&self) -> Result<Video, ApiRequestError> {
+        let url = format!("{}/{}", self.openai.inner.base_url, API_URL);
+        let res = self
+            .openai
+            .inner
+            .client
+            .post(url)
+            .authorize(&self.openai)
+            .await?
+            .multipart(self.form()?)
+            .send_observed(&self.openai)
+            .await?;
+        parse_video(res).await
+    }
+}
+
+impl Video {
+    /// Polls until the video is completed or failed. A failed video is returned as is, with
+    /// the reason in [`Video::error`].
+    pub async fn wait(self, openai: &OpenAi, backoff: Backoff) -> Result<Video, ApiRequestError> {
+        let mut video = self;
+        let mut delays = backoff.delays();
+        while !video.status.is_terminal() {
+            if let Some(delay) = delays.next() {
+                tokio::time::sleep(delay).await;
+            }
+            video = openai.retrieve_video(&video.id).await?;
+        }
+        Ok(video)
+    }
+}
+
+impl OpenAi {
+    pub fn create_video(
+        &self,
+    ) -> CreateVideoRequestBuilder<create_video_request_builder::SetOpenai> {
+        CreateVideoRequest::builder().openai(self.clone())
+    }
+
+    pub async fn retrieve_video(&self, video_id: &str) -> Result<Video, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, video_id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_video(res).await
+    }
+
+    /// Videos of the organization, newest first. Pass the last id of a page as `after` to get
+    /// the next one.
+    pub async fn list_videos(
+        &self,
+        after: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<VideoList, ApiRequestError> {
+        let url = format!("{}/{}", self.inner.base_url, API_URL);
+        let mut query = Vec::new();
+        if let Some(after) = after {
+            query.push(("after", after.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .query(&query)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_video(res).await
+    }
+
+    pub async fn delete_video(&self, video_id: &str) -> Result<DeletedVideo, ApiRequestError> {
+        let url = format!("{}/{}/{}", self.inner.base_url, API_URL, video_id);
+        let res = self
+            .inner
+            .client
+            .delete(url)
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        parse_video(res).await
+    }
+
+    /// Starts a new video from a completed one, changed as described by `prompt`.
+    pub async fn remix_video(
+        &self,
+        video_id: &str,
+        prompt: impl Into<String>,
+    ) -> Result<Video, ApiRequestError> {
+        let url = format!("{}/{}/{}/remix", self.inner.base_url, API_URL, video_id);
+        let res = self
+            .inner
+            .client
+            .post(url)
+            .authorize(self)
+            .await?
+            .json(&serde_json::json!({ "prompt": prompt.into() }))
+            .send_observed(self)
+            .await?;
+        parse_video(res).await
+    }
+
+    /// Downloads an asset of a completed video.
+    pub async fn video_content(
+        &self,
+        video_id: &str,
+        variant: VideoVariant,
+    ) -> Result<Vec<u8>, ApiRequestError> {
+        let url = format!("{}/{}/{}/content", self.inner.base_url, API_URL, video_id);
+        let res = self
+            .inner
+            .client
+            .get(url)
+            .query(&[("variant", variant.as_str())])
+            .authorize(self)
+            .await?
+            .send_observed(self)
+            .await?;
+        if res.status().is_success() {
+            Ok(res.bytes().await?.to_vec())
+        } else {
+            Err(ApiRequestError::from_response(res).await)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_video_deserialization() {
+        let video: Video = serde_json::from_value(json!({
+            "id": "video_123",
+            "object": "video",
+            "model": "sora-2",
+            "status": "in_progress",
+            "progress": 42,
+            "created_at": 1759938772,
+            "size": "1280x720",
+            "seconds": "8",
+            "quality": "standard"
+        }))
+        .unwrap();
+        assert_eq!(video.status, VideoStatus::InProgress);
+        assert!(!video.status.is_terminal());
+        assert_eq!(video.size, Some(VideoSize::Landscape720p));
+        assert_eq!(video.progress, Some(42));
+
+        assert_eq!(
+            InputReference::new("first_frame.PNG", Vec::new()).mime(),
+            "image/png"
+        );
+        assert_eq!(VideoSize::PortraitHd.as_str(), "1024x1792");
+
+        let deleted: DeletedVideo = serde_json::from_value(json!({
+            "id": "video_123",
+            "object": "video.deleted",
+            "deleted": true
+        }))
+        .unwrap();
+        assert!(deleted.deleted);
+    }
+}
@@ -0,0 +1,247 @@
+//! Verification and parsing of incoming OpenAI webhooks.
+//!
+//! Webhooks follow the Standard Webhooks scheme: the `webhook-signature` header carries one or
+//! more `v1,<base64>` HMAC-SHA256 signatures of `{webhook-id}.{webhook-timestamp}.{body}`, keyed
+//! with the base64 part of the `whsec_...` secret shown in the dashboard.
+//!
+//! ```no_run
+//! # fn example(headers: &reqwest::header::HeaderMap, body: &[u8]) -> Result<(), openai_ox::webhooks::WebhookError> {
+//! use openai_ox::webhooks::{WebhookEventType, WebhookVerifier};
+//!
+//! let verifier = WebhookVerifier::new(&std::env::var("OPENAI_WEBHOOK_SECRET").unwrap())?;
+//! let event = verifier.unwrap(headers, body)?;
+//! if event.event_type == WebhookEventType::BatchCompleted {
+//!     println!("batch {} is done", event.data.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+const SECRET_PREFIX: &str = "whsec_";
+const ID_HEADER: &str = "webhook-id";
+const TIMESTAMP_HEADER: &str = "webhook-timestamp";
+const SIGNATURE_HEADER: &str = "webhook-signature";
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook secret")]
+    InvalidSecret,
+    #[error("Missing header: {0}")]
+    MissingHeader(&'static str),
+    #[error("Invalid webhook timestamp")]
+    InvalidTimestamp,
+    #[error("Webhook timestamp is outside the tolerance window")]
+    TimestampOutOfTolerance,
+    #[error("No matching webhook signature")]
+    InvalidSignature,
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    #[serde(rename = "batch.completed")]
+    BatchCompleted,
+    #[serde(rename = "batch.failed")]
+    BatchFailed,
+    #[serde(rename = "batch.cancelled")]
+    BatchCancelled,
+    #[serde(rename = "batch.expired")]
+    BatchExpired,
+    #[serde(rename = "fine_tuning.job.succeeded")]
+    FineTuningJobSucceeded,
+    #[serde(rename = "fine_tuning.job.failed")]
+    FineTuningJobFailed,
+    #[serde(rename = "fine_tuning.job.cancelled")]
+    FineTuningJobCancelled,
+    #[serde(rename = "response.completed")]
+    ResponseCompleted,
+    #[serde(rename = "response.failed")]
+    ResponseFailed,
+    #[serde(rename = "response.cancelled")]
+    ResponseCancelled,
+    #[serde(rename = "response.incomplete")]
+    ResponseIncomplete,
+    /// Any event type not modelled above.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Reference to the object the event is about; fetch it to get the full state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEventData {
+    /// Id of the batch, fine-tuning job or response.
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub data: WebhookEventData,
+}
+
+#[derive(Clone)]
+pub struct WebhookVerifier {
+    key: Vec<u8>,
+    tolerance: Duration,
+}
+
+impl std::fmt::Debug for WebhookVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookVerifier")
+            .field("key", &"[REDACTED]")
+            .field("tolerance", &self.tolerance)
+            .finish()
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, WebhookError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(WebhookError::MissingHeader(name))
+}
+
+impl WebhookVerifier {
+    /// Creates a verifier from a `whsec_...` secret.
+    pub fn new(secret: &str) -> Result<Self, WebhookError> {
+        let encoded = secret.strip_prefix(SECRET_PREFIX).unwrap_or(secret);
+        let key = STANDARD
+            .decode(encoded)
+            .map_err(|_| WebhookError::InvalidSecret)?;
+        Ok(WebhookVerifier {
+            key,
+            tolerance: Duration::from_secs(300),
+        })
+    }
+
+    /// Maximum allowed distance between the signed timestamp and now, 5 minutes by default.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Checks the signature headers against the raw request body.
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), WebhookError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.verify_at(
+            header(headers, ID_HEADER)?,
+            header(headers, TIMESTAMP_HEADER)?,
+            header(headers, SIGNATURE_HEADER)?,
+            body,
+            now,
+        )
+    }
+
+    /// Like [`verify`](WebhookVerifier::verify) with explicit header values and current time in
+    /// seconds since the epoch.
+    pub fn verify_at(
+        &self,
+        id: &str,
+        timestamp: &str,
+        signatures: &str,
+        body: &[u8],
+        now: u64,
+    ) -> Result<(), WebhookError> {
+        let timestamp: u64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| WebhookError::InvalidTimestamp)?;
+        if timestamp.abs_diff(now) > self.tolerance.as_secs() {
+            return Err(WebhookError::TimestampOutOfTolerance);
+        }
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).map_err(|_| WebhookError::InvalidSecret)?;
+        mac.update(format!("{}.{}.", id, timestamp).as_bytes());
+        mac.update(body);
+
+        let matches = signatures
+            .split_whitespace()
+            .filter_map(|signature| signature.strip_prefix("v1,"))
+            .filter_map(|signature| STANDARD.decode(signature).ok())
+            .any(|signature| mac.clone().verify_slice(&signature).is_ok());
+        if matches {
+            Ok(())
+        } else {
+            Err(WebhookError::InvalidSignature)
+        }
+    }
+
+    /// Verifies the request and deserializes its body.
+    pub fn unwrap(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookEvent, WebhookError> {
+        self.verify(headers, body)?;
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"test-webhook-key";
+    const BODY: &str = r#"{"id":"evt_1","object":"event","created_at":1750000000,"type":"batch.completed","data":{"id":"batch_1"}}"#;
+
+    fn sign(id: &str, timestamp: u64, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(KEY).unwrap();
+        mac.update(format!("{}.{}.{}", id, timestamp, body).as_bytes());
+        format!("v1,{}", STANDARD.encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_and_parse() {
+        let verifier = WebhookVerifier::new(&format!("whsec_{}", STANDARD.encode(KEY))).unwrap();
+        let now = 1_750_000_000;
+        let signatures = format!("v1,bm9wZQ== {}", sign("wh_1", now, BODY));
+
+        verifier
+            .verify_at(
+                "wh_1",
+                &now.to_string(),
+                &signatures,
+                BODY.as_bytes(),
+                now + 10,
+            )
+            .unwrap();
+        assert!(matches!(
+            verifier.verify_at("wh_1", &now.to_string(), &signatures, b"{}", now),
+            Err(WebhookError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verifier.verify_at(
+                "wh_1",
+                &now.to_string(),
+                &signatures,
+                BODY.as_bytes(),
+                now + 301
+            ),
+            Err(WebhookError::TimestampOutOfTolerance)
+        ));
+
+        let event: WebhookEvent = serde_json::from_str(BODY).unwrap();
+        assert_eq!(event.event_type, WebhookEventType::BatchCompleted);
+        assert_eq!(event.data.id, "batch_1");
+
+        let event: WebhookEvent = serde_json::from_str(
+            r#"{"id":"evt_2","object":"event","created_at":1,"type":"eval.run.succeeded","data":{"id":"evalrun_1"}}"#,
+        )
+        .unwrap();
+        assert_eq!(event.event_type, WebhookEventType::Unknown);
+    }
+}